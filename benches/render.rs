@@ -0,0 +1,78 @@
+//! Benchmarks for rendering very large pages with `--render`.
+//!
+//! tealdeer has no library target, so the renderer is measured through the `tldr` binary, like in
+//! the startup benchmarks. The page is large enough that parsing and rendering it dominates the
+//! startup time. Run them with `cargo bench --bench render`.
+
+use std::{
+    fmt::Write,
+    fs,
+    process::{Command, Stdio},
+};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use tempfile::TempDir;
+
+/// The number of examples on the generated page.
+const EXAMPLES: usize = 5000;
+
+/// Create an empty config directory and a page with [`EXAMPLES`] examples, which contain
+/// placeholders, escaped braces, code spans and the command name.
+fn setup() -> TempDir {
+    let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    fs::create_dir(dir.path().join("config")).unwrap();
+
+    let mut page = String::from(
+        "# large\n\n> A page with many examples.\n> More information: <https://example.com>.\n",
+    );
+    for i in 0..EXAMPLES {
+        write!(
+            page,
+            "\n- Example {i} with `inline code` and a [{{{{placeholder}}}}]:\n\n\
+             `large --option-{i} {{{{path/to/file_{i}}}}} | large \\{{\\{{literal\\}}\\}} {{{{value}}}}`\n"
+        )
+        .unwrap();
+    }
+    fs::write(dir.path().join("large.md"), page).unwrap();
+    dir
+}
+
+fn tldr(dir: &TempDir, args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_tldr"));
+    command
+        .arg("--render")
+        .arg(dir.path().join("large.md"))
+        .args(args)
+        .env("TEALDEER_CONFIG_DIR", dir.path().join("config"))
+        .env(
+            "TEALDEER_SYSTEM_CONFIG_DIR",
+            dir.path().join("system-config"),
+        )
+        .env_remove("TEALDEER_CACHE_DIR")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    command
+}
+
+fn render(c: &mut Criterion) {
+    let dir = setup();
+    let cases: &[(&str, &[&str])] = &[
+        ("rendered", &["--color", "never"]),
+        ("rendered_styled", &["--color", "always"]),
+        ("plain", &["--plain"]),
+        ("raw", &["--raw"]),
+        ("grep", &["--color", "always", "--grep", "file_4"]),
+    ];
+
+    let mut group = c.benchmark_group("render");
+    group.sample_size(20);
+    for (name, args) in cases {
+        group.bench_function(*name, |b| {
+            b.iter(|| tldr(&dir, args).status().expect("Failed to run tldr"));
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, render);
+criterion_main!(benches);
@@ -0,0 +1,90 @@
+//! Benchmarks for the startup latency of the `tldr` binary.
+//!
+//! Every iteration spawns a new process, because the time until a page is shown includes loading
+//! the config and probing the cache for the page. Run them with `cargo bench`.
+
+use std::{
+    fs,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use tempfile::TempDir;
+
+/// Create a config and cache directory containing the pages from `tests/cache`.
+fn setup() -> TempDir {
+    let dir = tempfile::tempdir().expect("Failed to create temporary directory");
+    copy_recursively(
+        &Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("cache"),
+        &dir.path().join("cache").join("tldr-pages"),
+    );
+    fs::create_dir(dir.path().join("config")).unwrap();
+    fs::write(
+        dir.path().join("config").join("config.toml"),
+        format!(
+            "[directories]\ncache_dir = {:?}\n\n[updates]\nauto_update = false\n",
+            dir.path().join("cache"),
+        ),
+    )
+    .unwrap();
+    dir
+}
+
+fn copy_recursively(source: &Path, destination: &Path) {
+    fs::create_dir_all(destination).unwrap();
+    for entry in fs::read_dir(source).unwrap() {
+        let entry = entry.unwrap();
+        let target = destination.join(entry.file_name());
+        if entry.file_type().unwrap().is_dir() {
+            copy_recursively(&entry.path(), &target);
+        } else {
+            fs::copy(entry.path(), target).unwrap();
+        }
+    }
+}
+
+fn tldr(dir: &TempDir, args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_tldr"));
+    command
+        .args(args)
+        .env("TEALDEER_CONFIG_DIR", dir.path().join("config"))
+        .env(
+            "TEALDEER_SYSTEM_CONFIG_DIR",
+            dir.path().join("system-config"),
+        )
+        .env_remove("TEALDEER_CACHE_DIR")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    command
+}
+
+fn startup(c: &mut Criterion) {
+    let dir = setup();
+    let cases: &[(&str, &[&str])] = &[
+        ("version", &["--version"]),
+        ("page", &["which"]),
+        ("page_not_found", &["does-not-exist"]),
+        (
+            "page_many_platforms",
+            &[
+                "-p", "android", "-p", "freebsd", "-p", "netbsd", "-p", "openbsd", "-p", "sunos",
+                "-p", "windows", "-p", "osx", "-p", "linux", "which",
+            ],
+        ),
+        ("list", &["--list"]),
+    ];
+
+    let mut group = c.benchmark_group("startup");
+    for (name, args) in cases {
+        group.bench_function(*name, |b| {
+            b.iter(|| tldr(&dir, args).status().expect("Failed to run tldr"));
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, startup);
+criterion_main!(benches);
@@ -1,34 +1,100 @@
 use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap},
+    fmt,
     fs::{self, File},
-    io::{Cursor, ErrorKind, Read},
-    path::{Path, PathBuf},
+    hash::{Hash, Hasher},
+    io::{self, BufReader, ErrorKind, Read, Seek, Write},
+    path::{Component, Path, PathBuf},
     time::{Duration, SystemTime},
 };
 
 use anyhow::{anyhow, bail, ensure, Context, Result};
+use clap::ValueEnum;
+use flate2::read::GzDecoder;
 use log::{debug, info};
+use serde_derive::Deserialize;
 use ureq::{
     http::StatusCode,
     tls::{RootCerts, TlsConfig, TlsProvider},
-    Agent,
+    Agent, Body,
 };
 use zip::ZipArchive;
 
 use crate::{
-    config::{Language, TlsBackend},
+    config::{ArchiveFormat, Language, TlsBackend},
+    minisign::PublicKey,
+    templates::expand_templates,
     types::PlatformType,
+    utils::{print_warning, write_atomically},
 };
 
 pub static TLDR_PAGES_DIR: &str = "tldr-pages";
 pub static TLDR_OLD_PAGES_DIR: &str = "tldr-master";
+pub static FETCHED_PAGES_DIR: &str = "fetched-pages";
+/// Name of the directory in the cache directory that contains one subdirectory for every
+/// [`PageSourceKind::TldrArchive`] source.
+pub static SOURCES_DIR: &str = "sources";
+
+/// Name of the directory in the state directory that contains the state directory of every cache,
+/// named after a hash of its pages directory (see [`Cache::cache_state_directory`]). The files
+/// below describe the pages of one cache, so caches with different pages directories (e.g. of
+/// different profiles) that share a state directory must not share them.
+static CACHES_DIR: &str = "caches";
+
+/// Name of the directory in the state directory of a cache that stores the `ETag`s of the
+/// downloaded archives, in one file per language.
+static ETAGS_DIR: &str = "etags";
+
+/// Name of the file in the state directory of a cache that stores the time of the last
+/// successful update, in seconds since the Unix epoch.
+static LAST_UPDATE_FILE: &str = "last_update";
+
+/// Name of the file in the state directory of a cache that stores the time of the last attempted
+/// update since the last successful update, in seconds since the Unix epoch.
+static LAST_UPDATE_ATTEMPT_FILE: &str = "last_update_attempt";
+
+/// Name of the file in the state directory of a cache that stores when the cache is due to be
+/// updated automatically, in seconds since the Unix epoch. The time is chosen once after every
+/// update, with a random jitter added to the update interval.
+static AUTO_UPDATE_DUE_FILE: &str = "auto_update_due";
+
+/// Name of the file in the state directory that stores the time of the last check for a new
+/// version of tealdeer, in seconds since the Unix epoch.
+static VERSION_CHECK_FILE: &str = "last_version_check";
+
+/// Name of the file in the state directory of a cache that stores when pages were last looked up
+/// in the directories of the cache for every language and platform. Every line contains the time
+/// (in seconds since the Unix epoch) and the directory relative to the pages directory (e.g.
+/// `pages.de/linux`), separated by a tab.
+static ACCESS_TIMES_FILE: &str = "access_times";
+
+/// Lookups are only recorded in [`ACCESS_TIMES_FILE`] if the directory wasn't looked up for this
+/// many seconds, so that the file isn't rewritten on every lookup.
+const ACCESS_TIME_RESOLUTION: u64 = 60 * 60;
+
+/// Name of the file in the state directory of a cache that lists the directories removed by
+/// [`Cache::evict`], relative to the pages directory (e.g. `pages.de/linux`), one per line. They
+/// are skipped by later updates, so that they aren't downloaded just to be removed again.
+static EVICTED_PAGES_FILE: &str = "evicted_pages";
+
+/// Name of the file in the pages directory that stores the index of the tldr pages, if the cache
+/// is updated from the index instead of from archives.
+static INDEX_FILE: &str = "index.json";
 
 #[derive(Clone)]
 pub struct CacheConfig<'a> {
     pub pages_directory: &'a Path,
+    /// Read-only pages installed for all users, e.g. by a distribution package. They have the
+    /// same layout as `pages_directory` and are searched after it.
+    pub system_pages_directory: Option<&'a Path>,
     pub custom_pages_directory: Option<&'a Path>,
     pub platforms: &'a [PlatformType],
     pub search_languages: &'a [Language<'a>],
     pub download_languages: &'a [Language<'a>],
+    pub download_platforms: &'a [PlatformType],
+    pub state_directory: &'a Path,
+    /// Additional sources of pages, sorted by descending priority.
+    pub sources: &'a [PageSource],
 }
 
 /// The directory backing this cache is checked to be populated at construction.
@@ -38,14 +104,606 @@ pub struct Cache<'a> {
 
 #[derive(Debug)]
 pub struct PageLookupResult {
-    pub page_path: PathBuf,
+    pub page: PageContent,
     pub patch_path: Option<PathBuf>,
+    /// Whether the page is a custom page from the custom pages directory.
+    pub custom_page: bool,
+    /// Whether template variables are expanded in the custom page and the patch, see
+    /// [`expand_templates`].
+    expand_templates: bool,
+}
+
+/// Check that `name` can be used as the name of a page file. Page names are joined into file
+/// paths and URLs, so they must not contain path separators or be `.` or `..`.
+pub fn validate_page_name(name: &str) -> Result<()> {
+    ensure!(
+        !name.is_empty() && !name.contains(['/', '\\']) && name != "." && name != "..",
+        "Invalid page name `{name}`, page names must not contain `/`, `\\` or `..` components",
+    );
+    Ok(())
+}
+
+/// Return the name of the page included by `line`, if it is an include directive like
+/// `<!-- include: tar -->`.
+pub fn include_directive(line: &str) -> Option<&str> {
+    let name = line
+        .trim()
+        .strip_prefix("<!--")?
+        .strip_suffix("-->")?
+        .trim()
+        .strip_prefix("include:")?
+        .trim();
+    (!name.is_empty()).then_some(name)
+}
+
+/// Where the content of a found page comes from.
+#[derive(Debug)]
+pub enum PageContent {
+    /// A page file in the tldr format.
+    File(PathBuf),
+    /// A page from a [`PageSource`] in another format, converted to the tldr format.
+    Converted(String),
+}
+
+/// The index of all tldr pages, as published at `https://tldr.sh/assets/index.json`.
+#[derive(Debug, Deserialize)]
+pub struct PagesIndex {
+    pub commands: Vec<IndexedCommand>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IndexedCommand {
+    pub name: String,
+    pub targets: Vec<IndexTarget>,
+}
+
+/// A platform and language for which a page exists.
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+pub struct IndexTarget {
+    pub os: String,
+    pub language: String,
+}
+
+impl PagesIndex {
+    /// The platforms and languages for which a page for `command` exists.
+    pub fn targets(&self, command: &str) -> Option<&[IndexTarget]> {
+        self.commands
+            .iter()
+            .find(|indexed| indexed.name == command)
+            .map(|indexed| indexed.targets.as_slice())
+    }
+}
+
+/// The priority of the tldr pages (including custom pages) among the configured
+/// [`PageSource`]s. Sources with a higher priority are searched before the tldr pages.
+pub const TLDR_PAGES_PRIORITY: i64 = 0;
+
+/// An additional source of pages, which are converted to the tldr format so that they are
+/// rendered like any other page.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PageSource {
+    pub name: String,
+    pub kind: PageSourceKind,
+    pub priority: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PageSourceKind {
+    /// A directory of cheat sheets in the format used by `cheat` (and cheat.sh), with one file
+    /// per command, optionally grouped in subdirectories.
+    CheatDirectory(PathBuf),
+    /// A server providing the cheat.sh HTTP API, e.g. `https://cheat.sh`.
+    CheatSh(String),
+    /// Pages in the tldr format, downloaded from archives like the official pages when updating.
+    TldrArchive {
+        /// The URL the archives are downloaded from, like `updates.archive_source`.
+        archive_source: String,
+        /// The languages to download and search, or `None` to use the configured ones.
+        languages: Option<Vec<String>>,
+        /// The directory in the cache that the archives are extracted to.
+        directory: PathBuf,
+    },
+}
+
+impl PageSource {
+    /// Look up the page for `command` in this source and convert it to the tldr format.
+    fn find_page(&self, command: &str, client: impl FnOnce() -> Agent) -> Result<Option<String>> {
+        // The command is part of the path or URL of the cheat sheet
+        validate_page_name(command)?;
+        let cheat_sheet = match &self.kind {
+            PageSourceKind::CheatDirectory(directory) => {
+                let mut candidates = vec![directory.join(command)];
+                if let Ok(entries) = fs::read_dir(directory) {
+                    let mut subdirectories = entries
+                        .filter_map(Result::ok)
+                        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+                        .map(|entry| entry.path().join(command))
+                        .collect::<Vec<_>>();
+                    subdirectories.sort();
+                    candidates.extend(subdirectories);
+                }
+                let Some(path) = candidates.into_iter().find(|path| path.is_file()) else {
+                    return Ok(None);
+                };
+                fs::read_to_string(&path)
+                    .with_context(|| format!("Could not read cheat sheet at {}", path.display()))?
+            }
+            // These are looked up like the tldr pages in the cache
+            PageSourceKind::TldrArchive { .. } => return Ok(None),
+            PageSourceKind::CheatSh(url) => {
+                // `T` disables the syntax highlighting with terminal escape sequences
+                let url = format!("{}/{command}?T", url.trim_end_matches('/'));
+                let Some(bytes) = Cache::download_bytes(&client(), &url)? else {
+                    return Ok(None);
+                };
+                let text = String::from_utf8_lossy(&bytes).into_owned();
+                if text.starts_with("Unknown topic") {
+                    return Ok(None);
+                }
+                text
+            }
+        };
+        Ok(Some(cheat_sheet_to_tldr(command, &self.name, &cheat_sheet)))
+    }
+}
+
+/// Convert a cheat sheet in the format used by `cheat`, where examples are commands preceded by
+/// `#` comments describing them, to a page in the tldr format.
+fn cheat_sheet_to_tldr(command: &str, source_name: &str, cheat_sheet: &str) -> String {
+    let mut page = format!("# {command}\n\n> Cheat sheet from the `{source_name}` source.\n");
+    let mut lines = cheat_sheet.lines().peekable();
+
+    // Skip the YAML front matter
+    if lines.peek().map(|line| line.trim_end()) == Some("---") {
+        lines.next();
+        for line in lines.by_ref() {
+            if line.trim_end() == "---" {
+                break;
+            }
+        }
+    }
+
+    let mut description = String::new();
+    for line in lines {
+        let line = line.trim_end();
+        if let Some(comment) = line.strip_prefix('#') {
+            if !description.is_empty() {
+                description.push(' ');
+            }
+            description.push_str(comment.trim());
+        } else if !line.trim().is_empty() {
+            if !description.is_empty() {
+                let separator = if description.ends_with(':') { "" } else { ":" };
+                page.push_str("\n- ");
+                page.push_str(&description);
+                page.push_str(separator);
+                page.push('\n');
+                description.clear();
+            }
+            // Braces would be interpreted as placeholders
+            let code = line.replace("{{", r"\{\{").replace("}}", r"\}\}");
+            page.push_str("\n`");
+            page.push_str(&code);
+            page.push_str("`\n");
+        }
+    }
+    page
+}
+
+/// The reason why an existing custom patch is not applied when showing a page.
+#[derive(Debug)]
+pub enum UnappliedPatch {
+    /// A custom page takes precedence over the upstream page the patch would be appended to.
+    ShadowedByCustomPage {
+        patch_path: PathBuf,
+        custom_page_path: PathBuf,
+    },
+    /// There is no upstream page in the cache that the patch could be appended to.
+    NoUpstreamPage { patch_path: PathBuf },
+    /// Checking whether the patch exists failed, e.g. because the directory is not readable.
+    Inaccessible {
+        patch_path: PathBuf,
+        error: io::Error,
+    },
+}
+
+impl fmt::Display for UnappliedPatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ShadowedByCustomPage {
+                patch_path,
+                custom_page_path,
+            } => write!(
+                f,
+                "The custom patch at {} is not applied, because the custom page at {} takes precedence.",
+                patch_path.display(),
+                custom_page_path.display(),
+            ),
+            Self::NoUpstreamPage { patch_path } => write!(
+                f,
+                "The custom patch at {} is not applied, because there is no page in the cache to append it to.",
+                patch_path.display(),
+            ),
+            Self::Inaccessible { patch_path, error } => write!(
+                f,
+                "Could not check for a custom patch at {}: {error}",
+                patch_path.display(),
+            ),
+        }
+    }
+}
+
+/// Whether the pages for a language in the cache are up to date with the upstream archive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LanguageStatus {
+    UpToDate,
+    Outdated,
+    /// The language is configured for download, but not in the cache yet.
+    NotCached,
+    /// There is no upstream archive for the language.
+    NotAvailable,
+    /// The status can't be determined, e.g. because the cache was updated by an older version of
+    /// tealdeer, which didn't store the `ETag` of the archive.
+    Unknown,
+}
+
+impl LanguageStatus {
+    /// Whether running an update would change the pages for this language.
+    pub fn needs_update(self) -> bool {
+        matches!(self, Self::Outdated | Self::NotCached | Self::Unknown)
+    }
+}
+
+impl fmt::Display for LanguageStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::UpToDate => "up to date",
+            Self::Outdated => "outdated",
+            Self::NotCached => "not downloaded yet",
+            Self::NotAvailable => "no pages available",
+            Self::Unknown => "unknown",
+        })
+    }
+}
+
+/// The hashes of the contents of all pages in the cache, by their path relative to the pages
+/// directory (e.g. `pages.en/common/tar.md`), to find out what an update changed.
+pub struct PageHashes(BTreeMap<String, u64>);
+
+/// The pages that were changed by an update, see [`PageHashes::changes`].
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CacheChanges {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub modified: Vec<String>,
+}
+
+impl PageHashes {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The pages that were added, removed and modified in `new` compared to `self`.
+    pub fn changes(&self, new: &PageHashes) -> CacheChanges {
+        let mut changes = CacheChanges::default();
+        for (path, hash) in &new.0 {
+            match self.0.get(path) {
+                None => changes.added.push(path.clone()),
+                Some(old_hash) if old_hash != hash => changes.modified.push(path.clone()),
+                Some(_) => {}
+            }
+        }
+        changes.removed = self
+            .0
+            .keys()
+            .filter(|path| !new.0.contains_key(*path))
+            .cloned()
+            .collect();
+        changes
+    }
+}
+
+/// A problem with the pages of a language in the cache, see [`Cache::check`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheProblem<'a> {
+    /// The language is configured for download, but there is no directory for it.
+    MissingLanguage(Language<'a>),
+    /// The directory for the language does not contain any pages.
+    EmptyLanguage(Language<'a>),
+    /// Some pages of the language are empty, e.g. because extracting them was interrupted.
+    EmptyPages {
+        language: Language<'a>,
+        count: usize,
+    },
+}
+
+impl<'a> CacheProblem<'a> {
+    pub fn language(&self) -> Language<'a> {
+        match *self {
+            Self::MissingLanguage(language)
+            | Self::EmptyLanguage(language)
+            | Self::EmptyPages { language, .. } => language,
+        }
+    }
+}
+
+/// The pages of a language and platform that were removed by [`Cache::evict`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvictedPages {
+    /// The directory relative to the pages directory, e.g. `pages.de/linux`.
+    pub directory: String,
+    /// The size of the removed pages in bytes.
+    pub size: u64,
+}
+
+impl fmt::Display for CacheProblem<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingLanguage(Language(language)) => {
+                write!(f, "{language}: no pages in the cache (they might not be available in this language)")
+            }
+            Self::EmptyLanguage(Language(language)) => {
+                write!(f, "{language}: the pages directory is empty")
+            }
+            Self::EmptyPages {
+                language: Language(language),
+                count,
+            } => write!(f, "{language}: {count} page(s) are empty"),
+        }
+    }
+}
+
+/// A downloaded pages archive, in one of the supported formats.
+enum Archive {
+    Zip(ZipArchive<BufReader<File>>),
+    TarGz(File),
+    TarZst(File),
+}
+
+impl Archive {
+    /// Open the archive in `file`, detecting its format from the content.
+    fn open(mut file: File) -> Result<Self> {
+        let mut magic = Vec::with_capacity(4);
+        (&mut file).take(4).read_to_end(&mut magic)?;
+        file.rewind()?;
+
+        match magic[..] {
+            [b'P', b'K', ..] => Ok(Self::Zip(ZipArchive::new(BufReader::new(file))?)),
+            [0x1f, 0x8b, ..] => Ok(Self::TarGz(file)),
+            [0x28, 0xb5, 0x2f, 0xfd] => Ok(Self::TarZst(file)),
+            _ => bail!("Unsupported archive format, expected a zip, tar.gz or tar.zst file."),
+        }
+    }
+
+    /// Extract the archive into `directory`, skipping the directories of platforms that are not
+    /// in `platforms`, and the directories in `skipped` (e.g. `linux`).
+    fn extract(self, directory: &Path, platforms: &[PlatformType], skipped: &[&str]) -> Result<()> {
+        fs::create_dir_all(directory)?;
+        match self {
+            Self::Zip(mut archive) => {
+                for i in 0..archive.len() {
+                    let mut entry = archive.by_index(i)?;
+                    // Skip entries with unsafe paths, like `zip` does when extracting
+                    let Some(path) = entry.enclosed_name() else {
+                        continue;
+                    };
+                    if !is_wanted_entry(&path, platforms, skipped) {
+                        continue;
+                    }
+                    let target = directory.join(path);
+                    if entry.is_dir() {
+                        fs::create_dir_all(&target)?;
+                    } else {
+                        if let Some(parent) = target.parent() {
+                            fs::create_dir_all(parent)?;
+                        }
+                        io::copy(&mut entry, &mut File::create(&target)?)?;
+                    }
+                }
+            }
+            Self::TarGz(file) => unpack_tar(
+                GzDecoder::new(BufReader::new(file)),
+                directory,
+                platforms,
+                skipped,
+            )?,
+            Self::TarZst(file) => {
+                unpack_tar(zstd::Decoder::new(file)?, directory, platforms, skipped)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Unpack the tar archive read from `reader` into `directory`, skipping the directories of
+/// platforms that are not in `platforms`, and the directories in `skipped`.
+fn unpack_tar(
+    reader: impl Read,
+    directory: &Path,
+    platforms: &[PlatformType],
+    skipped: &[&str],
+) -> Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if is_wanted_entry(&entry.path()?, platforms, skipped) {
+            // `unpack_in` skips entries with unsafe paths
+            entry.unpack_in(directory)?;
+        }
+    }
+    Ok(())
+}
+
+/// The certificates of the `ca-certificates` package of Termux, if tealdeer runs in Termux. The
+/// platform verifier can't be used there, because it needs the context of an Android app.
+#[cfg(feature = "rustls-with-native-roots")]
+fn termux_root_certs() -> Option<RootCerts> {
+    if !crate::utils::is_termux() {
+        return None;
+    }
+    let prefix = std::env::var_os("PREFIX")?;
+    let pem = fs::read(Path::new(&prefix).join("etc").join("tls").join("cert.pem")).ok()?;
+    let certs: Vec<_> = ureq::tls::parse_pem(&pem)
+        .filter_map(|item| match item {
+            Ok(ureq::tls::PemItem::Certificate(cert)) => Some(cert),
+            _ => None,
+        })
+        .collect();
+    (!certs.is_empty()).then(|| RootCerts::new_with_certs(&certs))
+}
+
+/// Whether `error` was caused by the network or the server being unreachable, e.g. because there
+/// is no internet connection, as opposed to other download errors like error responses or invalid
+/// archives.
+pub fn is_network_unreachable(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<ureq::Error>(),
+            Some(
+                ureq::Error::Io(_)
+                    | ureq::Error::HostNotFound
+                    | ureq::Error::ConnectionFailed
+                    | ureq::Error::Timeout(_)
+            )
+        )
+    })
+}
+
+/// The path of a `file://` URL, which is used for local archive sources.
+fn file_url_path(url: &str) -> Option<&Path> {
+    url.strip_prefix("file://").map(Path::new)
+}
+
+/// An identifier of the state of a local file, which is stored like the `ETag` of a downloaded
+/// archive, so that changes of local archives are detected as well.
+fn local_etag(metadata: &fs::Metadata) -> Option<String> {
+    let modified = metadata
+        .modified()
+        .ok()?
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .ok()?;
+    Some(format!("\"{}-{}\"", metadata.len(), modified.as_nanos()))
+}
+
+/// Check that the directory of a local `archive_source` exists, as missing archives would
+/// otherwise be treated like languages without pages.
+fn check_archive_source(archive_source: &str) -> Result<()> {
+    if let Some(directory) = file_url_path(archive_source) {
+        ensure!(
+            directory.is_dir(),
+            "The archive source {} is not a directory.",
+            directory.display(),
+        );
+    }
+    Ok(())
+}
+
+/// Open the local file at `path`. If it doesn't exist, `Ok(None)` is returned, like for URLs
+/// where nothing is found.
+fn open_local_file(path: &Path) -> Result<Option<File>> {
+    match File::open(path) {
+        Ok(file) => Ok(Some(file)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Could not open {}", path.display())),
+    }
+}
+
+/// The total size in bytes of the files in `path`, which may also be a single file.
+fn directory_size(path: &Path) -> Result<u64> {
+    let metadata = fs::symlink_metadata(path)?;
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        size += directory_size(&entry?.path())?;
+    }
+    Ok(size)
+}
+
+/// Find the custom pages and patches in `directory` that use the old naming convention
+/// (`<name>.page` and `<name>.patch` instead of `<name>.page.md` and `<name>.patch.md`), sorted
+/// by name.
+pub fn find_old_custom_pages(directory: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    let entries = fs::read_dir(directory).with_context(|| {
+        format!(
+            "Could not read custom pages directory {}",
+            directory.display()
+        )
+    })?;
+    for entry in entries {
+        let path = entry?.path();
+        let is_old = path
+            .extension()
+            .is_some_and(|extension| extension == "page" || extension == "patch");
+        if is_old && path.is_file() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Whether the archive entry at `path` should be extracted. Entries in the directory of a
+/// platform that is not in `platforms` are skipped, all other entries (like the license) are kept.
+fn is_wanted_entry(path: &Path, platforms: &[PlatformType], skipped: &[&str]) -> bool {
+    let Some(Component::Normal(first)) = path
+        .components()
+        .find(|component| *component != Component::CurDir)
+    else {
+        return true;
+    };
+    if skipped.iter().any(|directory| first == *directory) {
+        return false;
+    }
+    PlatformType::value_variants()
+        .iter()
+        .find(|platform| platform.directory_name() == first)
+        .is_none_or(|platform| platforms.contains(platform))
+}
+
+/// A file downloaded by [`Cache::download`].
+struct Download {
+    /// A temporary file containing the archive, which is deleted when it is dropped, or the
+    /// archive itself if it is a local file.
+    file: File,
+    etag: Option<String>,
+    /// The size of the file in bytes.
+    size: u64,
+}
+
+/// The pages downloaded by [`Cache::update`].
+#[derive(Debug, Default)]
+pub struct DownloadedPages<'a> {
+    /// The languages for which pages were available.
+    pub languages: Vec<Language<'a>>,
+    /// The total size of the downloaded archives in bytes.
+    pub bytes: u64,
+}
+
+/// The index downloaded by [`Cache::update_index`].
+#[derive(Debug)]
+pub struct DownloadedIndex {
+    /// The number of commands in the index.
+    pub commands: usize,
+    /// The size of the index in bytes.
+    pub bytes: u64,
 }
 
 impl<'a> Cache<'a> {
     /// Try opening a cache at the location given by `config.pages_directory`. If no directory
     /// exists at this location, `Ok(None)` is returned.
+    ///
+    /// If the pages directory does not exist, but there are system pages, the cache is opened
+    /// anyway, so that the system pages can be used without an update. The pages directory is
+    /// only created once pages are written to it.
     pub fn open(config: CacheConfig<'a>) -> Result<Option<Self>> {
+        if !config.pages_directory.exists()
+            && config.system_pages_directory.is_some_and(Path::is_dir)
+        {
+            return Ok(Some(Cache { config }));
+        }
         match config.pages_directory.metadata() {
             Ok(md) => {
                 ensure!(
@@ -68,6 +726,10 @@ impl<'a> Cache<'a> {
     /// boolean indicating whether the cache was newly created.
     pub fn open_or_create(config: CacheConfig<'a>) -> Result<(Self, bool)> {
         if let Some(cache) = Self::open(config.clone())? {
+            // Only the system pages exist, the pages directory is needed for the update
+            if !config.pages_directory.exists() {
+                cache.create_pages_directory()?;
+            }
             return Ok((cache, false));
         }
 
@@ -85,42 +747,488 @@ impl<'a> Cache<'a> {
         Ok((Cache { config }, true))
     }
 
+    /// The time since the last successful update.
+    ///
+    /// If the time of the last update was not recorded (e.g. because the cache was populated by
+    /// an older version of tealdeer), the modification time of the pages directory is used.
     pub fn age(&self) -> Result<Duration> {
-        let mtime = self.config.pages_directory.metadata()?.modified()?;
         SystemTime::now()
-            .duration_since(mtime)
-            .context("Error comparing cache mtime with current time")
+            .duration_since(self.last_update()?)
+            .context("Error comparing the time of the last update with the current time")
+    }
+
+    /// The time of the last successful update, see [`Cache::age`].
+    pub fn last_update(&self) -> Result<SystemTime> {
+        match Self::read_timestamp(&self.cache_state_path(LAST_UPDATE_FILE)) {
+            Some(last_update) => Ok(last_update),
+            None => Ok(self.config.pages_directory.metadata()?.modified()?),
+        }
+    }
+
+    /// When the cache is due to be updated automatically, if this was already decided since the
+    /// last update.
+    pub fn auto_update_due_time(&self) -> Option<SystemTime> {
+        Self::read_timestamp(&self.cache_state_path(AUTO_UPDATE_DUE_FILE))
+    }
+
+    /// Record when the cache is due to be updated automatically, until the next update.
+    pub fn set_auto_update_due_time(&self, due: SystemTime) -> Result<()> {
+        Self::write_timestamp(&self.cache_state_path(AUTO_UPDATE_DUE_FILE), due)
+    }
+
+    /// Whether the last attempted update was more than `interval` ago. Only attempts since the
+    /// last successful update are considered.
+    pub fn update_attempt_due(&self, interval: Duration) -> bool {
+        Self::read_timestamp(&self.cache_state_path(LAST_UPDATE_ATTEMPT_FILE))
+            .and_then(|last_attempt| SystemTime::now().duration_since(last_attempt).ok())
+            .is_none_or(|elapsed| elapsed >= interval)
+    }
+
+    /// Record that an update is being attempted, before accessing the network.
+    pub fn record_update_attempt(&self) -> Result<()> {
+        Self::write_timestamp(
+            &self.cache_state_path(LAST_UPDATE_ATTEMPT_FILE),
+            SystemTime::now(),
+        )
+    }
+
+    /// Whether the last check for a new version of tealdeer was more than `interval` ago.
+    pub fn version_check_due(&self, interval: Duration) -> bool {
+        Self::read_timestamp(&self.config.state_directory.join(VERSION_CHECK_FILE))
+            .and_then(|last_check| SystemTime::now().duration_since(last_check).ok())
+            .is_none_or(|elapsed| elapsed >= interval)
+    }
+
+    /// Record that the latest version of tealdeer was just checked.
+    pub fn record_version_check(&self) -> Result<()> {
+        Self::write_timestamp(
+            &self.config.state_directory.join(VERSION_CHECK_FILE),
+            SystemTime::now(),
+        )
+    }
+
+    /// The state directory of this cache, see [`CACHES_DIR`]. The name is a hash of the path of
+    /// the pages directory that is stable across builds of tealdeer.
+    fn cache_state_directory(&self) -> PathBuf {
+        // FNV-1a, as the hash of `DefaultHasher` may change between Rust versions
+        let hash = self
+            .config
+            .pages_directory
+            .as_os_str()
+            .as_encoded_bytes()
+            .iter()
+            .fold(0xcbf2_9ce4_8422_2325_u64, |hash, &byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+            });
+        self.config
+            .state_directory
+            .join(CACHES_DIR)
+            .join(format!("{hash:016x}"))
+    }
+
+    /// The path of the file called `file_name` in the state directory of this cache.
+    fn cache_state_path(&self, file_name: &str) -> PathBuf {
+        self.cache_state_directory().join(file_name)
+    }
+
+    /// Create the directory that contains the state file at `path`, if it doesn't exist yet.
+    fn create_state_directory(path: &Path) -> Result<()> {
+        let directory = path.parent().unwrap_or(path);
+        fs::create_dir_all(directory)
+            .with_context(|| format!("Could not create state directory {}", directory.display()))
+    }
+
+    fn read_timestamp(path: &Path) -> Option<SystemTime> {
+        let content = fs::read_to_string(path).ok()?;
+        match content.trim().parse() {
+            Ok(seconds) => Some(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds)),
+            Err(e) => {
+                debug!("Ignoring invalid timestamp in {}: {e}", path.display());
+                None
+            }
+        }
+    }
+
+    /// Record a successful update, which starts a new update cycle: the time of the next
+    /// automatic update is chosen again, and failed attempts no longer delay it.
+    fn write_last_update(&self) -> Result<()> {
+        Self::write_timestamp(&self.cache_state_path(LAST_UPDATE_FILE), SystemTime::now())?;
+        self.remove_state_file(AUTO_UPDATE_DUE_FILE)?;
+        self.remove_state_file(LAST_UPDATE_ATTEMPT_FILE)
+    }
+
+    /// Remove a file from the state directory of this cache, if it exists.
+    fn remove_state_file(&self, file_name: &str) -> Result<()> {
+        let path = self.cache_state_path(file_name);
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("Could not remove {}", path.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn write_timestamp(path: &Path, time: SystemTime) -> Result<()> {
+        let seconds = time
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .context("The time is before the Unix epoch")?
+            .as_secs();
+        Self::create_state_directory(path)?;
+        write_atomically(path, format!("{seconds}\n"))
+            .with_context(|| format!("Could not write {}", path.display()))
+    }
+
+    /// Look up `command` in the configured page sources and the tldr pages (see
+    /// [`Cache::find_page`]), in order of their priority.
+    ///
+    /// A source that fails, e.g. because it is unreachable while offline, is skipped with a
+    /// warning, so that the remaining sources and the cache are still searched.
+    pub fn find_page_in_sources(
+        &self,
+        command: &str,
+        tls_backend: TlsBackend,
+        enable_styles: bool,
+    ) -> Option<PageLookupResult> {
+        let mut searched_tldr_pages = false;
+        for source in self.config.sources {
+            if !searched_tldr_pages && source.priority <= TLDR_PAGES_PRIORITY {
+                searched_tldr_pages = true;
+                if let Some(result) = self.find_page(command) {
+                    return Some(result);
+                }
+            }
+            let page = if let PageSourceKind::TldrArchive {
+                languages,
+                directory,
+                ..
+            } = &source.kind
+            {
+                let languages = self.source_languages(languages.as_deref());
+                self.with_pages(directory, &languages)
+                    .find_upstream_page(command)
+                    .map(PageContent::File)
+            } else {
+                match source.find_page(command, || Self::build_client(tls_backend)) {
+                    Ok(page) => page.map(PageContent::Converted),
+                    Err(e) => {
+                        print_warning(
+                            enable_styles,
+                            &format!(
+                                "Could not look up `{command}` in the `{}` source: {e:#}",
+                                source.name
+                            ),
+                        );
+                        None
+                    }
+                }
+            };
+            if let Some(page) = page {
+                return Some(PageLookupResult {
+                    page,
+                    patch_path: None,
+                    custom_page: false,
+                    expand_templates: false,
+                });
+            }
+        }
+
+        if searched_tldr_pages {
+            None
+        } else {
+            self.find_page(command)
+        }
+    }
+
+    /// The languages of a [`PageSourceKind::TldrArchive`] source, falling back to the search
+    /// languages of this cache.
+    fn source_languages<'b>(&self, languages: Option<&'b [String]>) -> Vec<Language<'b>>
+    where
+        'a: 'b,
+    {
+        languages.map_or_else(
+            || self.config.search_languages.to_vec(),
+            |languages| languages.iter().map(|lang| Language(lang)).collect(),
+        )
+    }
+
+    /// A cache for the tldr pages in `pages_directory`, with the same platforms as this one.
+    fn with_pages<'b>(&self, pages_directory: &'b Path, languages: &'b [Language<'b>]) -> Cache<'b>
+    where
+        'a: 'b,
+    {
+        Cache {
+            config: CacheConfig {
+                pages_directory,
+                system_pages_directory: None,
+                custom_pages_directory: None,
+                search_languages: languages,
+                download_languages: languages,
+                sources: &[],
+                ..self.config.clone()
+            },
+        }
+    }
+
+    /// The same cache, but only searching the pages of `platforms`.
+    pub fn with_platforms<'b>(&self, platforms: &'b [PlatformType]) -> Cache<'b>
+    where
+        'a: 'b,
+    {
+        Cache {
+            config: CacheConfig {
+                platforms,
+                ..self.config.clone()
+            },
+        }
+    }
+
+    /// Download the archives of the [`PageSourceKind::TldrArchive`] sources into their
+    /// directories. Returns the names of the updated sources.
+    pub fn update_sources(
+        &self,
+        archive_format: ArchiveFormat,
+        tls_backend: TlsBackend,
+    ) -> Result<Vec<&str>> {
+        let mut updated = Vec::new();
+        for source in self.config.sources {
+            let PageSourceKind::TldrArchive {
+                archive_source,
+                languages,
+                directory,
+            } = &source.kind
+            else {
+                continue;
+            };
+
+            let languages = self.source_languages(languages.as_deref());
+            fs::create_dir_all(directory)
+                .with_context(|| format!("Could not create directory {}", directory.display()))?;
+            // The state of the source is kept apart from the tldr pages by its pages directory
+            let mut source_cache = self.with_pages(directory, &languages);
+            // The public key only applies to the archives of the tldr pages
+            source_cache
+                .update(archive_source, archive_format, tls_backend, None)
+                .with_context(|| format!("Could not update the `{}` source", source.name))?;
+            updated.push(source.name.as_str());
+        }
+        Ok(updated)
     }
 
     pub fn find_page(&self, command: &str) -> Option<PageLookupResult> {
-        let page_filename = format!("{command}.md");
-        let patch_filename = format!("{command}.patch.md");
-        let custom_filename = format!("{command}.page.md");
+        if let Some(custom_page) = self.find_custom_page(command) {
+            return Some(PageLookupResult {
+                custom_page: true,
+                ..PageLookupResult::with_page(custom_page)
+            });
+        }
 
-        if let Some(custom_pages_dir) = self.config.custom_pages_directory {
-            let custom_page = custom_pages_dir.join(custom_filename);
-            if custom_page.is_file() {
-                return Some(PageLookupResult::with_page(custom_page));
+        self.find_upstream_page(command).map(|page| {
+            PageLookupResult::with_page(page).with_optional_patch(self.find_patch(command))
+        })
+    }
+
+    /// Replace the include directives (like `<!-- include: tar -->`) in the custom page found for
+    /// `command` with the examples of the included pages, which are looked up with
+    /// [`Cache::find_page`]. Their titles and descriptions are left out. Other results are
+    /// returned unchanged.
+    ///
+    /// This will return an error if an included page cannot be found, or if pages include each
+    /// other.
+    pub fn resolve_includes(
+        &self,
+        command: &str,
+        result: PageLookupResult,
+    ) -> Result<PageLookupResult> {
+        self.resolve_includes_from(result, &mut vec![command.to_string()])
+    }
+
+    /// Resolve the includes of `result`, which was included by the pages in `stack`.
+    fn resolve_includes_from(
+        &self,
+        result: PageLookupResult,
+        stack: &mut Vec<String>,
+    ) -> Result<PageLookupResult> {
+        if !result.custom_page {
+            return Ok(result);
+        }
+        let mut content = String::new();
+        result
+            .reader()?
+            .read_to_string(&mut content)
+            .context("Could not read custom page")?;
+        if !content
+            .lines()
+            .any(|line| include_directive(line).is_some())
+        {
+            return Ok(result);
+        }
+
+        let mut merged = String::with_capacity(content.len());
+        for line in content.split_inclusive('\n') {
+            let Some(name) = include_directive(line) else {
+                merged.push_str(line);
+                continue;
+            };
+            if stack.iter().any(|included| included == name) {
+                bail!(
+                    "Custom pages include each other: {} -> {name}",
+                    stack.join(" -> ")
+                );
             }
+            let included = self
+                .find_page(name)
+                .with_context(|| {
+                    format!(
+                        "Page `{name}` included by the custom page for `{}` not found",
+                        stack[stack.len() - 1]
+                    )
+                })?
+                .with_expanded_templates(result.expand_templates);
+            stack.push(name.to_string());
+            let included = self.resolve_includes_from(included, stack)?;
+            stack.pop();
+
+            let mut included_content = String::new();
+            included
+                .reader()?
+                .read_to_string(&mut included_content)
+                .with_context(|| format!("Could not read included page `{name}`"))?;
+            let examples: Vec<_> = included_content
+                .lines()
+                .filter(|line| !line.starts_with('#') && !line.starts_with('>'))
+                .skip_while(|line| line.trim().is_empty())
+                .collect();
+            merged.push_str(examples.join("\n").trim_end());
+            merged.push('\n');
         }
 
+        // Templates were expanded while reading the pages
+        Ok(PageLookupResult {
+            page: PageContent::Converted(merged),
+            patch_path: None,
+            custom_page: true,
+            expand_templates: false,
+        })
+    }
+
+    /// Return the path of the custom page (`<command>.page.md`) for `command`, if it exists.
+    pub fn find_custom_page(&self, command: &str) -> Option<PathBuf> {
+        self.config
+            .custom_pages_directory
+            .map(|dir| dir.join(format!("{command}.page.md")))
+            .filter(|path| path.is_file())
+    }
+
+    /// Return the path of the custom patch (`<command>.patch.md`) for `command`, if it exists.
+    pub fn find_patch(&self, command: &str) -> Option<PathBuf> {
+        self.config
+            .custom_pages_directory
+            .map(|dir| dir.join(format!("{command}.patch.md")))
+            .filter(|path| path.is_file())
+    }
+
+    /// Check whether a custom patch for `command` exists but would not be applied by
+    /// [`Cache::find_page`].
+    pub fn find_unapplied_patch(&self, command: &str) -> Option<UnappliedPatch> {
         let patch_path = self
             .config
-            .custom_pages_directory
-            .map(|dir| dir.join(&patch_filename))
-            .filter(|path| path.is_file());
+            .custom_pages_directory?
+            .join(format!("{command}.patch.md"));
+
+        match patch_path.metadata() {
+            Ok(md) if md.is_file() => {}
+            Ok(_) => return None,
+            Err(e) if e.kind() == ErrorKind::NotFound => return None,
+            Err(error) => return Some(UnappliedPatch::Inaccessible { patch_path, error }),
+        }
+
+        if let Some(custom_page_path) = self.find_custom_page(command) {
+            Some(UnappliedPatch::ShadowedByCustomPage {
+                patch_path,
+                custom_page_path,
+            })
+        } else if self.find_upstream_page(command).is_none() {
+            Some(UnappliedPatch::NoUpstreamPage { patch_path })
+        } else {
+            None
+        }
+    }
+
+    /// Return the path of the page for `command` in the cache, ignoring custom pages and patches.
+    pub fn find_upstream_page(&self, command: &str) -> Option<PathBuf> {
+        self.probe_page_paths(self.config.pages_directory, command, Path::is_file)
+            .or_else(|| {
+                self.config
+                    .system_pages_directory
+                    .and_then(|directory| self.probe_page_paths(directory, command, Path::is_file))
+            })
+    }
+
+    /// Return the page for `command` in the cache for each searched platform that has one, in
+    /// order of preference, ignoring custom pages and patches. The language of each page is
+    /// chosen like with [`Cache::find_upstream_page`].
+    pub fn find_upstream_pages(&self, command: &str) -> Vec<(PlatformType, PathBuf)> {
+        self.config
+            .platforms
+            .iter()
+            .filter_map(|&platform| {
+                self.with_platforms(&[platform])
+                    .find_upstream_page(command)
+                    .map(|page| (platform, page))
+            })
+            .collect()
+    }
+
+    /// Return the names of the platforms for which a page for `command` exists, as used for the
+    /// directories of the tldr pages (e.g. `osx`). They are taken from `index` if the cache has
+    /// one, and from the pages in the cache for any platform otherwise. Custom pages are ignored.
+    pub fn page_platforms(&self, command: &str, index: Option<&PagesIndex>) -> Vec<String> {
+        if let Some(index) = index {
+            let mut platforms: Vec<String> = Vec::new();
+            for target in index.targets(command).unwrap_or_default() {
+                if !platforms.contains(&target.os) {
+                    platforms.push(target.os.clone());
+                }
+            }
+            return platforms;
+        }
+
+        Cache {
+            config: CacheConfig {
+                platforms: PlatformType::value_variants(),
+                ..self.config.clone()
+            },
+        }
+        .find_upstream_pages(command)
+        .into_iter()
+        .map(|(platform, _)| platform.directory_name().to_string())
+        .collect()
+    }
+
+    /// Return the first path `<directory>/pages.<language>/<platform>/<command>.md` that `accept`
+    /// returns true for, trying platforms and languages in order of preference.
+    ///
+    /// A single buffer is reused for all candidates, because this is on the hot path of every
+    /// page lookup and there can be many combinations of platforms and languages.
+    fn probe_page_paths(
+        &self,
+        directory: &Path,
+        command: &str,
+        mut accept: impl FnMut(&Path) -> bool,
+    ) -> Option<PathBuf> {
+        let mut path = PathBuf::with_capacity(directory.as_os_str().len() + command.len() + 32);
 
         for &platform in self.config.platforms {
             for language in self.config.search_languages {
-                let mut search_path = self.config.pages_directory.to_path_buf();
-                search_path.push(language.directory_name());
-                search_path.push(platform.directory_name());
-                search_path.push(&page_filename);
+                path.as_mut_os_string().clear();
+                path.push(directory);
+                language.push_directory_name(&mut path);
+                path.push(platform.directory_name());
+                path.push(command);
+                path.as_mut_os_string().push(".md");
 
-                if search_path.is_file() {
-                    return Some(
-                        PageLookupResult::with_page(search_path).with_optional_patch(patch_path),
-                    );
+                if accept(&path) {
+                    return Some(path);
                 }
             }
         }
@@ -128,6 +1236,169 @@ impl<'a> Cache<'a> {
         None
     }
 
+    /// Return the path of a page for `command` in `fetched_directory` (see [`Cache::fetch_page`])
+    /// that was fetched less than `max_age` ago.
+    pub fn find_fetched_page(
+        &self,
+        fetched_directory: &Path,
+        command: &str,
+        max_age: Duration,
+    ) -> Option<PathBuf> {
+        self.probe_page_paths(fetched_directory, command, |path| {
+            let Ok(modified) = path.metadata().and_then(|md| md.modified()) else {
+                return false;
+            };
+            let age = SystemTime::now()
+                .duration_since(modified)
+                .unwrap_or_default();
+            age < max_age
+        })
+    }
+
+    /// Download only the page for `command` from `pages_source` (a copy of the tldr repository
+    /// layout, e.g. `https://raw.githubusercontent.com/tldr-pages/tldr/main`) and store it in
+    /// `fetched_directory`. Platforms and languages are tried in the same order as when searching
+    /// the cache. If no page is found, `Ok(None)` is returned.
+    pub fn fetch_page(
+        &self,
+        fetched_directory: &Path,
+        command: &str,
+        pages_source: &str,
+        tls_backend: TlsBackend,
+    ) -> Result<Option<PathBuf>> {
+        let client = Self::build_client(tls_backend);
+        for &platform in self.config.platforms {
+            for language in self.config.search_languages {
+                let page = Self::fetch_page_into(
+                    &client,
+                    fetched_directory,
+                    command,
+                    pages_source,
+                    *language,
+                    platform,
+                )?;
+                if page.is_some() {
+                    return Ok(page);
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Download the page for `command` in `language` and for `platform` from `pages_source` and
+    /// store it in `directory`, using the same layout as the pages directory.
+    fn fetch_page_into(
+        client: &Agent,
+        directory: &Path,
+        command: &str,
+        pages_source: &str,
+        language: Language<'_>,
+        platform: PlatformType,
+    ) -> Result<Option<PathBuf>> {
+        validate_page_name(command)?;
+        let page_filename = format!("{command}.md");
+        let url = format!(
+            "{pages_source}/{}/{}/{page_filename}",
+            language.upstream_directory_name(),
+            platform.directory_name(),
+        );
+        let Some(page) = Self::download_bytes(client, &url)? else {
+            return Ok(None);
+        };
+
+        let directory = directory
+            .join(language.directory_name())
+            .join(platform.directory_name());
+        fs::create_dir_all(&directory)
+            .with_context(|| format!("Could not create directory {}", directory.display()))?;
+        let path = directory.join(&page_filename);
+        write_atomically(&path, page)
+            .with_context(|| format!("Could not write page to {}", path.display()))?;
+        Ok(Some(path))
+    }
+
+    /// Read the index of the tldr pages from the cache. Returns `Ok(None)` if the cache is not
+    /// updated from the index.
+    pub fn index(&self) -> Result<Option<PagesIndex>> {
+        let path = self.config.pages_directory.join(INDEX_FILE);
+        let content = match fs::read(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Could not read {}", path.display())),
+        };
+        let index = serde_json::from_slice(&content)
+            .with_context(|| format!("Invalid index of the tldr pages at {}", path.display()))?;
+        Ok(Some(index))
+    }
+
+    /// Download the index of the tldr pages from `index_url` and replace the pages directory with
+    /// it. The pages themselves are only fetched when they are looked up, see
+    /// [`Cache::fetch_indexed_page`].
+    pub fn update_index(
+        &mut self,
+        index_url: &str,
+        tls_backend: TlsBackend,
+    ) -> Result<DownloadedIndex> {
+        let client = Self::build_client(tls_backend);
+        let content = Self::download_bytes(&client, index_url)?
+            .with_context(|| format!("No index of the tldr pages found at {index_url}"))?;
+        let index: PagesIndex = serde_json::from_slice(&content)
+            .with_context(|| format!("Invalid index of the tldr pages at {index_url}"))?;
+
+        // Previously fetched pages might be outdated now, so they are fetched again on demand
+        self.reset_pages_directory()?;
+        let path = self.config.pages_directory.join(INDEX_FILE);
+        write_atomically(&path, &content)
+            .with_context(|| format!("Could not write index to {}", path.display()))?;
+
+        self.write_last_update()?;
+        Ok(DownloadedIndex {
+            commands: index.commands.len(),
+            bytes: content.len() as u64,
+        })
+    }
+
+    /// Download the page for `command` from `pages_source` into the pages directory, if the index
+    /// lists it for one of the configured platforms and languages. These are tried in the same
+    /// order as when searching the cache, so that the page is found there from now on.
+    pub fn fetch_indexed_page(
+        &self,
+        index: &PagesIndex,
+        command: &str,
+        pages_source: &str,
+        tls_backend: TlsBackend,
+    ) -> Result<Option<PathBuf>> {
+        let Some(targets) = index.targets(command) else {
+            return Ok(None);
+        };
+
+        let client = Self::build_client(tls_backend);
+        for &platform in self.config.platforms {
+            for language in self.config.search_languages {
+                let is_available = targets.iter().any(|target| {
+                    target.os == platform.directory_name() && target.language == language.0
+                });
+                if !is_available {
+                    continue;
+                }
+                let page = Self::fetch_page_into(
+                    &client,
+                    self.config.pages_directory,
+                    command,
+                    pages_source,
+                    *language,
+                    platform,
+                )?;
+                if page.is_some() {
+                    return Ok(page);
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
     pub fn list_pages(&self) -> Result<impl IntoIterator<Item = String>> {
         let mut pages = Vec::new();
 
@@ -156,83 +1427,481 @@ impl<'a> Cache<'a> {
                 }
             }
 
-            Ok(())
+            Ok(())
+        };
+
+        let pages_directories = [
+            Some(self.config.pages_directory),
+            self.config.system_pages_directory,
+        ];
+        for pages_directory in pages_directories.into_iter().flatten() {
+            let mut search_path = pages_directory.to_path_buf();
+            for language in self.config.search_languages {
+                search_path.push(language.directory_name());
+                for platform in self.config.platforms {
+                    search_path.push(platform.directory_name());
+                    append_all(&search_path, ".md")?;
+                    search_path.pop();
+                }
+                search_path.pop();
+            }
+        }
+
+        if let Some(custom_pages_dir) = self.config.custom_pages_directory {
+            append_all(custom_pages_dir, ".page.md")?;
+        }
+
+        pages.sort_unstable();
+        pages.dedup();
+        Ok(pages)
+    }
+
+    /// The pages that are listed in the index of the tldr pages (see [`Cache::index`]) for the
+    /// configured platforms and languages, including the ones that were not fetched yet.
+    pub fn list_indexed_pages(&self) -> Result<Vec<String>> {
+        let Some(index) = self.index()? else {
+            return Ok(Vec::new());
+        };
+        Ok(index
+            .commands
+            .into_iter()
+            .filter(|command| {
+                command.targets.iter().any(|target| {
+                    self.config
+                        .platforms
+                        .iter()
+                        .any(|platform| target.os == platform.directory_name())
+                        && self
+                            .config
+                            .search_languages
+                            .iter()
+                            .any(|language| target.language == language.0)
+                })
+            })
+            .map(|command| command.name)
+            .collect())
+    }
+
+    pub fn old_custom_pages_exist(&self) -> Result<bool> {
+        let Some(directory) = self.config.custom_pages_directory else {
+            return Ok(false);
+        };
+        if !directory.is_dir() {
+            return Ok(false);
+        }
+        Ok(!find_old_custom_pages(directory)?.is_empty())
+    }
+
+    /// Hash the contents of all pages in the cache, to compare them with the pages after an
+    /// update.
+    pub fn page_hashes(&self) -> Result<PageHashes> {
+        let mut hashes = BTreeMap::new();
+        for language in self.cached_languages()? {
+            let language_directory = format!("pages.{language}");
+            for platform_entry in
+                fs::read_dir(self.config.pages_directory.join(&language_directory))?
+            {
+                let platform_entry = platform_entry?;
+                if !platform_entry.file_type()?.is_dir() {
+                    continue;
+                }
+                let platform = platform_entry.file_name().to_string_lossy().into_owned();
+                for page_entry in fs::read_dir(platform_entry.path())? {
+                    let page_entry = page_entry?;
+                    let path = page_entry.path();
+                    if path.extension().is_none_or(|extension| extension != "md") {
+                        continue;
+                    }
+                    let file_name = page_entry.file_name().to_string_lossy().into_owned();
+                    let mut hasher = DefaultHasher::new();
+                    fs::read(&path)?.hash(&mut hasher);
+                    hashes.insert(
+                        format!("{language_directory}/{platform}/{file_name}"),
+                        hasher.finish(),
+                    );
+                }
+            }
+        }
+        Ok(PageHashes(hashes))
+    }
+
+    /// Return the languages for which pages are present in the cache, sorted by name.
+    pub fn cached_languages(&self) -> Result<Vec<String>> {
+        Self::languages_in(self.config.pages_directory)
+    }
+
+    /// Return the languages for which pages are present in the cache or in the system pages,
+    /// sorted by name.
+    pub fn available_languages(&self) -> Result<Vec<String>> {
+        let mut languages = self.cached_languages()?;
+        if let Some(system_pages_directory) = self.config.system_pages_directory {
+            if system_pages_directory.is_dir() {
+                languages.extend(Self::languages_in(system_pages_directory)?);
+                languages.sort_unstable();
+                languages.dedup();
+            }
+        }
+        Ok(languages)
+    }
+
+    fn languages_in(pages_directory: &Path) -> Result<Vec<String>> {
+        let mut languages = Vec::new();
+        // There is no pages directory if only system pages were used so far
+        if !pages_directory.exists() {
+            return Ok(languages);
+        }
+        for entry in fs::read_dir(pages_directory)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+            if let Some(language) = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_prefix("pages."))
+            {
+                languages.push(language.to_string());
+            }
+        }
+        languages.sort_unstable();
+        Ok(languages)
+    }
+
+    /// Return the languages for which pages are present in the cache, but which are not in
+    /// `self.config().download_languages`.
+    pub fn orphaned_languages(&self) -> Result<Vec<String>> {
+        let mut languages = self.cached_languages()?;
+        languages.retain(|cached| {
+            !self
+                .config
+                .download_languages
+                .iter()
+                .any(|lang| lang.0 == cached)
+        });
+        Ok(languages)
+    }
+
+    /// Remove the pages of all orphaned languages (see [`Cache::orphaned_languages`]) from the
+    /// cache and return the removed languages.
+    pub fn prune_languages(&self) -> Result<Vec<String>> {
+        let languages = self.orphaned_languages()?;
+        for language in &languages {
+            self.remove_language(Language(language))?;
+        }
+        Ok(languages)
+    }
+
+    /// Remove the pages of `language` from the cache. Returns whether there were any.
+    pub fn remove_language(&self, language: Language) -> Result<bool> {
+        let path = self.config.pages_directory.join(language.directory_name());
+        if !path.exists() {
+            return Ok(false);
+        }
+        fs::remove_dir_all(&path)
+            .with_context(|| format!("Could not remove {}", path.display()))?;
+        Ok(true)
+    }
+
+    /// Record that a page in `page_path` was looked up, so that its language and platform are
+    /// evicted last by [`Cache::evict`]. Pages outside of the pages directory are ignored, and
+    /// lookups within [`ACCESS_TIME_RESOLUTION`] of the recorded one aren't written.
+    pub fn record_access(&self, page_path: &Path) -> Result<()> {
+        let Some(directory) = page_path
+            .strip_prefix(self.config.pages_directory)
+            .ok()
+            .and_then(|relative| relative.parent())
+            .and_then(Path::to_str)
+        else {
+            return Ok(());
         };
+        let directory = directory.replace('\\', "/");
 
-        let mut search_path = self.config.pages_directory.to_path_buf();
-        for language in self.config.search_languages {
-            search_path.push(language.directory_name());
-            for platform in self.config.platforms {
-                search_path.push(platform.directory_name());
-                append_all(&search_path, ".md")?;
-                search_path.pop();
-            }
-            search_path.pop();
+        let seconds = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .context("The current time is before the Unix epoch")?
+            .as_secs();
+        let mut access_times = self.read_access_times();
+        if access_times
+            .get(&directory)
+            .is_some_and(|&recorded| seconds.saturating_sub(recorded) < ACCESS_TIME_RESOLUTION)
+        {
+            return Ok(());
         }
+        access_times.insert(directory, seconds);
 
-        if let Some(custom_pages_dir) = self.config.custom_pages_directory {
-            append_all(custom_pages_dir, ".page.md")?;
+        let path = self.cache_state_path(ACCESS_TIMES_FILE);
+        Self::create_state_directory(&path)?;
+        let mut access_times: Vec<_> = access_times.into_iter().collect();
+        access_times.sort_unstable();
+        let mut content = String::new();
+        for (directory, seconds) in access_times {
+            content.push_str(&seconds.to_string());
+            content.push('\t');
+            content.push_str(&directory);
+            content.push('\n');
         }
-
-        pages.sort_unstable();
-        pages.dedup();
-        Ok(pages)
+        write_atomically(&path, content)
+            .with_context(|| format!("Could not write {}", path.display()))
     }
 
-    pub fn old_custom_pages_exist(&self) -> Result<bool> {
-        let Some(directory) = self.config.custom_pages_directory else {
-            return Ok(false);
-        };
-        let Ok(file_iter) = fs::read_dir(directory) else {
-            return Ok(false);
+    /// The times of the last lookups (in seconds since the Unix epoch) by directory, see
+    /// [`ACCESS_TIMES_FILE`]. Lines that cannot be parsed are skipped.
+    fn read_access_times(&self) -> HashMap<String, u64> {
+        let path = self.cache_state_path(ACCESS_TIMES_FILE);
+        let Ok(content) = fs::read_to_string(&path) else {
+            return HashMap::new();
         };
+        content
+            .lines()
+            .filter_map(|line| {
+                let (seconds, directory) = line.split_once('\t')?;
+                Some((directory.to_string(), seconds.parse().ok()?))
+            })
+            .collect()
+    }
+
+    /// The directories removed by [`Cache::evict`], see [`EVICTED_PAGES_FILE`].
+    fn read_evicted_pages(&self) -> Vec<String> {
+        let path = self.cache_state_path(EVICTED_PAGES_FILE);
+        fs::read_to_string(path)
+            .map(|content| content.lines().map(str::to_string).collect())
+            .unwrap_or_default()
+    }
+
+    /// Forget the directories removed by [`Cache::evict`], so that the next update downloads them
+    /// again, e.g. after the size of the cache is no longer limited.
+    pub fn forget_evicted_pages(&self) -> Result<()> {
+        self.remove_state_file(EVICTED_PAGES_FILE)
+    }
+
+    /// Remove the pages of the least recently looked up languages and platforms from the cache
+    /// until it is no larger than `max_size` bytes. Directories that were never looked up are
+    /// removed first, the common pages of the first download language are never removed. Returns
+    /// the removed directories, in the order they were removed.
+    ///
+    /// The removed directories are recorded in [`EVICTED_PAGES_FILE`] and skipped by later
+    /// updates.
+    pub fn evict(&self, max_size: u64) -> Result<Vec<EvictedPages>> {
+        let protected = self.config.download_languages.first().map(|language| {
+            format!(
+                "{}/{}",
+                language.directory_name(),
+                PlatformType::Common.directory_name()
+            )
+        });
+        let mut candidates = Vec::new();
+        let mut total_size = 0;
+        for language in self.cached_languages()? {
+            let language_directory = self
+                .config
+                .pages_directory
+                .join(Language(&language).directory_name());
+            for entry in fs::read_dir(&language_directory)? {
+                let entry = entry?;
+                let size = directory_size(&entry.path())?;
+                total_size += size;
+                let directory = format!(
+                    "{}/{}",
+                    Language(&language).directory_name(),
+                    entry.file_name().to_string_lossy(),
+                );
+                if entry.file_type()?.is_dir() && protected.as_ref() != Some(&directory) {
+                    candidates.push(EvictedPages { directory, size });
+                }
+            }
+        }
+
+        let access_times = self.read_access_times();
+        candidates.sort_by_cached_key(|candidate| {
+            (
+                access_times.get(&candidate.directory).copied().unwrap_or(0),
+                candidate.directory.clone(),
+            )
+        });
 
-        for entry in file_iter {
-            if let Some(extension) = entry?.path().extension() {
-                if extension == "page" || extension == "patch" {
-                    return Ok(true);
+        let mut evicted = Vec::new();
+        for candidate in candidates {
+            if total_size <= max_size {
+                break;
+            }
+            let path = self.config.pages_directory.join(&candidate.directory);
+            fs::remove_dir_all(&path)
+                .with_context(|| format!("Could not remove {}", path.display()))?;
+            total_size -= candidate.size;
+
+            // Don't leave languages without any platforms behind
+            if let Some(language_directory) = path.parent() {
+                if fs::read_dir(language_directory)?.next().is_none() {
+                    fs::remove_dir(language_directory).with_context(|| {
+                        format!("Could not remove {}", language_directory.display())
+                    })?;
                 }
             }
+            evicted.push(candidate);
         }
 
-        Ok(false)
+        if !evicted.is_empty() {
+            let mut evicted_pages = self.read_evicted_pages();
+            for pages in &evicted {
+                if !evicted_pages.contains(&pages.directory) {
+                    evicted_pages.push(pages.directory.clone());
+                }
+            }
+            let path = self.cache_state_path(EVICTED_PAGES_FILE);
+            Self::create_state_directory(&path)?;
+            let mut content = evicted_pages.join("\n");
+            content.push('\n');
+            write_atomically(&path, content)
+                .with_context(|| format!("Could not write {}", path.display()))?;
+        }
+        Ok(evicted)
     }
 
-    pub fn clear(self) -> Result<()> {
-        fs::remove_dir_all(self.config.pages_directory).with_context(|| {
+    /// Create the pages directory, which doesn't exist yet if only system pages were used so far.
+    fn create_pages_directory(&self) -> Result<()> {
+        fs::create_dir_all(self.config.pages_directory).with_context(|| {
             format!(
-                "Could not remove pages directory at {}",
+                "Cache directory `{}` cannot be created",
                 self.config.pages_directory.display(),
             )
         })
     }
 
+    /// Remove all pages from the pages directory, or create it if it doesn't exist.
+    fn reset_pages_directory(&self) -> Result<()> {
+        if self.config.pages_directory.exists() {
+            fs::remove_dir_all(self.config.pages_directory)?;
+        }
+        self.create_pages_directory()
+    }
+
+    pub fn clear(self) -> Result<()> {
+        if self.config.pages_directory.exists() {
+            fs::remove_dir_all(self.config.pages_directory).with_context(|| {
+                format!(
+                    "Could not remove pages directory at {}",
+                    self.config.pages_directory.display(),
+                )
+            })?;
+        }
+
+        for source in self.config.sources {
+            if let PageSourceKind::TldrArchive { directory, .. } = &source.kind {
+                if directory.exists() {
+                    fs::remove_dir_all(directory).with_context(|| {
+                        format!(
+                            "Could not remove the pages of the `{}` source at {}",
+                            source.name,
+                            directory.display(),
+                        )
+                    })?;
+                }
+            }
+        }
+
+        let last_update = self.cache_state_path(LAST_UPDATE_FILE);
+        if last_update.exists() {
+            fs::remove_file(&last_update)
+                .with_context(|| format!("Could not remove {}", last_update.display()))?;
+        }
+        Ok(())
+    }
+
     /// Download archives for the languages in `self.config().download_languages` and replace the
     /// pages directory with the newly downloaded pages. As not all languages might have pages
-    /// available (for example, `en_US` instead of `en`), an iterator yielding all languages which
-    /// were successfully downloaded is returned.
+    /// available (for example, `en_US` instead of `en`), the languages which were successfully
+    /// downloaded are returned.
+    ///
+    /// Nothing is changed if a download fails. Errors caused by an unreachable network can be told
+    /// apart from other errors with [`is_network_unreachable`].
     pub fn update(
         &mut self,
-        archive_url: &str,
+        archive_source: &str,
+        archive_format: ArchiveFormat,
+        tls_backend: TlsBackend,
+        public_key: Option<&PublicKey>,
+    ) -> Result<DownloadedPages<'a>> {
+        let downloaded = self.download_languages(
+            self.config.download_languages,
+            archive_source,
+            archive_format,
+            tls_backend,
+            public_key,
+            true,
+        )?;
+        self.write_last_update()?;
+        Ok(downloaded)
+    }
+
+    /// Download archives for `languages` and replace only their directories in the cache, e.g.
+    /// to repair the problems found by [`Cache::check`]. Returns the languages which were
+    /// successfully downloaded.
+    pub fn repair(
+        &mut self,
+        languages: &[Language<'a>],
+        archive_source: &str,
+        archive_format: ArchiveFormat,
+        tls_backend: TlsBackend,
+        public_key: Option<&PublicKey>,
+    ) -> Result<Vec<Language<'a>>> {
+        self.download_languages(
+            languages,
+            archive_source,
+            archive_format,
+            tls_backend,
+            public_key,
+            false,
+        )
+        .map(|downloaded| downloaded.languages)
+    }
+
+    /// Download and extract the archives for `languages`. If `replace_all` is true, the whole
+    /// pages directory is replaced, otherwise only the directories of the downloaded languages.
+    ///
+    /// If a `public_key` is given, the archives are only extracted if their minisign signature
+    /// (at the URL of the archive with `.minisig` appended) was made with it.
+    fn download_languages(
+        &mut self,
+        languages: &[Language<'a>],
+        archive_source: &str,
+        archive_format: ArchiveFormat,
         tls_backend: TlsBackend,
-    ) -> Result<impl IntoIterator<Item = Language<'_>>> {
+        public_key: Option<&PublicKey>,
+        replace_all: bool,
+    ) -> Result<DownloadedPages<'a>> {
+        check_archive_source(archive_source)?;
         let client = Self::build_client(tls_backend);
 
-        // Download everything before deleting anything
-        let mut archives = self
+        // The archives are stored next to the pages directory instead of the system's temporary
+        // directory, which is often backed by memory on small devices.
+        let temp_directory = self
             .config
-            .download_languages
+            .pages_directory
+            .parent()
+            .unwrap_or(self.config.pages_directory);
+
+        // Download everything before deleting anything
+        let archives = languages
             .iter()
             .map(|&lang| {
-                Ok((
-                    lang,
-                    Self::download(
-                        &client,
-                        &format!("{archive_url}/tldr-{}.zip", lang.directory_name()),
-                    )?
-                    .map(|bytes| ZipArchive::new(Cursor::new(bytes)))
-                    .transpose()?,
-                ))
+                let archive_url = lang.archive_url(archive_source, archive_format);
+                let download = Self::download(&client, &archive_url, temp_directory)?;
+                let archive = download
+                    .map(|mut download| -> Result<_> {
+                        if let Some(public_key) = public_key {
+                            Self::verify_signature(
+                                &client,
+                                &archive_url,
+                                &mut download.file,
+                                public_key,
+                            )?;
+                        }
+                        let archive = Archive::open(download.file)
+                            .with_context(|| format!("Invalid archive at {archive_url}"))?;
+                        Ok((archive, download.etag, download.size))
+                    })
+                    .transpose()?;
+                Ok((lang, archive))
             })
             .collect::<Result<Vec<_>>>()?;
 
@@ -242,33 +1911,199 @@ impl<'a> Cache<'a> {
         // But renaming a directory doesn't work across filesystems and Rust
         // does not yet offer a recursive directory copying function. So for
         // now, we'll use this approach.
-        fs::remove_dir_all(self.config.pages_directory)?;
-        fs::create_dir(self.config.pages_directory)?;
+        if replace_all {
+            self.reset_pages_directory()?;
+        }
 
-        for (lang, archive) in &mut archives {
-            if let Some(archive) = archive {
+        let evicted = self.read_evicted_pages();
+        let mut downloaded = DownloadedPages::default();
+        for (lang, archive) in archives {
+            if let Some((archive, etag, size)) = archive {
                 info!("Extracting archive for {lang:?}");
-                archive.extract(self.config.pages_directory.join(lang.directory_name()))?;
+                let language_directory = self.config.pages_directory.join(lang.directory_name());
+                if !replace_all && language_directory.exists() {
+                    fs::remove_dir_all(&language_directory)?;
+                }
+                let skipped: Vec<&str> = evicted
+                    .iter()
+                    .filter_map(|directory| {
+                        directory
+                            .strip_prefix(&lang.directory_name())?
+                            .strip_prefix('/')
+                    })
+                    .collect();
+                archive.extract(
+                    &language_directory,
+                    self.config.download_platforms,
+                    &skipped,
+                )?;
+                self.write_etag(lang, etag.as_deref())?;
+                downloaded.languages.push(lang);
+                downloaded.bytes += size;
             } else {
                 info!("No archive found for {lang:?}");
             }
         }
 
-        Ok(archives
-            .into_iter()
-            .filter_map(|(lang, archive)| archive.is_some().then_some(lang)))
+        Ok(downloaded)
+    }
+
+    /// Verify the minisign signature of the downloaded archive from `archive_url` in `file`.
+    fn verify_signature(
+        client: &Agent,
+        archive_url: &str,
+        file: &mut File,
+        public_key: &PublicKey,
+    ) -> Result<()> {
+        let signature_url = format!("{archive_url}.minisig");
+        let signature = Self::download_bytes(client, &signature_url)?
+            .with_context(|| format!("No signature found at {signature_url}"))?;
+        let signature = String::from_utf8(signature)
+            .with_context(|| format!("Invalid signature at {signature_url}"))?;
+        file.rewind()?;
+        public_key
+            .verify(&mut *file, &signature)
+            .with_context(|| format!("Could not verify the signature of {archive_url}"))?;
+        file.rewind()?;
+        Ok(())
+    }
+
+    /// Check the pages of the languages in `self.config().download_languages` for problems, like
+    /// the ones left behind by an interrupted update.
+    pub fn check(&self) -> Result<Vec<CacheProblem<'a>>> {
+        let mut problems = Vec::new();
+
+        for &language in self.config.download_languages {
+            let language_directory = self.config.pages_directory.join(language.directory_name());
+            if !language_directory.is_dir() {
+                problems.push(CacheProblem::MissingLanguage(language));
+                continue;
+            }
+
+            let mut pages = 0;
+            let mut empty_pages = 0;
+            for platform_entry in fs::read_dir(&language_directory)? {
+                let platform_entry = platform_entry?;
+                if !platform_entry.file_type()?.is_dir() {
+                    continue;
+                }
+                for page_entry in fs::read_dir(platform_entry.path())? {
+                    let page_entry = page_entry?;
+                    if page_entry.path().extension().is_some_and(|ext| ext == "md") {
+                        pages += 1;
+                        if page_entry.metadata()?.len() == 0 {
+                            empty_pages += 1;
+                        }
+                    }
+                }
+            }
+
+            if pages == 0 {
+                problems.push(CacheProblem::EmptyLanguage(language));
+            } else if empty_pages > 0 {
+                problems.push(CacheProblem::EmptyPages {
+                    language,
+                    count: empty_pages,
+                });
+            }
+        }
+
+        Ok(problems)
+    }
+
+    /// Check whether the upstream archives for the languages in
+    /// `self.config().download_languages` differ from the ones in the cache, without downloading
+    /// them. This compares the `ETag` headers of the archives with the ones stored when updating.
+    pub fn check_for_updates(
+        &self,
+        archive_source: &str,
+        archive_format: ArchiveFormat,
+        tls_backend: TlsBackend,
+    ) -> Result<Vec<(Language<'a>, LanguageStatus)>> {
+        check_archive_source(archive_source)?;
+        let client = Self::build_client(tls_backend);
+
+        self.config
+            .download_languages
+            .iter()
+            .map(|&lang| {
+                let url = lang.archive_url(archive_source, archive_format);
+                info!("Checking archive at {url}");
+                let remote_etag = if let Some(path) = file_url_path(&url) {
+                    match open_local_file(path)? {
+                        Some(file) => local_etag(&file.metadata()?),
+                        None => return Ok((lang, LanguageStatus::NotAvailable)),
+                    }
+                } else {
+                    match client.head(&url).call() {
+                        Ok(response) if response.status().is_success() => response
+                            .headers()
+                            .get("etag")
+                            .and_then(|etag| etag.to_str().ok())
+                            .map(str::to_string),
+                        Ok(response) if response.status() == StatusCode::NOT_FOUND => {
+                            return Ok((lang, LanguageStatus::NotAvailable));
+                        }
+                        Ok(response) => {
+                            return Err(ureq::Error::StatusCode(response.status().as_u16()))
+                                .with_context(|| format!("Could not check tldr pages at {url}"));
+                        }
+                        Err(e) => {
+                            return Err(e)
+                                .with_context(|| format!("Could not check tldr pages at {url}"));
+                        }
+                    }
+                };
+
+                let language_directory = self.config.pages_directory.join(lang.directory_name());
+                if !language_directory.is_dir() {
+                    return Ok((lang, LanguageStatus::NotCached));
+                }
+                let local_etag = fs::read_to_string(self.etag_path(lang)).ok();
+                let status = match (local_etag, remote_etag) {
+                    (Some(local), Some(remote)) if local == remote => LanguageStatus::UpToDate,
+                    (Some(_), Some(_)) => LanguageStatus::Outdated,
+                    _ => LanguageStatus::Unknown,
+                };
+                Ok((lang, status))
+            })
+            .collect()
     }
 
     pub fn config(&self) -> &CacheConfig<'a> {
         &self.config
     }
+
+    /// The path of the file in the state directory that stores the `ETag` of the archive for
+    /// `language`.
+    fn etag_path(&self, language: Language) -> PathBuf {
+        self.cache_state_path(ETAGS_DIR)
+            .join(language.directory_name())
+    }
+
+    /// Store the `ETag` of the archive for `language`, or remove the stored one if the server
+    /// did not send one.
+    fn write_etag(&self, language: Language, etag: Option<&str>) -> Result<()> {
+        let path = self.etag_path(language);
+        if let Some(etag) = etag {
+            Self::create_state_directory(&path)?;
+            write_atomically(&path, etag)
+                .with_context(|| format!("Could not write {}", path.display()))?;
+        } else if path.exists() {
+            fs::remove_file(&path)
+                .with_context(|| format!("Could not remove {}", path.display()))?;
+        }
+        Ok(())
+    }
 }
 
 impl PageLookupResult {
     pub fn with_page(page_path: PathBuf) -> Self {
         Self {
-            page_path,
+            page: PageContent::File(page_path),
             patch_path: None,
+            custom_page: false,
+            expand_templates: false,
         }
     }
 
@@ -277,6 +2112,13 @@ impl PageLookupResult {
         self
     }
 
+    /// Expand template variables (like `{hostname}`) in the custom page or patch when reading
+    /// them, if `enabled`. Pages from the tldr pages and other sources are never expanded.
+    pub fn with_expanded_templates(mut self, enabled: bool) -> Self {
+        self.expand_templates = enabled;
+        self
+    }
+
     /// Create a reader that sequentially reads from the page and the
     /// patch, as if they were concatenated.
     ///
@@ -284,15 +2126,25 @@ impl PageLookupResult {
     /// cannot be opened.
     pub fn reader(&self) -> Result<Box<dyn Read>> {
         // Open page file
-        let page_file = File::open(&self.page_path)
-            .with_context(|| format!("Could not open page file at {}", self.page_path.display()))?;
-
-        // Open patch file
-        let patch_file_opt = match &self.patch_path {
-            Some(path) => Some(
+        let page_file: Box<dyn Read> = match &self.page {
+            PageContent::File(path) if self.custom_page && self.expand_templates => {
+                Box::new(Self::read_expanded(path, "page")?)
+            }
+            PageContent::File(path) => Box::new(
                 File::open(path)
-                    .with_context(|| format!("Could not open patch file at {}", path.display()))?,
+                    .with_context(|| format!("Could not open page file at {}", path.display()))?,
             ),
+            PageContent::Converted(page) => Box::new(io::Cursor::new(page.clone().into_bytes())),
+        };
+
+        // Open patch file
+        let patch_file_opt: Option<Box<dyn Read>> = match &self.patch_path {
+            Some(path) if self.expand_templates => {
+                Some(Box::new(Self::read_expanded(path, "patch")?))
+            }
+            Some(path) => Some(Box::new(File::open(path).with_context(|| {
+                format!("Could not open patch file at {}", path.display())
+            })?)),
             None => None,
         };
 
@@ -308,12 +2160,46 @@ impl PageLookupResult {
             Box::new(page_file) as Box<dyn Read>
         })
     }
+
+    /// Read the `kind` (page or patch) file at `path` with its template variables expanded.
+    fn read_expanded(path: &Path, kind: &str) -> Result<io::Cursor<Vec<u8>>> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Could not open {kind} file at {}", path.display()))?;
+        Ok(io::Cursor::new(
+            expand_templates(&content).into_owned().into_bytes(),
+        ))
+    }
 }
 
 impl Language<'_> {
     fn directory_name(&self) -> String {
         format!("pages.{}", self.0)
     }
+
+    /// Push the [`directory_name`](Self::directory_name) onto `path` without allocating.
+    fn push_directory_name(&self, path: &mut PathBuf) {
+        path.push("pages");
+        path.as_mut_os_string().push(".");
+        path.as_mut_os_string().push(self.0);
+    }
+
+    /// The URL of the archive for this language in `archive_source`.
+    fn archive_url(&self, archive_source: &str, archive_format: ArchiveFormat) -> String {
+        format!(
+            "{archive_source}/tldr-{}.{}",
+            self.directory_name(),
+            archive_format.extension(),
+        )
+    }
+
+    /// The name of the pages directory in the tldr repository, where English pages are in `pages`.
+    fn upstream_directory_name(&self) -> String {
+        if self.0 == "en" {
+            "pages".to_string()
+        } else {
+            self.directory_name()
+        }
+    }
 }
 
 impl PlatformType {
@@ -333,7 +2219,7 @@ impl PlatformType {
 }
 
 impl Cache<'_> {
-    fn build_client(tls_backend: TlsBackend) -> Agent {
+    pub(crate) fn build_client(tls_backend: TlsBackend) -> Agent {
         let tls_builder = match tls_backend {
             #[cfg(feature = "native-tls")]
             TlsBackend::NativeTls => TlsConfig::builder()
@@ -346,7 +2232,7 @@ impl Cache<'_> {
             #[cfg(feature = "rustls-with-native-roots")]
             TlsBackend::RustlsWithNativeRoots => TlsConfig::builder()
                 .provider(TlsProvider::Rustls)
-                .root_certs(RootCerts::PlatformVerifier),
+                .root_certs(termux_root_certs().unwrap_or(RootCerts::PlatformVerifier)),
         };
         let config = Agent::config_builder()
             .http_status_as_error(false) // because we want to handle them
@@ -356,22 +2242,108 @@ impl Cache<'_> {
         config.into()
     }
 
-    /// Download the archive from the specified URL.
-    fn download(client: &Agent, archive_url: &str) -> Result<Option<Vec<u8>>> {
-        info!("Downloading archive from {archive_url}");
-        let response = client.get(archive_url).call();
-        match response {
-            Ok(response) if response.status().is_success() => {
-                let mut buf: Vec<u8> = Vec::new();
-                response.into_body().into_reader().read_to_end(&mut buf)?;
-                debug!("{} bytes downloaded", buf.len());
-                Ok(Some(buf))
-            }
+    /// Download a single page from `url`, e.g. to render it without adding it to the cache.
+    pub fn download_page(url: &str, tls_backend: TlsBackend) -> Result<Vec<u8>> {
+        let client = Self::build_client(tls_backend);
+        Self::download_bytes(&client, url)?.with_context(|| format!("No page found at {url}"))
+    }
+
+    /// Send a GET request to `url`. If nothing is found at `url`, `Ok(None)` is returned.
+    fn get(client: &Agent, url: &str) -> Result<Option<ureq::http::Response<Body>>> {
+        info!("Downloading from {url}");
+        match client.get(url).call() {
+            Ok(response) if response.status().is_success() => Ok(Some(response)),
             Ok(response) if response.status() == StatusCode::NOT_FOUND => Ok(None),
-            _ => {
-                bail!("Could not download tldr pages from {archive_url}: {response:?}")
+            Ok(response) => Err(ureq::Error::StatusCode(response.status().as_u16()))
+                .with_context(|| format!("Could not download tldr pages from {url}")),
+            Err(e) => Err(e).with_context(|| format!("Could not download tldr pages from {url}")),
+        }
+    }
+
+    /// Download the file at `url` into memory. This should only be used for small files like
+    /// single pages.
+    fn download_bytes(client: &Agent, url: &str) -> Result<Option<Vec<u8>>> {
+        if let Some(path) = file_url_path(url) {
+            let Some(mut file) = open_local_file(path)? else {
+                return Ok(None);
+            };
+            let mut bytes = Vec::new();
+            file.read_to_end(&mut bytes)
+                .with_context(|| format!("Could not read {}", path.display()))?;
+            return Ok(Some(bytes));
+        }
+
+        let Some(response) = Self::get(client, url)? else {
+            return Ok(None);
+        };
+        let bytes = response
+            .into_body()
+            .read_to_vec()
+            .with_context(|| format!("Could not download tldr pages from {url}"))?;
+        debug!("{} bytes downloaded", bytes.len());
+        Ok(Some(bytes))
+    }
+
+    /// Download the archive at `archive_url` into a temporary file in `temp_directory`.
+    ///
+    /// The response body is streamed to the file instead of being buffered in memory, to keep the
+    /// memory usage low on small devices. Local archives at `file://` URLs are used directly.
+    fn download(
+        client: &Agent,
+        archive_url: &str,
+        temp_directory: &Path,
+    ) -> Result<Option<Download>> {
+        if let Some(path) = file_url_path(archive_url) {
+            info!("Reading archive from {}", path.display());
+            let Some(file) = open_local_file(path)? else {
+                return Ok(None);
+            };
+            let metadata = file.metadata()?;
+            return Ok(Some(Download {
+                etag: local_etag(&metadata),
+                size: metadata.len(),
+                file,
+            }));
+        }
+
+        let Some(response) = Self::get(client, archive_url)? else {
+            return Ok(None);
+        };
+        let etag = response
+            .headers()
+            .get("etag")
+            .and_then(|etag| etag.to_str().ok())
+            .map(str::to_string);
+        let mut file = tempfile::tempfile_in(temp_directory).with_context(|| {
+            format!(
+                "Could not create a temporary file in {}",
+                temp_directory.display(),
+            )
+        })?;
+
+        // Read and write separately, so that network and I/O errors can be told apart
+        let mut reader = response.into_body().into_reader();
+        let mut buffer = [0; 8192];
+        let mut size = 0;
+        loop {
+            let n = reader
+                .read(&mut buffer)
+                .map_err(ureq::Error::Io)
+                .with_context(|| format!("Could not download tldr pages from {archive_url}"))?;
+            if n == 0 {
+                break;
             }
+            file.write_all(&buffer[..n])
+                .context("Could not write the downloaded archive")?;
+            size += n;
         }
+        file.rewind()?;
+        debug!("{size} bytes downloaded");
+        Ok(Some(Download {
+            file,
+            etag,
+            size: size as u64,
+        }))
     }
 }
 
@@ -429,4 +2401,418 @@ mod tests {
 
         assert_eq!(&buf, b"Hello\n");
     }
+
+    #[test]
+    fn test_reader_with_expanded_templates() {
+        let dir = tempfile::tempdir().unwrap();
+        let page_path = dir.path().join("test.md");
+        let patch_path = dir.path().join("test.patch.md");
+        fs::write(&page_path, "`ls {env:PATH} {{path}}`\n").unwrap();
+        fs::write(&patch_path, "`cd {env:PATH}`").unwrap();
+        let path = std::env::var("PATH").unwrap();
+
+        let read = |result: PageLookupResult| {
+            let mut content = String::new();
+            result
+                .reader()
+                .unwrap()
+                .read_to_string(&mut content)
+                .unwrap();
+            content
+        };
+        // Only custom pages and patches are expanded
+        assert_eq!(
+            read(
+                PageLookupResult::with_page(page_path.clone())
+                    .with_optional_patch(Some(patch_path))
+                    .with_expanded_templates(true)
+            ),
+            format!("`ls {{env:PATH}} {{{{path}}}}`\n\n`cd {path}`")
+        );
+        let custom_page = PageLookupResult {
+            custom_page: true,
+            ..PageLookupResult::with_page(page_path)
+        };
+        assert_eq!(
+            read(custom_page.with_expanded_templates(true)),
+            format!("`ls {path} {{{{path}}}}`\n")
+        );
+    }
+
+    /// The entries of the test archives.
+    const ARCHIVE_ENTRIES: [&str; 3] = ["common/foo.md", "windows/bar.md", "LICENSE.md"];
+
+    /// Write a tar archive containing [`ARCHIVE_ENTRIES`] into `writer`.
+    fn write_tar(writer: impl Write) {
+        let mut builder = tar::Builder::new(writer);
+        for path in ARCHIVE_ENTRIES {
+            let content = b"# foo\n";
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            builder
+                .append_data(&mut header, path, &content[..])
+                .unwrap();
+        }
+        builder.finish().unwrap();
+    }
+
+    fn extract_archive(file: File, platforms: &[PlatformType]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        Archive::open(file)
+            .unwrap()
+            .extract(dir.path(), platforms, &[])
+            .unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_extract_tar_gz() {
+        let mut file = tempfile::tempfile().unwrap();
+        let mut encoder = flate2::write::GzEncoder::new(&mut file, flate2::Compression::default());
+        write_tar(&mut encoder);
+        encoder.finish().unwrap();
+        file.rewind().unwrap();
+
+        let dir = extract_archive(file, PlatformType::value_variants());
+        assert_eq!(
+            fs::read_to_string(dir.path().join("common").join("foo.md")).unwrap(),
+            "# foo\n",
+        );
+    }
+
+    #[test]
+    fn test_extract_tar_zst() {
+        let mut file = tempfile::tempfile().unwrap();
+        let mut encoder = zstd::Encoder::new(&mut file, 0).unwrap();
+        write_tar(&mut encoder);
+        encoder.finish().unwrap();
+        file.rewind().unwrap();
+
+        let dir = extract_archive(file, PlatformType::value_variants());
+        assert_eq!(
+            fs::read_to_string(dir.path().join("common").join("foo.md")).unwrap(),
+            "# foo\n",
+        );
+    }
+
+    #[test]
+    fn test_skip_evicted_entries() {
+        let platforms = [PlatformType::Common, PlatformType::Linux];
+        assert!(is_wanted_entry(Path::new("linux/foo.md"), &platforms, &[]));
+        assert!(!is_wanted_entry(
+            Path::new("linux/foo.md"),
+            &platforms,
+            &["linux"]
+        ));
+        assert!(is_wanted_entry(
+            Path::new("common/foo.md"),
+            &platforms,
+            &["linux"]
+        ));
+    }
+
+    #[test]
+    fn test_extract_only_download_platforms() {
+        // zip
+        let mut file = tempfile::tempfile().unwrap();
+        let mut writer = zip::ZipWriter::new(&mut file);
+        for path in ARCHIVE_ENTRIES {
+            writer
+                .start_file(path, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(b"# foo\n").unwrap();
+        }
+        writer.finish().unwrap();
+        file.rewind().unwrap();
+        let zip_dir = extract_archive(file, &[PlatformType::Common]);
+
+        // tar.gz
+        let mut file = tempfile::tempfile().unwrap();
+        let mut encoder = flate2::write::GzEncoder::new(&mut file, flate2::Compression::default());
+        write_tar(&mut encoder);
+        encoder.finish().unwrap();
+        file.rewind().unwrap();
+        let tar_dir = extract_archive(file, &[PlatformType::Common]);
+
+        for dir in [zip_dir, tar_dir] {
+            assert!(dir.path().join("common").join("foo.md").is_file());
+            assert!(dir.path().join("LICENSE.md").is_file());
+            assert!(!dir.path().join("windows").exists());
+        }
+    }
+
+    #[test]
+    fn test_unsupported_archive() {
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(b"<html>Not found</html>").unwrap();
+        file.rewind().unwrap();
+
+        let error = Archive::open(file).err().unwrap();
+        assert!(error.to_string().contains("Unsupported archive format"));
+    }
+
+    #[test]
+    fn test_find_upstream_page_order() {
+        let dir = tempfile::tempdir().unwrap();
+        for page in ["pages.en/common/foo.md", "pages.de/linux/foo.md"] {
+            let path = dir.path().join(page);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            File::create(path).unwrap();
+        }
+
+        let platforms = [PlatformType::Linux, PlatformType::Common];
+        let languages = [Language("en"), Language("de")];
+        let cache = Cache::open(CacheConfig {
+            pages_directory: dir.path(),
+            custom_pages_directory: None,
+            system_pages_directory: None,
+            platforms: &platforms,
+            search_languages: &languages,
+            download_languages: &languages,
+            download_platforms: &platforms,
+            state_directory: dir.path(),
+            sources: &[],
+        })
+        .unwrap()
+        .unwrap();
+
+        // Platforms take precedence over languages
+        assert_eq!(
+            cache.find_upstream_page("foo"),
+            Some(dir.path().join("pages.de").join("linux").join("foo.md")),
+        );
+        assert_eq!(cache.find_upstream_page("bar"), None);
+    }
+
+    #[test]
+    fn test_page_hash_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let write_page = |path: &str, content: &str| {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+        write_page("pages.en/common/kept.md", "# kept");
+        write_page("pages.en/common/modified.md", "# modified");
+        write_page("pages.de/linux/removed.md", "# removed");
+
+        let cache = Cache::open(CacheConfig {
+            pages_directory: dir.path(),
+            custom_pages_directory: None,
+            system_pages_directory: None,
+            platforms: &[],
+            search_languages: &[],
+            download_languages: &[],
+            download_platforms: &[],
+            state_directory: dir.path(),
+            sources: &[],
+        })
+        .unwrap()
+        .unwrap();
+        let old = cache.page_hashes().unwrap();
+
+        write_page("pages.en/common/modified.md", "# modified\n\n> Changed");
+        write_page("pages.en/osx/added.md", "# added");
+        fs::remove_file(dir.path().join("pages.de/linux/removed.md")).unwrap();
+
+        assert_eq!(
+            old.changes(&cache.page_hashes().unwrap()),
+            CacheChanges {
+                added: vec!["pages.en/osx/added.md".into()],
+                removed: vec!["pages.de/linux/removed.md".into()],
+                modified: vec!["pages.en/common/modified.md".into()],
+            }
+        );
+    }
+
+    #[test]
+    fn test_evict() {
+        let dir = tempfile::tempdir().unwrap();
+        let pages_directory = dir.path().join("pages");
+        let state_directory = dir.path().join("state");
+        for page in [
+            "pages.en/common/a.md",
+            "pages.en/linux/b.md",
+            "pages.de/common/c.md",
+        ] {
+            let path = pages_directory.join(page);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, [b'x'; 100]).unwrap();
+        }
+        let cache = Cache::open(CacheConfig {
+            pages_directory: &pages_directory,
+            custom_pages_directory: None,
+            system_pages_directory: None,
+            platforms: &[],
+            search_languages: &[],
+            download_languages: &[Language("en")],
+            download_platforms: &[],
+            state_directory: &state_directory,
+            sources: &[],
+        })
+        .unwrap()
+        .unwrap();
+
+        let page = pages_directory.join("pages.de/common/c.md");
+        let access_times_path = cache.cache_state_path(ACCESS_TIMES_FILE);
+        assert!(access_times_path.starts_with(state_directory.join(CACHES_DIR)));
+        cache.record_access(&page).unwrap();
+        let access_times = fs::read_to_string(&access_times_path).unwrap();
+        assert!(access_times.ends_with("\tpages.de/common\n"));
+        // Lookups shortly after the recorded one aren't written
+        let (seconds, _) = access_times.split_once('\t').unwrap();
+        let recent = format!(
+            "{}\tpages.de/common\n",
+            seconds.parse::<u64>().unwrap() - 10
+        );
+        fs::write(&access_times_path, &recent).unwrap();
+        cache.record_access(&page).unwrap();
+        assert_eq!(fs::read_to_string(&access_times_path).unwrap(), recent);
+        fs::write(
+            &access_times_path,
+            "5\tpages.en/common\n3\tpages.de/common\n",
+        )
+        .unwrap();
+
+        // Pages that were never looked up go first
+        assert_eq!(
+            cache.evict(150).unwrap(),
+            [
+                EvictedPages {
+                    directory: "pages.en/linux".into(),
+                    size: 100,
+                },
+                EvictedPages {
+                    directory: "pages.de/common".into(),
+                    size: 100,
+                },
+            ]
+        );
+        assert_eq!(cache.cached_languages().unwrap(), ["en"]);
+        assert!(pages_directory.join("pages.en/common/a.md").exists());
+        assert_eq!(cache.evict(100).unwrap(), []);
+        // The common pages of the first download language are kept
+        assert_eq!(cache.evict(0).unwrap(), []);
+
+        // The evicted directories are skipped by updates until they are forgotten
+        assert_eq!(
+            cache.read_evicted_pages(),
+            ["pages.en/linux", "pages.de/common"]
+        );
+        // Caches with other pages directories sharing the state directory are not affected
+        let other_pages_directory = dir.path().join("other-pages");
+        let other_cache = Cache {
+            config: CacheConfig {
+                pages_directory: &other_pages_directory,
+                ..cache.config.clone()
+            },
+        };
+        assert!(other_cache.read_evicted_pages().is_empty());
+
+        cache.forget_evicted_pages().unwrap();
+        assert!(cache.read_evicted_pages().is_empty());
+    }
+
+    #[test]
+    fn test_version_check_due() {
+        let dir = tempfile::tempdir().unwrap();
+        let state_directory = dir.path().join("state");
+        let cache = Cache::open(CacheConfig {
+            pages_directory: dir.path(),
+            custom_pages_directory: None,
+            system_pages_directory: None,
+            platforms: &[],
+            search_languages: &[],
+            download_languages: &[],
+            download_platforms: &[],
+            state_directory: &state_directory,
+            sources: &[],
+        })
+        .unwrap()
+        .unwrap();
+        let week = Duration::from_secs(7 * 24 * 60 * 60);
+
+        assert!(cache.version_check_due(week));
+        cache.record_version_check().unwrap();
+        assert!(!cache.version_check_due(week));
+        assert!(cache.version_check_due(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_cheat_sheet_to_tldr() {
+        let cheat_sheet = "---\nsyntax: bash\ntags: [ vcs ]\n---\n\
+                           # To show the status\n# of the working tree:\ngit status\n\n\
+                           # To print {{braces}}\necho '{{x}}'\necho done\n";
+        assert_eq!(
+            cheat_sheet_to_tldr("git", "local", cheat_sheet),
+            "# git\n\n> Cheat sheet from the `local` source.\n\n\
+             - To show the status of the working tree:\n\n`git status`\n\n\
+             - To print {{braces}}:\n\n`echo '\\{\\{x\\}\\}'`\n\n`echo done`\n",
+        );
+    }
+
+    #[test]
+    fn test_validate_page_name() {
+        for name in ["tar", "git-commit", "7z", "..foo", "a.b"] {
+            assert!(validate_page_name(name).is_ok(), "{name}");
+        }
+        for name in ["", ".", "..", "../x", "a/b", "a\\b", "/etc/passwd"] {
+            assert!(validate_page_name(name).is_err(), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_page_source_rejects_invalid_names() {
+        let source = PageSource {
+            name: "cheat.sh".to_string(),
+            kind: PageSourceKind::CheatSh("http://127.0.0.1:1".to_string()),
+            priority: 0,
+        };
+        // The name is rejected before anything is downloaded
+        let error = source
+            .find_page("../x", || panic!("No client should be created"))
+            .unwrap_err();
+        assert!(error.to_string().contains("Invalid page name"));
+    }
+
+    #[test]
+    fn test_pages_index_targets() {
+        // The upstream index contains additional fields, which are ignored
+        let index: PagesIndex = serde_json::from_str(
+            r#"{"commands": [{
+                "name": "tar",
+                "platform": ["common"],
+                "language": ["en", "de"],
+                "targets": [{"os": "common", "language": "en"}, {"os": "common", "language": "de"}]
+            }]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            index.targets("tar").unwrap(),
+            [
+                IndexTarget {
+                    os: "common".to_string(),
+                    language: "en".to_string(),
+                },
+                IndexTarget {
+                    os: "common".to_string(),
+                    language: "de".to_string(),
+                },
+            ]
+        );
+        assert!(index.targets("zip").is_none());
+    }
+
+    #[test]
+    fn test_is_network_unreachable() {
+        let offline = anyhow::Error::new(ureq::Error::HostNotFound).context("Could not download");
+        assert!(is_network_unreachable(&offline));
+
+        let server_error = anyhow::Error::new(ureq::Error::StatusCode(500));
+        assert!(!is_network_unreachable(&server_error));
+        let io_error = anyhow::Error::new(io::Error::other("disk full"));
+        assert!(!is_network_unreachable(&io_error));
+    }
 }
@@ -1,10 +1,13 @@
 //! Definition of the CLI arguments and options.
 
-use std::path::PathBuf;
+use std::{num::NonZeroUsize, path::PathBuf};
 
 use clap::{builder::ArgAction, ArgGroup, Parser};
 
-use crate::types::{ColorOptions, PlatformType};
+use crate::{
+    config::{parse_config_override, parse_language, parse_profile},
+    types::{ColorOptions, CompletionShell, HookShell, PlatformArg, ReportFormat},
+};
 
 // Note: flag names are specified explicitly in clap attributes
 // to improve readability and allow contributors to grep names like "clear-cache"
@@ -23,7 +26,9 @@ use crate::types::{ColorOptions, PlatformType};
 To view usage examples, run tldr tldr or tldr tealdeer.",
     arg_required_else_help = true,
     help_expected = true,
-    group = ArgGroup::new("command_or_file").args(&["command", "render"]),
+    group = ArgGroup::new("command_or_file").args(&["command", "render", "render_url", "history", "random", "random_platform", "tip"]),
+    group = ArgGroup::new("reports").args(&["update", "show_paths"]).multiple(true),
+    group = ArgGroup::new("outputs").args(&["update", "show_paths", "command", "render", "render_url", "random", "random_platform"]).multiple(true),
 )]
 pub(crate) struct Cli {
     /// The command to show (e.g. `tar` or `git log`)
@@ -34,15 +39,94 @@ pub(crate) struct Cli {
     #[arg(short = 'l', long = "list")]
     pub list: bool,
 
+    /// Show the platforms for which each page exists with `--list`
+    #[arg(long = "show-platforms", requires = "list")]
+    pub show_platforms: bool,
+
+    /// Show a random page from the cache
+    #[arg(long = "random")]
+    pub random: bool,
+
+    /// Show a random page for a random platform
+    #[arg(long = "random-platform")]
+    pub random_platform: bool,
+
+    /// Print a random example from the cache in two lines, e.g. for a login banner
+    #[arg(long = "tip")]
+    pub tip: bool,
+
+    /// List the pages whose name or description contains a keyword, like `apropos`
+    #[arg(long = "apropos", value_name = "KEYWORD")]
+    pub apropos: Option<String>,
+
+    /// Search all pages for examples whose command contains the given flag or text
+    #[arg(long = "which-flag", value_name = "FLAG", allow_hyphen_values = true)]
+    pub which_flag: Option<String>,
+
+    /// List recently viewed pages, or show the page with the given number again
+    #[arg(long = "history", value_name = "N", num_args(0..=1))]
+    #[allow(clippy::option_option)] // clap uses this to make the value optional
+    pub history: Option<Option<usize>>,
+
+    /// Show how often each page in the history was looked up
+    #[arg(long = "stats-usage", conflicts_with = "history")]
+    pub stats_usage: bool,
+
+    /// List all custom pages and patches
+    #[arg(long = "list-custom")]
+    pub list_custom: bool,
+
     /// Edit custom page with `EDITOR`
     #[arg(long, requires = "command")]
     pub edit_page: bool,
 
+    /// Seed a new custom page with the page from the cache when using `--edit-page`
+    #[arg(long = "from-cache", requires = "edit_page")]
+    pub from_cache: bool,
+
+    /// Create a skeleton page for the command in the custom pages directory (or in the file
+    /// given with `--output-file`) and edit it with `EDITOR`
+    #[arg(long = "new-page", requires = "command", conflicts_with_all = ["edit_page", "from_cache"])]
+    pub new_page: bool,
+
     /// Edit custom patch with `EDITOR`
-    #[arg(long, requires = "command", conflicts_with = "edit_page")]
+    #[arg(long, requires = "command", conflicts_with_all = ["edit_page", "new_page"])]
     pub edit_patch: bool,
 
-    /// Render a specific markdown file
+    /// Delete custom page
+    #[arg(long = "delete-page", requires = "command", conflicts_with_all = ["edit_page", "edit_patch"])]
+    pub delete_page: bool,
+
+    /// Delete custom patch
+    #[arg(long = "delete-patch", requires = "command", conflicts_with_all = ["edit_page", "edit_patch", "delete_page"])]
+    pub delete_patch: bool,
+
+    /// Show the page with the lines added by its custom patch highlighted
+    #[arg(long = "diff-patch", requires = "command", conflicts_with_all = ["edit_page", "edit_patch"])]
+    pub diff_patch: bool,
+
+    /// Show the platforms and languages for which the page exists (requires `updates.use_index`)
+    #[arg(long = "availability", requires = "command", conflicts_with_all = ["edit_page", "edit_patch"])]
+    pub availability: bool,
+
+    /// Show the page for each platform given with `--platform` one after another
+    #[arg(long = "compare", requires_all = ["command", "platforms"], conflicts_with_all = ["edit_page", "edit_patch", "availability"])]
+    pub compare: bool,
+
+    /// Show the page for each searched platform if they differ, instead of only the first one
+    #[arg(long = "all-matches", requires = "command", conflicts_with_all = ["edit_page", "edit_patch", "availability", "compare", "fill"])]
+    pub all_matches: bool,
+
+    /// Exit with status 0 if the page exists and 1 otherwise, without printing anything
+    #[arg(long = "exists", requires = "command", conflicts_with_all = ["edit_page", "edit_patch", "availability"])]
+    pub exists: bool,
+
+    /// Check custom pages and patches for formatting mistakes
+    #[arg(long = "lint")]
+    pub lint: bool,
+
+    /// Render a specific markdown file (`-` to read it from stdin), or all markdown files in a
+    /// directory
     #[arg(
         short = 'f',
         long = "render",
@@ -51,23 +135,64 @@ pub(crate) struct Cli {
     )]
     pub render: Option<PathBuf>,
 
-    /// Override the operating system, can be specified multiple times in order of preference
+    /// Only check the pages passed to `--render` for formatting mistakes instead of rendering
+    /// them
+    #[arg(long = "check", requires = "render", conflicts_with = "command")]
+    pub check: bool,
+
+    /// Render the pages passed to `--render` again whenever they change, until interrupted
+    #[arg(
+        long = "watch",
+        requires = "render",
+        conflicts_with_all = ["command", "check", "output_file", "pager"]
+    )]
+    pub watch: bool,
+
+    /// Download a markdown page from a URL and render it, without using the cache
+    #[arg(long = "render-url", value_name = "URL", conflicts_with = "command")]
+    pub render_url: Option<String>,
+
+    /// Override the operating system, can be specified multiple times in order of preference.
+    /// Use `all` to search every platform
     #[arg(
         short = 'p',
         long = "platform",
         value_name = "PLATFORM",
         action = ArgAction::Append,
     )]
-    pub platforms: Option<Vec<PlatformType>>,
+    pub platforms: Option<Vec<PlatformArg>>,
 
     /// Override the language
-    #[arg(short = 'L', long = "language")]
+    #[arg(short = 'L', long = "language", value_parser = parse_language)]
     pub language: Option<String>,
 
+    /// Override the languages to download when updating, can be specified multiple times
+    #[arg(
+        long = "download-language",
+        value_name = "LANGUAGE",
+        action = ArgAction::Append,
+        value_parser = parse_language,
+    )]
+    pub download_languages: Option<Vec<String>>,
+
     /// Update the local cache
     #[arg(short = 'u', long = "update")]
     pub update: bool,
 
+    /// The format of the report of `--update` and `--show-paths`, `json` prints a JSON object
+    /// (e.g. with the number of changed pages, or with the source of each path). `tokens` prints
+    /// the parts of a page as JSON objects, one per line, for other programs to render it
+    #[arg(long = "output", value_name = "FORMAT", requires = "outputs")]
+    pub output: Option<ReportFormat>,
+
+    /// Check whether an update would change the cache, without updating it
+    #[arg(long = "dry-run-update", conflicts_with = "update")]
+    pub dry_run_update: bool,
+
+    /// Check the cache for missing or incomplete pages and offer to download them again
+    #[arg(long = "check-cache", conflicts_with_all = ["update", "dry_run_update"])]
+    pub check_cache: bool,
+
     /// If auto update is configured, disable it for this run
     #[arg(long = "no-auto-update", requires = "command_or_file")]
     pub no_auto_update: bool,
@@ -76,10 +201,40 @@ pub(crate) struct Cli {
     #[arg(short = 'c', long = "clear-cache")]
     pub clear_cache: bool,
 
+    /// Remove pages for languages that are not configured for download from the cache
+    #[arg(long = "prune-languages")]
+    pub prune_languages: bool,
+
+    /// Add a language to `updates.download_languages` in the config file and download its pages
+    #[arg(long = "add-language", value_name = "LANGUAGE", value_parser = parse_language)]
+    pub add_language: Option<String>,
+
+    /// Remove a language from `updates.download_languages` in the config file and from the cache
+    #[arg(
+        long = "remove-language",
+        value_name = "LANGUAGE",
+        value_parser = parse_language,
+        conflicts_with = "add_language"
+    )]
+    pub remove_language: Option<String>,
+
     /// Override config file location
     #[arg(long = "config-path", value_name = "FILE")]
     pub config_path: Option<PathBuf>,
 
+    /// Use the config file of a profile, i.e. `config.<NAME>.toml` in the config directory
+    #[arg(long = "profile", value_name = "NAME", value_parser = parse_profile, conflicts_with = "config_path")]
+    pub profile: Option<String>,
+
+    /// Override a config option for this invocation, e.g. `--set display.compact=true`
+    #[arg(long = "set", value_name = "KEY=VALUE", value_parser = parse_config_override)]
+    pub set: Vec<(String, String)>,
+
+    /// Write the rendered page to a file instead of stdout. Styles are only kept with
+    /// `--color always`
+    #[arg(short = 'o', long = "output-file", value_name = "FILE")]
+    pub output_file: Option<PathBuf>,
+
     /// Use a pager to page output
     #[arg(long = "pager", requires = "command_or_file")]
     pub pager: bool,
@@ -88,14 +243,93 @@ pub(crate) struct Cli {
     #[arg(short = 'r', long = "raw", requires = "command_or_file")]
     pub raw: bool,
 
-    /// Suppress informational messages
-    #[arg(short = 'q', long = "quiet")]
-    pub quiet: bool,
+    /// Display the page without styles and with the default layout, ignoring the config, e.g.
+    /// for tests and scripts
+    #[arg(long = "plain", requires = "command_or_file", conflicts_with = "raw")]
+    pub plain: bool,
+
+    /// Only display the example with the given number (starting at 1)
+    #[arg(
+        long = "example",
+        value_name = "N",
+        requires = "command_or_file",
+        conflicts_with = "raw"
+    )]
+    pub example: Option<NonZeroUsize>,
+
+    /// Print the code of the example selected with `--example` with its placeholders filled in,
+    /// asking for the values that are not set in the `[placeholders]` config section
+    #[arg(long = "fill", requires_all = ["example", "command"], conflicts_with = "compare")]
+    pub fill: bool,
+
+    /// Only display the examples that contain the given text (ignoring case)
+    #[arg(
+        long = "grep",
+        value_name = "TEXT",
+        requires = "command_or_file",
+        conflicts_with = "raw",
+        allow_hyphen_values = true
+    )]
+    pub grep: Option<String>,
+
+    /// Fail if the page contains lines that are not valid in the tldr format, instead of
+    /// warning about them
+    #[arg(long = "strict", requires = "command_or_file", conflicts_with = "raw")]
+    pub strict: bool,
+
+    /// Suppress informational messages and warnings, specify twice to suppress errors as well
+    #[arg(short = 'q', long = "quiet", action = ArgAction::Count)]
+    pub quiet: u8,
+
+    /// Don't warn about deprecated features that are still in use
+    #[arg(long = "no-deprecation-warnings")]
+    pub no_deprecation_warnings: bool,
 
     /// Show file and directory paths used by tealdeer
     #[arg(long = "show-paths")]
     pub show_paths: bool,
 
+    /// Print the effective configuration, noting the source of each value
+    #[arg(long = "print-config")]
+    pub print_config: bool,
+
+    /// Describe a config option (e.g. `updates.auto_update`) with its default and current value
+    #[arg(long = "explain-config", value_name = "KEY")]
+    pub explain_config: Option<String>,
+
+    /// Rewrite deprecated options in the config file, keeping a backup of the original
+    #[arg(long = "migrate-config")]
+    pub migrate_config: bool,
+
+    /// Rename custom pages and patches with the old `.page` and `.patch` extensions to `.page.md`
+    /// and `.patch.md`
+    #[arg(long = "migrate-custom-pages")]
+    pub migrate_custom_pages: bool,
+
+    /// Only print what `--migrate-custom-pages` would rename
+    #[arg(long = "dry-run", requires = "migrate_custom_pages")]
+    pub dry_run: bool,
+
+    /// Interactively create a config file and download the pages
+    #[arg(long = "setup", conflicts_with_all = ["seed_config", "migrate_config"])]
+    pub setup: bool,
+
+    /// Print a shell snippet that points to the tldr page of commands that are not found
+    #[arg(long = "print-hook", value_name = "SHELL")]
+    pub print_hook: Option<HookShell>,
+
+    /// Print the completion script for a shell
+    #[arg(long = "completions", value_name = "SHELL")]
+    pub completions: Option<CompletionShell>,
+
+    /// Replace tldr with the binary of the latest release on GitHub
+    #[arg(long = "self-update")]
+    pub self_update: bool,
+
+    /// Serve the cached pages over HTTP, for other machines to use as `archive_source`
+    #[arg(long = "serve-cache", value_name = "ADDRESS")]
+    pub serve_cache: Option<String>,
+
     /// Create a basic config
     #[arg(long = "seed-config")]
     pub seed_config: bool,
@@ -104,6 +338,10 @@ pub(crate) struct Cli {
     #[arg(long = "color", value_name = "WHEN")]
     pub color: Option<ColorOptions>,
 
+    /// Print the time spent in the different phases of the run, for benchmarking
+    #[arg(long = "timings", hide = true)]
+    pub timings: bool,
+
     /// Print the version
     // Note: We override the version flag because clap uses `-V` by default,
     // while TLDR specification requires `-v` to be used.
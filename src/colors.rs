@@ -0,0 +1,221 @@
+//! Conversion of colors to the number of colors a terminal supports, see `style.color_depth`.
+//!
+//! Terminals that only support the 16 basic colors may show the escape sequences of ANSI 256 and
+//! RGB colors as garbage, or ignore them. Such colors are replaced with the nearest color the
+//! terminal supports.
+
+use serde_derive::{Deserialize, Serialize};
+use yansi::{Color, Style};
+
+/// The colors supported by the terminal.
+#[derive(Copy, Clone, Default, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ColorDepth {
+    /// Detect the colors from the `TERM` and `COLORTERM` env variables.
+    #[default]
+    #[serde(rename = "auto")]
+    Auto,
+    /// The 8 basic colors and their bright variants.
+    #[serde(rename = "8")]
+    Basic,
+    /// The 256 colors of ANSI codes.
+    #[serde(rename = "256")]
+    Ansi256,
+    /// 24-bit RGB colors.
+    #[serde(rename = "truecolor")]
+    TrueColor,
+}
+
+/// `TERM` values of terminals that only support the basic colors, according to their terminfo
+/// entries.
+const BASIC_COLOR_TERMS: &[&str] = &[
+    "ansi",
+    "cons25",
+    "linux",
+    "screen",
+    "tmux",
+    "vt100",
+    "vt220",
+    "xterm",
+    "xterm-color",
+    "xterm-16color",
+];
+
+impl ColorDepth {
+    /// Resolve `Auto` using the values of the `TERM` and `COLORTERM` env variables.
+    ///
+    /// Terminals that support RGB colors set `COLORTERM` to `truecolor` or `24bit`. Otherwise,
+    /// `TERM` values ending in `-256color` mean ANSI 256 colors, and terminals that are known to
+    /// support no more than the basic colors are limited to those. All other terminals are assumed
+    /// to support RGB colors.
+    pub fn resolve(self, term: Option<&str>, colorterm: Option<&str>) -> Self {
+        if self != Self::Auto {
+            return self;
+        }
+        if matches!(colorterm, Some("truecolor" | "24bit")) {
+            return Self::TrueColor;
+        }
+        match term {
+            Some(term) if term.ends_with("-256color") => Self::Ansi256,
+            Some(term) if BASIC_COLOR_TERMS.contains(&term) => Self::Basic,
+            _ => Self::TrueColor,
+        }
+    }
+
+    /// Replace the colors of `style` that are not supported with the nearest supported ones.
+    pub fn limit_style(self, mut style: Style) -> Style {
+        style.foreground = style.foreground.map(|color| self.limit(color));
+        style.background = style.background.map(|color| self.limit(color));
+        style
+    }
+
+    /// Replace `color` with the nearest supported color, if it is not supported.
+    fn limit(self, color: Color) -> Color {
+        match (self, color) {
+            (Self::Basic, Color::Fixed(code)) if code < 16 => BASIC_COLORS[usize::from(code)],
+            (Self::Basic, Color::Fixed(code)) => nearest_basic(ansi_to_rgb(code)),
+            (Self::Basic, Color::Rgb(r, g, b)) => nearest_basic((r, g, b)),
+            (Self::Ansi256, Color::Rgb(r, g, b)) => Color::Fixed(nearest_ansi((r, g, b))),
+            _ => color,
+        }
+    }
+}
+
+/// The basic colors in the order of their ANSI codes.
+const BASIC_COLORS: [Color; 16] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::White,
+    Color::BrightBlack,
+    Color::BrightRed,
+    Color::BrightGreen,
+    Color::BrightYellow,
+    Color::BrightBlue,
+    Color::BrightMagenta,
+    Color::BrightCyan,
+    Color::BrightWhite,
+];
+
+/// The RGB values of the basic colors, as used by xterm. Terminals can change them, but they are
+/// close enough to find the nearest color.
+const BASIC_RGB: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// The levels of each component in the 6×6×6 color cube of the ANSI codes 16 to 231.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// The RGB value of the ANSI color `code`.
+fn ansi_to_rgb(code: u8) -> (u8, u8, u8) {
+    match code {
+        0..=15 => BASIC_RGB[usize::from(code)],
+        16..=231 => {
+            let index = code - 16;
+            (
+                CUBE_LEVELS[usize::from(index / 36)],
+                CUBE_LEVELS[usize::from(index / 6 % 6)],
+                CUBE_LEVELS[usize::from(index % 6)],
+            )
+        }
+        232..=255 => {
+            let level = 8 + (code - 232) * 10;
+            (level, level, level)
+        }
+    }
+}
+
+/// The squared distance between two RGB colors.
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    [(a.0, b.0), (a.1, b.1), (a.2, b.2)]
+        .into_iter()
+        .map(|(x, y)| u32::from(x.abs_diff(y)).pow(2))
+        .sum()
+}
+
+fn nearest_basic(rgb: (u8, u8, u8)) -> Color {
+    let nearest = (0..16)
+        .min_by_key(|&code| distance(rgb, BASIC_RGB[code]))
+        .expect("There are basic colors");
+    BASIC_COLORS[nearest]
+}
+
+/// The ANSI code of the color nearest to `rgb`. Only the color cube and the grays are
+/// considered, as the basic colors often differ between terminals.
+fn nearest_ansi(rgb: (u8, u8, u8)) -> u8 {
+    (16..=255)
+        .min_by_key(|&code| distance(rgb, ansi_to_rgb(code)))
+        .expect("There are ANSI colors")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve() {
+        let auto = |term, colorterm| ColorDepth::Auto.resolve(term, colorterm);
+        assert_eq!(auto(Some("linux"), None), ColorDepth::Basic);
+        assert_eq!(auto(Some("xterm"), None), ColorDepth::Basic);
+        assert_eq!(auto(Some("screen"), None), ColorDepth::Basic);
+        assert_eq!(auto(Some("xterm-256color"), None), ColorDepth::Ansi256);
+        assert_eq!(auto(Some("tmux-256color"), None), ColorDepth::Ansi256);
+        assert_eq!(
+            auto(Some("xterm-256color"), Some("truecolor")),
+            ColorDepth::TrueColor
+        );
+        assert_eq!(auto(Some("xterm"), Some("24bit")), ColorDepth::TrueColor);
+        assert_eq!(auto(Some("alacritty"), None), ColorDepth::TrueColor);
+        assert_eq!(auto(None, None), ColorDepth::TrueColor);
+        assert_eq!(
+            ColorDepth::Ansi256.resolve(Some("linux"), None),
+            ColorDepth::Ansi256
+        );
+        assert_eq!(
+            ColorDepth::Basic.resolve(None, Some("truecolor")),
+            ColorDepth::Basic
+        );
+    }
+
+    #[test]
+    fn limit_to_basic_colors() {
+        let depth = ColorDepth::Basic;
+        assert_eq!(depth.limit(Color::Rgb(250, 10, 10)), Color::BrightRed);
+        assert_eq!(depth.limit(Color::Rgb(0, 0, 200)), Color::Blue);
+        assert_eq!(depth.limit(Color::Fixed(9)), Color::BrightRed);
+        // 208 is orange (255, 135, 0)
+        assert_eq!(depth.limit(Color::Fixed(208)), Color::Yellow);
+        assert_eq!(depth.limit(Color::Fixed(244)), Color::BrightBlack);
+        assert_eq!(depth.limit(Color::Cyan), Color::Cyan);
+    }
+
+    #[test]
+    fn limit_to_ansi_colors() {
+        let depth = ColorDepth::Ansi256;
+        assert_eq!(depth.limit(Color::Rgb(255, 135, 0)), Color::Fixed(208));
+        assert_eq!(depth.limit(Color::Rgb(100, 100, 100)), Color::Fixed(241));
+        assert_eq!(depth.limit(Color::Fixed(208)), Color::Fixed(208));
+        assert_eq!(
+            ColorDepth::TrueColor.limit(Color::Rgb(1, 2, 3)),
+            Color::Rgb(1, 2, 3)
+        );
+    }
+}
@@ -0,0 +1,28 @@
+//! Shell completion scripts, printed by `tldr --completions`.
+//!
+//! The scripts are generated from the command line arguments in [`Cli`], so that they always know
+//! all flags of the running version, also when tealdeer is installed with `cargo install`.
+
+use clap::CommandFactory;
+use clap_complete::{generate, Generator, Shell};
+use clap_complete_nushell::Nushell;
+
+use crate::{cli::Cli, types::CompletionShell};
+
+/// The completion script for `shell`.
+pub fn completion_script(shell: CompletionShell) -> String {
+    match shell {
+        CompletionShell::Bash => generate_script(Shell::Bash),
+        CompletionShell::Zsh => generate_script(Shell::Zsh),
+        CompletionShell::Fish => generate_script(Shell::Fish),
+        CompletionShell::Powershell => generate_script(Shell::PowerShell),
+        CompletionShell::Elvish => generate_script(Shell::Elvish),
+        CompletionShell::Nushell => generate_script(Nushell),
+    }
+}
+
+fn generate_script(generator: impl Generator) -> String {
+    let mut script = Vec::new();
+    generate(generator, &mut Cli::command(), "tldr", &mut script);
+    String::from_utf8(script).expect("Completion scripts are valid UTF-8")
+}
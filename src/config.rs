@@ -1,8 +1,10 @@
 use std::{
     borrow::Cow,
-    env, fmt,
-    fs::{self, File},
-    io::{ErrorKind, Write},
+    collections::BTreeMap,
+    env,
+    fmt::{self, Write as _},
+    fs,
+    io::ErrorKind,
     path::{Component, Path, PathBuf},
     sync::LazyLock,
     time::Duration,
@@ -17,13 +19,20 @@ use serde_derive::{Deserialize, Serialize};
 use yansi::{Color, Style};
 
 use crate::{
+    cache::{PageSource, PageSourceKind, SOURCES_DIR},
+    colors::ColorDepth,
+    config_docs,
+    deprecation::Deprecation,
     extensions::Dedup as _,
-    types::{PathSource, PlatformType},
+    minisign::PublicKey,
+    types::{ColorOptions, PathSource, PlatformType},
+    utils::{is_termux, write_atomically},
 };
 
 pub const CONFIG_FILE_NAME: &str = "config.toml";
 pub const MAX_CACHE_AGE: Duration = Duration::from_secs(2_592_000); // 30 days
 const DEFAULT_UPDATE_INTERVAL_HOURS: u64 = MAX_CACHE_AGE.as_secs() / 3600; // 30 days
+const DEFAULT_MIN_UPDATE_ATTEMPT_INTERVAL_MINUTES: u64 = 5;
 const SUPPORTED_TLS_BACKENDS: &[RawTlsBackend] = &[
     #[cfg(feature = "native-tls")]
     RawTlsBackend::NativeTls,
@@ -41,9 +50,18 @@ pub(crate) fn supported_tls_backends_string() -> String {
         .join(", ")
 }
 
-fn default_underline() -> bool {
-    false
-}
+/// Sample values shown for common placeholders with `display.example_values`.
+const DEFAULT_EXAMPLE_VALUES: &[(&str, &str)] = &[
+    ("file", "notes.txt"),
+    ("filename", "notes.txt"),
+    ("path/to/file", "notes.txt"),
+    ("directory", "Documents"),
+    ("path/to/directory", "Documents"),
+    ("path", "Documents/notes.txt"),
+    ("user", "alice"),
+    ("username", "alice"),
+    ("url", "https://example.com"),
+];
 
 const fn default_base_indent() -> usize {
     2
@@ -53,14 +71,6 @@ const fn default_command_indent() -> usize {
     6
 }
 
-fn default_bold() -> bool {
-    false
-}
-
-fn default_italic() -> bool {
-    false
-}
-
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(rename_all = "lowercase")]
 pub enum RawColor {
@@ -70,7 +80,7 @@ pub enum RawColor {
     Yellow,
     Blue,
     Magenta,
-    Purple, // Backwards compatibility with ansi_term (until tealdeer 1.5.0)
+    Purple, // Backwards compatibility with ansi_term (until tealdeer 1.5.0), see `migrate_config`
     Cyan,
     White,
     Ansi(u8),
@@ -94,16 +104,24 @@ impl From<RawColor> for Color {
     }
 }
 
+/// The style of a style target. Attributes that are not set are taken from the `base` style, and
+/// are disabled if there is none.
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 struct RawStyle {
     pub foreground: Option<RawColor>,
     pub background: Option<RawColor>,
-    #[serde(default = "default_underline")]
-    pub underline: bool,
-    #[serde(default = "default_bold")]
-    pub bold: bool,
-    #[serde(default = "default_italic")]
-    pub italic: bool,
+    #[serde(default)]
+    pub underline: Option<bool>,
+    #[serde(default)]
+    pub bold: Option<bool>,
+    #[serde(default)]
+    pub italic: Option<bool>,
+    /// The style target whose attributes are used for the attributes that are not set.
+    #[serde(default)]
+    pub base: Option<StyleTarget>,
+    /// Whether the target is styled at all. This is not inherited from the `base` style.
+    #[serde(default)]
+    pub enabled: Option<bool>,
 }
 
 #[allow(clippy::derivable_impls)] // Explicitly control defaults
@@ -112,16 +130,76 @@ impl Default for RawStyle {
         Self {
             foreground: None,
             background: None,
-            underline: false,
-            bold: false,
-            italic: false,
+            underline: None,
+            bold: None,
+            italic: None,
+            base: None,
+            enabled: None,
+        }
+    }
+}
+
+impl RawStyle {
+    /// Take the attributes that are not set from `base`.
+    fn inherit(self, base: Self) -> Self {
+        Self {
+            foreground: self.foreground.or(base.foreground),
+            background: self.background.or(base.background),
+            underline: self.underline.or(base.underline),
+            bold: self.bold.or(base.bold),
+            italic: self.italic.or(base.italic),
+            base: None,
+            enabled: self.enabled,
         }
     }
+
+    /// The style with the implicit defaults of the attributes set explicitly, so that styles with
+    /// the same effect are equal.
+    fn normalized(self) -> Self {
+        Self {
+            underline: Some(self.underline.unwrap_or(false)),
+            bold: Some(self.bold.unwrap_or(false)),
+            italic: Some(self.italic.unwrap_or(false)),
+            enabled: Some(self.enabled.unwrap_or(true)),
+            ..self
+        }
+    }
+}
+
+/// The parts of a page that can be styled, as named in the `[style]` config section.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StyleTarget {
+    Description,
+    CommandName,
+    ExampleText,
+    ExampleCode,
+    ExampleVariable,
+    ExampleOption,
+    Title,
+}
+
+impl fmt::Display for StyleTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Description => "description",
+            Self::CommandName => "command_name",
+            Self::ExampleText => "example_text",
+            Self::ExampleCode => "example_code",
+            Self::ExampleVariable => "example_variable",
+            Self::ExampleOption => "example_option",
+            Self::Title => "title",
+        };
+        f.write_str(name)
+    }
 }
 
 impl From<RawStyle> for Style {
     fn from(raw_style: RawStyle) -> Self {
         let mut style = Self::default();
+        if raw_style.enabled == Some(false) {
+            return style;
+        }
 
         if let Some(foreground) = raw_style.foreground {
             style = style.fg(Color::from(foreground));
@@ -131,15 +209,15 @@ impl From<RawStyle> for Style {
             style = style.bg(Color::from(background));
         }
 
-        if raw_style.underline {
+        if raw_style.underline == Some(true) {
             style = style.underline();
         }
 
-        if raw_style.bold {
+        if raw_style.bold == Some(true) {
             style = style.bold();
         }
 
-        if raw_style.italic {
+        if raw_style.italic == Some(true) {
             style = style.italic();
         }
 
@@ -147,8 +225,12 @@ impl From<RawStyle> for Style {
     }
 }
 
-#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
 struct RawStyleConfig {
+    #[serde(default)]
+    pub background: StyleBackground,
+    #[serde(default)]
+    pub color_depth: ColorDepth,
     #[serde(default)]
     pub description: RawStyle,
     #[serde(default)]
@@ -159,17 +241,180 @@ struct RawStyleConfig {
     pub example_code: RawStyle,
     #[serde(default)]
     pub example_variable: RawStyle,
+    /// Falls back to the `example_code` style if not set
+    #[serde(default)]
+    pub example_option: Option<RawStyle>,
+    /// Falls back to the `command_name` style if not set
+    #[serde(default)]
+    pub title: Option<RawStyle>,
 }
 
-impl From<&RawStyleConfig> for StyleConfig {
-    fn from(raw_style_config: &RawStyleConfig) -> Self {
-        Self {
-            command_name: raw_style_config.command_name.into(),
-            description: raw_style_config.description.into(),
-            example_text: raw_style_config.example_text.into(),
-            example_code: raw_style_config.example_code.into(),
-            example_variable: raw_style_config.example_variable.into(),
+impl RawStyleConfig {
+    /// The default styles, which are chosen for the terminal background.
+    fn default_for(background: TerminalBackground) -> Self {
+        let mut style = Self::default();
+        let (text, code) = match background {
+            TerminalBackground::Dark => (RawColor::Green, RawColor::Cyan),
+            // Green and cyan are hard to read on light backgrounds
+            TerminalBackground::Light => (RawColor::Magenta, RawColor::Blue),
+        };
+        style.example_text.foreground = Some(text);
+        style.command_name.foreground = Some(code);
+        style.example_code.foreground = Some(code);
+        style.example_variable.foreground = Some(code);
+        style.example_variable.underline = Some(true);
+        style
+    }
+
+    /// The configured style of `target`. The styles of `example_option` and `title` are based on
+    /// the `example_code` and `command_name` styles if they are not set.
+    fn raw_style(&self, target: StyleTarget) -> RawStyle {
+        let fallback = |base| RawStyle {
+            base: Some(base),
+            ..RawStyle::default()
+        };
+        match target {
+            StyleTarget::Description => self.description,
+            StyleTarget::CommandName => self.command_name,
+            StyleTarget::ExampleText => self.example_text,
+            StyleTarget::ExampleCode => self.example_code,
+            StyleTarget::ExampleVariable => self.example_variable,
+            StyleTarget::ExampleOption => self
+                .example_option
+                .unwrap_or_else(|| fallback(StyleTarget::ExampleCode)),
+            StyleTarget::Title => self
+                .title
+                .unwrap_or_else(|| fallback(StyleTarget::CommandName)),
+        }
+    }
+
+    /// The style of `target` with the attributes inherited from its base styles. `derived` are
+    /// the targets whose styles are based on `target`, to detect cycles.
+    fn resolved_style(
+        &self,
+        target: StyleTarget,
+        derived: &mut Vec<StyleTarget>,
+    ) -> Result<RawStyle> {
+        if derived.contains(&target) {
+            bail!("The style `{target}` is based on itself (`style.{target}.base`)");
+        }
+        let style = self.raw_style(target);
+        let Some(base) = style.base else {
+            return Ok(style);
+        };
+        derived.push(target);
+        let base_style = self.resolved_style(base, derived)?;
+        derived.pop();
+        Ok(style.inherit(base_style))
+    }
+
+    /// The styles of all targets, see [`RawStyleConfig::resolved_style`].
+    fn resolve(&self) -> Result<StyleConfig> {
+        let style =
+            |target| -> Result<Style> { Ok(self.resolved_style(target, &mut Vec::new())?.into()) };
+        Ok(StyleConfig {
+            description: style(StyleTarget::Description)?,
+            command_name: style(StyleTarget::CommandName)?,
+            example_text: style(StyleTarget::ExampleText)?,
+            example_code: style(StyleTarget::ExampleCode)?,
+            example_variable: style(StyleTarget::ExampleVariable)?,
+            example_option: style(StyleTarget::ExampleOption)?,
+            title: style(StyleTarget::Title)?,
+        })
+    }
+
+    /// The styles to use on a terminal with the configured background. If no styles are
+    /// configured, the default styles for the background are used. Otherwise, the style of every
+    /// target that is configured with the default style (as written by `--seed-config`) is
+    /// replaced with the default style for the background, and all other styles are kept.
+    fn for_background(mut self, colorfgbg: Option<&str>) -> Self {
+        let background = match self.background {
+            StyleBackground::Auto => {
+                TerminalBackground::from_colorfgbg(colorfgbg).unwrap_or(TerminalBackground::Dark)
+            }
+            StyleBackground::Dark => TerminalBackground::Dark,
+            StyleBackground::Light => TerminalBackground::Light,
+        };
+        let dark_defaults = Self::default_for(TerminalBackground::Dark);
+        let defaults = Self::default_for(background);
+        let unstyled = Self {
+            background: self.background,
+            color_depth: self.color_depth,
+            ..Self::default()
+        };
+        if self == unstyled {
+            return Self {
+                background: self.background,
+                color_depth: self.color_depth,
+                ..defaults
+            };
+        }
+
+        for (style, dark_default, default) in [
+            (
+                &mut self.description,
+                dark_defaults.description,
+                defaults.description,
+            ),
+            (
+                &mut self.command_name,
+                dark_defaults.command_name,
+                defaults.command_name,
+            ),
+            (
+                &mut self.example_text,
+                dark_defaults.example_text,
+                defaults.example_text,
+            ),
+            (
+                &mut self.example_code,
+                dark_defaults.example_code,
+                defaults.example_code,
+            ),
+            (
+                &mut self.example_variable,
+                dark_defaults.example_variable,
+                defaults.example_variable,
+            ),
+        ] {
+            if style.normalized() == dark_default.normalized() {
+                *style = default;
+            }
         }
+        self
+    }
+}
+
+/// The background color of the terminal, which the default styles are chosen for.
+#[derive(Copy, Clone, Default, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StyleBackground {
+    /// Detect the background from the `COLORFGBG` env variable, assuming a dark background if it
+    /// is not set.
+    #[default]
+    Auto,
+    Dark,
+    Light,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum TerminalBackground {
+    Dark,
+    Light,
+}
+
+impl TerminalBackground {
+    /// Detect the background from the value of the `COLORFGBG` env variable, which some terminals
+    /// (like Konsole and rxvt) set to the foreground and background color, e.g. `15;0`. The
+    /// background is the last of the ANSI color numbers, of which only white (7) and bright white
+    /// (15) are light.
+    fn from_colorfgbg(colorfgbg: Option<&str>) -> Option<Self> {
+        let background: u8 = colorfgbg?.rsplit(';').next()?.parse().ok()?;
+        Some(if matches!(background, 7 | 15) {
+            Self::Light
+        } else {
+            Self::Dark
+        })
     }
 }
 
@@ -182,6 +427,26 @@ struct RawDisplayConfig {
     #[serde(default)]
     pub show_title: bool,
     #[serde(default)]
+    pub title_decoration: TitleDecoration,
+    #[serde(default)]
+    pub option_style: OptionStyle,
+    #[serde(default)]
+    pub shell: ExampleShell,
+    #[serde(default)]
+    pub show_example_numbers: bool,
+    #[serde(default)]
+    pub raw_when_piped: bool,
+    #[serde(default)]
+    pub example_values: bool,
+    #[serde(default)]
+    pub wrap_text: bool,
+    #[serde(default)]
+    pub expand_templates: bool,
+    #[serde(default)]
+    pub follow_aliases: bool,
+    #[serde(default)]
+    pub color: ColorOptions,
+    #[serde(default)]
     pub indent: RawIndent,
 }
 
@@ -208,6 +473,23 @@ impl From<&RawDisplayConfig> for DisplayConfig {
             compact: raw_display_config.compact,
             use_pager: raw_display_config.use_pager,
             show_title: raw_display_config.show_title,
+            title_decoration: raw_display_config.title_decoration,
+            option_style: raw_display_config.option_style,
+            shell: raw_display_config.shell,
+            show_example_numbers: raw_display_config.show_example_numbers,
+            raw_when_piped: raw_display_config.raw_when_piped,
+            wrap_text: raw_display_config.wrap_text,
+            expand_templates: raw_display_config.expand_templates,
+            follow_aliases: raw_display_config.follow_aliases,
+            color: raw_display_config.color,
+            width: None,
+            example: None,
+            grep: None,
+            strict: false,
+            // Set in `Config::from_raw`, as the values are in the `placeholders` section
+            example_values: None,
+            // Set in `Config::from_raw`, as it depends on the `style` section
+            highlight_options: false,
             indent: Indent {
                 base: raw_display_config.indent.base,
                 command: raw_display_config.indent.command,
@@ -224,10 +506,22 @@ const fn default_auto_update_interval_hours() -> u64 {
     DEFAULT_UPDATE_INTERVAL_HOURS
 }
 
+const fn default_min_update_attempt_interval_minutes() -> u64 {
+    DEFAULT_MIN_UPDATE_ATTEMPT_INTERVAL_MINUTES
+}
+
 fn default_archive_source() -> String {
     "https://github.com/tldr-pages/tldr/releases/latest/download".to_owned()
 }
 
+fn default_pages_source() -> String {
+    "https://raw.githubusercontent.com/tldr-pages/tldr/main".to_owned()
+}
+
+fn default_index_url() -> String {
+    "https://tldr.sh/assets/index.json".to_owned()
+}
+
 /// Controls when a warning about an outdated cache is printed.
 ///
 /// Currently, the only nameable option is `"never"`. In the future, this may
@@ -245,14 +539,40 @@ struct RawUpdatesConfig {
     pub auto_update: bool,
     #[serde(default = "default_auto_update_interval_hours")]
     pub auto_update_interval_hours: u64,
+    #[serde(default)]
+    pub auto_update_jitter_hours: u64,
+    #[serde(default = "default_min_update_attempt_interval_minutes")]
+    pub min_update_attempt_interval_minutes: u64,
     #[serde(default = "default_archive_source")]
     pub archive_source: String,
     #[serde(default)]
+    pub archive_format: ArchiveFormat,
+    #[serde(default)]
     pub tls_backend: RawTlsBackend,
     #[serde(default)]
     pub download_languages: Option<Vec<String>>,
     #[serde(default)]
+    pub download_platforms: Option<Vec<RawPlatformType>>,
+    #[serde(default)]
     pub warn_cache_age: Option<RawWarnCacheAge>,
+    #[serde(default)]
+    pub fetch_missing: bool,
+    #[serde(default = "default_pages_source")]
+    pub pages_source: String,
+    #[serde(default = "default_auto_update_interval_hours")]
+    pub fetched_pages_ttl_hours: u64,
+    #[serde(default)]
+    pub use_index: bool,
+    #[serde(default = "default_index_url")]
+    pub index_url: String,
+    #[serde(default)]
+    pub check_new_version: bool,
+    #[serde(default)]
+    pub archive_public_key: Option<String>,
+    #[serde(default)]
+    pub show_changes: bool,
+    #[serde(default)]
+    pub max_cache_size_mb: Option<u64>,
 }
 
 impl Default for RawUpdatesConfig {
@@ -260,23 +580,41 @@ impl Default for RawUpdatesConfig {
         Self {
             auto_update: false,
             auto_update_interval_hours: DEFAULT_UPDATE_INTERVAL_HOURS,
+            auto_update_jitter_hours: 0,
+            min_update_attempt_interval_minutes: DEFAULT_MIN_UPDATE_ATTEMPT_INTERVAL_MINUTES,
             archive_source: default_archive_source(),
+            archive_format: ArchiveFormat::default(),
             tls_backend: RawTlsBackend::default(),
             download_languages: None,
+            download_platforms: None,
             warn_cache_age: None,
+            fetch_missing: false,
+            pages_source: default_pages_source(),
+            fetched_pages_ttl_hours: DEFAULT_UPDATE_INTERVAL_HOURS,
+            use_index: false,
+            index_url: default_index_url(),
+            check_new_version: false,
+            archive_public_key: None,
+            show_changes: false,
+            max_cache_size_mb: None,
         }
     }
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[allow(clippy::struct_field_names)] // The field names are the config keys
 struct RawDirectoriesConfig {
     #[serde(default)]
     pub cache_dir: Option<PathBuf>,
     #[serde(default)]
     pub custom_pages_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub state_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub system_pages_dir: Option<PathBuf>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 enum RawPlatformType {
     Current,
@@ -302,10 +640,77 @@ impl RawPlatformType {
     }
 }
 
+fn default_cheat_sh_url() -> String {
+    "https://cheat.sh".to_owned()
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+enum RawSourceKind {
+    #[serde(rename = "cheat")]
+    Cheat,
+    #[serde(rename = "cheat.sh")]
+    CheatSh,
+    #[serde(rename = "tldr")]
+    Tldr,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+struct RawSourceConfig {
+    pub kind: RawSourceKind,
+    /// The directory of a `cheat` source.
+    pub path: Option<PathBuf>,
+    /// The server of a `cheat.sh` source, or the archive source of a `tldr` source.
+    pub url: Option<String>,
+    /// The languages of a `tldr` source.
+    pub languages: Option<Vec<String>>,
+    #[serde(default)]
+    pub priority: i64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+struct RawHistoryConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub boost_frequent_pages: bool,
+}
+
+impl From<&RawHistoryConfig> for HistoryConfig {
+    fn from(raw_history_config: &RawHistoryConfig) -> Self {
+        Self {
+            enabled: raw_history_config.enabled,
+            boost_frequent_pages: raw_history_config.boost_frequent_pages,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+struct RawDeprecationsConfig {
+    #[serde(default)]
+    pub hidden: Vec<Deprecation>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+struct RawHooksConfig {
+    pub pre_update: Option<String>,
+    pub post_update: Option<String>,
+}
+
+impl<'a> From<&'a RawHooksConfig> for HooksConfig<'a> {
+    fn from(raw_hooks_config: &'a RawHooksConfig) -> Self {
+        Self {
+            pre_update: raw_hooks_config.pre_update.as_deref(),
+            post_update: raw_hooks_config.post_update.as_deref(),
+        }
+    }
+}
+
 #[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
 struct RawSearchConfig {
     pub languages: Option<Vec<String>>,
     pub platforms: Option<Vec<RawPlatformType>>,
+    #[serde(default)]
+    pub platform_fallbacks: BTreeMap<RawPlatformType, Vec<RawPlatformType>>,
 }
 
 impl<'a> From<&'a RawSearchConfig> for SearchConfig<'a> {
@@ -325,11 +730,52 @@ impl<'a> From<&'a RawSearchConfig> for SearchConfig<'a> {
                 RawPlatformType::All,
             ])
         };
+        let platform_fallbacks = raw_search_config
+            .platform_fallbacks
+            .iter()
+            .flat_map(|(platform, fallbacks)| {
+                let fallbacks = RawPlatformType::flatten(fallbacks.iter().copied());
+                RawPlatformType::flatten([*platform])
+                    .into_iter()
+                    .map(move |platform| (platform, fallbacks.clone()))
+            })
+            .collect();
 
-        Self {
+        let mut search = Self {
             languages,
-            platforms,
+            languages_configured: raw_search_config.languages.is_some(),
+            platforms: Vec::new(),
+            platform_fallbacks,
+        };
+        search.platforms = search.compute_platforms(&platforms);
+        search
+    }
+}
+
+impl SearchConfig<'_> {
+    /// Insert the fallbacks from `platform_fallbacks` after every platform in `platforms`, and
+    /// their fallbacks after them, in turn. Platforms are only searched once, at their first
+    /// position.
+    pub fn compute_platforms(&self, platforms: &[PlatformType]) -> Vec<PlatformType> {
+        fn push_with_fallbacks(
+            platform: PlatformType,
+            fallbacks: &BTreeMap<PlatformType, Vec<PlatformType>>,
+            computed: &mut Vec<PlatformType>,
+        ) {
+            if computed.contains(&platform) {
+                return;
+            }
+            computed.push(platform);
+            for &fallback in fallbacks.get(&platform).into_iter().flatten() {
+                push_with_fallbacks(fallback, fallbacks, computed);
+            }
         }
+
+        let mut computed = Vec::new();
+        for &platform in platforms {
+            push_with_fallbacks(platform, &self.platform_fallbacks, &mut computed);
+        }
+        computed
     }
 }
 
@@ -341,26 +787,34 @@ struct RawConfig {
     updates: RawUpdatesConfig,
     directories: RawDirectoriesConfig,
     search: RawSearchConfig,
+    history: RawHistoryConfig,
+    hooks: RawHooksConfig,
+    deprecations: RawDeprecationsConfig,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    sources: BTreeMap<String, RawSourceConfig>,
+    /// Values for placeholders shown with `display.example_values`, by placeholder text.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    placeholders: BTreeMap<String, String>,
+    /// Commands that are looked up instead of their aliases, by alias.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    aliases: BTreeMap<String, String>,
 }
 
 impl Default for RawConfig {
     fn default() -> Self {
-        let mut raw_config = RawConfig {
-            style: RawStyleConfig::default(),
+        RawConfig {
+            style: RawStyleConfig::default_for(TerminalBackground::Dark),
             display: RawDisplayConfig::default(),
             updates: RawUpdatesConfig::default(),
             directories: RawDirectoriesConfig::default(),
             search: RawSearchConfig::default(),
-        };
-
-        // Set default config
-        raw_config.style.example_text.foreground = Some(RawColor::Green);
-        raw_config.style.command_name.foreground = Some(RawColor::Cyan);
-        raw_config.style.example_code.foreground = Some(RawColor::Cyan);
-        raw_config.style.example_variable.foreground = Some(RawColor::Cyan);
-        raw_config.style.example_variable.underline = true;
-
-        raw_config
+            history: RawHistoryConfig::default(),
+            hooks: RawHooksConfig::default(),
+            deprecations: RawDeprecationsConfig::default(),
+            sources: BTreeMap::new(),
+            placeholders: BTreeMap::new(),
+            aliases: BTreeMap::new(),
+        }
     }
 }
 
@@ -371,16 +825,99 @@ pub struct StyleConfig {
     pub example_text: Style,
     pub example_code: Style,
     pub example_variable: Style,
+    pub example_option: Style,
+    pub title: Style,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+impl StyleConfig {
+    /// Replace the colors that are not supported with `depth` with the nearest supported ones.
+    fn with_color_depth(self, depth: ColorDepth) -> Self {
+        Self {
+            description: depth.limit_style(self.description),
+            command_name: depth.limit_style(self.command_name),
+            example_text: depth.limit_style(self.example_text),
+            example_code: depth.limit_style(self.example_code),
+            example_variable: depth.limit_style(self.example_variable),
+            example_option: depth.limit_style(self.example_option),
+            title: depth.limit_style(self.title),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct DisplayConfig {
     pub compact: bool,
     pub use_pager: bool,
     pub show_title: bool,
+    pub title_decoration: TitleDecoration,
+    pub option_style: OptionStyle,
+    pub shell: ExampleShell,
+    pub show_example_numbers: bool,
+    /// Whether to print pages raw when the output isn't a terminal.
+    pub raw_when_piped: bool,
+    pub wrap_text: bool,
+    /// Whether to expand template variables like `{hostname}` in custom pages and patches.
+    pub expand_templates: bool,
+    /// Whether to show the page of the aliased command instead of a page for an alias.
+    pub follow_aliases: bool,
+    /// Whether to use styles, unless overridden by `--color`.
+    pub color: ColorOptions,
+    /// The width of the terminal, at which text is wrapped if `wrap_text` is enabled.
+    pub width: Option<usize>,
+    /// Only render the example with this number (starting at 1), set by `--example`.
+    pub example: Option<usize>,
+    /// Only render the examples that contain this text, set by `--grep`.
+    pub grep: Option<String>,
+    /// Fail on pages with unrecognized lines instead of warning about them, set by `--strict`.
+    pub strict: bool,
+    /// Values shown instead of the placeholders with the same text, if `example_values` is
+    /// enabled.
+    pub example_values: Option<BTreeMap<String, String>>,
+    /// Whether options in examples are yielded on their own, which is only needed if they are
+    /// shown differently than the rest of the code.
+    pub highlight_options: bool,
     pub indent: Indent,
 }
 
+/// How the title of a page is decorated if `show_title` is enabled.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TitleDecoration {
+    /// Only the command name.
+    #[default]
+    None,
+    /// A line below the command name.
+    Underline,
+    /// A box around the command name.
+    Box,
+    /// A `#` in front of the command name, like in the markdown of the page.
+    Hash,
+}
+
+/// How placeholders with alternative options, like `{{[-f|--format]}}`, are shown.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OptionStyle {
+    /// All alternatives, e.g. `[-f|--format]`.
+    #[default]
+    Both,
+    /// Only the short option, e.g. `-f`.
+    Short,
+    /// Only the long option, e.g. `--format`.
+    Long,
+}
+
+/// The shell that examples are adapted to, see `display.shell`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExampleShell {
+    /// A POSIX shell like bash or zsh, which the examples are written for.
+    #[default]
+    Sh,
+    Fish,
+    Powershell,
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Indent {
     pub base: usize,
@@ -391,10 +928,33 @@ pub struct Indent {
 pub struct UpdatesConfig<'a> {
     pub auto_update: bool,
     pub auto_update_interval: Duration,
-    pub archive_source: &'a str,
+    /// The maximum random time added to `auto_update_interval`, so that not all shells started
+    /// at once update the cache at the same time.
+    pub auto_update_jitter: Duration,
+    /// The time after an attempted update before automatic updates are attempted again.
+    pub min_update_attempt_interval: Duration,
+    /// The URL of the archives, where local directories are given as `file://` URLs.
+    pub archive_source: Cow<'a, str>,
+    pub archive_format: ArchiveFormat,
     pub tls_backend: TlsBackend,
     pub download_languages: Vec<Language<'a>>,
+    pub download_platforms: Vec<PlatformType>,
     pub warn_cache_age: Option<Duration>,
+    pub fetch_missing: bool,
+    pub pages_source: &'a str,
+    pub fetched_pages_ttl: Duration,
+    /// Whether to download the index of the tldr pages instead of archives, and fetch pages from
+    /// `pages_source` when they are looked up.
+    pub use_index: bool,
+    pub index_url: &'a str,
+    /// Whether to check for a new release of tealdeer when the cache is updated.
+    pub check_new_version: bool,
+    /// The key that the archives of the tldr pages must be signed with, if any.
+    pub archive_public_key: Option<PublicKey>,
+    /// Whether to print which pages were added, removed and modified by an update.
+    pub show_changes: bool,
+    /// The size in bytes that the pages in the cache may take up after an update, if limited.
+    pub max_cache_size: Option<u64>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -416,20 +976,82 @@ impl fmt::Display for PathWithSource {
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[allow(clippy::struct_field_names)]
 pub struct DirectoriesConfig {
     pub cache_dir: PathWithSource,
     pub custom_pages_dir: Option<PathWithSource>,
+    /// Directory for metadata that changes while using tealdeer, like the `ETag`s of the
+    /// downloaded archives.
+    pub state_dir: PathWithSource,
+    /// Read-only pages directory shared by all users, searched after the user's cache.
+    pub system_pages_dir: Option<PathWithSource>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct HistoryConfig {
+    /// Whether page lookups are recorded in the history.
+    pub enabled: bool,
+    /// Whether frequently looked up pages are preferred when suggesting similar pages.
+    pub boost_frequent_pages: bool,
+}
+
+/// Commands that are run around events, like updates of the cache.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct HooksConfig<'a> {
+    /// Run before the cache is updated.
+    pub pre_update: Option<&'a str>,
+    /// Run after the cache was updated successfully.
+    pub post_update: Option<&'a str>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct SearchConfig<'a> {
     pub languages: Vec<Language<'a>>,
+    /// Whether `languages` are set in the config instead of being taken from the locale.
+    pub languages_configured: bool,
     pub platforms: Vec<PlatformType>,
+    /// The platforms to search after a platform, before continuing with the next one in
+    /// `platforms`.
+    pub platform_fallbacks: BTreeMap<PlatformType, Vec<PlatformType>>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Language<'a>(pub &'a str);
 
+/// Check that `language` looks like a language code (e.g. `de` or `pt_BR`).
+///
+/// Language codes are used to build directory names in the cache, so this also makes sure that
+/// they cannot point outside of it.
+pub fn validate_language(language: &str) -> Result<()> {
+    ensure!(
+        !language.is_empty()
+            && language
+                .chars()
+                .all(|chr| chr.is_ascii_alphanumeric() || chr == '_'),
+        "Invalid language `{language}`, expected a language code like `de` or `pt_BR`",
+    );
+    Ok(())
+}
+
+/// Parse a language given on the command line, see [`validate_language`].
+pub fn parse_language(language: &str) -> Result<String> {
+    validate_language(language)?;
+    Ok(language.to_string())
+}
+
+/// Parse the name of a profile given on the command line. The name is part of a file name, so
+/// only letters, digits, `-` and `_` are allowed.
+pub fn parse_profile(profile: &str) -> Result<String> {
+    ensure!(
+        !profile.is_empty()
+            && profile
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+        "Invalid profile `{profile}`, the name may only contain letters, digits, `-` and `_`",
+    );
+    Ok(profile.to_string())
+}
+
 fn get_languages<'a>(
     env_lang: Option<&'a str>,
     env_language: Option<&'a str>,
@@ -475,6 +1097,30 @@ pub fn get_languages_from_env<'a>() -> Vec<Language<'a>> {
     )
 }
 
+/// The format of the archives at `archive_source`, which determines their file extension.
+///
+/// When extracting an archive, the format is detected from its content.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ArchiveFormat {
+    #[default]
+    #[serde(rename = "zip")]
+    Zip,
+    #[serde(rename = "tar.gz")]
+    TarGz,
+    #[serde(rename = "tar.zst")]
+    TarZst,
+}
+
+impl ArchiveFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Zip => "zip",
+            Self::TarGz => "tar.gz",
+            Self::TarZst => "tar.zst",
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum RawTlsBackend {
@@ -488,8 +1134,17 @@ pub enum RawTlsBackend {
 
 impl Default for RawTlsBackend {
     fn default() -> Self {
-        *SUPPORTED_TLS_BACKENDS.first().unwrap()
+        default_tls_backend(SUPPORTED_TLS_BACKENDS, cfg!(target_os = "android"))
+    }
+}
+
+/// The first of the `supported` backends, except on Android, where the bundled `WebPKI` roots are
+/// preferred: The native roots can only be looked up by Android apps, not in Termux.
+fn default_tls_backend(supported: &[RawTlsBackend], android: bool) -> RawTlsBackend {
+    if android && supported.contains(&RawTlsBackend::RustlsWithWebpkiRoots) {
+        return RawTlsBackend::RustlsWithWebpkiRoots;
     }
+    *supported.first().unwrap()
 }
 
 impl std::fmt::Display for RawTlsBackend {
@@ -560,6 +1215,18 @@ pub struct Config<'a> {
     pub updates: UpdatesConfig<'a>,
     pub directories: DirectoriesConfig,
     pub search: SearchConfig<'a>,
+    pub history: HistoryConfig,
+    pub hooks: HooksConfig<'a>,
+    /// The deprecated features whose warnings are hidden, see [`DeprecationWarnings`].
+    ///
+    /// [`DeprecationWarnings`]: crate::deprecation::DeprecationWarnings
+    pub hidden_deprecations: Vec<Deprecation>,
+    /// Additional sources of pages, sorted by descending priority.
+    pub sources: Vec<PageSource>,
+    /// Values for placeholders from the `[placeholders]` section, by placeholder text.
+    pub placeholders: BTreeMap<String, String>,
+    /// Commands from the `[aliases]` section, by alias.
+    pub aliases: BTreeMap<String, String>,
     pub file_path: PathWithSource,
 }
 
@@ -569,33 +1236,109 @@ impl<'a> Config<'a> {
     /// For this, some values need to be converted to other types and some
     /// defaults need to be set (sometimes based on env variables).
     fn from_raw(raw_config: &'a RawConfig, config_file_path: PathWithSource) -> Result<Self> {
-        let style = (&raw_config.style).into();
-        let display = (&raw_config.display).into();
+        for (key, languages) in [
+            ("search.languages", &raw_config.search.languages),
+            (
+                "updates.download_languages",
+                &raw_config.updates.download_languages,
+            ),
+        ] {
+            for language in languages.iter().flatten() {
+                validate_language(language).with_context(|| format!("Invalid value in `{key}`"))?;
+            }
+        }
+
+        let colorfgbg = env::var("COLORFGBG").ok();
+        let style = raw_config
+            .style
+            .for_background(colorfgbg.as_deref())
+            .resolve()
+            .context("Invalid style config")?
+            .with_color_depth(raw_config.style.color_depth.resolve(
+                env::var("TERM").ok().as_deref(),
+                env::var("COLORTERM").ok().as_deref(),
+            ));
+        let mut display: DisplayConfig = (&raw_config.display).into();
+        display.highlight_options = style.example_option != style.example_code;
+        if raw_config.display.example_values {
+            let mut values: BTreeMap<String, String> = DEFAULT_EXAMPLE_VALUES
+                .iter()
+                .map(|(placeholder, value)| ((*placeholder).to_string(), (*value).to_string()))
+                .collect();
+            values.extend(raw_config.placeholders.clone());
+            display.example_values = Some(values);
+        }
         let search: SearchConfig<'a> = (&raw_config.search).into();
+        let history = (&raw_config.history).into();
+
+        let relative_path_root = config_file_path
+            .path()
+            .parent()
+            .context("Failed to get config directory")?;
+        let home_path = env::home_dir();
 
         let updates = UpdatesConfig {
             auto_update: raw_config.updates.auto_update,
             auto_update_interval: Duration::from_secs(
                 raw_config.updates.auto_update_interval_hours * 3600,
             ),
-            archive_source: &raw_config.updates.archive_source,
+            auto_update_jitter: Duration::from_secs(
+                raw_config.updates.auto_update_jitter_hours * 3600,
+            ),
+            min_update_attempt_interval: Duration::from_secs(
+                raw_config.updates.min_update_attempt_interval_minutes * 60,
+            ),
+            archive_source: resolve_archive_source(
+                &raw_config.updates.archive_source,
+                relative_path_root,
+                home_path.as_deref(),
+            )?,
+            archive_format: raw_config.updates.archive_format,
             tls_backend: raw_config.updates.tls_backend.try_into()?,
             download_languages: raw_config.updates.download_languages.as_ref().map_or_else(
                 || search.languages.clone(),
                 |languages| languages.iter().map(|lang| Language(lang)).collect(),
             ),
+            download_platforms: RawPlatformType::flatten(
+                raw_config
+                    .updates
+                    .download_platforms
+                    .as_deref()
+                    .unwrap_or(&[RawPlatformType::All])
+                    .iter()
+                    .copied(),
+            ),
             warn_cache_age: match raw_config.updates.warn_cache_age {
                 None => Some(MAX_CACHE_AGE),
                 Some(RawWarnCacheAge::Never) => None,
             },
+            fetch_missing: raw_config.updates.fetch_missing,
+            pages_source: &raw_config.updates.pages_source,
+            fetched_pages_ttl: Duration::from_secs(
+                raw_config.updates.fetched_pages_ttl_hours * 3600,
+            ),
+            use_index: raw_config.updates.use_index,
+            index_url: &raw_config.updates.index_url,
+            check_new_version: raw_config.updates.check_new_version,
+            archive_public_key: raw_config
+                .updates
+                .archive_public_key
+                .as_deref()
+                .map(PublicKey::parse)
+                .transpose()
+                .context("Invalid public key in updates.archive_public_key")?,
+            show_changes: raw_config.updates.show_changes,
+            max_cache_size: raw_config
+                .updates
+                .max_cache_size_mb
+                .map(|megabytes| {
+                    megabytes
+                        .checked_mul(1024 * 1024)
+                        .context("The value of `updates.max_cache_size_mb` is too large")
+                })
+                .transpose()?,
         };
 
-        let relative_path_root = config_file_path
-            .path()
-            .parent()
-            .context("Failed to get config directory")?;
-        let home_path = env::home_dir();
-
         // Determine directories config. For this, we need to take some
         // additional factory into account, like env variables, or the
         // user config.
@@ -603,8 +1346,7 @@ impl<'a> Config<'a> {
         let cache_dir = if let Ok(env_var) = env::var(cache_dir_env_var) {
             // For backwards compatibility reasons, the cache directory can be
             // overridden using an env variable. This is deprecated and will be
-            // phased out in the future.
-            eprintln!("Warning: The ${cache_dir_env_var} env variable is deprecated, use the `cache_dir` option in the config file instead.");
+            // phased out in the future, the warning is shown by `main`.
             PathWithSource {
                 path: PathBuf::from(env_var),
                 source: PathSource::EnvVar,
@@ -619,7 +1361,7 @@ impl<'a> Config<'a> {
                 path: resolved_path,
                 source: PathSource::ConfigFile,
             }
-        } else if let Ok(default_dir) = get_app_root(AppDataType::UserCache, &crate::APP_INFO) {
+        } else if let Ok(default_dir) = default_app_dir(AppDataType::UserCache) {
             // Otherwise, fall back to the default user cache directory.
             PathWithSource {
                 path: default_dir,
@@ -646,7 +1388,7 @@ impl<'a> Config<'a> {
             })
             .transpose()?
             .or_else(|| {
-                get_app_root(AppDataType::UserData, &crate::APP_INFO)
+                default_app_dir(AppDataType::UserData)
                     .map(|path| {
                         // Note: The `join("")` call ensures that there's a trailing slash
                         PathWithSource {
@@ -656,20 +1398,129 @@ impl<'a> Config<'a> {
                     })
                     .ok()
             });
+        let state_dir = if let Some(config_value) = &raw_config.directories.state_dir {
+            // Resolve possible ~ prefixed path
+            let expanded_path = expand_home(config_value, home_path.as_deref())?;
+            // Resolve possible relative path.
+            let resolved_path = relative_path_root.join(expanded_path);
+
+            PathWithSource {
+                path: resolved_path,
+                source: PathSource::ConfigFile,
+            }
+        } else {
+            PathWithSource {
+                path: get_default_state_dir(home_path.as_deref())?,
+                source: PathSource::OsConvention,
+            }
+        };
+        let system_pages_dir = raw_config
+            .directories
+            .system_pages_dir
+            .as_ref()
+            .map(|path| -> Result<PathWithSource> {
+                let expanded_path = expand_home(path, home_path.as_deref())?;
+                Ok(PathWithSource {
+                    path: relative_path_root.join(expanded_path),
+                    source: PathSource::ConfigFile,
+                })
+            })
+            .transpose()?;
         let directories = DirectoriesConfig {
             cache_dir,
             custom_pages_dir,
+            state_dir,
+            system_pages_dir,
         };
 
+        let mut sources = raw_config
+            .sources
+            .iter()
+            .map(|(name, source)| -> Result<PageSource> {
+                let kind = match source.kind {
+                    RawSourceKind::Cheat => {
+                        let path = source.path.as_ref().with_context(|| {
+                            format!("The `cheat` source `{name}` requires a `path`")
+                        })?;
+                        let expanded_path = expand_home(path, home_path.as_deref())?;
+                        PageSourceKind::CheatDirectory(relative_path_root.join(expanded_path))
+                    }
+                    RawSourceKind::CheatSh => PageSourceKind::CheatSh(
+                        source.url.clone().unwrap_or_else(default_cheat_sh_url),
+                    ),
+                    RawSourceKind::Tldr => {
+                        // The name is used as a directory name in the cache
+                        ensure!(
+                            !name.is_empty()
+                                && name
+                                    .chars()
+                                    .all(|c| { c.is_ascii_alphanumeric() || c == '-' || c == '_' }),
+                            "The name of the `tldr` source `{name}` may only contain letters, \
+                             digits, `-` and `_`"
+                        );
+                        let url = source.url.as_deref().with_context(|| {
+                            format!("The `tldr` source `{name}` requires a `url`")
+                        })?;
+                        let archive_source =
+                            resolve_archive_source(url, relative_path_root, home_path.as_deref())?
+                                .into_owned();
+                        for language in source.languages.iter().flatten() {
+                            validate_language(language).with_context(|| {
+                                format!("Invalid value in `sources.{name}.languages`")
+                            })?;
+                        }
+                        PageSourceKind::TldrArchive {
+                            archive_source,
+                            languages: source.languages.clone(),
+                            directory: directories.cache_dir.path().join(SOURCES_DIR).join(name),
+                        }
+                    }
+                };
+                Ok(PageSource {
+                    name: name.clone(),
+                    kind,
+                    priority: source.priority,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        // The sort is stable, so sources with the same priority are ordered by name
+        sources.sort_by_key(|source| std::cmp::Reverse(source.priority));
+
         Ok(Self {
             style,
             display,
             updates,
             directories,
             search,
+            history,
+            hooks: (&raw_config.hooks).into(),
+            hidden_deprecations: raw_config.deprecations.hidden.clone(),
+            sources,
+            placeholders: raw_config.placeholders.clone(),
+            aliases: raw_config
+                .aliases
+                .iter()
+                .map(|(alias, command)| (alias.to_lowercase(), command.to_lowercase()))
+                .collect(),
             file_path: config_file_path,
         })
     }
+
+    /// Replace the first word of `command` with the command it is an alias for in the
+    /// `[aliases]` section. Aliases are only resolved once, so they can't refer to each other.
+    pub fn resolve_alias<'c>(&'c self, command: &'c [String]) -> Vec<&'c str> {
+        let Some((first, rest)) = command.split_first() else {
+            return Vec::new();
+        };
+        let first = match self.aliases.get(&first.to_lowercase()) {
+            Some(resolved) => resolved.as_str(),
+            None => first.as_str(),
+        };
+        first
+            .split_whitespace()
+            .chain(rest.iter().map(String::as_str))
+            .collect()
+    }
 }
 
 /// Expands tilde (~) prefixed directories into its absolute version
@@ -695,56 +1546,631 @@ fn expand_home<'a>(input_path: &'a Path, home_path: Option<&Path>) -> Result<Cow
     Ok(Cow::Borrowed(input_path))
 }
 
-/// The [`ConfigLoader`] is used to load a [`Config`] from a file.
-///
-/// Since the rich [`Config`] keeps references to [`RawConfig`], the raw config needs to be kept alive outside of the
-/// [`Config`]. The [`ConfigLoader`] thus offers the following flow:
-/// 1. Read a raw config using [`ConfigLoader::read`] or [`ConfigLoader::read_default_path`].
-/// 2. Validate the contents to a [`Config`] that borrows the [`ConfigLoader`].
-pub struct ConfigLoader {
-    raw: RawConfig,
-    path: PathWithSource,
+/// Resolve an `archive_source` that is a path instead of a URL to a `file://` URL. Like other
+/// paths in the config, `~` is expanded and relative paths are relative to the config file.
+fn resolve_archive_source<'s>(
+    archive_source: &'s str,
+    relative_path_root: &Path,
+    home_path: Option<&Path>,
+) -> Result<Cow<'s, str>> {
+    if archive_source.contains("://") {
+        return Ok(Cow::Borrowed(archive_source));
+    }
+    let expanded_path = expand_home(Path::new(archive_source), home_path)?;
+    Ok(Cow::Owned(format!(
+        "file://{}",
+        relative_path_root.join(expanded_path).display()
+    )))
 }
 
-impl ConfigLoader {
-    fn read_internal(path: PathWithSource, allow_not_found: bool) -> Result<Self> {
-        match fs::read_to_string(&path.path) {
-            Ok(content) => Ok(Self {
-                raw: toml::from_str(&content).with_context(|| {
-                    format!(
-                        "Could not parse config file contents as toml from {}.",
-                        path.path.display()
-                    )
-                })?,
-                path,
-            }),
-            Err(e) if allow_not_found && e.kind() == ErrorKind::NotFound => Ok(Self {
-                raw: RawConfig::default(),
-                path,
-            }),
-            Err(e) => Err(e).context(format!(
-                "Could not read config file contents from {}.",
-                path.path().display()
-            )),
+/// Where the value of a config option comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ValueSource {
+    Default,
+    SystemConfigFile,
+    ConfigFile,
+    EnvVar(String),
+    Cli,
+    Inferred(&'static str),
+}
+
+impl fmt::Display for ValueSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => f.write_str("default"),
+            Self::SystemConfigFile => f.write_str("system config file"),
+            Self::ConfigFile => f.write_str("config file"),
+            Self::EnvVar(name) => write!(f, "env variable {name}"),
+            Self::Cli => f.write_str("command line argument"),
+            Self::Inferred(description) => f.write_str(description),
+        }
+    }
+}
+
+/// Record `source` for all values in `table`, using dotted keys starting with `prefix`.
+fn record_sources(
+    sources: &mut BTreeMap<String, ValueSource>,
+    table: &toml::Table,
+    prefix: &str,
+    source: &ValueSource,
+) {
+    for (key, value) in table {
+        let key = format!("{prefix}{key}");
+        match value {
+            toml::Value::Table(section) => {
+                record_sources(sources, section, &format!("{key}."), source);
+            }
+            _ => {
+                sources.insert(key, source.clone());
+            }
+        }
+    }
+}
+
+/// Find the recorded source of the dotted `key`, or of one of the options in it if it's a section.
+fn find_source<'a>(
+    sources: &'a BTreeMap<String, ValueSource>,
+    key: &str,
+) -> Option<&'a ValueSource> {
+    sources
+        .iter()
+        .find(|(source_key, _)| is_same_or_child_key(source_key, key))
+        .map(|(_, source)| source)
+}
+
+/// Return a table containing all known config options.
+fn known_config_keys() -> toml::Table {
+    let style = RawStyle {
+        foreground: Some(RawColor::Black),
+        background: Some(RawColor::Black),
+        underline: Some(false),
+        bold: Some(false),
+        italic: Some(false),
+        base: Some(StyleTarget::Description),
+        enabled: Some(true),
+    };
+    let raw_config = RawConfig {
+        style: RawStyleConfig {
+            background: StyleBackground::Auto,
+            color_depth: ColorDepth::Auto,
+            description: style,
+            command_name: style,
+            example_text: style,
+            example_code: style,
+            example_variable: style,
+            example_option: Some(style),
+            title: Some(style),
+        },
+        updates: RawUpdatesConfig {
+            download_languages: Some(Vec::new()),
+            download_platforms: Some(Vec::new()),
+            warn_cache_age: Some(RawWarnCacheAge::Never),
+            archive_public_key: Some(String::new()),
+            max_cache_size_mb: Some(0),
+            ..RawUpdatesConfig::default()
+        },
+        directories: RawDirectoriesConfig {
+            cache_dir: Some(PathBuf::new()),
+            custom_pages_dir: Some(PathBuf::new()),
+            state_dir: Some(PathBuf::new()),
+            system_pages_dir: Some(PathBuf::new()),
+        },
+        search: RawSearchConfig {
+            languages: Some(Vec::new()),
+            platforms: Some(Vec::new()),
+            platform_fallbacks: BTreeMap::new(),
+        },
+        hooks: RawHooksConfig {
+            pre_update: Some(String::new()),
+            post_update: Some(String::new()),
+        },
+        ..RawConfig::default()
+    };
+    toml::Table::try_from(raw_config).expect("Failed to serialize config")
+}
+
+/// Find the known config key in the same section that is most similar to the unknown dotted `key`.
+fn suggest_known_key(known_keys: &toml::Table, key: &str) -> Option<String> {
+    let (section, name) = key.rsplit_once('.').unwrap_or(("", key));
+    let mut candidates = known_keys;
+    for part in section.split('.').filter(|part| !part.is_empty()) {
+        candidates = candidates.get(part)?.as_table()?;
+    }
+
+    candidates
+        .keys()
+        .map(|candidate| (strsim::jaro_winkler(name, candidate), candidate))
+        .filter(|(similarity, _)| *similarity > 0.8)
+        .max_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, candidate)| {
+            if section.is_empty() {
+                candidate.clone()
+            } else {
+                format!("{section}.{candidate}")
+            }
+        })
+}
+
+/// Whether the dotted `key` is `parent` or an option in the section `parent`.
+fn is_same_or_child_key(key: &str, parent: &str) -> bool {
+    key.strip_prefix(parent)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+/// The option or section at the dotted `key` in `table`.
+fn get_dotted_key<'t>(table: &'t toml::Table, key: &str) -> Option<&'t toml::Value> {
+    let (sections, option) = key.rsplit_once('.').unwrap_or(("", key));
+    let mut current = table;
+    for section in sections.split('.').filter(|section| !section.is_empty()) {
+        current = current.get(section)?.as_table()?;
+    }
+    current.get(option)
+}
+
+/// The source of the option at the dotted `key`, which is the recorded source of the option or
+/// of its closest section. Options without a recorded source are defaults.
+fn source_of<'s>(sources: &'s BTreeMap<String, ValueSource>, key: &str) -> &'s ValueSource {
+    sources
+        .iter()
+        .filter(|(source_key, _)| is_same_or_child_key(key, source_key))
+        .max_by_key(|(source_key, _)| source_key.len())
+        .map_or(&ValueSource::Default, |(_, source)| source)
+}
+
+/// Set the option at the dotted `key` in `table`, creating sections as needed.
+fn set_dotted_key(table: &mut toml::Table, key: &str, value: toml::Value) {
+    let (sections, option) = key.rsplit_once('.').unwrap_or(("", key));
+    let mut current = table;
+    for section in sections.split('.').filter(|section| !section.is_empty()) {
+        let entry = current
+            .entry(section)
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+        if !entry.is_table() {
+            *entry = toml::Value::Table(toml::Table::new());
+        }
+        current = entry.as_table_mut().unwrap();
+    }
+    current.insert(option.to_string(), value);
+}
+
+fn languages_to_value(languages: &[Language]) -> toml::Value {
+    toml::Value::Array(
+        languages
+            .iter()
+            .map(|Language(language)| toml::Value::String((*language).to_string()))
+            .collect(),
+    )
+}
+
+/// Write the options in `table` as TOML, followed by a comment noting their source.
+///
+/// Options without a recorded source (or a recorded source for one of their sections) are
+/// annotated as defaults.
+fn write_annotated_table(
+    output: &mut impl fmt::Write,
+    table: &toml::Table,
+    section: &str,
+    sources: &BTreeMap<String, ValueSource>,
+) -> fmt::Result {
+    let (options, sections): (Vec<_>, Vec<_>) =
+        table.iter().partition(|(_, value)| !value.is_table());
+
+    if !options.is_empty() && !section.is_empty() {
+        write!(output, "\n[{section}]\n")?;
+    }
+    for (option, value) in options {
+        let key = if section.is_empty() {
+            option.clone()
+        } else {
+            format!("{section}.{option}")
+        };
+        writeln!(output, "{option} = {value} # {}", source_of(sources, &key))?;
+    }
+
+    for (name, value) in sections {
+        let subsection = if section.is_empty() {
+            name.clone()
+        } else {
+            format!("{section}.{name}")
+        };
+        if let toml::Value::Table(subtable) = value {
+            write_annotated_table(output, subtable, &subsection, sources)?;
+        }
+    }
+    Ok(())
+}
+
+/// Read the config file at `path` as a TOML table.
+///
+/// Returns `None` if `allow_not_found` is set and there is no file at `path`.
+fn read_config_table(path: &Path, allow_not_found: bool) -> Result<Option<toml::Table>> {
+    match fs::read_to_string(path) {
+        Ok(content) => toml::from_str(&content).map(Some).with_context(|| {
+            format!(
+                "Could not parse config file contents as toml from {}.",
+                path.display()
+            )
+        }),
+        Err(e) if allow_not_found && e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).context(format!(
+            "Could not read config file contents from {}.",
+            path.display()
+        )),
+    }
+}
+
+/// Recursively merge the config `overlay` into `base`.
+///
+/// Sections present in both tables are merged, all other values in `overlay` replace the ones in
+/// `base`.
+fn merge_config_tables(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_section)), toml::Value::Table(overlay_section)) => {
+                merge_config_tables(base_section, overlay_section);
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Prefix of environment variables that override config options.
+const ENV_OVERRIDE_PREFIX: &str = "TEALDEER_";
+
+/// Separator between the sections of a config key in an override environment variable.
+const ENV_OVERRIDE_SEPARATOR: &str = "__";
+
+/// Merge config overrides from environment variables into the config `table`.
+///
+/// A variable like `TEALDEER_UPDATES__AUTO_UPDATE=true` sets the option `updates.auto_update`.
+/// Values are parsed as TOML values, and used as plain strings if that fails. Variables without a
+/// section separator (like `TEALDEER_CONFIG_DIR`) are not config overrides and are ignored.
+///
+/// Returns the dotted keys of the overridden options together with the names of the variables.
+fn apply_env_overrides(
+    table: &mut toml::Table,
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> Result<Vec<(String, String)>> {
+    let mut overrides: Vec<_> = vars
+        .into_iter()
+        .filter_map(|(name, value)| {
+            let key = name.strip_prefix(ENV_OVERRIDE_PREFIX)?.to_lowercase();
+            key.contains(ENV_OVERRIDE_SEPARATOR)
+                .then_some((key, name, value))
+        })
+        .collect();
+    // Make the result independent of the order of the environment
+    overrides.sort_unstable();
+
+    let mut applied = Vec::new();
+    for (key, name, value) in overrides {
+        let (sections, option) = key.rsplit_once(ENV_OVERRIDE_SEPARATOR).unwrap();
+        let mut current = &mut *table;
+        for section in sections.split(ENV_OVERRIDE_SEPARATOR) {
+            let entry = current
+                .entry(section)
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+            let Some(section_table) = entry.as_table_mut() else {
+                bail!(
+                    "Cannot apply `{ENV_OVERRIDE_PREFIX}{}`: `{section}` is not a config section.",
+                    key.to_uppercase()
+                );
+            };
+            current = section_table;
         }
+        current.insert(option.to_owned(), parse_override_value(value));
+        applied.push((key.replace(ENV_OVERRIDE_SEPARATOR, "."), name));
     }
 
-    /// Create a loader that uses the config at `path`.
-    pub fn read(path: PathBuf) -> Result<Self> {
+    Ok(applied)
+}
+
+/// Apply `--set key=value` overrides from the command line to `table`, see
+/// [`parse_config_override`]. Values are parsed like those of environment variables.
+fn apply_cli_overrides(table: &mut toml::Table, overrides: &[(String, String)]) -> Result<()> {
+    for (key, value) in overrides {
+        let (sections, option) = key.rsplit_once('.').unwrap_or(("", key));
+        let mut current = &mut *table;
+        for section in sections.split('.').filter(|section| !section.is_empty()) {
+            let entry = current
+                .entry(section)
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+            let Some(section_table) = entry.as_table_mut() else {
+                bail!("Cannot apply `--set {key}=...`: `{section}` is not a config section.");
+            };
+            current = section_table;
+        }
+        current.insert(option.to_owned(), parse_override_value(value.clone()));
+    }
+    Ok(())
+}
+
+/// Parse a `key=value` override given with `--set`, where `key` is a dotted config key like
+/// `display.compact`.
+pub fn parse_config_override(arg: &str) -> Result<(String, String)> {
+    let (key, value) = arg
+        .split_once('=')
+        .with_context(|| format!("Invalid override `{arg}`, expected `KEY=VALUE`"))?;
+    ensure!(
+        !key.is_empty() && key.split('.').all(|part| !part.is_empty()),
+        "Invalid config key `{key}`, expected a key like `display.compact`",
+    );
+    Ok((key.to_owned(), value.to_owned()))
+}
+
+fn parse_override_value(value: String) -> toml::Value {
+    toml::from_str::<toml::Table>(&format!("value = {value}"))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or(toml::Value::String(value))
+}
+
+/// The [`ConfigLoader`] is used to load a [`Config`] from a file.
+///
+/// The user config file is merged on top of the system-wide config file (see
+/// [`get_system_config_path`]), if present. Options can be overridden with
+/// `TEALDEER_<SECTION>__<OPTION>` environment variables, which are merged on top of both (see
+/// [`apply_env_overrides`]), and with `--set` on the command line, which takes precedence over
+/// everything else (see [`apply_cli_overrides`]).
+///
+/// Since the rich [`Config`] keeps references to [`RawConfig`], the raw config needs to be kept alive outside of the
+/// [`Config`]. The [`ConfigLoader`] thus offers the following flow:
+/// 1. Read a raw config using [`ConfigLoader::read`] or [`ConfigLoader::read_default_path`].
+/// 2. Validate the contents to a [`Config`] that borrows the [`ConfigLoader`].
+pub struct ConfigLoader {
+    raw: RawConfig,
+    path: PathWithSource,
+    sources: BTreeMap<String, ValueSource>,
+    warnings: Vec<String>,
+}
+
+impl ConfigLoader {
+    fn read_internal(
+        path: PathWithSource,
+        allow_not_found: bool,
+        cli_overrides: &[(String, String)],
+    ) -> Result<Self> {
+        let mut sources = BTreeMap::new();
+
+        let mut table = toml::Table::new();
+        let system_path = get_system_config_path();
+        if let Some(system_path) = &system_path {
+            if let Some(system_table) = read_config_table(system_path.path(), true)? {
+                record_sources(
+                    &mut sources,
+                    &system_table,
+                    "",
+                    &ValueSource::SystemConfigFile,
+                );
+                table = system_table;
+            }
+        }
+        if let Some(user_table) = read_config_table(path.path(), allow_not_found)? {
+            record_sources(&mut sources, &user_table, "", &ValueSource::ConfigFile);
+            merge_config_tables(&mut table, user_table);
+        }
+
+        for (key, name) in apply_env_overrides(&mut table, env::vars())? {
+            sources.retain(|existing, _| !is_same_or_child_key(existing, &key));
+            sources.insert(key, ValueSource::EnvVar(name));
+        }
+        apply_cli_overrides(&mut table, cli_overrides)?;
+        for (key, _) in cli_overrides {
+            sources.retain(|existing, _| !is_same_or_child_key(existing, key));
+            sources.insert(key.clone(), ValueSource::Cli);
+        }
+        let mut unknown_keys = Vec::new();
+        let raw = serde_ignored::deserialize(toml::Value::Table(table), |key| {
+            unknown_keys.push(key.to_string());
+        })
+        .with_context(|| {
+            format!(
+                "Could not parse config from {}, `{ENV_OVERRIDE_PREFIX}*` environment variables and \
+                 `--set` options.",
+                path.path.display()
+            )
+        })?;
+
+        // Unknown keys are not an error, so that configs keep working when options are removed
+        let mut warnings = Vec::new();
+        if !unknown_keys.is_empty() {
+            let known_keys = known_config_keys();
+            let messages = unknown_keys.iter().map(|key| {
+                let location = match (find_source(&sources, key), &system_path) {
+                    (Some(ValueSource::SystemConfigFile), Some(system_path)) => {
+                        format!("system config file {}", system_path.path().display())
+                    }
+                    (Some(ValueSource::EnvVar(name)), _) => format!("env variable {name}"),
+                    (Some(ValueSource::Cli), _) => "`--set`".to_string(),
+                    _ => format!("config file {}", path.path().display()),
+                };
+                match suggest_known_key(&known_keys, key) {
+                    Some(suggestion) => {
+                        format!("Unknown key `{key}` in {location}, did you mean `{suggestion}`?")
+                    }
+                    None => format!("Unknown key `{key}` in {location}"),
+                }
+            });
+            warnings.extend(messages);
+        }
+
+        Ok(Self {
+            raw,
+            path,
+            sources,
+            warnings,
+        })
+    }
+
+    /// Create a loader that uses the config at `path`. The `cli_overrides` are applied on top,
+    /// see [`apply_cli_overrides`].
+    pub fn read(path: PathBuf, cli_overrides: &[(String, String)]) -> Result<Self> {
         Self::read_internal(
             PathWithSource {
                 path,
                 source: PathSource::Cli,
             },
             false,
+            cli_overrides,
         )
     }
 
     /// Create a loader that uses the default config file location. If no file is present at the default location, the
     /// default configuration is used.
-    pub fn read_default_path() -> Result<Self> {
+    pub fn read_default_path(cli_overrides: &[(String, String)]) -> Result<Self> {
         let path = get_default_config_path().context("Could not determine default config path.")?;
-        Self::read_internal(path, true)
+        Self::read_internal(path, true, cli_overrides)
+    }
+
+    /// Render the effective configuration as TOML, with a comment noting the source of each value.
+    ///
+    /// `config` must have been loaded from this loader. Options that are not set explicitly are
+    /// filled in from the resolved `config`, and `cli_overrides` (dotted keys and values) replace
+    /// the options overridden by command line arguments.
+    pub fn effective_config(
+        &self,
+        config: &Config,
+        cli_overrides: Vec<(&str, toml::Value)>,
+    ) -> Result<String> {
+        let (table, sources) = self.effective_table(config, cli_overrides)?;
+        let mut output = format!("# Effective configuration, config file: {}\n", self.path);
+        write_annotated_table(&mut output, &table, "", &sources)?;
+        Ok(output)
+    }
+
+    /// Describe the config option or section at the dotted `key`: its description from the docs,
+    /// its default value and its effective value (see [`ConfigLoader::effective_config`]).
+    pub fn explain_config(
+        &self,
+        config: &Config,
+        cli_overrides: Vec<(&str, toml::Value)>,
+        key: &str,
+    ) -> Result<String> {
+        let (table, sources) = self.effective_table(config, cli_overrides)?;
+        let known_keys = known_config_keys();
+        // The names of sources, placeholders and aliases are chosen by the user
+        let is_user_table = ["sources", "placeholders", "aliases"]
+            .iter()
+            .any(|table| is_same_or_child_key(key, table));
+        if get_dotted_key(&known_keys, key).is_none()
+            && !(is_user_table && get_dotted_key(&table, key).is_some())
+        {
+            match suggest_known_key(&known_keys, key) {
+                Some(suggestion) => {
+                    bail!("Unknown config key `{key}`, did you mean `{suggestion}`?")
+                }
+                None => bail!("Unknown config key `{key}`."),
+            }
+        }
+
+        let mut output = format!("{key}\n");
+        let docs = config_docs::option_docs(key);
+        if let Some(description) = docs.as_ref().and_then(|docs| docs.description.as_ref()) {
+            write!(output, "\n{description}\n")?;
+        }
+        output.push('\n');
+        // The options of sections are shown like with `--print-config`
+        if let Some(toml::Value::Table(section)) = get_dotted_key(&table, key) {
+            if let Some(docs) = &docs {
+                writeln!(output, "Docs:    {}", docs.url)?;
+            }
+            write_annotated_table(&mut output, section, key, &sources)?;
+            return Ok(output);
+        }
+
+        let default_table = toml::Table::try_from(RawConfig::default())
+            .context("Could not serialize the default config")?;
+        match get_dotted_key(&default_table, key) {
+            Some(value) => writeln!(output, "Default: {value}")?,
+            None => writeln!(output, "Default: not set")?,
+        }
+        match get_dotted_key(&table, key) {
+            Some(value) => writeln!(output, "Current: {value} ({})", source_of(&sources, key))?,
+            None => writeln!(output, "Current: not set")?,
+        }
+        if let Some(docs) = &docs {
+            writeln!(output, "Docs:    {}", docs.url)?;
+        }
+        Ok(output)
+    }
+
+    /// The effective configuration as a TOML table, and the source of each value. See
+    /// [`ConfigLoader::effective_config`].
+    fn effective_table(
+        &self,
+        config: &Config,
+        cli_overrides: Vec<(&str, toml::Value)>,
+    ) -> Result<(toml::Table, BTreeMap<String, ValueSource>)> {
+        let mut table =
+            toml::Table::try_from(&self.raw).context("Could not serialize the effective config")?;
+        let mut sources = self.sources.clone();
+
+        let mut set_resolved = |key: &str, value: toml::Value, source: ValueSource| {
+            set_dotted_key(&mut table, key, value);
+            sources.retain(|existing, _| !is_same_or_child_key(existing, key));
+            sources.insert(key.to_string(), source);
+        };
+
+        if self.raw.search.languages.is_none() {
+            set_resolved(
+                "search.languages",
+                languages_to_value(&config.search.languages),
+                ValueSource::Inferred("from the LANG and LANGUAGE env variables"),
+            );
+        }
+        if self.raw.search.platforms.is_none() {
+            set_resolved(
+                "search.platforms",
+                toml::Value::try_from(&config.search.platforms)?,
+                ValueSource::Default,
+            );
+        }
+        if self.raw.updates.download_platforms.is_none() {
+            set_resolved(
+                "updates.download_platforms",
+                toml::Value::try_from(&config.updates.download_platforms)?,
+                ValueSource::Default,
+            );
+        }
+        if self.raw.updates.download_languages.is_none() {
+            set_resolved(
+                "updates.download_languages",
+                languages_to_value(&config.updates.download_languages),
+                ValueSource::Inferred("default: same as search.languages"),
+            );
+        }
+        for (key, path) in [
+            ("directories.cache_dir", Some(&config.directories.cache_dir)),
+            (
+                "directories.custom_pages_dir",
+                config.directories.custom_pages_dir.as_ref(),
+            ),
+            ("directories.state_dir", Some(&config.directories.state_dir)),
+        ] {
+            let Some(path) = path else { continue };
+            let source = match path.source {
+                PathSource::ConfigFile => continue,
+                PathSource::EnvVar => ValueSource::EnvVar("TEALDEER_CACHE_DIR".to_string()),
+                PathSource::OsConvention => ValueSource::Inferred("OS convention"),
+                PathSource::Cli => ValueSource::Cli,
+            };
+            set_resolved(
+                key,
+                toml::Value::String(path.path().display().to_string()),
+                source,
+            );
+        }
+        for (key, value) in cli_overrides {
+            set_resolved(key, value, ValueSource::Cli);
+        }
+        Ok((table, sources))
+    }
+
+    /// Problems found while reading the config that should be shown to the user, like unknown keys.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
     }
 
     /// Parse the read [`RawConfig`] into a [`Config`].
@@ -769,11 +2195,97 @@ pub fn get_config_dir() -> Result<(PathBuf, PathSource)> {
     }
 
     // Otherwise, fall back to the user config directory.
-    let dirs = get_app_root(AppDataType::UserConfig, &crate::APP_INFO)
+    let dirs = default_app_dir(AppDataType::UserConfig)
         .context("Failed to determine the user config directory")?;
     Ok((dirs, PathSource::OsConvention))
 }
 
+/// Return the default directory of `data_type` for tealdeer.
+///
+/// Termux has no Android app context to look up the directories of the app with, so the XDG base
+/// directories in the home directory of Termux are used there.
+fn default_app_dir(data_type: AppDataType) -> Result<PathBuf> {
+    if is_termux() {
+        return termux_app_dir(data_type, env::home_dir().as_deref(), |name| {
+            env::var_os(name)
+        });
+    }
+    Ok(get_app_root(data_type, &crate::APP_INFO)?)
+}
+
+fn termux_app_dir(
+    data_type: AppDataType,
+    home: Option<&Path>,
+    env_var: impl Fn(&str) -> Option<std::ffi::OsString>,
+) -> Result<PathBuf> {
+    let (env_var_name, default) = match data_type {
+        AppDataType::UserCache => ("XDG_CACHE_HOME", [".cache"].as_slice()),
+        AppDataType::UserConfig => ("XDG_CONFIG_HOME", [".config"].as_slice()),
+        _ => ("XDG_DATA_HOME", [".local", "share"].as_slice()),
+    };
+    // Relative paths are invalid according to the specification and must be ignored
+    let base = match env_var(env_var_name)
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+    {
+        Some(base) => base,
+        None => home
+            .context("Could not determine the home directory")?
+            .join(PathBuf::from_iter(default)),
+    };
+    Ok(base.join(crate::NAME))
+}
+
+/// Return the default state directory.
+///
+/// On Linux and other Unix systems, this follows the XDG base directory specification and uses
+/// `$XDG_STATE_HOME/tealdeer`, falling back to `~/.local/state/tealdeer`. macOS and Windows have
+/// no separate location for state, so the user data directory is used there.
+fn get_default_state_dir(home_path: Option<&Path>) -> Result<PathBuf> {
+    if cfg!(any(target_os = "macos", windows)) {
+        return default_app_dir(AppDataType::UserData)
+            .context("Failed to determine the user data directory");
+    }
+
+    // Relative paths are invalid according to the specification and must be ignored
+    let state_home = env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute());
+    match (state_home, home_path) {
+        (Some(state_home), _) => Ok(state_home.join(crate::NAME)),
+        (None, Some(home)) => Ok(home.join(".local").join("state").join(crate::NAME)),
+        (None, None) => bail!("Could not determine the state directory"),
+    }
+}
+
+/// Return the path to the system-wide config file.
+///
+/// The system config dir path can be overridden using the `TEALDEER_SYSTEM_CONFIG_DIR` env
+/// variable. Otherwise, `/etc/tealdeer` is used on Unix and `%ProgramData%\tealdeer` on Windows.
+///
+/// Note that this function does not verify whether the file at that location
+/// exists, or is a file.
+pub fn get_system_config_path() -> Option<PathWithSource> {
+    if let Some(value) = env::var_os("TEALDEER_SYSTEM_CONFIG_DIR") {
+        return Some(PathWithSource {
+            path: PathBuf::from(value).join(CONFIG_FILE_NAME),
+            source: PathSource::EnvVar,
+        });
+    }
+
+    let config_dir = if cfg!(windows) {
+        PathBuf::from(env::var_os("ProgramData")?).join("tealdeer")
+    } else if cfg!(unix) {
+        PathBuf::from("/etc/tealdeer")
+    } else {
+        return None;
+    };
+    Some(PathWithSource {
+        path: config_dir.join(CONFIG_FILE_NAME),
+        source: PathSource::OsConvention,
+    })
+}
+
 /// Return the path to the config file.
 ///
 /// Note that this function does not verify whether the file at that location
@@ -787,10 +2299,60 @@ pub fn get_default_config_path() -> Result<PathWithSource> {
     })
 }
 
+/// Return the path to the config file of `profile`, i.e. `config.<profile>.toml` next to the
+/// default config file.
+pub fn get_profile_config_path(profile: &str) -> Result<PathBuf> {
+    let (config_dir, _) = get_config_dir()?;
+    Ok(config_dir.join(format!("config.{profile}.toml")))
+}
+
 /// Create default config file.
 /// path: Can be specified to create the config in that path instead of
 /// the default path.
 pub fn make_default_config(path: Option<&Path>) -> Result<PathBuf> {
+    write_config(path, &RawConfig::default(), false)
+}
+
+/// The color theme chosen in the setup wizard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetupTheme {
+    /// The default colors.
+    Default,
+    /// No colors, only bold and underlined text.
+    Monochrome,
+}
+
+/// The choices made in the setup wizard (`tldr --setup`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetupChoices {
+    pub auto_update: bool,
+    pub languages: Vec<String>,
+    pub use_pager: bool,
+    pub theme: SetupTheme,
+}
+
+/// Create a config file from the choices made in the setup wizard.
+///
+/// An existing config file is only replaced if `overwrite` is set. See [`make_default_config`]
+/// for the meaning of `path`.
+pub fn make_setup_config(
+    path: Option<&Path>,
+    choices: &SetupChoices,
+    overwrite: bool,
+) -> Result<PathBuf> {
+    let mut raw_config = RawConfig::default();
+    raw_config.updates.auto_update = choices.auto_update;
+    raw_config.search.languages = Some(choices.languages.clone());
+    raw_config.display.use_pager = choices.use_pager;
+    if choices.theme == SetupTheme::Monochrome {
+        raw_config.style = RawStyleConfig::default();
+        raw_config.style.command_name.bold = Some(true);
+        raw_config.style.example_variable.underline = Some(true);
+    }
+    write_config(path, &raw_config, overwrite)
+}
+
+fn write_config(path: Option<&Path>, raw_config: &RawConfig, overwrite: bool) -> Result<PathBuf> {
     let config_file_path = if let Some(p) = path {
         p.into()
     } else {
@@ -812,25 +2374,235 @@ pub fn make_default_config(path: Option<&Path>) -> Result<PathBuf> {
 
     // Ensure that a config file doesn't get overwritten
     ensure!(
-        !config_file_path.is_file(),
+        overwrite || !config_file_path.is_file(),
         "A configuration file already exists at {}, no action was taken.",
         config_file_path.to_str().unwrap()
     );
 
-    // Create default config
-    let serialized_config =
-        toml::to_string(&RawConfig::default()).context("Failed to serialize default config")?;
+    // Create config
+    let serialized_config = toml::to_string(raw_config).context("Failed to serialize config")?;
 
-    // Write default config
-    let mut config_file =
-        File::create(&config_file_path).context("Could not create config file")?;
-    let _wc = config_file
-        .write(serialized_config.as_bytes())
-        .context("Could not write to config file")?;
+    // Write config
+    write_atomically(&config_file_path, serialized_config)
+        .context("Could not create config file")?;
 
     Ok(config_file_path)
 }
 
+/// Rewrite deprecated options in `content` (the contents of a config file) to the current format.
+///
+/// Returns the migrated contents and a description of each change. Formatting and comments are
+/// preserved.
+fn migrate_config_content(
+    content: &str,
+    cache_dir_env_var: Option<&str>,
+) -> Result<(String, Vec<String>)> {
+    let mut document: toml_edit::DocumentMut = content
+        .parse()
+        .context("Could not parse config file as toml")?;
+    let mut changes = Vec::new();
+
+    if let Some(style) = document
+        .get_mut("style")
+        .and_then(toml_edit::Item::as_table_like_mut)
+    {
+        if style.remove("highlight").is_some() {
+            changes.push("Removed the obsolete `style.highlight` section".to_string());
+        }
+        for (name, item) in style.iter_mut() {
+            let Some(element) = item.as_table_like_mut() else {
+                continue;
+            };
+            for key in ["foreground", "background"] {
+                let Some(value) = element.get_mut(key).and_then(toml_edit::Item::as_value_mut)
+                else {
+                    continue;
+                };
+                if value.as_str() == Some("purple") {
+                    let decor = value.decor().clone();
+                    *value = "magenta".into();
+                    *value.decor_mut() = decor;
+                    changes.push(format!(
+                        "Replaced the color `purple` with `magenta` in `style.{name}.{key}`"
+                    ));
+                }
+            }
+        }
+    }
+
+    if let Some(cache_dir) = cache_dir_env_var {
+        let directories = document
+            .entry("directories")
+            .or_insert_with(toml_edit::table)
+            .as_table_like_mut()
+            .context("`directories` in the config file is not a table")?;
+        if !directories.contains_key("cache_dir") {
+            directories.insert("cache_dir", toml_edit::value(cache_dir));
+            changes.push(format!(
+                "Moved the cache directory from the $TEALDEER_CACHE_DIR env variable to `directories.cache_dir` ({cache_dir})"
+            ));
+        }
+    }
+
+    Ok((document.to_string(), changes))
+}
+
+/// Migrate the config file at `path` to the current format, see [`migrate_config_content`].
+///
+/// If anything was changed, the original file is kept as a backup next to it, with `.bak`
+/// appended to its name. Returns the path of the backup, if any, and a description of each change.
+pub fn migrate_config(path: &Path) -> Result<(Option<PathBuf>, Vec<String>)> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Could not read config file from {}", path.display()))?;
+    let cache_dir_env_var = env::var("TEALDEER_CACHE_DIR").ok();
+    let (migrated, changes) = migrate_config_content(&content, cache_dir_env_var.as_deref())
+        .with_context(|| format!("Could not migrate config file at {}", path.display()))?;
+    if changes.is_empty() {
+        return Ok((None, changes));
+    }
+
+    let mut backup_path = path.as_os_str().to_owned();
+    backup_path.push(".bak");
+    let backup_path = PathBuf::from(backup_path);
+    ensure!(
+        !backup_path.exists(),
+        "A backup of the config file already exists at {}, please move it out of the way first",
+        backup_path.display(),
+    );
+    fs::copy(path, &backup_path).with_context(|| {
+        format!(
+            "Could not back up the config file to {}",
+            backup_path.display()
+        )
+    })?;
+    write_atomically(path, migrated)
+        .with_context(|| format!("Could not write config file to {}", path.display()))?;
+
+    Ok((Some(backup_path), changes))
+}
+
+/// The list of languages at `table.key` in a config file, if it is set.
+fn languages_in_document(
+    document: &toml_edit::DocumentMut,
+    table: &str,
+    key: &str,
+) -> Result<Option<Vec<String>>> {
+    let Some(item) = document.get(table).and_then(|table| table.get(key)) else {
+        return Ok(None);
+    };
+    let languages = item
+        .as_array()
+        .and_then(|array| {
+            array
+                .iter()
+                .map(|lang| lang.as_str().map(str::to_string))
+                .collect::<Option<Vec<_>>>()
+        })
+        .with_context(|| format!("`{table}.{key}` in the config file is not a list of strings"))?;
+    Ok(Some(languages))
+}
+
+/// Apply `edit` to `updates.download_languages` in `content` (the contents of a config file).
+/// Formatting and comments are preserved. Returns `None` if `edit` didn't change the languages.
+///
+/// If `content` sets the download languages, only those are edited, values from other config
+/// files, env variables or `--set` are never written to the file. Otherwise, `edit` starts with
+/// `effective_languages`, the download languages currently in use (e.g. the languages of the
+/// locale), so that none of them are dropped by the edit.
+fn edit_download_languages_content(
+    content: &str,
+    effective_languages: &[Language],
+    edit: impl FnOnce(&mut Vec<String>) -> Result<()>,
+) -> Result<Option<String>> {
+    let mut document: toml_edit::DocumentMut = content
+        .parse()
+        .context("Could not parse config file as toml")?;
+    let original = match languages_in_document(&document, "updates", "download_languages")? {
+        Some(languages) => languages,
+        None => effective_languages
+            .iter()
+            .map(|Language(lang)| lang.to_string())
+            .collect(),
+    };
+    let mut languages = original.clone();
+    edit(&mut languages)?;
+    if languages == original {
+        return Ok(None);
+    }
+
+    let updates = document
+        .entry("updates")
+        .or_insert_with(toml_edit::table)
+        .as_table_like_mut()
+        .context("`updates` in the config file is not a table")?;
+    updates.insert(
+        "download_languages",
+        toml_edit::value(languages.iter().collect::<toml_edit::Array>()),
+    );
+    Ok(Some(document.to_string()))
+}
+
+/// Apply `edit` to `updates.download_languages` in the config file at `path`, creating the file
+/// if it does not exist yet. See [`edit_download_languages_content`].
+fn edit_download_languages(
+    path: &Path,
+    effective_languages: &[Language],
+    edit: impl FnOnce(&mut Vec<String>) -> Result<()>,
+) -> Result<()> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+        Err(e) => {
+            return Err(e)
+                .with_context(|| format!("Could not read config file from {}", path.display()))
+        }
+    };
+    let Some(content) = edit_download_languages_content(&content, effective_languages, edit)
+        .with_context(|| format!("Could not edit config file at {}", path.display()))?
+    else {
+        return Ok(());
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("Could not create config directory")?;
+    }
+    write_atomically(path, content)
+        .with_context(|| format!("Could not write config file to {}", path.display()))
+}
+
+/// Add `language` to `updates.download_languages` in the config file at `path`.
+/// `effective_languages` are the download languages currently in use, see
+/// [`edit_download_languages_content`].
+pub fn add_download_language(
+    path: &Path,
+    effective_languages: &[Language],
+    language: &str,
+) -> Result<()> {
+    edit_download_languages(path, effective_languages, |languages| {
+        if !languages.iter().any(|lang| lang == language) {
+            languages.push(language.to_string());
+        }
+        Ok(())
+    })
+}
+
+/// Remove `language` from `updates.download_languages` in the config file at `path`.
+/// `effective_languages` are the download languages currently in use, see
+/// [`edit_download_languages_content`].
+pub fn remove_download_language(
+    path: &Path,
+    effective_languages: &[Language],
+    language: &str,
+) -> Result<()> {
+    edit_download_languages(path, effective_languages, |languages| {
+        languages.retain(|lang| lang != language);
+        ensure!(
+            !languages.is_empty(),
+            "`{language}` is the only language configured for download and cannot be removed.",
+        );
+        Ok(())
+    })
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -843,6 +2615,341 @@ mod test {
         assert_eq!(raw_config, deserialized);
     }
 
+    #[test]
+    fn merge_tables() {
+        let mut base: toml::Table = toml::from_str(
+            "[updates]\nauto_update = true\narchive_source = 'https://mirror'\n\
+             [style.command_name]\nforeground = 'red'\nbold = true\n",
+        )
+        .unwrap();
+        let overlay: toml::Table = toml::from_str(
+            "[updates]\nauto_update = false\n[style.command_name]\nforeground = 'blue'\n",
+        )
+        .unwrap();
+        merge_config_tables(&mut base, overlay);
+
+        let expected: toml::Table = toml::from_str(
+            "[updates]\nauto_update = false\narchive_source = 'https://mirror'\n\
+             [style.command_name]\nforeground = 'blue'\nbold = true\n",
+        )
+        .unwrap();
+        assert_eq!(base, expected);
+    }
+
+    #[test]
+    fn known_keys_match_defaults() {
+        // Every option in the default config must be a known key
+        let mut keys = BTreeMap::new();
+        let default_table = toml::Table::try_from(RawConfig::default()).unwrap();
+        record_sources(&mut keys, &default_table, "", &ValueSource::Default);
+        let mut known_keys = BTreeMap::new();
+        record_sources(
+            &mut known_keys,
+            &known_config_keys(),
+            "",
+            &ValueSource::Default,
+        );
+        assert!(keys.keys().all(|key| known_keys.contains_key(key)));
+        assert!(known_keys.contains_key("search.languages"));
+        assert!(known_keys.contains_key("style.command_name.foreground"));
+    }
+
+    #[test]
+    fn known_keys_cover_all_options() {
+        // Every field is set, so that no `Option` is skipped when serializing. The struct
+        // literals are exhaustive on purpose: adding an option without extending this test (and
+        // `known_config_keys`) doesn't compile.
+        let style = RawStyle {
+            foreground: Some(RawColor::Black),
+            background: Some(RawColor::Black),
+            underline: Some(false),
+            bold: Some(false),
+            italic: Some(false),
+            base: Some(StyleTarget::Description),
+            enabled: Some(true),
+        };
+        let raw_config = RawConfig {
+            style: RawStyleConfig {
+                background: StyleBackground::Auto,
+                color_depth: ColorDepth::Auto,
+                description: style,
+                command_name: style,
+                example_text: style,
+                example_code: style,
+                example_variable: style,
+                example_option: Some(style),
+                title: Some(style),
+            },
+            display: RawDisplayConfig {
+                compact: false,
+                use_pager: false,
+                show_title: false,
+                title_decoration: TitleDecoration::default(),
+                option_style: OptionStyle::default(),
+                shell: ExampleShell::default(),
+                show_example_numbers: false,
+                raw_when_piped: false,
+                example_values: false,
+                wrap_text: false,
+                expand_templates: false,
+                follow_aliases: false,
+                color: ColorOptions::default(),
+                indent: RawIndent {
+                    base: 0,
+                    command: 0,
+                },
+            },
+            updates: RawUpdatesConfig {
+                auto_update: false,
+                auto_update_interval_hours: 0,
+                auto_update_jitter_hours: 0,
+                min_update_attempt_interval_minutes: 0,
+                archive_source: String::new(),
+                archive_format: ArchiveFormat::default(),
+                tls_backend: RawTlsBackend::default(),
+                download_languages: Some(Vec::new()),
+                download_platforms: Some(Vec::new()),
+                warn_cache_age: Some(RawWarnCacheAge::Never),
+                fetch_missing: false,
+                pages_source: String::new(),
+                fetched_pages_ttl_hours: 0,
+                use_index: false,
+                index_url: String::new(),
+                check_new_version: false,
+                archive_public_key: Some(String::new()),
+                show_changes: false,
+                max_cache_size_mb: Some(0),
+            },
+            directories: RawDirectoriesConfig {
+                cache_dir: Some(PathBuf::new()),
+                custom_pages_dir: Some(PathBuf::new()),
+                state_dir: Some(PathBuf::new()),
+                system_pages_dir: Some(PathBuf::new()),
+            },
+            search: RawSearchConfig {
+                languages: Some(Vec::new()),
+                platforms: Some(Vec::new()),
+                platform_fallbacks: BTreeMap::new(),
+            },
+            history: RawHistoryConfig {
+                enabled: false,
+                boost_frequent_pages: false,
+            },
+            hooks: RawHooksConfig {
+                pre_update: Some(String::new()),
+                post_update: Some(String::new()),
+            },
+            deprecations: RawDeprecationsConfig { hidden: Vec::new() },
+            // The keys of these tables are chosen by the user
+            sources: BTreeMap::new(),
+            placeholders: BTreeMap::new(),
+            aliases: BTreeMap::new(),
+        };
+
+        let mut keys = BTreeMap::new();
+        record_sources(
+            &mut keys,
+            &toml::Table::try_from(raw_config).unwrap(),
+            "",
+            &ValueSource::Default,
+        );
+        let mut known_keys = BTreeMap::new();
+        record_sources(
+            &mut known_keys,
+            &known_config_keys(),
+            "",
+            &ValueSource::Default,
+        );
+        assert_eq!(
+            keys.keys().collect::<Vec<_>>(),
+            known_keys.keys().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn suggest_keys() {
+        let known_keys = known_config_keys();
+        assert_eq!(
+            suggest_known_key(&known_keys, "updates.auto_update_interval").as_deref(),
+            Some("updates.auto_update_interval_hours")
+        );
+        assert_eq!(
+            suggest_known_key(&known_keys, "display.compcat").as_deref(),
+            Some("display.compact")
+        );
+        assert_eq!(
+            suggest_known_key(&known_keys, "updaets").as_deref(),
+            Some("updates")
+        );
+        assert_eq!(suggest_known_key(&known_keys, "display.xyz"), None);
+    }
+
+    #[test]
+    fn env_overrides() {
+        let mut table: toml::Table =
+            toml::from_str("[display]\ncompact = false\nuse_pager = true\n").unwrap();
+        let vars = [
+            ("TEALDEER_DISPLAY__COMPACT", "true"),
+            ("TEALDEER_DISPLAY__INDENT__BASE", "4"),
+            ("TEALDEER_STYLE__EXAMPLE_TEXT__FOREGROUND", "red"),
+            ("TEALDEER_UPDATES__DOWNLOAD_LANGUAGES", "['en', 'de']"),
+            ("TEALDEER_CONFIG_DIR", "/ignored"),
+            ("OTHER__VARIABLE", "ignored"),
+        ]
+        .map(|(name, value)| (name.to_owned(), value.to_owned()));
+        let applied = apply_env_overrides(&mut table, vars).unwrap();
+        assert_eq!(applied[0].0, "display.compact");
+        assert_eq!(applied[0].1, "TEALDEER_DISPLAY__COMPACT");
+
+        let raw_config: RawConfig = table.try_into().unwrap();
+        assert!(raw_config.display.compact);
+        assert!(raw_config.display.use_pager);
+        assert_eq!(raw_config.display.indent.base, 4);
+        assert_eq!(
+            raw_config.style.example_text.foreground,
+            Some(RawColor::Red)
+        );
+        assert_eq!(
+            raw_config.updates.download_languages,
+            Some(vec!["en".to_owned(), "de".to_owned()])
+        );
+    }
+
+    #[test]
+    fn env_override_of_non_section() {
+        let mut table: toml::Table = toml::from_str("[display]\ncompact = true\n").unwrap();
+        let vars = [("TEALDEER_DISPLAY__COMPACT__X".to_owned(), "1".to_owned())];
+        assert!(apply_env_overrides(&mut table, vars).is_err());
+    }
+
+    #[test]
+    fn cli_overrides() {
+        let mut table: toml::Table = toml::from_str("[display]\ncompact = false\n").unwrap();
+        let overrides = ["display.compact=true", "style.command_name.foreground=red"]
+            .map(|arg| parse_config_override(arg).unwrap());
+        apply_cli_overrides(&mut table, &overrides).unwrap();
+
+        let raw_config: RawConfig = table.try_into().unwrap();
+        assert!(raw_config.display.compact);
+        assert_eq!(
+            raw_config.style.command_name.foreground,
+            Some(RawColor::Red)
+        );
+
+        assert!(parse_config_override("display.compact").is_err());
+        assert!(parse_config_override("display..compact=true").is_err());
+        let mut table: toml::Table = toml::from_str("[display]\ncompact = true\n").unwrap();
+        let overrides = [parse_config_override("display.compact.x=1").unwrap()];
+        assert!(apply_cli_overrides(&mut table, &overrides).is_err());
+    }
+
+    #[test]
+    fn setup_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE_NAME);
+        let choices = SetupChoices {
+            auto_update: true,
+            languages: vec!["de".to_owned(), "en".to_owned()],
+            use_pager: false,
+            theme: SetupTheme::Monochrome,
+        };
+        make_setup_config(Some(&path), &choices, false).unwrap();
+        assert!(make_setup_config(Some(&path), &choices, false).is_err());
+        make_setup_config(Some(&path), &choices, true).unwrap();
+
+        let raw_config: RawConfig = toml::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert!(raw_config.updates.auto_update);
+        assert_eq!(raw_config.search.languages, Some(choices.languages));
+        assert!(!raw_config.display.use_pager);
+        assert_eq!(raw_config.style.command_name.foreground, None);
+        assert_eq!(raw_config.style.command_name.bold, Some(true));
+    }
+
+    #[test]
+    fn migrate_config() {
+        let content = "# My config\n\
+                       [style.command_name]\nforeground = \"purple\" # favorite color\n\
+                       background = \"purple\"\n\n\
+                       [style.highlight]\nforeground = \"red\"\n";
+        let (migrated, changes) = migrate_config_content(content, Some("/cache")).unwrap();
+        assert_eq!(
+            migrated,
+            "# My config\n\
+             [style.command_name]\nforeground = \"magenta\" # favorite color\n\
+             background = \"magenta\"\n\
+             \n[directories]\ncache_dir = \"/cache\"\n"
+        );
+        assert_eq!(changes.len(), 4);
+
+        let (migrated_again, changes) = migrate_config_content(&migrated, Some("/cache")).unwrap();
+        assert_eq!(migrated_again, migrated);
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn edit_download_languages() {
+        let add = |language: &'static str| {
+            move |languages: &mut Vec<String>| {
+                languages.push(language.to_string());
+                Ok(())
+            }
+        };
+
+        let english = [Language("en")];
+        let content = "# My config\n[updates]\nauto_update = true # daily\n";
+        let edited = edit_download_languages_content(content, &english, add("de"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            edited,
+            "# My config\n[updates]\nauto_update = true # daily\n\
+             download_languages = [\"en\", \"de\"]\n"
+        );
+
+        let edited = edit_download_languages_content(&edited, &english, |languages| {
+            languages.retain(|lang| lang != "de");
+            Ok(())
+        })
+        .unwrap()
+        .unwrap();
+        assert!(edited.ends_with("download_languages = [\"en\"]\n"));
+        assert_eq!(
+            edit_download_languages_content(&edited, &english, |_| Ok(())).unwrap(),
+            None
+        );
+
+        // Without download languages in the file, the effective ones are the starting point
+        let edited = edit_download_languages_content(
+            "[search]\nlanguages = ['it']\n",
+            &[Language("it"), Language("en")],
+            add("fr"),
+        )
+        .unwrap()
+        .unwrap();
+        assert!(edited.ends_with("[updates]\ndownload_languages = [\"it\", \"en\", \"fr\"]\n"));
+
+        assert!(edit_download_languages_content(
+            "[updates]\ndownload_languages = 'en'\n",
+            &english,
+            add("fr")
+        )
+        .is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolve_local_archive_source() {
+        let root = Path::new("/etc/tealdeer");
+        let home = Some(Path::new("/home/user"));
+        let resolve = |source| resolve_archive_source(source, root, home).unwrap();
+
+        assert_eq!(resolve("https://mirror/tldr"), "https://mirror/tldr");
+        assert_eq!(resolve("file:///srv/tldr"), "file:///srv/tldr");
+        assert_eq!(resolve("/srv/tldr"), "file:///srv/tldr");
+        assert_eq!(resolve("mirror"), "file:///etc/tealdeer/mirror");
+        assert_eq!(resolve("~/mirror"), "file:///home/user/mirror");
+    }
+
     #[test]
     fn expand_path_with_valid_home() {
         let home = Some(PathBuf::from("/foo/bar"));
@@ -925,6 +3032,189 @@ mod test {
         );
     }
 
+    #[test]
+    fn resolve_alias() {
+        let raw_config = RawConfig {
+            aliases: BTreeMap::from([
+                ("k".into(), "kubectl".into()),
+                ("DC".into(), "docker compose".into()),
+            ]),
+            ..RawConfig::default()
+        };
+        let config = Config::from_raw(
+            &raw_config,
+            PathWithSource {
+                path: PathBuf::from("/path/to/config/config.toml"),
+                source: PathSource::OsConvention,
+            },
+        )
+        .unwrap();
+
+        let command = |words: &[&str]| words.iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            config.resolve_alias(&command(&["k", "get"])),
+            ["kubectl", "get"]
+        );
+        assert_eq!(
+            config.resolve_alias(&command(&["dc", "up"])),
+            ["docker", "compose", "up"]
+        );
+        // Only the first word is resolved
+        assert_eq!(config.resolve_alias(&command(&["tar", "k"])), ["tar", "k"]);
+        assert!(config.resolve_alias(&[]).is_empty());
+    }
+
+    #[test]
+    fn invalid_languages() {
+        assert!(validate_language("de").is_ok());
+        assert!(validate_language("pt_BR").is_ok());
+        assert!(validate_language("").is_err());
+        assert!(validate_language("../de").is_err());
+        assert!(validate_language("de en").is_err());
+
+        let mut raw_config = RawConfig::default();
+        raw_config.updates.download_languages = Some(vec!["en".into(), "../../etc".into()]);
+        let error = Config::from_raw(
+            &raw_config,
+            PathWithSource {
+                path: PathBuf::from("/path/to/config/config.toml"),
+                source: PathSource::OsConvention,
+            },
+        )
+        .unwrap_err();
+        assert!(format!("{error:?}").contains("updates.download_languages"));
+    }
+
+    #[test]
+    fn max_cache_size_overflow() {
+        let mut raw_config = RawConfig::default();
+        raw_config.updates.max_cache_size_mb = Some(u64::MAX / 1024);
+        let error = Config::from_raw(
+            &raw_config,
+            PathWithSource {
+                path: PathBuf::from("/path/to/config/config.toml"),
+                source: PathSource::OsConvention,
+            },
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("updates.max_cache_size_mb"));
+    }
+
+    #[test]
+    fn styles_for_background() {
+        let dark = RawStyleConfig::default_for(TerminalBackground::Dark);
+        let light = RawStyleConfig::default_for(TerminalBackground::Light);
+        assert_eq!(RawConfig::default().style.for_background(None), dark);
+        assert_eq!(
+            RawConfig::default().style.for_background(Some("0;15")),
+            light
+        );
+        assert_eq!(
+            RawConfig::default()
+                .style
+                .for_background(Some("15;default;0")),
+            dark
+        );
+
+        let configured = RawStyleConfig {
+            background: StyleBackground::Light,
+            ..RawStyleConfig::default()
+        };
+        assert_eq!(
+            configured.for_background(None),
+            RawStyleConfig {
+                background: StyleBackground::Light,
+                ..light
+            }
+        );
+
+        // Configured styles are kept, targets with the default style use the default for the
+        // background
+        let mut custom = RawStyleConfig {
+            background: StyleBackground::Light,
+            ..dark
+        };
+        custom.command_name.foreground = Some(RawColor::Red);
+        assert_eq!(
+            custom.for_background(None),
+            RawStyleConfig {
+                background: StyleBackground::Light,
+                command_name: custom.command_name,
+                ..light
+            }
+        );
+        let mut unset = configured;
+        unset.command_name.foreground = Some(RawColor::Red);
+        assert_eq!(unset.for_background(Some("0;15")), unset);
+    }
+
+    #[test]
+    fn style_inheritance() {
+        let raw_style_config: RawStyleConfig = toml::from_str(
+            "[example_code]\n\
+             foreground = 'blue'\n\
+             bold = true\n\
+             [example_variable]\n\
+             base = 'example_code'\n\
+             bold = false\n\
+             italic = true\n\
+             [command_name]\n\
+             base = 'example_code'\n\
+             enabled = false\n",
+        )
+        .unwrap();
+        let style = raw_style_config.resolve().unwrap();
+        assert_eq!(style.example_variable, Style::new().blue().italic());
+        assert_eq!(style.command_name, Style::new());
+        // `example_option` is based on `example_code` by default
+        assert_eq!(style.example_option, Style::new().blue().bold());
+        // The title is based on the disabled `command_name` style, but is enabled itself
+        assert_eq!(style.title, Style::new().blue().bold());
+
+        let raw_style_config: RawStyleConfig = toml::from_str(
+            "[example_code]\nbase = 'example_option'\n\
+             [example_option]\nbase = 'example_code'\n",
+        )
+        .unwrap();
+        let error = raw_style_config.resolve().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The style `example_code` is based on itself (`style.example_code.base`)"
+        );
+    }
+
+    #[test]
+    fn platform_fallbacks() {
+        let raw_search_config: RawSearchConfig = toml::from_str(
+            "platforms = ['openbsd', 'common', 'linux']\n\
+             [platform_fallbacks]\n\
+             openbsd = ['freebsd']\n\
+             freebsd = ['linux', 'openbsd']\n\
+             macos = ['linux']\n",
+        )
+        .unwrap();
+        let search = SearchConfig::from(&raw_search_config);
+
+        // Fallbacks are resolved transitively and each platform is only searched once
+        assert_eq!(
+            search.platforms,
+            [
+                PlatformType::OpenBsd,
+                PlatformType::FreeBsd,
+                PlatformType::Linux,
+                PlatformType::Common,
+            ]
+        );
+        assert_eq!(
+            search.compute_platforms(&[PlatformType::OsX, PlatformType::Common]),
+            [PlatformType::OsX, PlatformType::Linux, PlatformType::Common]
+        );
+        assert_eq!(
+            search.compute_platforms(&[PlatformType::Windows]),
+            [PlatformType::Windows]
+        );
+    }
+
     mod language {
         use super::*;
 
@@ -996,4 +3286,60 @@ mod test {
             );
         }
     }
+
+    mod android {
+        use super::*;
+
+        #[test]
+        fn termux_app_dirs() {
+            let home = Path::new("/data/data/com.termux/files/home");
+            let no_env = |_: &str| None;
+            assert_eq!(
+                termux_app_dir(AppDataType::UserCache, Some(home), no_env).unwrap(),
+                home.join(".cache").join("tealdeer"),
+            );
+            assert_eq!(
+                termux_app_dir(AppDataType::UserConfig, Some(home), no_env).unwrap(),
+                home.join(".config").join("tealdeer"),
+            );
+            assert_eq!(
+                termux_app_dir(AppDataType::UserData, Some(home), no_env).unwrap(),
+                home.join(".local").join("share").join("tealdeer"),
+            );
+            assert!(termux_app_dir(AppDataType::UserCache, None, no_env).is_err());
+
+            // Absolute XDG base directories take precedence
+            let cache_home = |name: &str| match name {
+                "XDG_CACHE_HOME" => Some("/sdcard/cache".into()),
+                "XDG_DATA_HOME" => Some("data".into()),
+                _ => None,
+            };
+            assert_eq!(
+                termux_app_dir(AppDataType::UserCache, Some(home), cache_home).unwrap(),
+                Path::new("/sdcard/cache").join("tealdeer"),
+            );
+            assert_eq!(
+                termux_app_dir(AppDataType::UserData, Some(home), cache_home).unwrap(),
+                home.join(".local").join("share").join("tealdeer"),
+            );
+        }
+
+        #[test]
+        fn tls_backend() {
+            let all = [
+                RawTlsBackend::NativeTls,
+                RawTlsBackend::RustlsWithWebpkiRoots,
+                RawTlsBackend::RustlsWithNativeRoots,
+            ];
+            assert_eq!(default_tls_backend(&all, false), RawTlsBackend::NativeTls);
+            assert_eq!(
+                default_tls_backend(&all, true),
+                RawTlsBackend::RustlsWithWebpkiRoots
+            );
+            assert_eq!(
+                default_tls_backend(&[RawTlsBackend::RustlsWithNativeRoots], true),
+                RawTlsBackend::RustlsWithNativeRoots
+            );
+        }
+    }
 }
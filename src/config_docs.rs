@@ -0,0 +1,219 @@
+//! Descriptions of the config options for `tldr --explain-config`.
+//!
+//! The descriptions are taken from the user documentation, so that they can't get out of sync
+//! with it: options are documented under a heading with their name (e.g. `` ## `use_pager` ``)
+//! or, like the style attributes, in a list item starting with their name.
+
+const DOCS_URL: &str = "https://tealdeer-rs.github.io/tealdeer";
+
+/// The documentation page of each config section, by section name.
+const SECTION_PAGES: &[(&str, &str, &str)] = &[
+    (
+        "aliases",
+        "config_aliases",
+        include_str!("../docs/src/config_aliases.md"),
+    ),
+    (
+        "deprecations",
+        "config_deprecations",
+        include_str!("../docs/src/config_deprecations.md"),
+    ),
+    (
+        "directories",
+        "config_directories",
+        include_str!("../docs/src/config_directories.md"),
+    ),
+    (
+        "display",
+        "config_display",
+        include_str!("../docs/src/config_display.md"),
+    ),
+    (
+        "history",
+        "config_history",
+        include_str!("../docs/src/config_history.md"),
+    ),
+    (
+        "hooks",
+        "config_hooks",
+        include_str!("../docs/src/config_hooks.md"),
+    ),
+    (
+        "search",
+        "config_search",
+        include_str!("../docs/src/config_search.md"),
+    ),
+    (
+        "sources",
+        "config_sources",
+        include_str!("../docs/src/config_sources.md"),
+    ),
+    (
+        "style",
+        "config_style",
+        include_str!("../docs/src/config_style.md"),
+    ),
+    (
+        "updates",
+        "config_updates",
+        include_str!("../docs/src/config_updates.md"),
+    ),
+];
+
+/// The documentation of a config option or section.
+#[derive(Debug, PartialEq, Eq)]
+pub struct OptionDocs {
+    /// The first paragraph of the documentation, if there is one.
+    pub description: Option<String>,
+    /// The URL of the documentation in the online docs.
+    pub url: String,
+}
+
+/// Find the documentation of the config option or section at the dotted `key`, e.g.
+/// `updates.auto_update` or `style.command_name.foreground`.
+pub fn option_docs(key: &str) -> Option<OptionDocs> {
+    let (section, option) = key.split_once('.').unwrap_or((key, ""));
+    let &(_, page, content) = SECTION_PAGES.iter().find(|(name, ..)| *name == section)?;
+    let page_url = format!("{DOCS_URL}/{page}.html");
+
+    if option.is_empty() {
+        let description = content
+            .lines()
+            .position(|line| line.starts_with("# "))
+            .and_then(|heading| paragraph_after(content, heading));
+        return Some(OptionDocs {
+            description,
+            url: page_url,
+        });
+    }
+
+    let heading_docs = |name: &str| {
+        let heading = format!(" `{name}`");
+        let position = content.lines().position(|line| {
+            line.starts_with('#') && line.trim_start_matches('#') == heading.as_str()
+        })?;
+        Some(OptionDocs {
+            description: paragraph_after(content, position),
+            url: format!("{page_url}#{}", heading_id(name)),
+        })
+    };
+    if let Some(docs) = heading_docs(option) {
+        return Some(docs);
+    }
+
+    // Each part of the key can be described in a list, e.g. the style target and attribute
+    let items: Vec<_> = option
+        .split('.')
+        .filter_map(|part| list_item(content, part))
+        .collect();
+    if !items.is_empty() {
+        return Some(OptionDocs {
+            description: Some(items.join("\n")),
+            url: page_url,
+        });
+    }
+
+    // Options in tables with arbitrary names (like `sources.<name>.kind`) are documented by
+    // their own name
+    let last = option.rsplit('.').next().unwrap_or(option);
+    Some(heading_docs(last).unwrap_or(OptionDocs {
+        description: None,
+        url: page_url,
+    }))
+}
+
+/// The first paragraph after the line with the index `line` in `content`. Code blocks and
+/// headings end the paragraph.
+fn paragraph_after(content: &str, line: usize) -> Option<String> {
+    let paragraph: Vec<_> = content
+        .lines()
+        .skip(line + 1)
+        .skip_while(|line| line.trim().is_empty())
+        .take_while(|line| {
+            !line.trim().is_empty() && !line.starts_with("```") && !line.starts_with('#')
+        })
+        .collect();
+    (!paragraph.is_empty()).then(|| paragraph.join("\n"))
+}
+
+/// The list item starting with `` `name` `` in `content`, including its continuation lines.
+fn list_item(content: &str, name: &str) -> Option<String> {
+    let marker = format!("- `{name}`");
+    let mut lines = content
+        .lines()
+        .skip_while(|line| !line.starts_with(&marker));
+    let mut item = lines.next()?.to_string();
+    for line in lines.take_while(|line| line.starts_with("  ") && !line.trim().is_empty()) {
+        item.push('\n');
+        item.push_str(line);
+    }
+    Some(item)
+}
+
+/// The ID of a heading in the online docs, which mdBook derives from its text.
+fn heading_id(text: &str) -> String {
+    text.chars()
+        .filter_map(|chr| match chr {
+            ' ' => Some('-'),
+            chr if chr.is_alphanumeric() || chr == '_' || chr == '-' => {
+                Some(chr.to_ascii_lowercase())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn option_with_heading() {
+        let docs = option_docs("display.use_pager").unwrap();
+        assert!(docs
+            .description
+            .unwrap()
+            .starts_with("Specifies whether the pager should be used by default"));
+        assert_eq!(
+            docs.url,
+            "https://tealdeer-rs.github.io/tealdeer/config_display.html#use_pager"
+        );
+
+        let docs = option_docs("display.indent.base").unwrap();
+        assert!(docs.description.unwrap().contains("(default `2`)"));
+        assert!(docs.url.ends_with("config_display.html#indentbase"));
+
+        let docs = option_docs("sources.company.kind").unwrap();
+        assert!(docs.url.ends_with("config_sources.html#kind"));
+
+        // The attributes of style targets are not the `style.background` option
+        let docs = option_docs("style.background").unwrap();
+        assert!(docs.url.ends_with("config_style.html#background"));
+        let docs = option_docs("style.title.background").unwrap();
+        assert!(docs.url.ends_with("config_style.html"));
+    }
+
+    #[test]
+    fn option_in_list() {
+        let docs = option_docs("style.command_name.foreground").unwrap();
+        assert_eq!(
+            docs.description.unwrap(),
+            "- `command_name`: The command name as part of the example code\n\
+             - `foreground` (color string, ANSI code, or RGB, see below)"
+        );
+        assert_eq!(
+            docs.url,
+            "https://tealdeer-rs.github.io/tealdeer/config_style.html"
+        );
+    }
+
+    #[test]
+    fn section() {
+        let docs = option_docs("updates").unwrap();
+        assert_eq!(
+            docs.description.as_deref(),
+            Some("This config section contains settings related to updating the tealdeer cache.")
+        );
+        assert!(option_docs("unknown.option").is_none());
+    }
+}
@@ -0,0 +1,189 @@
+//! Warnings about deprecated features that are still in use.
+//!
+//! Each warning is shown at most once per day, so that it doesn't get in the way when looking up
+//! pages. When they were last shown is stored in the state directory. Warnings can be hidden with
+//! `deprecations.hidden` in the config, or all at once with `--no-deprecation-warnings`.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{Context, Result};
+use log::debug;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::utils::{print_warning, write_atomically};
+
+/// Name of the file in the state directory that stores when the deprecation warnings were last
+/// shown. Every line contains the name of a warning and the time (in seconds since the Unix
+/// epoch), separated by a tab.
+static DEPRECATION_WARNINGS_FILE: &str = "deprecation_warnings";
+
+/// How long a deprecation warning is not shown again after it was shown.
+const WARNING_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A deprecated feature, which can be named in `deprecations.hidden` to hide its warning.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Deprecation {
+    /// The cache directory is set with the `TEALDEER_CACHE_DIR` env variable.
+    CacheDirEnvVar,
+    /// Custom pages are named `<name>.page` or `<name>.patch` instead of `<name>.page.md`.
+    OldCustomPageNames,
+    /// Pages were found at the cache location of older tealdeer versions.
+    OldCacheLocation,
+}
+
+impl Deprecation {
+    /// The name of the deprecation in the config and the state file.
+    fn name(self) -> &'static str {
+        match self {
+            Self::CacheDirEnvVar => "cache_dir_env_var",
+            Self::OldCustomPageNames => "old_custom_page_names",
+            Self::OldCacheLocation => "old_cache_location",
+        }
+    }
+}
+
+pub struct DeprecationWarnings {
+    path: PathBuf,
+    /// The deprecations whose warnings are never shown, or `None` if no warnings are shown.
+    hidden: Option<Vec<Deprecation>>,
+    enable_styles: bool,
+}
+
+impl DeprecationWarnings {
+    /// Create the warnings, which are not shown for the deprecations in `hidden`. If `hidden` is
+    /// `None`, no warnings are shown at all.
+    pub fn new(
+        state_directory: &Path,
+        hidden: Option<Vec<Deprecation>>,
+        enable_styles: bool,
+    ) -> Self {
+        Self {
+            path: state_directory.join(DEPRECATION_WARNINGS_FILE),
+            hidden,
+            enable_styles,
+        }
+    }
+
+    /// Print `message` as a warning about `deprecation`, unless it is hidden or was shown less
+    /// than a day ago. Problems with the state file are only logged, as they must not prevent
+    /// tealdeer from working.
+    pub fn warn(&self, deprecation: Deprecation, message: &str) {
+        let Some(hidden) = &self.hidden else {
+            return;
+        };
+        if hidden.contains(&deprecation) {
+            return;
+        }
+
+        let now = SystemTime::now();
+        let mut last_shown = self.read();
+        if let Some(&time) = last_shown.get(deprecation.name()) {
+            if now
+                .duration_since(time)
+                .is_ok_and(|age| age < WARNING_INTERVAL)
+            {
+                debug!("Not showing the warning about {deprecation:?} again today");
+                return;
+            }
+        }
+
+        print_warning(self.enable_styles, message);
+        last_shown.insert(deprecation.name().to_string(), now);
+        if let Err(e) = self.write(&last_shown) {
+            debug!("Could not record the deprecation warning: {e:#}");
+        }
+    }
+
+    /// When the warnings were last shown, by name. Lines that cannot be parsed are skipped.
+    fn read(&self) -> BTreeMap<String, SystemTime> {
+        let Ok(content) = fs::read_to_string(&self.path) else {
+            return BTreeMap::new();
+        };
+        content
+            .lines()
+            .filter_map(|line| {
+                let (name, seconds) = line.split_once('\t')?;
+                let seconds = seconds.parse().ok()?;
+                Some((
+                    name.to_string(),
+                    SystemTime::UNIX_EPOCH + Duration::from_secs(seconds),
+                ))
+            })
+            .collect()
+    }
+
+    fn write(&self, last_shown: &BTreeMap<String, SystemTime>) -> Result<()> {
+        let mut content = String::new();
+        for (name, time) in last_shown {
+            let seconds = time
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .context("The time is before the Unix epoch")?
+                .as_secs();
+            content.push_str(name);
+            content.push('\t');
+            content.push_str(&seconds.to_string());
+            content.push('\n');
+        }
+        if let Some(directory) = self.path.parent() {
+            fs::create_dir_all(directory).with_context(|| {
+                format!("Could not create state directory {}", directory.display())
+            })?;
+        }
+        write_atomically(&self.path, content)
+            .with_context(|| format!("Could not write {}", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warnings_are_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        let warnings = DeprecationWarnings::new(dir.path(), Some(Vec::new()), false);
+        warnings.warn(Deprecation::OldCacheLocation, "Old cache location");
+        let last_shown = warnings.read();
+        assert_eq!(
+            last_shown.keys().collect::<Vec<_>>(),
+            ["old_cache_location"]
+        );
+
+        // Warnings that are shown again today are not recorded again
+        warnings.warn(Deprecation::OldCacheLocation, "Old cache location");
+        assert_eq!(warnings.read(), last_shown);
+
+        fs::write(&warnings.path, "old_cache_location\t1000\n").unwrap();
+        warnings.warn(Deprecation::OldCacheLocation, "Old cache location");
+        assert_eq!(warnings.read(), last_shown);
+    }
+
+    #[test]
+    fn hidden_warnings_are_not_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        let hidden = vec![Deprecation::CacheDirEnvVar];
+        let warnings = DeprecationWarnings::new(dir.path(), Some(hidden), false);
+        warnings.warn(Deprecation::CacheDirEnvVar, "Deprecated env variable");
+        DeprecationWarnings::new(dir.path(), None, false)
+            .warn(Deprecation::OldCacheLocation, "Old cache location");
+        assert!(!dir.path().join(DEPRECATION_WARNINGS_FILE).exists());
+    }
+
+    #[test]
+    fn names_match_config() {
+        for deprecation in [
+            Deprecation::CacheDirEnvVar,
+            Deprecation::OldCustomPageNames,
+            Deprecation::OldCacheLocation,
+        ] {
+            let value = toml::Value::try_from(deprecation).unwrap();
+            assert_eq!(value.as_str(), Some(deprecation.name()));
+        }
+    }
+}
@@ -31,3 +31,20 @@ impl FindFrom for str {
             .map(|i| i + start)
     }
 }
+
+/// Like `str::find`, but ignores the ASCII case of the needle.
+pub(crate) trait FindIgnoreAsciiCase {
+    fn find_ignore_ascii_case(&self, needle: &Self) -> Option<usize>;
+}
+
+impl FindIgnoreAsciiCase for str {
+    fn find_ignore_ascii_case(&self, needle: &Self) -> Option<usize> {
+        if needle.is_empty() {
+            return Some(0);
+        }
+        // Matches always start at a character boundary, because the needle is valid UTF-8
+        self.as_bytes()
+            .windows(needle.len())
+            .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+    }
+}
@@ -1,8 +1,16 @@
 //! Functions related to formatting and printing lines from a `Tokenizer`.
 
+use std::{borrow::Cow, collections::BTreeMap};
+
 use log::debug;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-use crate::{config::Indent, extensions::FindFrom, types::LineType};
+use crate::{
+    config::{DisplayConfig, OptionStyle, TitleDecoration},
+    extensions::{FindFrom, FindIgnoreAsciiCase},
+    shell::adapt_to_shell,
+    types::LineType,
+};
 
 #[derive(Debug, Clone, Copy, Eq)]
 /// Represents a snippet from a page of a specific highlighting class.
@@ -10,9 +18,15 @@ pub enum PageSnippet<T> {
     CommandName(T),
     Variable(T),
     NormalCode(T),
+    /// An option in example code, e.g. `-f` or `--format` (without a value after `=`).
+    CommandOption(T),
     Description(T),
     Text(T),
     Title(T),
+    /// A code span (`` `code` ``) in a description or example text, without the backticks.
+    InlineCode(T),
+    /// A link (`<https://…>`) in a description or example text, without the angle brackets.
+    Link(T),
     Linebreak,
 }
 
@@ -26,9 +40,12 @@ impl<T> PageSnippet<T> {
             PageSnippet::CommandName(s) => PageSnippet::CommandName(f(s)),
             PageSnippet::Variable(s) => PageSnippet::Variable(f(s)),
             PageSnippet::NormalCode(s) => PageSnippet::NormalCode(f(s)),
+            PageSnippet::CommandOption(s) => PageSnippet::CommandOption(f(s)),
             PageSnippet::Description(s) => PageSnippet::Description(f(s)),
             PageSnippet::Text(s) => PageSnippet::Text(f(s)),
             PageSnippet::Title(s) => PageSnippet::Title(f(s)),
+            PageSnippet::InlineCode(s) => PageSnippet::InlineCode(f(s)),
+            PageSnippet::Link(s) => PageSnippet::Link(f(s)),
             PageSnippet::Linebreak => PageSnippet::Linebreak,
         }
     }
@@ -40,9 +57,12 @@ impl<T: PartialEq<U>, U> PartialEq<PageSnippet<U>> for PageSnippet<T> {
             (PageSnippet::CommandName(s), PageSnippet::CommandName(t))
             | (PageSnippet::Variable(s), PageSnippet::Variable(t))
             | (PageSnippet::NormalCode(s), PageSnippet::NormalCode(t))
+            | (PageSnippet::CommandOption(s), PageSnippet::CommandOption(t))
             | (PageSnippet::Description(s), PageSnippet::Description(t))
             | (PageSnippet::Text(s), PageSnippet::Text(t))
-            | (PageSnippet::Title(s), PageSnippet::Title(t)) => s == t,
+            | (PageSnippet::Title(s), PageSnippet::Title(t))
+            | (PageSnippet::InlineCode(s), PageSnippet::InlineCode(t))
+            | (PageSnippet::Link(s), PageSnippet::Link(t)) => s == t,
             (PageSnippet::Linebreak, PageSnippet::Linebreak) => true,
             _ => false,
         }
@@ -54,86 +74,352 @@ impl PageSnippet<&str> {
         use PageSnippet::*;
 
         match self {
-            CommandName(s) | Variable(s) | NormalCode(s) | Description(s) | Text(s) | Title(s) => {
-                s.is_empty()
-            }
+            CommandName(s) | Variable(s) | NormalCode(s) | CommandOption(s) | Description(s)
+            | Text(s) | Title(s) | InlineCode(s) | Link(s) => s.is_empty(),
             Linebreak => false,
         }
     }
 }
 
+/// The number of examples on a page, and how many of them were shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HighlightSummary {
+    pub examples: usize,
+    pub shown_examples: usize,
+    /// The number of lines that are not valid in the tldr format and were left out.
+    pub unrecognized_lines: usize,
+}
+
 /// Parse the content of each line yielded by `lines` and yield `HighLightingSnippet`s accordingly.
+///
+/// If `display.example` or `display.grep` is set, only the title and the lines of the selected
+/// examples are yielded.
 pub fn highlight_lines<L, F, E>(
     lines: L,
     process_snippet: &mut F,
-    keep_empty_lines: bool,
-    show_title: bool,
-    indent: Indent,
-) -> Result<(), E>
+    display: &DisplayConfig,
+) -> Result<HighlightSummary, E>
 where
     L: Iterator<Item = LineType>,
     F: for<'snip> FnMut(PageSnippet<&'snip str>) -> Result<(), E>,
 {
-    let base_indent = " ".repeat(indent.base);
-    let command_indent = " ".repeat(indent.command);
-    let mut command = String::new();
+    let mut renderer = LineRenderer {
+        display,
+        base_indent: " ".repeat(display.indent.base),
+        command_indent: " ".repeat(display.indent.command),
+        command: String::new(),
+        shown_examples: 0,
+    };
+
+    // The lines of an example are collected until it is complete, to know whether it is selected
+    let mut example_number = 0;
+    let mut example_lines = Vec::new();
+    let mut unrecognized_lines = 0;
     for line in lines {
+        if let LineType::Other(_) = line {
+            unrecognized_lines += 1;
+            continue;
+        }
+        if let LineType::ExampleText(_) = line {
+            if example_number > 0 {
+                renderer.render_example(example_number, &mut example_lines, process_snippet)?;
+            }
+            example_number += 1;
+        }
+
+        if example_number > 0 {
+            example_lines.push(line);
+        } else if !renderer.is_filtered() || matches!(line, LineType::Title(_)) {
+            renderer.render(line, example_number, process_snippet)?;
+        }
+    }
+    if example_number > 0 {
+        renderer.render_example(example_number, &mut example_lines, process_snippet)?;
+    }
+    process_snippet(PageSnippet::Linebreak)?;
+
+    Ok(HighlightSummary {
+        examples: example_number,
+        shown_examples: renderer.shown_examples,
+        unrecognized_lines,
+    })
+}
+
+struct LineRenderer<'a> {
+    display: &'a DisplayConfig,
+    base_indent: String,
+    command_indent: String,
+    command: String,
+    shown_examples: usize,
+}
+
+impl LineRenderer<'_> {
+    fn is_filtered(&self) -> bool {
+        self.display.example.is_some() || self.display.grep.is_some()
+    }
+
+    fn is_selected(&self, example_number: usize, lines: &[LineType]) -> bool {
+        let matches_grep = |pattern: &String| {
+            lines.iter().any(|line| match line {
+                LineType::ExampleText(text) => text.find_ignore_ascii_case(pattern).is_some(),
+                LineType::ExampleCode(code) => code
+                    .replace("{{", "")
+                    .replace("}}", "")
+                    .find_ignore_ascii_case(pattern)
+                    .is_some(),
+                _ => false,
+            })
+        };
+        self.display
+            .example
+            .is_none_or(|selected| selected == example_number)
+            && self.display.grep.as_ref().is_none_or(matches_grep)
+    }
+
+    /// Split `text` into the lines it is wrapped to, if wrapping is enabled. The lines are
+    /// indented by the base indent and `prefix_width` more columns.
+    fn wrap<'t>(&self, text: &'t str, prefix_width: usize) -> Vec<&'t str> {
+        match self.display.width {
+            Some(width) if self.display.wrap_text => wrap_text(
+                text,
+                width
+                    .saturating_sub(self.base_indent.len() + prefix_width)
+                    .max(MIN_WRAP_WIDTH),
+            ),
+            _ => vec![text],
+        }
+    }
+
+    /// Render the collected `lines` of an example if it is selected, and clear them.
+    fn render_example<E>(
+        &mut self,
+        example_number: usize,
+        lines: &mut Vec<LineType>,
+        process_snippet: &mut impl FnMut(PageSnippet<&str>) -> Result<(), E>,
+    ) -> Result<(), E> {
+        if !self.is_selected(example_number, lines) {
+            lines.clear();
+            return Ok(());
+        }
+
+        // The empty line in front of the first example belongs to the description, which is not
+        // shown if the examples are filtered
+        if self.is_filtered() && self.shown_examples == 0 && !self.display.compact {
+            process_snippet(PageSnippet::Linebreak)?;
+        }
+        self.shown_examples += 1;
+        for line in lines.drain(..) {
+            self.render(line, example_number, process_snippet)?;
+        }
+        Ok(())
+    }
+
+    fn render<E>(
+        &mut self,
+        line: LineType,
+        example_number: usize,
+        process_snippet: &mut impl FnMut(PageSnippet<&str>) -> Result<(), E>,
+    ) -> Result<(), E> {
         match line {
             LineType::Empty => {
-                if keep_empty_lines {
+                if !self.display.compact {
                     process_snippet(PageSnippet::Linebreak)?;
                 }
             }
             LineType::Title(title) => {
-                if show_title {
-                    process_snippet(PageSnippet::Linebreak)?;
-                    process_snippet(PageSnippet::Title(&base_indent))?;
-                    process_snippet(PageSnippet::Title(&title))?;
+                if self.display.show_title {
                     process_snippet(PageSnippet::Linebreak)?;
+                    highlight_title(
+                        &title,
+                        &self.base_indent,
+                        self.display.title_decoration,
+                        process_snippet,
+                    )?;
                 } else {
                     debug!("Ignoring title");
                 }
                 // This is safe as long as the parsed title is only the command,
                 // and the iterator yields values in order of appearance.
-                command = title;
-                debug!("Detected command name: {}", &command);
+                self.command = title;
+                debug!("Detected command name: {}", &self.command);
             }
             LineType::Description(text) => {
-                process_snippet(PageSnippet::Description(&base_indent))?;
-                process_snippet(PageSnippet::Description(&text))?;
-                process_snippet(PageSnippet::Linebreak)?;
+                for part in self.wrap(&text, 0) {
+                    process_snippet(PageSnippet::Description(&self.base_indent))?;
+                    highlight_inline(part, PageSnippet::Description, process_snippet)?;
+                    process_snippet(PageSnippet::Linebreak)?;
+                }
             }
             LineType::ExampleText(text) => {
-                process_snippet(PageSnippet::Text(&base_indent))?;
-                process_snippet(PageSnippet::Text(&text))?;
-                process_snippet(PageSnippet::Linebreak)?;
+                let number = if self.display.show_example_numbers {
+                    format!("{example_number}. ")
+                } else {
+                    String::new()
+                };
+                // Continuation lines are aligned with the text after the number
+                for (i, part) in self.wrap(&text, number.len()).into_iter().enumerate() {
+                    process_snippet(PageSnippet::Text(&self.base_indent))?;
+                    if i == 0 {
+                        process_snippet(PageSnippet::Text(&number))?;
+                    } else {
+                        process_snippet(PageSnippet::Text(&" ".repeat(number.len())))?;
+                    }
+                    highlight_inline(part, PageSnippet::Text, process_snippet)?;
+                    process_snippet(PageSnippet::Linebreak)?;
+                }
             }
             LineType::ExampleCode(text) => {
-                process_snippet(PageSnippet::NormalCode(&command_indent))?;
-                highlight_code(&command, &text, process_snippet)?;
+                let adapted = adapt_to_shell(&text, self.display.shell);
+                process_snippet(PageSnippet::NormalCode(&self.command_indent))?;
+                highlight_code(
+                    &self.command,
+                    &adapted.code,
+                    self.display.option_style,
+                    self.display.highlight_options,
+                    self.display.example_values.as_ref(),
+                    process_snippet,
+                )?;
+                // A comment works in all supported shells, and keeps the code copyable
+                if adapted.incompatible {
+                    process_snippet(PageSnippet::NormalCode("  # needs a POSIX shell"))?;
+                }
                 process_snippet(PageSnippet::Linebreak)?;
             }
+            // Unrecognized lines are counted by `highlight_lines` instead
+            LineType::Other(_) => {}
+        }
+        Ok(())
+    }
+}
+
+/// Text is never wrapped to fewer columns than this, even in very narrow terminals.
+const MIN_WRAP_WIDTH: usize = 20;
 
-            LineType::Other(text) => debug!("Unknown line type: {text:?}"),
+/// Split `text` into lines that take up at most `width` columns in the terminal. Lines are
+/// broken at spaces and next to wide characters, as CJK text does not separate words with
+/// spaces. Words that are longer than a line are not split.
+fn wrap_text(text: &str, width: usize) -> Vec<&str> {
+    let mut lines = Vec::new();
+    let mut rest = text;
+    while let Some(split) = line_break(rest, width) {
+        lines.push(rest[..split].trim_end());
+        rest = rest[split..].trim_start();
+    }
+    if !rest.is_empty() || lines.is_empty() {
+        lines.push(rest);
+    }
+    lines
+}
+
+/// Find the byte index at which `text` has to be broken to fit into `width` columns, or `None`
+/// if it fits or can't be broken.
+fn line_break(text: &str, width: usize) -> Option<usize> {
+    let mut columns = 0;
+    let mut last_break = None;
+    let mut previous_wide = false;
+    let mut in_code_span = false;
+    for (i, c) in text.char_indices() {
+        // Code spans are kept on one line, so that their backticks stay paired
+        if c == '`' {
+            in_code_span = !in_code_span && text[i + 1..].contains('`');
+        }
+        let char_width = c.width().unwrap_or(0);
+        let wide = char_width > 1;
+        if i > 0 && !in_code_span && (c == ' ' || wide || previous_wide) {
+            // Break at the last opportunity that fits, or at the first one if none does
+            if columns > width {
+                return last_break.or(Some(i));
+            }
+            last_break = Some(i);
         }
+        columns += char_width;
+        previous_wide = wide;
+    }
+    last_break.filter(|_| columns > width)
+}
+
+/// Yield `text` as `plain` snippets, except for its code spans (`` `code` ``) and links
+/// (`<https://…>`), whose markup is removed.
+fn highlight_inline<'t, E>(
+    mut text: &'t str,
+    plain: fn(&'t str) -> PageSnippet<&'t str>,
+    process_snippet: &mut impl FnMut(PageSnippet<&str>) -> Result<(), E>,
+) -> Result<(), E> {
+    let mut start = 0;
+    while let Some(marker) = text[start..].find(['`', '<']).map(|i| start + i) {
+        let inner = &text[marker + 1..];
+        let span = if text[marker..].starts_with('`') {
+            inner
+                .find('`')
+                .map(|end| (PageSnippet::InlineCode(&inner[..end]), end))
+        } else if inner.starts_with("https://") || inner.starts_with("http://") {
+            inner
+                .find(['>', ' '])
+                .filter(|&end| inner[end..].starts_with('>'))
+                .map(|end| (PageSnippet::Link(&inner[..end]), end))
+        } else {
+            None
+        };
+        let Some((snippet, end)) = span else {
+            // Unpaired markers are printed as they are
+            start = marker + 1;
+            continue;
+        };
+        process_snippet(plain(&text[..marker]))?;
+        process_snippet(snippet)?;
+        text = &inner[end + 1..];
+        start = 0;
+    }
+    process_snippet(plain(text))
+}
+
+/// Yield the lines of the title, decorated according to `decoration`.
+fn highlight_title<E>(
+    title: &str,
+    indent: &str,
+    decoration: TitleDecoration,
+    process_snippet: &mut impl FnMut(PageSnippet<&str>) -> Result<(), E>,
+) -> Result<(), E> {
+    let mut line = |text: &str| {
+        process_snippet(PageSnippet::Title(indent))?;
+        process_snippet(PageSnippet::Title(text))?;
+        process_snippet(PageSnippet::Linebreak)
+    };
+
+    let width = title.width();
+    match decoration {
+        TitleDecoration::None => line(title),
+        TitleDecoration::Underline => {
+            line(title)?;
+            line(&"─".repeat(width))
+        }
+        TitleDecoration::Box => {
+            let border = "─".repeat(width + 2);
+            line(&format!("┌{border}┐"))?;
+            line(&format!("│ {title} │"))?;
+            line(&format!("└{border}┘"))
+        }
+        TitleDecoration::Hash => line(&format!("# {title}")),
     }
-    process_snippet(PageSnippet::Linebreak)?;
-    Ok(())
 }
 
 /// Highlight code examples.
 /// - parse placeholders (`{{ curly braces }}`)
 /// - replace escaped placeholder markers (`\{\{` and `\}\}`)
+/// - yield options as `CommandOption` if `split_options` is set
 fn highlight_code<E>(
     command: &str,
     mut text: &str,
+    option_style: OptionStyle,
+    split_options: bool,
+    example_values: Option<&BTreeMap<String, String>>,
     process_snippet: &mut impl FnMut(PageSnippet<&str>) -> Result<(), E>,
 ) -> Result<(), E> {
     // We replace escaped placeholder markers at the end so that our replacing does not interfere
     // with finding the actual markers.
-    // NOTE: This is not optimal, as it allocates one String for each `replace`
-    let replace_escaped = |s: &str| s.replace(r"\{\{", "{{").replace(r"\}\}", "}}");
-
+    // Options can only start at the beginning of the line or after whitespace, not right after a
+    // placeholder (like the `-backup` in `{{file}}-backup`)
+    let mut at_token_start = true;
     loop {
         // Find placeholder markers and split into code and placeholder accordingly
 
@@ -156,23 +442,102 @@ fn highlight_code<E>(
             highlight_code_segment(
                 command,
                 &replace_escaped(&text[..start_marker]),
+                at_token_start,
+                split_options,
                 process_snippet,
             )?;
         }
-        process_snippet(PageSnippet::Variable(&replace_escaped(placeholder_content)))?;
+        highlight_placeholder(
+            &replace_escaped(placeholder_content),
+            option_style,
+            example_values,
+            process_snippet,
+        )?;
 
         text = &text[end_marker + 2..];
+        at_token_start = false;
     }
 
     if !text.is_empty() {
-        highlight_code_segment(command, &replace_escaped(text), process_snippet)?;
+        highlight_code_segment(
+            command,
+            &replace_escaped(text),
+            at_token_start,
+            split_options,
+            process_snippet,
+        )?;
     }
 
     Ok(())
 }
 
+/// Yields a placeholder as `Variable`, or the value for it in `example_values` if there is one.
+/// Placeholders for alternative options, like `{{[-f|--format]}}`, are yielded as options
+/// instead, shown according to `option_style`.
+fn highlight_placeholder<E>(
+    content: &str,
+    option_style: OptionStyle,
+    example_values: Option<&BTreeMap<String, String>>,
+    process_snippet: &mut impl FnMut(PageSnippet<&str>) -> Result<(), E>,
+) -> Result<(), E> {
+    if let Some(value) = example_values.and_then(|values| values.get(content)) {
+        return process_snippet(PageSnippet::Variable(value));
+    }
+    let Some(alternatives) = option_alternatives(content) else {
+        return process_snippet(PageSnippet::Variable(content));
+    };
+
+    let selected = match option_style {
+        OptionStyle::Both => None,
+        OptionStyle::Short => alternatives.iter().find(|option| !option.starts_with("--")),
+        OptionStyle::Long => alternatives.iter().find(|option| option.starts_with("--")),
+    };
+    if let Some(option) = selected {
+        return process_snippet(PageSnippet::CommandOption(option));
+    }
+
+    // All alternatives are shown if there is none of the selected kind
+    process_snippet(PageSnippet::NormalCode("["))?;
+    for (i, option) in alternatives.iter().enumerate() {
+        if i > 0 {
+            process_snippet(PageSnippet::NormalCode("|"))?;
+        }
+        process_snippet(PageSnippet::CommandOption(option))?;
+    }
+    process_snippet(PageSnippet::NormalCode("]"))
+}
+
+/// Split a placeholder like `[-f|--format]` into its alternative options, or return `None` if it
+/// is not a group of alternative options.
+fn option_alternatives(content: &str) -> Option<Vec<&str>> {
+    let alternatives: Vec<&str> = content
+        .strip_prefix('[')?
+        .strip_suffix(']')?
+        .split('|')
+        .collect();
+    let is_option = |option: &&str| {
+        option.starts_with('-')
+            && option.trim_start_matches('-').len() + 2 >= option.len()
+            && option
+                .trim_start_matches('-')
+                .starts_with(|c: char| c.is_alphanumeric())
+            && !option.contains(char::is_whitespace)
+    };
+    (alternatives.len() > 1 && alternatives.iter().all(is_option)).then_some(alternatives)
+}
+
+/// Replace escaped placeholder markers (`\{\{` and `\}\}`) in `s`. Most code doesn't contain
+/// any, so it is only copied if needed.
+fn replace_escaped(s: &str) -> Cow<'_, str> {
+    if s.contains(r"\{\{") || s.contains(r"\}\}") {
+        Cow::Owned(s.replace(r"\{\{", "{{").replace(r"\}\}", "}}"))
+    } else {
+        Cow::Borrowed(s)
+    }
+}
+
 /// Find a "{{" (or "}}") substring that does not overlap with a preceding "\{\{" (or "\}\}").
-fn find_marker(s: &str, marker: &str, forbidden_prefix: &str) -> Option<usize> {
+pub(crate) fn find_marker(s: &str, marker: &str, forbidden_prefix: &str) -> Option<usize> {
     let mut search_start = 0;
     loop {
         let marker_index = s.find_from(marker, search_start)?;
@@ -195,11 +560,16 @@ fn find_marker(s: &str, marker: &str, forbidden_prefix: &str) -> Option<usize> {
 }
 
 /// Yields `NormalCode` and `CommandName` in alternating order according to the occurrences of
-/// `command_name` in `segment`. Variables are not detected here, see `highlight_code`
-/// instead.
+/// `command_name` in `segment`, with options in the code yielded as `CommandOption` if
+/// `split_options` is set. Variables are not detected here, see `highlight_code` instead.
+///
+/// `at_token_start` tells whether `segment` starts a new word, i.e. it is not directly preceded
+/// by a placeholder.
 fn highlight_code_segment<'a, E>(
     command_name: &'a str,
     mut segment: &'a str,
+    mut at_token_start: bool,
+    split_options: bool,
     process_snippet: &mut impl FnMut(PageSnippet<&'a str>) -> Result<(), E>,
 ) -> Result<(), E> {
     if !command_name.is_empty() {
@@ -207,10 +577,16 @@ fn highlight_code_segment<'a, E>(
         while let Some(match_start) = segment.find_from(command_name, search_start) {
             let match_end = match_start + command_name.len();
             if is_freestanding_substring(segment, (match_start, match_end)) {
-                process_snippet(PageSnippet::NormalCode(&segment[..match_start]))?;
+                highlight_options(
+                    &segment[..match_start],
+                    at_token_start,
+                    split_options,
+                    process_snippet,
+                )?;
                 process_snippet(PageSnippet::CommandName(command_name))?;
                 segment = &segment[match_end..];
                 search_start = 0;
+                at_token_start = true;
             } else {
                 search_start = segment[match_start..]
                     .char_indices()
@@ -219,8 +595,49 @@ fn highlight_code_segment<'a, E>(
             }
         }
     }
-    process_snippet(PageSnippet::NormalCode(segment))?;
-    Ok(())
+    highlight_options(segment, at_token_start, split_options, process_snippet)
+}
+
+/// Yields the options (words starting with `-` or `--`, up to a `=`) in `code` as
+/// `CommandOption`, and the code in between as `NormalCode`. If `split_options` is not set, all
+/// of `code` is yielded as a single `NormalCode`.
+fn highlight_options<'a, E>(
+    code: &'a str,
+    at_token_start: bool,
+    split_options: bool,
+    process_snippet: &mut impl FnMut(PageSnippet<&'a str>) -> Result<(), E>,
+) -> Result<(), E> {
+    if !split_options {
+        return process_snippet(PageSnippet::NormalCode(code));
+    }
+    let is_option_char = |c: char| c.is_alphanumeric() || c == '-' || c == '_';
+
+    let mut normal_start = 0;
+    let mut search_start = 0;
+    while let Some(dash) = code[search_start..].find('-').map(|i| search_start + i) {
+        let word_start = if dash == 0 {
+            at_token_start
+        } else {
+            code[..dash].ends_with(char::is_whitespace)
+        };
+        let name = code[dash..].trim_start_matches('-');
+        let dashes = code.len() - dash - name.len();
+        let is_option =
+            word_start && dashes <= 2 && name.starts_with(|c: char| c.is_alphanumeric());
+        if !is_option {
+            search_start = dash + dashes;
+            continue;
+        }
+
+        let end = name
+            .find(|c: char| !is_option_char(c))
+            .map_or(code.len(), |i| dash + dashes + i);
+        process_snippet(PageSnippet::NormalCode(&code[normal_start..dash]))?;
+        process_snippet(PageSnippet::CommandOption(&code[dash..end]))?;
+        normal_start = end;
+        search_start = end;
+    }
+    process_snippet(PageSnippet::NormalCode(&code[normal_start..]))
 }
 
 /// Checks whether the characters right before and after the substring (given by half-open index interval) are whitespace (if they exist).
@@ -268,6 +685,14 @@ mod tests {
     }
 
     fn run<'a>(cmd: &'a str, segment: &'a str) -> Vec<PageSnippet<String>> {
+        run_with_option_style(cmd, segment, OptionStyle::Both)
+    }
+
+    fn run_with_option_style<'a>(
+        cmd: &'a str,
+        segment: &'a str,
+        option_style: OptionStyle,
+    ) -> Vec<PageSnippet<String>> {
         let mut yielded = Vec::new();
         let mut process_snippet = |snip: PageSnippet<&str>| {
             if !snip.is_empty() {
@@ -276,7 +701,8 @@ mod tests {
             Ok::<(), ()>(())
         };
 
-        highlight_code(cmd, segment, &mut process_snippet).expect("highlight code segment failed");
+        highlight_code(cmd, segment, option_style, true, None, &mut process_snippet)
+            .expect("highlight code segment failed");
         yielded
     }
 
@@ -289,19 +715,29 @@ mod tests {
             assert!(run("make", "").is_empty());
             assert_eq!(
                 &run("make", "make all CC=clang -q"),
-                &[CommandName("make"), NormalCode(" all CC=clang -q")]
+                &[
+                    CommandName("make"),
+                    NormalCode(" all CC=clang "),
+                    CommandOption("-q")
+                ]
             );
             assert_eq!(
                 &run("make", "  make money --always-make"),
                 &[
                     NormalCode("  "),
                     CommandName("make"),
-                    NormalCode(" money --always-make")
+                    NormalCode(" money "),
+                    CommandOption("--always-make")
                 ]
             );
             assert_eq!(
                 &run("git commit", "git commit -m 'git commit'"),
-                &[CommandName("git commit"), NormalCode(" -m 'git commit'"),]
+                &[
+                    CommandName("git commit"),
+                    NormalCode(" "),
+                    CommandOption("-m"),
+                    NormalCode(" 'git commit'"),
+                ]
             );
         }
 
@@ -337,6 +773,46 @@ mod tests {
             );
         }
 
+        #[test]
+        fn test_options() {
+            assert_eq!(
+                &run("tar", "tar -xvf {{archive}} --directory={{path}}"),
+                &[
+                    CommandName("tar"),
+                    NormalCode(" "),
+                    CommandOption("-xvf"),
+                    NormalCode(" "),
+                    Variable("archive"),
+                    NormalCode(" "),
+                    CommandOption("--directory"),
+                    NormalCode("="),
+                    Variable("path"),
+                ]
+            );
+            // Dashes that don't start an option
+            assert_eq!(
+                &run("cat", "cat - {{file}}-backup a-b --- -- --_x"),
+                &[
+                    CommandName("cat"),
+                    NormalCode(" - "),
+                    Variable("file"),
+                    NormalCode("-backup a-b --- -- --_x"),
+                ]
+            );
+            assert_eq!(
+                &run("ls", "ls -1 | grep -v -- -x"),
+                &[
+                    CommandName("ls"),
+                    NormalCode(" "),
+                    CommandOption("-1"),
+                    NormalCode(" | grep "),
+                    CommandOption("-v"),
+                    NormalCode(" -- "),
+                    CommandOption("-x"),
+                ]
+            );
+        }
+
         #[test]
         fn test_empty_command() {
             let segment = "some code";
@@ -352,6 +828,55 @@ mod tests {
         use super::*;
         use PageSnippet::*;
 
+        #[test]
+        fn alternative_options() {
+            let segment = "tar {{[-x|--extract]}} {{[-f|--file]}} {{path}}";
+            assert_eq!(
+                run("tar", segment),
+                [
+                    CommandName("tar"),
+                    NormalCode(" "),
+                    NormalCode("["),
+                    CommandOption("-x"),
+                    NormalCode("|"),
+                    CommandOption("--extract"),
+                    NormalCode("]"),
+                    NormalCode(" "),
+                    NormalCode("["),
+                    CommandOption("-f"),
+                    NormalCode("|"),
+                    CommandOption("--file"),
+                    NormalCode("]"),
+                    NormalCode(" "),
+                    Variable("path"),
+                ],
+            );
+            assert_eq!(
+                run_with_option_style("tar", segment, OptionStyle::Short)[2..5],
+                [CommandOption("-x"), NormalCode(" "), CommandOption("-f")],
+            );
+            assert_eq!(
+                run_with_option_style("tar", segment, OptionStyle::Long)[2..5],
+                [
+                    CommandOption("--extract"),
+                    NormalCode(" "),
+                    CommandOption("--file")
+                ],
+            );
+
+            // All alternatives are shown if there is no option of the selected kind
+            assert_eq!(
+                run_with_option_style("", "{{[-a|-b]}}", OptionStyle::Long).len(),
+                5
+            );
+
+            // Other placeholders in brackets are variables
+            assert_eq!(run("", "{{[a|b]}}"), [Variable("[a|b]")]);
+            assert_eq!(run("", "{{[-a]}}"), [Variable("[-a]")]);
+            assert_eq!(run("", "{{[-a|---b]}}"), [Variable("[-a|---b]")]);
+            assert_eq!(run("", "{{[-a|-b c]}}"), [Variable("[-a|-b c]")]);
+        }
+
         #[test]
         fn variable_vs_escaped() {
             assert_eq!(
@@ -369,9 +894,9 @@ mod tests {
                 ),
                 [
                     CommandName("docker inspect"),
-                    NormalCode(
-                        " --format '{{range.NetworkSettings.Networks}}{{.IPAddress}}{{end}}' "
-                    ),
+                    NormalCode(" "),
+                    CommandOption("--format"),
+                    NormalCode(" '{{range.NetworkSettings.Networks}}{{.IPAddress}}{{end}}' "),
                     Variable("container"),
                 ],
             );
@@ -401,7 +926,9 @@ mod tests {
                 run("git stash", "git stash show --patch {{stash@{0}}}"),
                 [
                     CommandName("git stash"),
-                    NormalCode(" show --patch "),
+                    NormalCode(" show "),
+                    CommandOption("--patch"),
+                    NormalCode(" "),
                     Variable("stash@{0}"),
                 ],
             );
@@ -428,7 +955,11 @@ mod tests {
                 [
                     CommandName("playerctl"),
                     NormalCode(" metadata "),
-                    Variable("[-f|--format]"),
+                    NormalCode("["),
+                    CommandOption("-f"),
+                    NormalCode("|"),
+                    CommandOption("--format"),
+                    NormalCode("]"),
                     NormalCode(" \""),
                     Variable("Now playing: {{artist}} - {{album}} - {{title}}"),
                     NormalCode("\""),
@@ -456,4 +987,107 @@ mod tests {
             assert_eq!(run("", r"Äxx{{x}}"), [NormalCode("Äxx"), Variable("x")],);
         }
     }
+
+    #[test]
+    fn test_highlight_inline() {
+        use PageSnippet::*;
+
+        let run = |text| {
+            let mut yielded = Vec::new();
+            highlight_inline(text, Description, &mut |snip: PageSnippet<&str>| {
+                if !snip.is_empty() {
+                    yielded.push(snip.map(str::to_string));
+                }
+                Ok::<(), ()>(())
+            })
+            .unwrap();
+            yielded
+        };
+
+        assert_eq!(
+            run("See `git reset` for more."),
+            [
+                Description("See "),
+                InlineCode("git reset"),
+                Description(" for more.")
+            ]
+        );
+        assert_eq!(
+            run("More information: <https://tldr.sh>."),
+            [
+                Description("More information: "),
+                Link("https://tldr.sh"),
+                Description(".")
+            ]
+        );
+        // Unpaired backticks and angle brackets that don't enclose a link are kept
+        assert_eq!(
+            run("Redirect with <file or `>"),
+            [Description("Redirect with <file or `>")]
+        );
+        assert_eq!(
+            run("<not a link> and `code`"),
+            [Description("<not a link> and "), InlineCode("code")]
+        );
+    }
+
+    #[test]
+    fn test_wrap_text() {
+        assert_eq!(wrap_text("", 10), [""]);
+        assert_eq!(wrap_text("short text", 10), ["short text"]);
+        assert_eq!(
+            wrap_text("split this text at spaces", 10),
+            ["split this", "text at", "spaces"]
+        );
+        assert_eq!(
+            wrap_text("keep overlong words together", 5),
+            ["keep", "overlong", "words", "together"]
+        );
+        assert_eq!(wrap_text("ääää ääää", 4), ["ääää", "ääää"]);
+
+        // Wide characters take up two columns, and lines can be broken between them
+        assert_eq!(
+            wrap_text("ファイルを一覧表示する", 8),
+            ["ファイル", "を一覧表", "示する"]
+        );
+        assert_eq!(wrap_text("列出 ls 文件", 7), ["列出 ls", "文件"]);
+
+        // Code spans are not split
+        assert_eq!(
+            wrap_text("run `git status --short` often", 10),
+            ["run", "`git status --short`", "often"]
+        );
+    }
+
+    #[test]
+    fn title_decorations() {
+        let render_title = |title, decoration| {
+            let mut lines = String::new();
+            highlight_title(title, "  ", decoration, &mut |snip| {
+                match snip {
+                    PageSnippet::Title(s) => lines.push_str(s),
+                    PageSnippet::Linebreak => lines.push('\n'),
+                    _ => unreachable!("Only titles are yielded"),
+                }
+                Ok::<(), ()>(())
+            })
+            .unwrap();
+            lines
+        };
+        let render = |decoration| render_title("git log", decoration);
+
+        assert_eq!(render(TitleDecoration::None), "  git log\n");
+        assert_eq!(render(TitleDecoration::Underline), "  git log\n  ───────\n");
+        assert_eq!(
+            render(TitleDecoration::Box),
+            "  ┌─────────┐\n  │ git log │\n  └─────────┘\n"
+        );
+        assert_eq!(render(TitleDecoration::Hash), "  # git log\n");
+
+        // The decoration matches the display width of wide characters
+        assert_eq!(
+            render_title("日本語", TitleDecoration::Underline),
+            "  日本語\n  ──────\n"
+        );
+    }
 }
@@ -0,0 +1,211 @@
+//! A history of the pages that were looked up, stored in the state directory.
+
+use std::{
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{Context, Result};
+use log::debug;
+
+/// Name of the file in the state directory that stores the history. Every line contains the
+/// time of a lookup (in seconds since the Unix epoch) and the name of the page, separated by a
+/// tab.
+static HISTORY_FILE: &str = "history";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub page: String,
+    pub time: SystemTime,
+}
+
+pub struct History {
+    path: PathBuf,
+}
+
+impl History {
+    pub fn new(state_directory: &Path) -> Self {
+        Self {
+            path: state_directory.join(HISTORY_FILE),
+        }
+    }
+
+    /// Append a lookup of `page` to the history.
+    pub fn record(&self, page: &str) -> Result<()> {
+        let seconds = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .context("The current time is before the Unix epoch")?
+            .as_secs();
+
+        if let Some(directory) = self.path.parent() {
+            fs::create_dir_all(directory).with_context(|| {
+                format!("Could not create state directory {}", directory.display())
+            })?;
+        }
+        let mut file = File::options()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Could not open {}", self.path.display()))?;
+        writeln!(file, "{seconds}\t{page}")
+            .with_context(|| format!("Could not write to {}", self.path.display()))
+    }
+
+    /// All lookups in the history, oldest first. Lines that cannot be parsed are skipped.
+    pub fn entries(&self) -> Result<Vec<HistoryEntry>> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Could not open {}", self.path.display()))
+            }
+        };
+
+        let mut entries = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line.with_context(|| format!("Could not read {}", self.path.display()))?;
+            let Some(entry) = parse_entry(&line) else {
+                debug!("Ignoring invalid history entry {line:?}");
+                continue;
+            };
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
+    /// The pages in the history, most recently looked up first. Every page is only contained
+    /// once, with the time of its latest lookup.
+    pub fn recent(&self) -> Result<Vec<HistoryEntry>> {
+        let mut recent: Vec<HistoryEntry> = Vec::new();
+        for entry in self.entries()?.into_iter().rev() {
+            if !recent.iter().any(|seen| seen.page == entry.page) {
+                recent.push(entry);
+            }
+        }
+        Ok(recent)
+    }
+
+    /// The number of lookups of every page in the history, most frequently looked up first.
+    pub fn usage_counts(&self) -> Result<Vec<(String, usize)>> {
+        let mut counts: Vec<(String, usize)> = Vec::new();
+        for entry in self.entries()? {
+            if let Some((_, count)) = counts.iter_mut().find(|(page, _)| *page == entry.page) {
+                *count += 1;
+            } else {
+                counts.push((entry.page, 1));
+            }
+        }
+        counts.sort_by(|(a_page, a_count), (b_page, b_count)| {
+            b_count.cmp(a_count).then_with(|| a_page.cmp(b_page))
+        });
+        Ok(counts)
+    }
+}
+
+fn parse_entry(line: &str) -> Option<HistoryEntry> {
+    let (seconds, page) = line.split_once('\t')?;
+    let seconds = seconds.parse().ok()?;
+    if page.is_empty() {
+        return None;
+    }
+    Some(HistoryEntry {
+        page: page.to_string(),
+        time: SystemTime::UNIX_EPOCH + Duration::from_secs(seconds),
+    })
+}
+
+/// Describe how long ago `time` was, e.g. `3 hours ago`.
+pub fn format_time_ago(time: SystemTime) -> String {
+    let seconds = SystemTime::now()
+        .duration_since(time)
+        .unwrap_or_default()
+        .as_secs();
+    let (count, unit) = match seconds {
+        0..60 => return "just now".to_string(),
+        60..3600 => (seconds / 60, "minute"),
+        3600..86400 => (seconds / 3600, "hour"),
+        _ => (seconds / 86400, "day"),
+    };
+    if count == 1 {
+        format!("1 {unit} ago")
+    } else {
+        format!("{count} {unit}s ago")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recent_pages_are_deduplicated() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join(HISTORY_FILE),
+            "10\ttar\n20\tgit-log\ninvalid\n30\ttar\n",
+        )
+        .unwrap();
+
+        let history = History::new(dir.path());
+        assert_eq!(history.entries().unwrap().len(), 3);
+        let recent = history.recent().unwrap();
+        let pages: Vec<_> = recent.iter().map(|entry| entry.page.as_str()).collect();
+        assert_eq!(pages, ["tar", "git-log"]);
+        assert_eq!(
+            recent[0].time,
+            SystemTime::UNIX_EPOCH + Duration::from_secs(30)
+        );
+    }
+
+    #[test]
+    fn usage_counts() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join(HISTORY_FILE),
+            "10\ttar\n20\tgit-log\n30\ttar\n40\tcurl\n",
+        )
+        .unwrap();
+
+        let counts = History::new(dir.path()).usage_counts().unwrap();
+        assert_eq!(
+            counts,
+            [
+                ("tar".to_string(), 2),
+                ("curl".to_string(), 1),
+                ("git-log".to_string(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn record_creates_state_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::new(&dir.path().join("state"));
+        assert!(history.entries().unwrap().is_empty());
+
+        history.record("tar").unwrap();
+        let entries = history.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].page, "tar");
+    }
+
+    #[test]
+    fn time_ago() {
+        let now = SystemTime::now();
+        assert_eq!(format_time_ago(now), "just now");
+        assert_eq!(
+            format_time_ago(now - Duration::from_secs(61)),
+            "1 minute ago"
+        );
+        assert_eq!(
+            format_time_ago(now - Duration::from_secs(3 * 3600 + 5)),
+            "3 hours ago"
+        );
+        assert_eq!(
+            format_time_ago(now - Duration::from_secs(2 * 86400)),
+            "2 days ago"
+        );
+    }
+}
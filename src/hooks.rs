@@ -0,0 +1,61 @@
+//! Shell snippets that hook tealdeer into the shell, printed by `tldr --print-hook`.
+
+use crate::types::HookShell;
+
+static BASH_HOOK: &str = r#"# Point to the tldr page of unknown commands. Installed by `tldr --print-hook bash`.
+# Set TEALDEER_HOOK_SHOW_PAGE=1 to show the page right away.
+command_not_found_handle() {
+    if command -v tldr >/dev/null && tldr --no-auto-update --exists -- "$1"; then
+        if [ -n "$TEALDEER_HOOK_SHOW_PAGE" ]; then
+            printf 'bash: %s: command not found\n' "$1" >&2
+            tldr --no-auto-update -- "$1"
+        else
+            printf 'bash: %s: command not found, but there is a tldr page: tldr %s\n' "$1" "$1" >&2
+        fi
+    else
+        printf 'bash: %s: command not found\n' "$1" >&2
+    fi
+    return 127
+}
+"#;
+
+static ZSH_HOOK: &str = r#"# Point to the tldr page of unknown commands. Installed by `tldr --print-hook zsh`.
+# Set TEALDEER_HOOK_SHOW_PAGE=1 to show the page right away.
+command_not_found_handler() {
+    if (( $+commands[tldr] )) && tldr --no-auto-update --exists -- "$1"; then
+        if [[ -n "$TEALDEER_HOOK_SHOW_PAGE" ]]; then
+            print -u2 "zsh: command not found: $1"
+            tldr --no-auto-update -- "$1"
+        else
+            print -u2 "zsh: command not found: $1, but there is a tldr page: tldr $1"
+        fi
+    else
+        print -u2 "zsh: command not found: $1"
+    fi
+    return 127
+}
+"#;
+
+static FISH_HOOK: &str = r"# Point to the tldr page of unknown commands. Installed by `tldr --print-hook fish`.
+# Set TEALDEER_HOOK_SHOW_PAGE=1 to show the page right away.
+function fish_command_not_found
+    __fish_default_command_not_found_handler $argv
+    if command -q tldr; and tldr --no-auto-update --exists -- $argv[1]
+        if set -q TEALDEER_HOOK_SHOW_PAGE
+            tldr --no-auto-update -- $argv[1]
+        else
+            printf 'There is a tldr page for it: tldr %s\n' $argv[1] >&2
+        end
+    end
+end
+";
+
+/// The snippet that installs a command-not-found handler in `shell`, which points to the tldr
+/// page of a missing command if there is one.
+pub fn command_not_found_hook(shell: HookShell) -> &'static str {
+    match shell {
+        HookShell::Bash => BASH_HOOK,
+        HookShell::Zsh => ZSH_HOOK,
+        HookShell::Fish => FISH_HOOK,
+    }
+}
@@ -0,0 +1,236 @@
+//! Translations of tealdeer's own messages.
+//!
+//! Only the most common messages are translated so far. The locale is taken from the `LC_ALL`,
+//! `LC_MESSAGES` and `LANG` env variables, like other command line tools do, and English is used
+//! for locales without translations.
+
+use std::{env, fmt, sync::LazyLock};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Locale {
+    English,
+    French,
+    German,
+}
+
+impl Locale {
+    /// Parse a POSIX locale like `de_DE.UTF-8`, falling back to English.
+    fn parse(locale: &str) -> Self {
+        let language = locale.split(['_', '.', '@']).next().unwrap_or_default();
+        match language {
+            "de" => Self::German,
+            "fr" => Self::French,
+            _ => Self::English,
+        }
+    }
+
+    fn from_env() -> Self {
+        if !cfg!(feature = "localized-messages") {
+            return Self::English;
+        }
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .map_or(Self::English, |value| Self::parse(&value))
+    }
+}
+
+static LOCALE: LazyLock<Locale> = LazyLock::new(Locale::from_env);
+
+/// A message shown to the user, which is displayed in the user's locale.
+#[derive(Clone, Copy, Debug)]
+pub enum Message<'a> {
+    /// Prefix of warnings.
+    WarningPrefix,
+    /// Prefix of errors.
+    ErrorPrefix,
+    /// There is no cache to look up pages in.
+    CacheNotFound,
+    /// Explains how to get a cache, after [`Message::CacheNotFound`].
+    CacheNotFoundHint,
+    /// The cache is older than `updates.warn_cache_age`.
+    CacheOutdated { days: u64 },
+    /// The requested page doesn't exist, with the names of similar pages.
+    PageNotFound {
+        command: &'a str,
+        suggestions: &'a [String],
+    },
+}
+
+impl Message<'_> {
+    fn fmt_locale(&self, locale: Locale, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self, locale) {
+            (Self::WarningPrefix, Locale::English) => f.write_str("Warning: "),
+            (Self::WarningPrefix, Locale::French) => f.write_str("Avertissement : "),
+            (Self::WarningPrefix, Locale::German) => f.write_str("Warnung: "),
+
+            (Self::ErrorPrefix, Locale::English) => f.write_str("Error: "),
+            (Self::ErrorPrefix, Locale::French) => f.write_str("Erreur : "),
+            (Self::ErrorPrefix, Locale::German) => f.write_str("Fehler: "),
+
+            (Self::CacheNotFound, Locale::English) => f.write_str(
+                "Page cache not found. Please run `tldr --update` to download the cache.",
+            ),
+            (Self::CacheNotFound, Locale::French) => f.write_str(
+                "Cache des pages introuvable. Veuillez exécuter `tldr --update` pour télécharger \
+                 le cache.",
+            ),
+            (Self::CacheNotFound, Locale::German) => f.write_str(
+                "Seiten-Cache nicht gefunden. Bitte führe `tldr --update` aus, um den Cache \
+                 herunterzuladen.",
+            ),
+
+            (Self::CacheNotFoundHint, Locale::English) => f.write_str(
+                "Note: You can optionally enable automatic cache updates by adding the\n\
+                 following config to your config file:\n\
+                 \n  [updates]\n  auto_update = true\n\n\
+                 The path to your config file can be looked up with `tldr --show-paths`.\n\
+                 To create an initial config file, use `tldr --seed-config`,\n\
+                 or run `tldr --setup` to create one interactively and download the cache.\n\n\
+                 You can find more tips and tricks in our docs:\n\n  ",
+            ),
+            (Self::CacheNotFoundHint, Locale::French) => f.write_str(
+                "Remarque : vous pouvez activer les mises à jour automatiques du cache en\n\
+                 ajoutant la configuration suivante à votre fichier de configuration :\n\
+                 \n  [updates]\n  auto_update = true\n\n\
+                 Le chemin de votre fichier de configuration est affiché par `tldr --show-paths`.\n\
+                 Pour créer un fichier de configuration initial, utilisez `tldr --seed-config`,\n\
+                 ou lancez `tldr --setup` pour le créer de manière interactive et télécharger\n\
+                 le cache.\n\n\
+                 Vous trouverez d'autres astuces dans notre documentation :\n\n  ",
+            ),
+            (Self::CacheNotFoundHint, Locale::German) => f.write_str(
+                "Hinweis: Automatische Cache-Updates lassen sich optional aktivieren, indem du\n\
+                 folgende Einstellung in deine Konfigurationsdatei einfügst:\n\
+                 \n  [updates]\n  auto_update = true\n\n\
+                 Den Pfad deiner Konfigurationsdatei zeigt `tldr --show-paths` an.\n\
+                 Eine erste Konfigurationsdatei erstellt `tldr --seed-config`,\n\
+                 oder `tldr --setup` erstellt sie interaktiv und lädt den Cache herunter.\n\n\
+                 Weitere Tipps und Tricks findest du in unserer Dokumentation:\n\n  ",
+            ),
+
+            (Self::CacheOutdated { days }, Locale::English) => write!(
+                f,
+                "The cache hasn't been updated for {days} days.\n\
+                 You should probably run `tldr --update` soon."
+            ),
+            (Self::CacheOutdated { days }, Locale::French) => write!(
+                f,
+                "Le cache n'a pas été mis à jour depuis {days} jours.\n\
+                 Vous devriez exécuter `tldr --update` prochainement."
+            ),
+            (Self::CacheOutdated { days }, Locale::German) => write!(
+                f,
+                "Der Cache wurde seit {days} Tagen nicht aktualisiert.\n\
+                 Du solltest bald `tldr --update` ausführen."
+            ),
+
+            (
+                Self::PageNotFound {
+                    command,
+                    suggestions,
+                },
+                _,
+            ) => {
+                let suggestions = suggestions
+                    .iter()
+                    .map(|page| format!("`{page}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                match locale {
+                    Locale::English => {
+                        writeln!(f, "Page `{command}` not found in cache.")?;
+                        if !suggestions.is_empty() {
+                            writeln!(f, "Did you mean {suggestions}?")?;
+                        }
+                        f.write_str(
+                            "Try updating with `tldr --update`, or submit a pull request to:\n",
+                        )?;
+                    }
+                    Locale::French => {
+                        writeln!(f, "Page `{command}` introuvable dans le cache.")?;
+                        if !suggestions.is_empty() {
+                            writeln!(f, "Vouliez-vous dire {suggestions} ?")?;
+                        }
+                        f.write_str(
+                            "Essayez de mettre à jour avec `tldr --update`, ou soumettez une pull \
+                             request à :\n",
+                        )?;
+                    }
+                    Locale::German => {
+                        writeln!(f, "Seite `{command}` nicht im Cache gefunden.")?;
+                        if !suggestions.is_empty() {
+                            writeln!(f, "Meintest du {suggestions}?")?;
+                        }
+                        f.write_str(
+                            "Versuche ein Update mit `tldr --update`, oder reiche einen Pull \
+                             Request ein:\n",
+                        )?;
+                    }
+                }
+                f.write_str("https://github.com/tldr-pages/tldr")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Message<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_locale(*LOCALE, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Localized<'a>(Message<'a>, Locale);
+
+    impl fmt::Display for Localized<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.0.fmt_locale(self.1, f)
+        }
+    }
+
+    #[test]
+    fn parse_locale() {
+        assert_eq!(Locale::parse("de_DE.UTF-8"), Locale::German);
+        assert_eq!(Locale::parse("de"), Locale::German);
+        assert_eq!(Locale::parse("fr_CA"), Locale::French);
+        assert_eq!(Locale::parse("en_US.UTF-8"), Locale::English);
+        assert_eq!(Locale::parse("C"), Locale::English);
+        assert_eq!(Locale::parse("pt_BR@euro"), Locale::English);
+    }
+
+    #[test]
+    fn page_not_found() {
+        let suggestions = ["git-add".to_owned(), "git-mv".to_owned()];
+        let message = Message::PageNotFound {
+            command: "git-ad",
+            suggestions: &suggestions,
+        };
+        assert_eq!(
+            Localized(message, Locale::English).to_string(),
+            "Page `git-ad` not found in cache.\n\
+             Did you mean `git-add`, `git-mv`?\n\
+             Try updating with `tldr --update`, or submit a pull request to:\n\
+             https://github.com/tldr-pages/tldr"
+        );
+        assert_eq!(
+            Localized(message, Locale::German)
+                .to_string()
+                .lines()
+                .nth(1),
+            Some("Meintest du `git-add`, `git-mv`?")
+        );
+
+        let message = Message::PageNotFound {
+            command: "git-ad",
+            suggestions: &[],
+        };
+        assert!(!Localized(message, Locale::French)
+            .to_string()
+            .contains("Vouliez-vous dire"));
+    }
+}
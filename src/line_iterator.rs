@@ -28,6 +28,10 @@ pub struct LineIterator<R: BufRead> {
     current_line: String,
     /// The tldr page format.
     format: TldrFormat,
+    /// The number of lines read from `reader` so far.
+    lines_read: usize,
+    /// The (1-based) number of the line the last yielded item starts on.
+    line_number: usize,
 }
 
 impl<R> LineIterator<R>
@@ -40,8 +44,30 @@ where
             first_line: true,
             current_line: String::new(),
             format: TldrFormat::Undecided,
+            lines_read: 0,
+            line_number: 0,
         }
     }
+
+    /// Create a `LineIterator` for a fragment in a known format (e.g. a patch), which does not
+    /// start with a title.
+    pub fn with_format(reader: R, format: TldrFormat) -> Self {
+        Self {
+            first_line: false,
+            format,
+            ..Self::new(reader)
+        }
+    }
+
+    /// The format of the page, which is decided once the first line has been read.
+    pub fn format(&self) -> &TldrFormat {
+        &self.format
+    }
+
+    /// The (1-based) number of the line in `reader` that the last yielded item starts on.
+    pub fn line_number(&self) -> usize {
+        self.line_number
+    }
 }
 
 impl<R: BufRead> Iterator for LineIterator<R> {
@@ -57,6 +83,9 @@ impl<R: BufRead> Iterator for LineIterator<R> {
                 None
             }
             Ok(_) => {
+                self.lines_read += 1;
+                self.line_number = self.lines_read;
+
                 // Handle new titles
                 if self.first_line {
                     if self.current_line.starts_with('#') {
@@ -71,6 +100,7 @@ impl<R: BufRead> Iterator for LineIterator<R> {
                             warn!("Could not read line from reader: {e:?}");
                             return None;
                         }
+                        self.lines_read += 1;
                         self.first_line = false;
                         self.format = TldrFormat::V2;
                         return Some(LineType::Title(self.current_line.trim_end().to_string()));
@@ -82,7 +112,11 @@ impl<R: BufRead> Iterator for LineIterator<R> {
                 match self.format {
                     TldrFormat::V1 => Some(LineType::from_v1(&self.current_line[..])),
                     TldrFormat::V2 => Some(LineType::from(&self.current_line[..])),
-                    TldrFormat::Undecided => panic!("Could not determine page format version"),
+                    // Only possible for fragments created with an undecided format, whose
+                    // lines can't be classified
+                    TldrFormat::Undecided => {
+                        Some(LineType::Other(self.current_line.trim_end().to_string()))
+                    }
                 }
             }
         }
@@ -119,4 +153,16 @@ mod test {
             LineType::Description("Description".to_string())
         );
     }
+
+    #[test]
+    fn test_line_numbers() {
+        let input = "The Title\n=========\n\n> Description\n";
+        let mut lines = LineIterator::new(input.as_bytes());
+        lines.next().unwrap();
+        assert_eq!(lines.line_number(), 1);
+        lines.next().unwrap();
+        assert_eq!(lines.line_number(), 3);
+        lines.next().unwrap();
+        assert_eq!(lines.line_number(), 4);
+    }
 }
@@ -0,0 +1,364 @@
+//! Validation of custom pages and patches.
+
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+
+use crate::{
+    cache::include_directive,
+    formatter::find_marker,
+    line_iterator::{LineIterator, TldrFormat},
+    types::LineType,
+};
+
+/// The kind of file in the custom pages directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CustomFileKind {
+    /// A custom page (`<name>.page.md`) that replaces the upstream page.
+    Page,
+    /// A custom patch (`<name>.patch.md`) that is appended to the upstream page.
+    Patch,
+}
+
+/// A formatting problem found in a custom page or patch.
+#[derive(Debug, PartialEq, Eq)]
+pub struct LintIssue {
+    /// The (1-based) line the issue was found on, if it refers to a specific line.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl LintIssue {
+    fn at(line: usize, message: impl Into<String>) -> Self {
+        Self {
+            line: Some(line),
+            message: message.into(),
+        }
+    }
+}
+
+/// Check the contents of a custom page or patch for formatting mistakes.
+///
+/// Patches are appended to a page, so they don't have a title and are parsed in the format of
+/// the page they are appended to. Since that page is not known here, patches containing indented
+/// lines are assumed to use the new format, all others are assumed to use the old one.
+pub fn lint_page(content: &str, kind: CustomFileKind) -> Vec<LintIssue> {
+    let mut lines = match kind {
+        CustomFileKind::Page => LineIterator::new(content.as_bytes()),
+        CustomFileKind::Patch => {
+            let format = if content.lines().any(|line| line.starts_with(' ')) {
+                TldrFormat::V2
+            } else {
+                TldrFormat::V1
+            };
+            LineIterator::with_format(content.as_bytes(), format)
+        }
+    };
+
+    let mut issues = Vec::new();
+    let mut seen_title = false;
+    // Line number of an example description that has not been followed by code yet
+    let mut pending_example = None;
+
+    while let Some(line) = lines.next() {
+        let line_number = lines.line_number();
+        match line {
+            LineType::Empty => {}
+            LineType::Title(title) => {
+                if kind == CustomFileKind::Patch {
+                    issues.push(LintIssue::at(
+                        line_number,
+                        "Patches must not contain a title",
+                    ));
+                } else if seen_title {
+                    issues.push(LintIssue::at(line_number, "Unexpected second title"));
+                } else if title.is_empty() {
+                    issues.push(LintIssue::at(line_number, "Title is empty"));
+                }
+                seen_title = true;
+            }
+            LineType::Description(_) => {
+                if let Some(example_line) = pending_example.take() {
+                    issues.push(LintIssue::at(
+                        example_line,
+                        "Example description is not followed by a code example",
+                    ));
+                }
+            }
+            LineType::ExampleText(text) => {
+                if *lines.format() == TldrFormat::V2 && text.starts_with(['-', '`', '*']) {
+                    issues.push(LintIssue::at(
+                        line_number,
+                        format!("Stray markdown in example description: {text:?}"),
+                    ));
+                }
+                if let Some(example_line) = pending_example.replace(line_number) {
+                    issues.push(LintIssue::at(
+                        example_line,
+                        "Example description is not followed by a code example",
+                    ));
+                }
+            }
+            LineType::ExampleCode(code) => {
+                if pending_example.take().is_none() {
+                    issues.push(LintIssue::at(
+                        line_number,
+                        "Code example without a preceding description",
+                    ));
+                }
+                issues.extend(
+                    placeholder_issues(&code)
+                        .into_iter()
+                        .map(|message| LintIssue::at(line_number, message)),
+                );
+            }
+            LineType::Other(text) if include_directive(&text).is_some() => {}
+            LineType::Other(text) => {
+                issues.push(LintIssue::at(
+                    line_number,
+                    format!("Unrecognized line: {text:?}"),
+                ));
+            }
+        }
+    }
+
+    if let Some(example_line) = pending_example {
+        issues.push(LintIssue::at(
+            example_line,
+            "Example description is not followed by a code example",
+        ));
+    }
+
+    issues
+}
+
+/// Check the placeholders (`{{...}}`) in a code example, finding them the same way the renderer
+/// does. Escaped markers (`\{\{` and `\}\}`) are not placeholders.
+fn placeholder_issues(mut code: &str) -> Vec<String> {
+    let mut issues = Vec::new();
+    loop {
+        let start = find_marker(code, "{{", r"\{\{");
+        let before_start = &code[..start.unwrap_or(code.len())];
+        if find_marker(before_start, "}}", r"\}\}").is_some() {
+            issues.push(format!(
+                "Stray `}}}}` outside of a placeholder in {before_start:?}, escape it as `\\}}\\}}`"
+            ));
+        }
+        let Some(start) = start else {
+            break;
+        };
+
+        let Some(mut end) = find_marker(&code[start + 2..], "}}", r"\}\}") else {
+            issues.push(format!(
+                "Placeholder {:?} is not closed with `}}}}`",
+                &code[start..]
+            ));
+            break;
+        };
+        end += start + 2;
+        // The renderer extends placeholders greedily, e.g. `{{stash@{0}}}`
+        while code.as_bytes().get(end + 2) == Some(&b'}') {
+            end += 1;
+        }
+
+        let content = &code[start + 2..end];
+        if content.trim().is_empty() {
+            issues.push("Empty placeholder `{{}}`".to_string());
+        } else if find_marker(content, "{{", r"\{\{").is_some() {
+            issues.push(format!(
+                "Nested placeholder in {:?}, placeholders can not contain `{{{{`",
+                &code[start..end + 2]
+            ));
+        }
+        code = &code[end + 2..];
+    }
+    issues
+}
+
+/// Lint all custom pages and patches in `directory`.
+///
+/// Returns every checked file (sorted by path) together with the issues found in it. Files using
+/// the old naming convention (`<name>.page` and `<name>.patch`) are reported as well.
+pub fn lint_custom_pages_dir(directory: &Path) -> Result<Vec<(PathBuf, Vec<LintIssue>)>> {
+    let file_iter = match fs::read_dir(directory) {
+        Ok(file_iter) => file_iter,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(e).with_context(|| {
+                format!(
+                    "Could not read custom pages directory at {}",
+                    directory.display()
+                )
+            })
+        }
+    };
+
+    let mut paths = Vec::new();
+    for entry in file_iter {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            paths.push(entry.path());
+        }
+    }
+    paths.sort_unstable();
+
+    let mut results = Vec::new();
+    for path in paths {
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+
+        let kind = if file_name.ends_with(".page.md") {
+            CustomFileKind::Page
+        } else if file_name.ends_with(".patch.md") {
+            CustomFileKind::Patch
+        } else {
+            if let Some(extension @ ("page" | "patch")) =
+                path.extension().and_then(|ext| ext.to_str())
+            {
+                let issue = LintIssue {
+                    line: None,
+                    message: format!(
+                        "Old naming convention, please rename to `{file_name}.md` (the `.{extension}` extension is no longer supported)"
+                    ),
+                };
+                results.push((path, vec![issue]));
+            }
+            continue;
+        };
+
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Could not read {}", path.display()))?;
+        let issues = lint_page(&content, kind);
+        results.push((path, issues));
+    }
+
+    Ok(results)
+}
+
+/// Find the markdown files in `directory` and its subdirectories, sorted by path.
+pub fn markdown_files(directory: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let entries = fs::read_dir(directory)
+        .with_context(|| format!("Could not read directory {}", directory.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            files.extend(markdown_files(&path)?);
+        } else if path.extension().is_some_and(|ext| ext == "md") {
+            files.push(path);
+        }
+    }
+    files.sort_unstable();
+    Ok(files)
+}
+
+/// Lint the pages at `paths`, e.g. pages written for the tldr-pages repository.
+///
+/// Returns every checked file together with the issues found in it.
+pub fn lint_pages(paths: &[PathBuf]) -> Result<Vec<(PathBuf, Vec<LintIssue>)>> {
+    paths
+        .iter()
+        .map(|path| {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("Could not read {}", path.display()))?;
+            Ok((path.clone(), lint_page(&content, CustomFileKind::Page)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines_with_issues(content: &str, kind: CustomFileKind) -> Vec<usize> {
+        lint_page(content, kind)
+            .into_iter()
+            .map(|issue| issue.line.unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_pages() {
+        let v1 = "# foo\n\n> Description.\n\n- Example:\n\n`foo {{bar}}`\n";
+        assert!(lint_page(v1, CustomFileKind::Page).is_empty());
+
+        let v2 = "foo\n===\n\n> Description.\n\nExample:\n\n    foo {{bar}}\n";
+        assert!(lint_page(v2, CustomFileKind::Page).is_empty());
+    }
+
+    #[test]
+    fn test_valid_patches() {
+        let v1 = "- Example:\n\n`foo {{bar}}`\n";
+        assert!(lint_page(v1, CustomFileKind::Patch).is_empty());
+
+        let v2 = "Example:\n\n    foo {{bar}}\n";
+        assert!(lint_page(v2, CustomFileKind::Patch).is_empty());
+    }
+
+    #[test]
+    fn test_example_without_code() {
+        let page = "# foo\n\n- Example:\n\n- Another example:\n\n`foo`\n\n- Last example:\n";
+        assert_eq!(lines_with_issues(page, CustomFileKind::Page), [3, 9]);
+    }
+
+    #[test]
+    fn test_code_without_example() {
+        let page = "foo\n===\n\n    foo\n";
+        assert_eq!(lines_with_issues(page, CustomFileKind::Page), [4]);
+    }
+
+    #[test]
+    fn test_titles() {
+        assert_eq!(
+            lines_with_issues("# foo\n# bar\n", CustomFileKind::Page),
+            [2]
+        );
+        assert_eq!(lines_with_issues("\n===\n", CustomFileKind::Page), [1]);
+        assert_eq!(
+            lines_with_issues("# foo\n- bar\n`bar`\n", CustomFileKind::Patch),
+            [1]
+        );
+    }
+
+    #[test]
+    fn test_placeholders() {
+        let issues = |code: &str| placeholder_issues(code).len();
+
+        assert_eq!(issues("git stash show --patch {{stash@{0}}}"), 0);
+        assert_eq!(
+            issues(r"docker inspect --format '\{\{.Id\}\}' {{container}}"),
+            0
+        );
+        assert_eq!(issues("awk '{print $1}' {{path/to/file}}"), 0);
+
+        assert_eq!(issues("foo {{bar"), 1);
+        assert_eq!(issues("foo bar}} {{baz}}"), 1);
+        assert_eq!(issues("foo {{bar}} baz}}"), 1);
+        assert_eq!(issues("foo {{}}"), 1);
+        assert_eq!(issues("foo {{bar {{baz}}"), 1);
+        assert_eq!(issues("foo }} {{bar"), 2);
+
+        assert_eq!(
+            lines_with_issues("# foo\n\n- Example:\n\n`foo {{bar`\n", CustomFileKind::Page),
+            [5]
+        );
+    }
+
+    #[test]
+    fn test_stray_markdown() {
+        assert_eq!(
+            lines_with_issues("# foo\n* item\n", CustomFileKind::Page),
+            [2]
+        );
+        assert_eq!(
+            lines_with_issues("foo\n===\n- Example:\n\n    foo\n", CustomFileKind::Page),
+            [3]
+        );
+    }
+}
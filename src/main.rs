@@ -29,39 +29,77 @@ compile_error!(
 
 use std::{
     env,
-    fs::create_dir_all,
-    io::{self, IsTerminal},
-    path::Path,
+    fs::{self, create_dir_all, File},
+    io::{self, BufWriter, IsTerminal, Read, Write},
+    path::{Path, PathBuf},
     process::{Command, ExitCode},
+    slice, thread,
+    time::{Duration, Instant, SystemTime},
 };
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, ensure, Context, Result};
 use app_dirs::AppInfo;
 use cache::{CacheConfig, TLDR_OLD_PAGES_DIR};
-use clap::Parser;
-use config::{ConfigLoader, Language, StyleConfig, TlsBackend};
-use log::debug;
-use types::PlatformType;
+use clap::{Parser, ValueEnum};
+use config::{ConfigLoader, Language, StyleConfig, TlsBackend, UpdatesConfig};
+use log::{debug, warn};
+use serde_derive::Serialize;
+use types::{PlatformArg, PlatformType};
+use unicode_width::UnicodeWidthStr;
 
 mod cache;
 mod cli;
+mod colors;
+mod completions;
 mod config;
+mod config_docs;
+mod deprecation;
 pub mod extensions;
 mod formatter;
+mod history;
+mod hooks;
+mod i18n;
 mod line_iterator;
+mod lint;
+mod minisign;
 mod output;
+mod self_update;
+#[cfg(feature = "serve")]
+mod serve;
+mod shell;
+mod templates;
+mod timings;
 mod types;
 mod utils;
 
 use crate::{
-    cache::{Cache, PageLookupResult, TLDR_PAGES_DIR},
+    cache::{
+        find_old_custom_pages, is_network_unreachable, validate_page_name, Cache, CacheChanges,
+        CacheProblem, PageContent, PageLookupResult, FETCHED_PAGES_DIR, TLDR_PAGES_DIR,
+    },
     cli::Cli,
+    completions::completion_script,
     config::{
-        get_config_dir, make_default_config, supported_tls_backends_string, Config, PathWithSource,
+        add_download_language, get_config_dir, get_default_config_path, get_languages_from_env,
+        get_profile_config_path, get_system_config_path, make_default_config, make_setup_config,
+        migrate_config, parse_language, remove_download_language, supported_tls_backends_string,
+        Config, PathWithSource, SetupChoices, SetupTheme,
+    },
+    deprecation::{Deprecation, DeprecationWarnings},
+    extensions::FindIgnoreAsciiCase,
+    history::{format_time_ago, History},
+    hooks::command_not_found_hook,
+    i18n::Message,
+    lint::{
+        lint_custom_pages_dir, lint_page, lint_pages, markdown_files, CustomFileKind, LintIssue,
     },
-    output::print_page,
-    types::ColorOptions,
-    utils::{print_error, print_warning},
+    output::{
+        alias_page, fill_example, page_summary, print_apropos_matches, print_flag_matches,
+        print_header, print_page, print_patch_diff, print_tip, OutputFile, OutputFormat,
+    },
+    timings::Timings,
+    types::{ColorOptions, ExitReason, PathSource, ReportFormat},
+    utils::{confirm, print_error, print_warning, prompt, set_warnings_enabled, terminal_width},
 };
 
 const NAME: &str = "tealdeer";
@@ -73,38 +111,975 @@ static TEALDEER_PAGE: &str =
     include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/pages/tealdeer.md"));
 
 /// Clear the cache
-fn clear_cache(cache: Cache, quietly: bool) -> Result<()> {
+fn clear_cache(cache: Cache, fetched_pages_dir: &Path, quietly: bool) -> Result<()> {
     let cache_dir = cache.config().pages_directory.display();
     cache.clear().context("Could not clear cache")?;
+    if fetched_pages_dir.exists() {
+        fs::remove_dir_all(fetched_pages_dir).with_context(|| {
+            format!(
+                "Could not remove fetched pages at {}",
+                fetched_pages_dir.display()
+            )
+        })?;
+    }
     if !quietly {
         eprintln!("Successfully cleared cache at `{cache_dir}`.");
     }
     Ok(())
 }
 
-/// Update the cache
-fn update_cache(
-    cache: &mut Cache,
-    archive_source: &str,
+/// Find a page that is missing from the cache among the individually fetched pages, or fetch it
+fn find_or_fetch_page(
+    cache: &Cache,
+    config: &Config,
+    command: &str,
+    quietly: bool,
+    enable_styles: bool,
+) -> Option<PageLookupResult> {
+    let fetched_pages_dir = config.directories.cache_dir.path().join(FETCHED_PAGES_DIR);
+    if let Some(page) = cache.find_fetched_page(
+        &fetched_pages_dir,
+        command,
+        config.updates.fetched_pages_ttl,
+    ) {
+        return Some(
+            PageLookupResult::with_page(page).with_optional_patch(cache.find_patch(command)),
+        );
+    }
+
+    if !quietly {
+        eprintln!(
+            "Page `{command}` not found in cache, fetching it from {}.",
+            config.updates.pages_source,
+        );
+    }
+    let fetched = cache.fetch_page(
+        &fetched_pages_dir,
+        command,
+        config.updates.pages_source,
+        config.updates.tls_backend,
+    );
+    let page = match fetched {
+        Ok(page) => page?,
+        Err(e) => {
+            print_warning(
+                enable_styles,
+                &format!("Could not fetch page `{command}`: {e:#}"),
+            );
+            return None;
+        }
+    };
+
+    Some(PageLookupResult::with_page(page).with_optional_patch(cache.find_patch(command)))
+}
+
+/// If the page in `result` is a page for an alias (see [`alias_page`]), return the page of the
+/// aliased command instead. Aliases are followed only once, and the page is kept if the page of
+/// the aliased command cannot be found.
+fn follow_alias(
+    cache: &Cache,
     tls_backend: TlsBackend,
+    command: &str,
+    result: PageLookupResult,
+    quietly: bool,
+    enable_styles: bool,
+) -> Result<PageLookupResult> {
+    let Some(alias) = alias_page(result.reader()?) else {
+        return Ok(result);
+    };
+    if alias.target == command {
+        return Ok(result);
+    }
+    let Some(target_result) = cache.find_page_in_sources(&alias.target, tls_backend, enable_styles)
+    else {
+        debug!("Page `{}` of the aliased command not found", alias.target);
+        return Ok(result);
+    };
+    if !quietly {
+        eprintln!(
+            "`{command}` is an alias of `{}`, showing the page for `{}`.",
+            alias.command, alias.target
+        );
+    }
+    Ok(target_result)
+}
+
+/// Report which languages in the cache are outdated. Returns whether an update is needed.
+fn check_for_updates(cache_config: CacheConfig, config: &Config) -> Result<bool> {
+    ensure!(
+        !config.updates.use_index,
+        "Checking for updates is not supported when the cache is updated from the index of the \
+         tldr pages (`updates.use_index`)."
+    );
+
+    let Some(cache) = Cache::open(cache_config)? else {
+        println!("The cache does not exist yet.");
+        return Ok(true);
+    };
+
+    let statuses = cache
+        .check_for_updates(
+            &config.updates.archive_source,
+            config.updates.archive_format,
+            config.updates.tls_backend,
+        )
+        .context("Could not check for updates")?;
+    for (Language(language), status) in &statuses {
+        println!("{language}: {status}");
+    }
+    Ok(statuses.iter().any(|(_, status)| status.needs_update()))
+}
+
+/// Download the pages again if the cache does not contain any languages, e.g. because it was
+/// corrupted. Without `auto_update`, a targeted error is returned instead. Returns whether the
+/// pages were downloaded.
+fn recover_empty_cache(
+    cache: &mut Cache,
+    config: &Config,
+    auto_update: bool,
+    quietly: bool,
+    enable_styles: bool,
+) -> Result<bool> {
+    if !cache.available_languages()?.is_empty() || cache.index()?.is_some() {
+        return Ok(false);
+    }
+
+    let pages_directory = cache.config().pages_directory.display().to_string();
+    ensure!(
+        auto_update,
+        "The cache at {pages_directory} does not contain any pages, it might be corrupted.\n\
+         Please run `tldr --update` to download the pages again."
+    );
+    print_warning(
+        enable_styles,
+        &format!(
+            "The cache at {pages_directory} does not contain any pages, downloading them again."
+        ),
+    );
+    update_cache(cache, config, false, quietly, enable_styles)?;
+    Ok(true)
+}
+
+/// Check the cache for problems and offer to download the affected languages again. Returns
+/// whether the cache is fine afterwards.
+fn check_cache(cache_config: CacheConfig, config: &Config, quietly: bool) -> Result<bool> {
+    let Some(mut cache) = Cache::open(cache_config)? else {
+        println!("The cache does not exist yet. Run `tldr --update` to download the pages.");
+        return Ok(false);
+    };
+
+    let problems = cache.check().context("Could not check the cache")?;
+    if problems.is_empty() {
+        println!("No problems found in the cache.");
+        return Ok(true);
+    }
+    for problem in &problems {
+        println!("{problem}");
+    }
+
+    let mut languages: Vec<_> = problems.iter().map(CacheProblem::language).collect();
+    languages.dedup();
+    if !confirm("Download the pages for these languages again?").context("Could not read answer")? {
+        return Ok(false);
+    }
+    let repaired_languages = cache
+        .repair(
+            &languages,
+            &config.updates.archive_source,
+            config.updates.archive_format,
+            config.updates.tls_backend,
+            config.updates.archive_public_key.as_ref(),
+        )
+        .context("Could not repair cache")?;
+    if !quietly {
+        let language_strings: Vec<_> = repaired_languages.iter().map(|lang| lang.0).collect();
+        eprintln!(
+            "Downloaded the pages for the following languages again: {}",
+            if language_strings.is_empty() {
+                "(none)".to_string()
+            } else {
+                language_strings.join(", ")
+            },
+        );
+    }
+
+    let remaining_problems = cache.check().context("Could not check the cache")?;
+    for problem in &remaining_problems {
+        println!("Not fixed: {problem}");
+    }
+    Ok(remaining_problems.is_empty())
+}
+
+/// Remove pages for languages that are not downloaded anymore from the cache
+fn prune_languages(cache: &Cache, quietly: bool) -> Result<()> {
+    let pruned_languages = cache
+        .prune_languages()
+        .context("Could not prune languages")?;
+    if !quietly {
+        if pruned_languages.is_empty() {
+            eprintln!("There are no pages for languages that are not configured for download.");
+        } else {
+            eprintln!(
+                "Removed pages for the following languages: {}",
+                pruned_languages.join(", "),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Download the pages for `language` and add it to the download languages in the config file.
+/// The config file is only changed if pages for the language exist.
+fn add_language(
+    cache_config: CacheConfig,
+    config: &Config,
+    language: &str,
     quietly: bool,
 ) -> Result<()> {
-    let downloaded_languages = cache
-        .update(archive_source, tls_backend)
+    let (mut cache, _) = Cache::open_or_create(cache_config)?;
+    let downloaded = cache
+        .repair(
+            &[Language(language)],
+            &config.updates.archive_source,
+            config.updates.archive_format,
+            config.updates.tls_backend,
+            config.updates.archive_public_key.as_ref(),
+        )
+        .with_context(|| format!("Could not download the pages for language `{language}`"))?;
+    ensure!(
+        !downloaded.is_empty(),
+        "There are no pages for language `{language}` at {}.",
+        config.updates.archive_source,
+    );
+
+    add_download_language(
+        config.file_path.path(),
+        &config.updates.download_languages,
+        language,
+    )?;
+    if !quietly {
+        eprintln!("Downloaded the pages for language `{language}`.");
+    }
+    Ok(())
+}
+
+/// Remove `language` from the download languages in the config file and its pages from the cache.
+fn remove_language(
+    cache_config: CacheConfig,
+    config: &Config,
+    language: &str,
+    quietly: bool,
+) -> Result<()> {
+    remove_download_language(
+        config.file_path.path(),
+        &config.updates.download_languages,
+        language,
+    )?;
+
+    let removed = match Cache::open(cache_config)? {
+        Some(cache) => cache.remove_language(Language(language))?,
+        None => false,
+    };
+    if !quietly {
+        if removed {
+            eprintln!("Removed the pages for language `{language}`.");
+        } else {
+            eprintln!("There were no pages for language `{language}` in the cache.");
+        }
+    }
+    Ok(())
+}
+
+/// Whether the cache should be updated automatically. The interval is extended by a random
+/// jitter, and updates are not attempted again shortly after the last attempt, so that many
+/// shells started at the same time don't all access the network.
+///
+/// The jitter is chosen once per update cycle, the resulting due time is kept in the state
+/// directory until the next update.
+fn auto_update_due(cache: &Cache, updates: &UpdatesConfig) -> Result<bool> {
+    let earliest = cache.last_update()? + updates.auto_update_interval;
+    let now = SystemTime::now();
+    if now < earliest {
+        return Ok(false);
+    }
+
+    let latest = earliest + updates.auto_update_jitter;
+    // A due time outside of the range was chosen with a different config
+    let due = match cache.auto_update_due_time() {
+        Some(due) if due <= latest && due + Duration::from_secs(1) >= earliest => due,
+        _ => {
+            let jitter = fastrand::u64(0..=updates.auto_update_jitter.as_secs());
+            let due = earliest + Duration::from_secs(jitter);
+            if let Err(e) = cache.set_auto_update_due_time(due) {
+                debug!("Could not record the time of the next automatic update: {e:?}");
+            }
+            due
+        }
+    };
+    Ok(now >= due && cache.update_attempt_due(updates.min_update_attempt_interval))
+}
+
+/// Run the command of a hook from the `[hooks]` section with the env variables in `env`. Its
+/// output is written to stderr, so that it doesn't end up in the output of pages.
+fn run_hook(name: &str, command: &str, env: &[(&str, String)]) -> Result<()> {
+    let mut words = shell_words::split(command)
+        .with_context(|| format!("Could not parse the `hooks.{name}` command `{command}`"))?
+        .into_iter();
+    let program = words
+        .next()
+        .with_context(|| format!("The `hooks.{name}` command is empty"))?;
+    let status = Command::new(&program)
+        .args(words)
+        .envs(env.iter().map(|(key, value)| (key, value)))
+        .stdout(io::stderr())
+        .status()
+        .with_context(|| format!("Could not run the `hooks.{name}` command `{program}`"))?;
+    ensure!(
+        status.success(),
+        "The `hooks.{name}` command `{command}` failed with {status}"
+    );
+    Ok(())
+}
+
+/// Run the `post_update` hook `command` with the env variables describing the update. The lists of
+/// changed pages are passed as files, as they can exceed the size limit of env variables (128 KiB
+/// on Linux) when many pages changed.
+fn run_post_update_hook(
+    command: &str,
+    pages_dir: (&str, String),
+    languages: &[&str],
+    changes: &CacheChanges,
+) -> Result<()> {
+    let write_list = |pages: &[String]| -> Result<tempfile::NamedTempFile> {
+        let mut file = tempfile::NamedTempFile::new()
+            .context("Could not create a file for the `hooks.post_update` command")?;
+        for page in pages {
+            writeln!(file, "{page}")?;
+        }
+        file.flush()?;
+        Ok(file)
+    };
+    let added = write_list(&changes.added)?;
+    let removed = write_list(&changes.removed)?;
+    let modified = write_list(&changes.modified)?;
+
+    let env = [
+        pages_dir,
+        ("TEALDEER_UPDATED_LANGUAGES", languages.join(",")),
+        (
+            "TEALDEER_ADDED_PAGES_FILE",
+            added.path().display().to_string(),
+        ),
+        (
+            "TEALDEER_REMOVED_PAGES_FILE",
+            removed.path().display().to_string(),
+        ),
+        (
+            "TEALDEER_MODIFIED_PAGES_FILE",
+            modified.path().display().to_string(),
+        ),
+    ];
+    run_hook("post_update", command, &env)
+}
+
+/// The report of an update that is printed with `tldr --update --output json`.
+#[derive(Clone, Debug, Default, Serialize)]
+struct UpdateReport {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// The languages whose pages were downloaded.
+    languages: Vec<String>,
+    /// The number of commands in the index, if only the index was downloaded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    indexed_commands: Option<usize>,
+    bytes_downloaded: u64,
+    pages_added: usize,
+    pages_removed: usize,
+    pages_modified: usize,
+    duration_ms: u128,
+}
+
+/// Update the cache as configured in `config.updates`. If `updates.use_index` is set, only the
+/// index of the tldr pages is downloaded instead of the archives.
+///
+/// The hooks in `config.hooks` are run before and after the update. As they can't undo the
+/// update, failing hooks are only reported as warnings. The pages that were changed are only
+/// counted in the returned report with `count_changes`, as all pages need to be read for that.
+fn update_cache(
+    cache: &mut Cache,
+    config: &Config,
+    count_changes: bool,
+    quietly: bool,
+    enable_styles: bool,
+) -> Result<UpdateReport> {
+    let updates = &config.updates;
+    let hooks = config.hooks;
+    let pages_dir = (
+        "TEALDEER_PAGES_DIR",
+        cache.config().pages_directory.display().to_string(),
+    );
+    if let Some(command) = hooks.pre_update {
+        if let Err(e) = run_hook("pre_update", command, slice::from_ref(&pages_dir)) {
+            print_warning(enable_styles, &format!("{e:#}"));
+        }
+    }
+
+    let archive_format = updates.archive_format;
+    let tls_backend = updates.tls_backend;
+    if updates.use_index {
+        let index_url = updates.index_url;
+        let index = cache
+            .update_index(index_url, tls_backend)
+            .context("Could not update cache")?;
+        let updated_sources = cache.update_sources(archive_format, tls_backend)?;
+        // No pages are downloaded, so none are reported as changed
+        if let Some(command) = hooks.post_update {
+            if let Err(e) = run_post_update_hook(command, pages_dir, &[], &CacheChanges::default())
+            {
+                print_warning(enable_styles, &format!("{e:#}"));
+            }
+        }
+        if !quietly {
+            eprintln!("Successfully updated cache.");
+            eprintln!(
+                "The index lists {} pages, they are downloaded when you look them up.",
+                index.commands,
+            );
+            print_updated_sources(&updated_sources);
+        }
+        return Ok(UpdateReport {
+            success: true,
+            indexed_commands: Some(index.commands),
+            bytes_downloaded: index.bytes,
+            ..UpdateReport::default()
+        });
+    }
+
+    // The update replaces the whole pages directory, so pages for languages that are not
+    // downloaded anymore are removed as well.
+    let orphaned_languages = cache.orphaned_languages().unwrap_or_default();
+    let old_pages =
+        if (updates.show_changes && !quietly) || hooks.post_update.is_some() || count_changes {
+            Some(cache.page_hashes()?)
+        } else {
+            None
+        };
+
+    // Without a size limit, the pages removed to stay below the limit are downloaded again
+    if updates.max_cache_size.is_none() {
+        cache.forget_evicted_pages()?;
+    }
+    let downloaded = cache
+        .update(
+            &updates.archive_source,
+            archive_format,
+            tls_backend,
+            updates.archive_public_key.as_ref(),
+        )
         .context("Could not update cache")?;
+    let evicted_pages = match updates.max_cache_size {
+        Some(max_cache_size) => cache.evict(max_cache_size)?,
+        None => Vec::new(),
+    };
+    let updated_sources = cache.update_sources(archive_format, tls_backend)?;
+    let language_strings: Vec<_> = downloaded.languages.iter().map(|lang| lang.0).collect();
+    let changes = match &old_pages {
+        Some(old_pages) => Some(old_pages.changes(&cache.page_hashes()?)),
+        None => None,
+    };
+
+    // The pages are hashed before the update if there is a `post_update` hook
+    if let (Some(command), Some(changes)) = (hooks.post_update, &changes) {
+        if let Err(e) = run_post_update_hook(command, pages_dir, &language_strings, changes) {
+            print_warning(enable_styles, &format!("{e:#}"));
+        }
+    }
+
     if !quietly {
         eprintln!("Successfully updated cache.");
         eprint!("Pages for the following languages were downloaded: ");
-        let language_strings: Vec<_> = downloaded_languages
-            .into_iter()
-            .map(|lang| lang.0)
-            .collect();
         if language_strings.is_empty() {
             eprintln!("(none)");
         } else {
             eprintln!("{}", language_strings.join(", "));
         }
+        print_updated_sources(&updated_sources);
+        // Comparing with an empty cache would list every page as added
+        if let Some(changes) = changes.as_ref().filter(|_| {
+            updates.show_changes && old_pages.as_ref().is_some_and(|pages| !pages.is_empty())
+        }) {
+            print_cache_changes(changes);
+        }
+
+        if !orphaned_languages.is_empty() {
+            print_warning(
+                enable_styles,
+                &format!(
+                    "Pages for the following languages were removed, because they are not \
+                     configured for download anymore: {}",
+                    orphaned_languages.join(", "),
+                ),
+            );
+        }
+
+        if !evicted_pages.is_empty() {
+            print_warning(
+                enable_styles,
+                &format!(
+                    "The following pages were removed to keep the cache below \
+                     `updates.max_cache_size_mb`, least recently used first: {}",
+                    evicted_pages
+                        .iter()
+                        .map(|pages| format!("{} ({})", pages.directory, format_size(pages.size)))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                ),
+            );
+        }
+
+        // Pages are looked up in English if they aren't translated, so without English pages
+        // many commands can't be found
+        let download_languages = cache.config().download_languages;
+        if !download_languages.is_empty() && !download_languages.contains(&Language("en")) {
+            print_warning(
+                enable_styles,
+                &format!(
+                    "English pages were not downloaded, so pages which are not translated to {} \
+                     can not be shown. Add \"en\" to `updates.download_languages` to use them \
+                     as a fallback.",
+                    download_languages
+                        .iter()
+                        .map(|lang| lang.0)
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                ),
+            );
+        }
+    }
+
+    let changes = changes.unwrap_or_default();
+    Ok(UpdateReport {
+        success: true,
+        languages: language_strings.into_iter().map(str::to_string).collect(),
+        bytes_downloaded: downloaded.bytes,
+        pages_added: changes.added.len(),
+        pages_removed: changes.removed.len(),
+        pages_modified: changes.modified.len(),
+        ..UpdateReport::default()
+    })
+}
+
+/// Print the report of an update, or of the error that it failed with, as JSON.
+fn print_update_report(result: &Result<UpdateReport>, duration: Duration) -> Result<()> {
+    let report = match result {
+        Ok(report) => UpdateReport {
+            duration_ms: duration.as_millis(),
+            ..report.clone()
+        },
+        Err(e) => UpdateReport {
+            error: Some(format!("{e:#}")),
+            duration_ms: duration.as_millis(),
+            ..UpdateReport::default()
+        },
+    };
+    println!(
+        "{}",
+        serde_json::to_string(&report).context("Could not serialize the update report")?
+    );
+    Ok(())
+}
+
+/// Print the pages that were added and removed by an update, and how many were modified.
+fn print_cache_changes(changes: &CacheChanges) {
+    // `pages.en/common/tar.md` is shown as `common/tar`, and as `common/tar (de)` for German
+    let describe = |paths: &[String]| -> String {
+        paths
+            .iter()
+            .map(|path| {
+                let (language, page) = path.split_once('/').unwrap_or(("", path));
+                let page = page.strip_suffix(".md").unwrap_or(page);
+                match language.strip_prefix("pages.") {
+                    Some("en") | None => page.to_string(),
+                    Some(language) => format!("{page} ({language})"),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    if changes.added.is_empty() && changes.removed.is_empty() && changes.modified.is_empty() {
+        eprintln!("No pages were changed.");
+        return;
+    }
+    if !changes.added.is_empty() {
+        eprintln!("Added pages: {}", describe(&changes.added));
+    }
+    if !changes.removed.is_empty() {
+        eprintln!("Removed pages: {}", describe(&changes.removed));
+    }
+    if !changes.modified.is_empty() {
+        eprintln!("Modified pages: {}", changes.modified.len());
+    }
+}
+
+fn print_updated_sources(updated_sources: &[&str]) {
+    if !updated_sources.is_empty() {
+        eprintln!(
+            "The pages of the following sources were updated: {}",
+            updated_sources.join(", "),
+        );
+    }
+}
+
+/// Show the platforms and languages for which a page for `command` exists, according to the
+/// index of the tldr pages.
+fn show_availability(cache: &Cache, command: &str) -> Result<bool> {
+    let index = cache.index()?.context(
+        "The cache does not contain the index of the tldr pages.\n\
+         Set `updates.use_index = true` in your config file and run `tldr --update` to download it.",
+    )?;
+    let Some(targets) = index.targets(command) else {
+        return Ok(false);
+    };
+
+    let mut platforms: Vec<(&str, Vec<&str>)> = Vec::new();
+    for target in targets {
+        match platforms.iter_mut().find(|(os, _)| *os == target.os) {
+            Some((_, languages)) => languages.push(&target.language),
+            None => platforms.push((&target.os, vec![&target.language])),
+        }
+    }
+    for (os, mut languages) in platforms {
+        languages.sort_unstable();
+        println!("{os}: {}", languages.join(", "));
+    }
+    Ok(true)
+}
+
+/// Print each of `pages` with the platforms for which it exists, e.g. `ls  [linux, osx]`. Pages
+/// that only exist as custom pages are printed without platforms.
+fn print_page_platforms(cache: &Cache, pages: &[String]) -> Result<()> {
+    let index = cache.index()?;
+    let width = pages.iter().map(|page| page.width()).max().unwrap_or(0);
+    let stdout = io::stdout();
+    let mut handle = BufWriter::new(stdout.lock());
+    for page in pages {
+        let platforms = cache.page_platforms(page, index.as_ref());
+        if platforms.is_empty() {
+            writeln!(handle, "{page}")?;
+        } else {
+            let padding = width - page.width();
+            writeln!(handle, "{page}{:padding$}  [{}]", "", platforms.join(", "))?;
+        }
     }
+    handle.flush().context("Could not write the page list")?;
+    Ok(())
+}
+
+/// Fetch a page that is missing from the cache, if the index of the tldr pages lists it
+fn fetch_indexed_page(
+    cache: &Cache,
+    config: &Config,
+    command: &str,
+    enable_styles: bool,
+) -> Result<Option<PageLookupResult>> {
+    let Some(index) = cache.index()? else {
+        return Ok(None);
+    };
+    let fetched = cache.fetch_indexed_page(
+        &index,
+        command,
+        config.updates.pages_source,
+        config.updates.tls_backend,
+    );
+    let page = match fetched {
+        Ok(page) => page,
+        Err(e) => {
+            print_warning(
+                enable_styles,
+                &format!("Could not fetch page `{command}`: {e:#}"),
+            );
+            return Ok(None);
+        }
+    };
+
+    Ok(page.map(|page| {
+        PageLookupResult::with_page(page).with_optional_patch(cache.find_patch(command))
+    }))
+}
+
+/// Show the upstream page for `command` together with the lines added by its custom patch
+fn diff_patch(cache: &Cache, command: &str, enable_styles: bool) -> Result<()> {
+    let Some(patch_path) = cache.find_patch(command) else {
+        bail!("No custom patch found for `{command}`.");
+    };
+    let Some(page_path) = cache.find_upstream_page(command) else {
+        bail!(
+            "Page `{command}` not found in cache, so the patch at {} is not applied.",
+            patch_path.display(),
+        );
+    };
+    if let Some(custom_page_path) = cache.find_custom_page(command) {
+        print_warning(
+            enable_styles,
+            &format!(
+                "The custom page at {} takes precedence, so this patch is currently not applied.",
+                custom_page_path.display(),
+            ),
+        );
+    }
+
+    let page = File::open(&page_path)
+        .with_context(|| format!("Could not open page file at {}", page_path.display()))?;
+    let patch = File::open(&patch_path)
+        .with_context(|| format!("Could not open patch file at {}", patch_path.display()))?;
+    print_patch_diff(page, patch, enable_styles)
+}
+
+/// Lint custom pages and patches. Returns whether any problems were found.
+fn lint_custom_pages(custom_pages_dir: &Path, quietly: bool) -> Result<bool> {
+    let results = lint_custom_pages_dir(custom_pages_dir)?;
+    Ok(report_lint_results(
+        &results,
+        "custom pages and patches",
+        quietly,
+    ))
+}
+
+/// Render the pages in `files` one after another, each with its path above it.
+fn render_pages(
+    output: &mut impl Write,
+    files: &[PathBuf],
+    format: OutputFormat,
+    enable_styles: bool,
+    use_pager: bool,
+    config: &Config,
+    timings: &mut Timings,
+) -> Result<()> {
+    for (index, file) in files.iter().enumerate() {
+        // Rendered pages already end with an empty line, raw ones do not
+        if index > 0 && format == OutputFormat::Raw {
+            writeln!(output).context("Could not write the page")?;
+        }
+        print_header(
+            output,
+            &file.display().to_string(),
+            enable_styles,
+            use_pager,
+            config,
+        )?;
+        print_page(
+            output,
+            PageLookupResult::with_page(file.clone()).reader()?,
+            format,
+            enable_styles,
+            use_pager,
+            config,
+            timings,
+        )?;
+    }
+    Ok(())
+}
+
+/// Render the page at `path`, or the pages in the directory at `path`, again whenever they
+/// change, until tealdeer is interrupted. Changes are detected by polling the modification times.
+fn watch_pages(
+    path: &Path,
+    format: OutputFormat,
+    enable_styles: bool,
+    config: &Config,
+    timings: &mut Timings,
+) -> Result<()> {
+    const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+    let mut last_state = None;
+    loop {
+        let files = if path.is_dir() {
+            markdown_files(path)?
+        } else {
+            vec![path.to_path_buf()]
+        };
+        let state: Vec<_> = files
+            .iter()
+            .map(|file| {
+                (
+                    file.clone(),
+                    fs::metadata(file).and_then(|m| m.modified()).ok(),
+                )
+            })
+            .collect();
+
+        if last_state.as_ref() != Some(&state) {
+            let mut stdout = BufWriter::new(io::stdout().lock());
+            // Clear the screen and move the cursor to the top left corner
+            write!(stdout, "\x1b[2J\x1b[H").context("Could not clear the screen")?;
+            let result = if path.is_dir() {
+                render_pages(
+                    &mut stdout,
+                    &files,
+                    format,
+                    enable_styles,
+                    false,
+                    config,
+                    timings,
+                )
+            } else {
+                // Editors may replace the file when saving, so it can be missing for a moment
+                PageLookupResult::with_page(path.to_path_buf())
+                    .reader()
+                    .and_then(|reader| {
+                        print_page(
+                            &mut stdout,
+                            reader,
+                            format,
+                            enable_styles,
+                            false,
+                            config,
+                            timings,
+                        )
+                    })
+            };
+            stdout.flush().context("Could not flush the output")?;
+            if let Err(e) = result {
+                print_error(enable_styles, &e);
+            }
+            last_state = Some(state);
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Print the problems found by linting, one per line. Returns whether there were any.
+fn report_lint_results(
+    results: &[(PathBuf, Vec<LintIssue>)],
+    checked: &str,
+    quietly: bool,
+) -> bool {
+    let mut problem_count = 0;
+    for (path, issues) in results {
+        for issue in issues {
+            match issue.line {
+                Some(line) => println!("{}:{line}: {}", path.display(), issue.message),
+                None => println!("{}: {}", path.display(), issue.message),
+            }
+        }
+        problem_count += issues.len();
+    }
+
+    if !quietly {
+        eprintln!(
+            "Checked {} {checked}, found {problem_count} problem(s).",
+            results.len(),
+        );
+    }
+    problem_count > 0
+}
+
+/// List custom pages and patches, marking custom pages that shadow a page from the cache
+fn list_custom_pages(custom_pages_dir: &Path, cache: Option<&Cache>) -> Result<()> {
+    let file_iter = match fs::read_dir(custom_pages_dir) {
+        Ok(file_iter) => file_iter,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => {
+            return Err(e).with_context(|| {
+                format!(
+                    "Could not read custom pages directory at {}",
+                    custom_pages_dir.display()
+                )
+            })
+        }
+    };
+
+    let mut file_names = Vec::new();
+    for entry in file_iter {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+        if let Ok(file_name) = entry.file_name().into_string() {
+            if file_name.ends_with(".page.md") || file_name.ends_with(".patch.md") {
+                file_names.push(file_name);
+            }
+        }
+    }
+    file_names.sort_unstable();
+
+    for file_name in file_names {
+        let shadows_page = file_name.strip_suffix(".page.md").is_some_and(|command| {
+            cache.is_some_and(|cache| cache.find_upstream_page(command).is_some())
+        });
+        if shadows_page {
+            println!("{file_name} (shadows official page)");
+        } else {
+            println!("{file_name}");
+        }
+    }
+
+    Ok(())
+}
+
+/// A path in the report of `tldr --show-paths --output json`.
+#[derive(Debug, Serialize)]
+struct ReportedPath {
+    path: PathBuf,
+    source: PathSource,
+    exists: bool,
+}
+
+impl ReportedPath {
+    fn new(path: PathBuf, source: PathSource) -> Self {
+        let exists = path.exists();
+        Self {
+            path,
+            source,
+            exists,
+        }
+    }
+}
+
+impl From<&PathWithSource> for ReportedPath {
+    fn from(path: &PathWithSource) -> Self {
+        Self::new(path.path.clone(), path.source)
+    }
+}
+
+/// The report of `tldr --show-paths --output json`. Paths that are not configured are `null`.
+#[derive(Debug, Serialize)]
+struct PathsReport {
+    config_dir: Option<ReportedPath>,
+    config_path: ReportedPath,
+    system_config_path: Option<ReportedPath>,
+    cache_dir: ReportedPath,
+    pages_dir: ReportedPath,
+    custom_pages_dir: Option<ReportedPath>,
+    state_dir: ReportedPath,
+    system_pages_dir: Option<ReportedPath>,
+}
+
+/// Print the paths used by tealdeer as a JSON object, for other tools to locate them.
+fn print_paths_report(config: &Config) -> Result<()> {
+    let directories = &config.directories;
+    let report = PathsReport {
+        config_dir: get_config_dir()
+            .ok()
+            .map(|(path, source)| ReportedPath::new(path, source)),
+        config_path: (&config.file_path).into(),
+        system_config_path: get_system_config_path().as_ref().map(Into::into),
+        cache_dir: (&directories.cache_dir).into(),
+        pages_dir: ReportedPath::new(
+            directories.cache_dir.path().join(TLDR_PAGES_DIR),
+            directories.cache_dir.source,
+        ),
+        custom_pages_dir: directories.custom_pages_dir.as_ref().map(Into::into),
+        state_dir: (&directories.state_dir).into(),
+        system_pages_dir: directories.system_pages_dir.as_ref().map(Into::into),
+    };
+    println!(
+        "{}",
+        serde_json::to_string(&report).context("Could not serialize the paths")?
+    );
     Ok(())
 }
 
@@ -121,6 +1096,16 @@ fn show_paths(config: &Config) {
         },
     );
     let config_path = config.file_path.to_string();
+    let system_config_path = get_system_config_path().map_or_else(
+        || "[None]".to_string(),
+        |path| {
+            if path.path().is_file() {
+                path.to_string()
+            } else {
+                format!("{path} [not present]")
+            }
+        },
+    );
     let cache_dir = config.directories.cache_dir.to_string();
     let pages_dir = {
         let mut path = config.directories.cache_dir.path.clone();
@@ -132,11 +1117,196 @@ fn show_paths(config: &Config) {
         Some(ref path_with_source) => path_with_source.to_string(),
         None => "[None]".to_string(),
     };
+    let state_dir = config.directories.state_dir.to_string();
+    let system_pages_dir = match config.directories.system_pages_dir {
+        Some(ref path_with_source) => path_with_source.to_string(),
+        None => "[None]".to_string(),
+    };
     println!("Config dir:       {config_dir}");
     println!("Config path:      {config_path}");
+    println!("System config:    {system_config_path}");
     println!("Cache dir:        {cache_dir}");
     println!("Pages dir:        {pages_dir}");
     println!("Custom pages dir: {custom_pages_dir}");
+    println!("State dir:        {state_dir}");
+    println!("System pages dir: {system_pages_dir}");
+}
+
+/// The config options that are overridden by command line arguments, as dotted keys and values.
+fn cli_overrides(config: &Config, args: &Cli) -> Result<Vec<(&'static str, toml::Value)>> {
+    let mut overrides = Vec::new();
+    if args.platforms.is_some() {
+        overrides.push((
+            "search.platforms",
+            toml::Value::try_from(&config.search.platforms)?,
+        ));
+    }
+    if let Some(language) = &args.language {
+        let languages = toml::Value::Array(vec![toml::Value::String(language.clone())]);
+        overrides.push(("search.languages", languages.clone()));
+        overrides.push(("updates.download_languages", languages));
+    }
+    if let Some(languages) = &args.download_languages {
+        overrides.push((
+            "updates.download_languages",
+            toml::Value::try_from(languages)?,
+        ));
+    }
+    if args.pager {
+        overrides.push(("display.use_pager", toml::Value::Boolean(true)));
+    }
+    if let Some(color) = args.color {
+        overrides.push(("display.color", toml::Value::try_from(color)?));
+    }
+    if args.no_auto_update {
+        overrides.push(("updates.auto_update", toml::Value::Boolean(false)));
+    }
+    Ok(overrides)
+}
+
+/// Migrate the config file at `path` and report the changes
+fn migrate_config_file(path: &Path, quietly: bool) -> Result<()> {
+    let (backup_path, changes) = migrate_config(path)?;
+    if quietly {
+        return Ok(());
+    }
+    let Some(backup_path) = backup_path else {
+        eprintln!("Config file at {} is up to date.", path.display());
+        return Ok(());
+    };
+    eprintln!("Migrated config file at {}:", path.display());
+    for change in changes {
+        eprintln!("- {change}");
+    }
+    eprintln!(
+        "The original config file was saved to {}.",
+        backup_path.display()
+    );
+    Ok(())
+}
+
+/// Rename the custom pages and patches in `custom_pages_dir` that use the old naming convention.
+/// With `dry_run`, the renames are only printed.
+fn migrate_custom_pages(
+    custom_pages_dir: &Path,
+    dry_run: bool,
+    quietly: bool,
+    enable_styles: bool,
+) -> Result<()> {
+    let old_pages = if custom_pages_dir.is_dir() {
+        find_old_custom_pages(custom_pages_dir)?
+    } else {
+        Vec::new()
+    };
+    if old_pages.is_empty() {
+        if !quietly {
+            eprintln!(
+                "No custom pages with the old naming convention found in {}.",
+                custom_pages_dir.display()
+            );
+        }
+        return Ok(());
+    }
+
+    let mut renamed = 0;
+    for old_path in old_pages {
+        let mut new_path = old_path.clone().into_os_string();
+        new_path.push(".md");
+        let new_path = PathBuf::from(new_path);
+        let (old_name, new_name) = (
+            old_path.file_name().unwrap_or_default().to_string_lossy(),
+            new_path.file_name().unwrap_or_default().to_string_lossy(),
+        );
+        if new_path.exists() {
+            print_warning(
+                enable_styles,
+                &format!("Not renaming {old_name}, because {new_name} already exists."),
+            );
+            continue;
+        }
+        if dry_run {
+            println!("{old_name} -> {new_name}");
+            continue;
+        }
+        fs::rename(&old_path, &new_path).with_context(|| {
+            format!(
+                "Could not rename {} to {}",
+                old_path.display(),
+                new_path.display()
+            )
+        })?;
+        if !quietly {
+            eprintln!("Renamed {old_name} to {new_name}.");
+        }
+        renamed += 1;
+    }
+    if !dry_run && !quietly {
+        eprintln!(
+            "Renamed {renamed} custom page(s) in {}.",
+            custom_pages_dir.display()
+        );
+    }
+    Ok(())
+}
+
+/// Interactively create a config file
+fn run_setup(config_path: Option<&Path>) -> Result<()> {
+    let existing_path = match config_path {
+        Some(path) => path.to_path_buf(),
+        None => get_default_config_path()?.path().to_path_buf(),
+    };
+    let overwrite = existing_path.is_file();
+    if overwrite
+        && !confirm(&format!(
+            "A config file already exists at {}. Replace it?",
+            existing_path.display()
+        ))?
+    {
+        bail!("Setup cancelled, the config file was not changed.");
+    }
+
+    eprintln!("Welcome to tealdeer! Please answer a few questions to create your config file.");
+    let auto_update = confirm("Update the pages automatically when they are older than 30 days?")?;
+
+    let default_languages: Vec<_> = get_languages_from_env()
+        .into_iter()
+        .map(|Language(language)| language)
+        .collect();
+    let languages = prompt(
+        "Languages of the pages, in order of preference (comma separated)",
+        &default_languages.join(", "),
+    )?
+    .split(',')
+    .map(str::trim)
+    .filter(|language| !language.is_empty())
+    .map(parse_language)
+    .collect::<Result<Vec<_>>>()?;
+    ensure!(
+        !languages.is_empty(),
+        "Please specify at least one language."
+    );
+
+    let use_pager = cfg!(not(windows)) && confirm("Show pages in a pager?")?;
+    let theme = match prompt("Color theme (default or monochrome)", "default")?.as_str() {
+        "default" => SetupTheme::Default,
+        "monochrome" => SetupTheme::Monochrome,
+        other => bail!("Unknown color theme `{other}`, expected `default` or `monochrome`."),
+    };
+
+    let choices = SetupChoices {
+        auto_update,
+        languages,
+        use_pager,
+        theme,
+    };
+    let config_file_path = make_setup_config(config_path, &choices, overwrite)
+        .context("Could not create config file")?;
+    eprintln!(
+        "Successfully created config file here: {}",
+        config_file_path.display()
+    );
+    eprintln!("Downloading the pages...");
+    Ok(())
 }
 
 fn create_config(path: Option<&Path>) -> Result<()> {
@@ -148,244 +1318,1267 @@ fn create_config(path: Option<&Path>) -> Result<()> {
     Ok(())
 }
 
-#[cfg(feature = "logging")]
-fn init_log() {
-    env_logger::init();
-}
+#[cfg(feature = "logging")]
+fn init_log() {
+    env_logger::init();
+}
+
+#[cfg(not(feature = "logging"))]
+fn init_log() {}
+
+/// Seed the custom page for `command` with the page from the cache, unless a custom page exists
+/// already.
+fn seed_custom_page(
+    cache_config: CacheConfig,
+    custom_pages_dir: &Path,
+    command: &str,
+) -> Result<()> {
+    let custom_page_path = custom_pages_dir.join(format!("{command}.page.md"));
+    if custom_page_path.exists() {
+        eprintln!(
+            "A custom page already exists at {}, it was not replaced with the page from the cache.",
+            custom_page_path.display(),
+        );
+        return Ok(());
+    }
+
+    let page_path = Cache::open(cache_config)?
+        .and_then(|cache| cache.find_upstream_page(command))
+        .with_context(|| {
+            format!("Page `{command}` not found in cache, cannot use it to seed the custom page.")
+        })?;
+
+    create_dir_all(custom_pages_dir).context("Failed to create custom pages directory")?;
+    fs::copy(&page_path, &custom_page_path).with_context(|| {
+        format!(
+            "Could not copy {} to {}",
+            page_path.display(),
+            custom_page_path.display(),
+        )
+    })?;
+    Ok(())
+}
+
+/// Write a page skeleton in the tldr format to `path`, which must not exist yet.
+fn create_page_skeleton(path: &Path, title: &str) -> Result<()> {
+    if path.exists() {
+        bail!(
+            "{} already exists, use `--edit-page` to edit it.",
+            path.display()
+        );
+    }
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        create_dir_all(parent)
+            .with_context(|| format!("Could not create directory {}", parent.display()))?;
+    }
+
+    let skeleton = format!(
+        "# {title}\n\
+         \n\
+         > Short description of what the command does.\n\
+         > More information: <https://example.com>.\n\
+         \n\
+         - Description of the first example:\n\
+         \n\
+         `{title} {{{{path/to/file}}}}`\n\
+         \n\
+         - Description of the second example:\n\
+         \n\
+         `{title} --option {{{{value}}}}`\n"
+    );
+    fs::write(path, skeleton).with_context(|| format!("Could not write {}", path.display()))
+}
+
+/// How often `updates.check_new_version` checks for a new release of tealdeer.
+const VERSION_CHECK_INTERVAL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Print a notice if a newer release of tealdeer is available, at most once per
+/// `VERSION_CHECK_INTERVAL`. Failing to check is not fatal.
+fn notify_new_version(cache: &Cache, tls_backend: TlsBackend, enable_styles: bool) {
+    if !cache.version_check_due(VERSION_CHECK_INTERVAL) {
+        return;
+    }
+    let client = Cache::build_client(tls_backend);
+    let release = match self_update::latest_release(&client) {
+        Ok(release) => release,
+        Err(e) => {
+            print_warning(
+                enable_styles,
+                &format!("Could not check for a new version of tealdeer: {e:#}"),
+            );
+            return;
+        }
+    };
+    let current = env!("CARGO_PKG_VERSION");
+    if self_update::is_newer(release.version(), current) {
+        eprintln!(
+            "tealdeer {} is available, you are using {current}.",
+            release.version()
+        );
+    }
+    if let Err(e) = cache.record_version_check() {
+        print_warning(enable_styles, &format!("{e:#}"));
+    }
+}
+
+/// Replace the running binary with the latest release, if it is newer.
+#[cfg(feature = "self-update")]
+fn update_binary(tls_backend: TlsBackend, quietly: bool) -> Result<()> {
+    let client = Cache::build_client(tls_backend);
+    let release = self_update::latest_release(&client)?;
+    let current = env!("CARGO_PKG_VERSION");
+    if !self_update::is_newer(release.version(), current) {
+        if !quietly {
+            eprintln!("tealdeer {current} is the latest version.");
+        }
+        return Ok(());
+    }
+    if !quietly {
+        eprintln!(
+            "Updating tealdeer from {current} to {}...",
+            release.version()
+        );
+    }
+    self_update::install_release(&client, &release)?;
+    if !quietly {
+        eprintln!("Successfully updated tealdeer to {}.", release.version());
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "self-update"))]
+fn update_binary(_: TlsBackend, _: bool) -> Result<()> {
+    bail!(
+        "This build of tealdeer does not support --self-update, please update it with the \
+         package manager it was installed with."
+    );
+}
+
+/// Serve the archives of the cached pages over HTTP, so that other machines can use this one as a
+/// mirror.
+#[cfg(feature = "serve")]
+fn serve_cache(cache_config: CacheConfig<'_>, address: &str, quietly: bool) -> Result<()> {
+    let cache = Cache::open(cache_config)?
+        .context("There is no cache to serve, please run `tldr --update` first.")?;
+    serve::serve(cache.config().pages_directory, address, quietly)
+}
+
+#[cfg(not(feature = "serve"))]
+fn serve_cache(_: CacheConfig<'_>, _: &str, _: bool) -> Result<()> {
+    bail!(
+        "This build of tealdeer does not support --serve-cache, it requires the `serve` feature."
+    );
+}
+
+/// Print the recently viewed pages, numbered so that they can be opened with `--history <N>`.
+fn list_history(history: &History) -> Result<()> {
+    let recent = history.recent()?;
+    if recent.is_empty() {
+        eprintln!("The history is empty.");
+        return Ok(());
+    }
+
+    let width = recent
+        .iter()
+        .map(|entry| entry.page.width())
+        .max()
+        .unwrap_or(0);
+    for (number, entry) in recent.iter().enumerate() {
+        // Padding with `{:<width$}` would count characters instead of columns
+        println!(
+            "{:>3}  {}{}  {}",
+            number + 1,
+            entry.page,
+            " ".repeat(width - entry.page.width()),
+            format_time_ago(entry.time),
+        );
+    }
+    Ok(())
+}
+
+/// Record a lookup of `page` in the history if it is enabled. Failing to do so is not fatal.
+fn record_in_history(history: &History, config: &Config, page: &str, enable_styles: bool) {
+    if !config.history.enabled {
+        return;
+    }
+    if let Err(e) = history.record(page) {
+        print_warning(
+            enable_styles,
+            &format!("Could not record the page in the history: {e:#}"),
+        );
+    }
+}
+
+/// Record a lookup of the page in `result` for evicting the least recently used pages if the cache
+/// size is limited. Failing to do so is not fatal.
+fn record_access(cache: &Cache, config: &Config, result: &PageLookupResult, enable_styles: bool) {
+    if config.updates.max_cache_size.is_none() {
+        return;
+    }
+    let PageContent::File(page_path) = &result.page else {
+        return;
+    };
+    if let Err(e) = cache.record_access(page_path) {
+        print_warning(
+            enable_styles,
+            &format!("Could not record the lookup of the page: {e:#}"),
+        );
+    }
+}
+
+/// Format a size in bytes for humans, e.g. `1.5 MB`.
+#[allow(clippy::cast_precision_loss)] // The sizes of caches are far below 2^52 bytes
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+/// Pick a random page from the pages for the configured platforms, or `None` if there are none.
+fn find_random_page(cache: &Cache) -> Result<Option<(String, PageLookupResult)>> {
+    // The pages are sorted, so that the selection only depends on the random number
+    let pages: Vec<String> = cache.list_pages()?.into_iter().collect();
+    let Some(page) = fastrand::choice(pages) else {
+        return Ok(None);
+    };
+    let result = cache
+        .find_page(&page)
+        .with_context(|| format!("Could not find the listed page `{page}`"))?;
+    Ok(Some((page, result)))
+}
+
+/// Pick a random platform that has pages in the cache, and a random page for it.
+fn find_random_platform_page(
+    cache: &Cache,
+) -> Result<Option<(PlatformType, String, PageLookupResult)>> {
+    let mut platforms = PlatformType::value_variants().to_vec();
+    fastrand::shuffle(&mut platforms);
+    for platform in platforms {
+        let search_platforms = [platform];
+        let Some(platform_cache) = Cache::open(CacheConfig {
+            platforms: &search_platforms,
+            ..cache.config().clone()
+        })?
+        else {
+            continue;
+        };
+        if let Some((page, result)) = find_random_page(&platform_cache)? {
+            return Ok(Some((platform, page, result)));
+        }
+    }
+    Ok(None)
+}
+
+/// Print a random example from a random page in the cache.
+fn print_random_tip(cache: &Cache, config: &Config) -> Result<()> {
+    // Some pages have no examples, so other pages are tried in random order until one is found
+    let mut pages: Vec<String> = cache.list_pages()?.into_iter().collect();
+    ensure!(!pages.is_empty(), "The cache does not contain any pages.");
+    fastrand::shuffle(&mut pages);
+    for page in pages {
+        let result = cache
+            .find_page(&page)
+            .with_context(|| format!("Could not find the listed page `{page}`"))?;
+        if print_tip(result.reader()?, config)? {
+            return Ok(());
+        }
+    }
+    bail!("Could not find a page with examples in the cache.");
+}
+
+/// The format in which pages are printed, as selected on the command line. With
+/// `display.raw_when_piped`, pages that are piped or written to a file are printed raw, unless
+/// options that only apply to rendered pages are used.
+fn output_format(args: &Cli, config: &Config) -> OutputFormat {
+    let piped = args.output_file.is_some() || !io::stdout().is_terminal();
+    let rendering_requested = args.pager
+        || args.example.is_some()
+        || args.grep.is_some()
+        || args.strict
+        || args.color == Some(ColorOptions::Always);
+    if args.output == Some(ReportFormat::Tokens) {
+        OutputFormat::Tokens
+    } else if args.plain {
+        OutputFormat::Plain
+    } else if args.raw || (config.display.raw_when_piped && piped && !rendering_requested) {
+        OutputFormat::Raw
+    } else {
+        OutputFormat::Rendered
+    }
+}
+
+/// Find the page for `command` specific to `platform`, falling back to the common page. The
+/// boolean indicates whether the common page was used.
+fn find_platform_page(
+    cache: &Cache,
+    command: &str,
+    platform: PlatformType,
+) -> Result<Option<(PageLookupResult, bool)>> {
+    for (search_platform, common_page) in [(platform, false), (PlatformType::Common, true)] {
+        let search_platforms = [search_platform];
+        let Some(platform_cache) = Cache::open(CacheConfig {
+            platforms: &search_platforms,
+            ..cache.config().clone()
+        })?
+        else {
+            continue;
+        };
+        if let Some(page) = platform_cache.find_upstream_page(command) {
+            let result =
+                PageLookupResult::with_page(page).with_optional_patch(cache.find_patch(command));
+            return Ok(Some((
+                result,
+                common_page && platform != PlatformType::Common,
+            )));
+        }
+    }
+    Ok(None)
+}
+
+/// The languages chosen for the search with `--language` or `search.languages` that have no
+/// pages in the cache. The languages of the locale are not checked, as there are often no pages
+/// for some of them (e.g. `de_DE`).
+fn missing_search_languages(cache: &Cache, config: &Config, args: &Cli) -> Result<Vec<String>> {
+    if config.updates.use_index || (args.language.is_none() && !config.search.languages_configured)
+    {
+        return Ok(Vec::new());
+    }
+    let available_languages = cache.available_languages()?;
+    Ok(cache
+        .config()
+        .search_languages
+        .iter()
+        .filter(|Language(language)| !available_languages.iter().any(|cached| cached == language))
+        .map(|Language(language)| (*language).to_string())
+        .collect())
+}
+
+/// The message for search languages without pages in the cache, see
+/// [`missing_search_languages`].
+fn no_pages_for_languages(languages: &[String]) -> String {
+    let list = languages
+        .iter()
+        .map(|language| format!("`{language}`"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let (noun, pronoun) = if languages.len() == 1 {
+        ("language", "the language")
+    } else {
+        ("languages", "the languages")
+    };
+    format!(
+        "No pages for {noun} {list} found in the cache.\n\
+         To download them, add {pronoun} to the `updates.download_languages` setting in your \
+         config file and run `tldr --update`."
+    )
+}
+
+/// Print the pages for `command` of several platforms one after another, each below a header
+/// naming the platform. Like a single page, aliases are followed and includes and templates are
+/// expanded. Returns whether any page was printed.
+///
+/// With `--compare`, the page for each platform given with `--platform` is printed, falling back
+/// to the common page. With `--all-matches`, the pages of all searched platforms are printed,
+/// leaving out pages with the same content as a page printed before. Nothing is printed then
+/// unless the pages differ, or if there is a custom page, which replaces the pages of all
+/// platforms; the page should be looked up as usual instead.
+fn print_platform_pages(
+    output: &mut impl Write,
+    cache: &Cache,
+    command: &str,
+    args: &Cli,
+    enable_styles: bool,
+    config: &Config,
+    timings: &mut Timings,
+) -> Result<bool> {
+    let dedup_by_content = args.all_matches;
+    let mut pages = Vec::new();
+    if args.compare {
+        for platform in PlatformArg::flatten(args.platforms.as_deref().unwrap_or_default()) {
+            if let Some((result, common_page)) = find_platform_page(cache, command, platform)? {
+                let header = if common_page {
+                    format!("{platform} (common page)")
+                } else {
+                    platform.to_string()
+                };
+                pages.push((header, platform, result));
+            }
+        }
+    } else {
+        if cache.find_custom_page(command).is_some() {
+            return Ok(false);
+        }
+        for (platform, page) in cache.find_upstream_pages(command) {
+            let result =
+                PageLookupResult::with_page(page).with_optional_patch(cache.find_patch(command));
+            pages.push((platform.to_string(), platform, result));
+        }
+    }
+
+    let mut variants: Vec<(String, Vec<u8>)> = Vec::new();
+    for (header, platform, result) in pages {
+        let result = if config.display.follow_aliases {
+            // The page of the aliased command is looked up for the same platform
+            follow_alias(
+                &cache.with_platforms(&[platform, PlatformType::Common]),
+                config.updates.tls_backend,
+                command,
+                result,
+                args.quiet > 0,
+                enable_styles,
+            )?
+        } else {
+            result
+        };
+        let result = cache.resolve_includes(
+            command,
+            result.with_expanded_templates(config.display.expand_templates),
+        )?;
+        let mut content = Vec::new();
+        result
+            .reader()?
+            .read_to_end(&mut content)
+            .context("Could not read the page")?;
+        if !(dedup_by_content && variants.iter().any(|(_, other)| *other == content)) {
+            variants.push((header, content));
+        }
+    }
+    if dedup_by_content && variants.len() < 2 {
+        return Ok(false);
+    }
+
+    let format = output_format(args, config);
+    for (i, (header, content)) in variants.iter().enumerate() {
+        // Rendered pages already end with an empty line, raw ones do not
+        if i > 0 && format == OutputFormat::Raw {
+            writeln!(output).context("Could not write the page")?;
+        }
+        print_header(output, header, enable_styles, args.pager, config)?;
+        print_page(
+            output,
+            &content[..],
+            format,
+            enable_styles,
+            args.pager,
+            config,
+            timings,
+        )?;
+    }
+    Ok(!variants.is_empty())
+}
+
+/// Print the examples of all pages whose command contains `flag`. Returns whether any were found.
+fn print_pages_with_flag(cache: &Cache, config: &Config, flag: &str) -> Result<bool> {
+    let mut found = false;
+    for page in cache.list_pages()? {
+        let result = cache
+            .find_page(&page)
+            .with_context(|| format!("Could not find the listed page `{page}`"))?;
+        found |= print_flag_matches(&page, result.reader()?, flag, config)?;
+    }
+    Ok(found)
+}
+
+/// How well a page matches the keyword of `--apropos`, where lower is better, or `None` if it
+/// does not match at all.
+fn apropos_rank(page: &str, summary: &str, keyword: &str) -> Option<u8> {
+    // Multi-word commands like `git checkout` are stored with dashes
+    let name = keyword.replace(' ', "-");
+    if page.eq_ignore_ascii_case(&name) {
+        Some(0)
+    } else if page.find_ignore_ascii_case(&name).is_some() {
+        Some(1)
+    } else if summary
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|word| word.eq_ignore_ascii_case(keyword))
+    {
+        Some(2)
+    } else if summary.find_ignore_ascii_case(keyword).is_some() {
+        Some(3)
+    } else {
+        None
+    }
+}
+
+/// Print the pages whose name or summary contains `keyword`, best matches first. Returns whether
+/// any were found.
+fn print_apropos(cache: &Cache, config: &Config, keyword: &str) -> Result<bool> {
+    let mut matches = Vec::new();
+    for page in cache.list_pages()? {
+        let result = cache
+            .find_page(&page)
+            .with_context(|| format!("Could not find the listed page `{page}`"))?;
+        let summary = page_summary(result.reader()?).unwrap_or_default();
+        if let Some(rank) = apropos_rank(&page, &summary, keyword) {
+            matches.push((rank, page, summary));
+        }
+    }
+    // The pages are listed in alphabetical order, which the stable sort keeps for equal ranks
+    matches.sort_by_key(|(rank, _, _)| *rank);
+    let matches: Vec<(String, String)> = matches
+        .into_iter()
+        .map(|(_, page, summary)| (page, summary))
+        .collect();
+    print_apropos_matches(&matches, config)?;
+    Ok(!matches.is_empty())
+}
+
+/// Print how often each page in the history was looked up, most frequently looked up first.
+fn print_usage_stats(history: &History) -> Result<()> {
+    let counts = history.usage_counts()?;
+    if counts.is_empty() {
+        eprintln!("The history is empty.");
+        return Ok(());
+    }
+
+    let width = counts[0].1.to_string().len();
+    for (page, count) in counts {
+        println!("{count:>width$}  {page}");
+    }
+    Ok(())
+}
+
+/// The minimum similarity of a page name to the looked up command to be suggested.
+const MIN_SUGGESTION_SIMILARITY: f64 = 0.8;
+
+/// The maximum number of similar pages that are suggested if a page was not found.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Find the pages in the cache that have names similar to `command`, most similar first.
+///
+/// If `usage_counts` are given, pages that were looked up frequently are ranked higher.
+fn similar_pages(
+    cache: &Cache,
+    command: &str,
+    usage_counts: &[(String, usize)],
+) -> Result<Vec<String>> {
+    let mut candidates: Vec<(f64, String)> = cache
+        .list_pages()?
+        .into_iter()
+        .filter_map(|page| {
+            let similarity = strsim::jaro_winkler(command, &page);
+            if similarity < MIN_SUGGESTION_SIMILARITY {
+                return None;
+            }
+            let count = usage_counts
+                .iter()
+                .find(|(used, _)| *used == page)
+                .map_or(0, |(_, count)| *count);
+            // Every lookup counts as much as a small difference in spelling, up to a limit
+            #[allow(clippy::cast_precision_loss)]
+            let boost = 0.01 * count.min(10) as f64;
+            Some((similarity + boost, page))
+        })
+        .collect();
+    candidates.sort_by(|(a_score, a_page), (b_score, b_page)| {
+        b_score.total_cmp(a_score).then_with(|| a_page.cmp(b_page))
+    });
+    candidates.dedup_by(|(_, a), (_, b)| a == b);
+    Ok(candidates
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, page)| page)
+        .collect())
+}
+
+/// Look up the name of the page with the given `number` in the output of `--history`.
+fn history_page(history: &History, number: usize) -> Result<String> {
+    let mut recent = history.recent()?;
+    ensure!(
+        (1..=recent.len()).contains(&number),
+        "There is no page with number {number} in the history, run `tldr --history` to list them."
+    );
+    Ok(recent.swap_remove(number - 1).page)
+}
+
+/// Delete a custom page or patch after asking for confirmation
+fn delete_custom_file(path: &Path, quietly: bool) -> Result<()> {
+    if !path.is_file() {
+        bail!("No file found at {}.", path.display());
+    }
+
+    if !confirm(&format!("Delete {}?", path.display())).context("Could not read answer")? {
+        eprintln!("Nothing was deleted.");
+        return Ok(());
+    }
+
+    fs::remove_file(path).with_context(|| format!("Could not delete {}", path.display()))?;
+    if !quietly {
+        eprintln!("Successfully deleted {}.", path.display());
+    }
+    Ok(())
+}
+
+/// The editor used if neither `VISUAL` nor `EDITOR` are set
+const DEFAULT_EDITOR: &str = if cfg!(windows) { "notepad" } else { "vi" };
 
-#[cfg(not(feature = "logging"))]
-fn init_log() {}
+/// Determine the editor command (with arguments) from `VISUAL` or `EDITOR`, falling back to
+/// [`DEFAULT_EDITOR`].
+fn editor_command() -> Result<(String, Vec<String>)> {
+    let editor = ["VISUAL", "EDITOR"]
+        .into_iter()
+        .find_map(|name| env::var(name).ok().filter(|value| !value.trim().is_empty()))
+        .unwrap_or_else(|| DEFAULT_EDITOR.to_string());
 
-fn spawn_editor(custom_pages_dir: &Path, file_name: &str) -> Result<()> {
-    create_dir_all(custom_pages_dir).context("Failed to create custom pages directory")?;
+    let mut words = shell_words::split(&editor)
+        .with_context(|| format!("Could not parse editor command `{editor}`"))?
+        .into_iter();
+    let program = words
+        .next()
+        .with_context(|| format!("Editor command `{editor}` is empty"))?;
+    Ok((program, words.collect()))
+}
 
-    let custom_page_path = custom_pages_dir.join(file_name);
+fn spawn_editor(custom_page_path: &Path) -> Result<()> {
     let Some(custom_page_path) = custom_page_path.to_str() else {
         return Err(anyhow!("`custom_page_path.to_str()` failed"));
     };
-    let Ok(editor) = env::var("EDITOR") else {
-        return Err(anyhow!(
-            "To edit a custom page, please set the `EDITOR` environment variable."
-        ));
-    };
+    let (editor, editor_args) = editor_command()?;
     println!("Editing {custom_page_path:?}");
 
-    let status = Command::new(&editor).arg(custom_page_path).status()?;
+    let status = Command::new(&editor)
+        .args(&editor_args)
+        .arg(custom_page_path)
+        .status()
+        .with_context(|| format!("Could not start editor `{editor}`"))?;
     if !status.success() {
         return Err(anyhow!("{editor} exit with code {:?}", status.code()));
     }
     Ok(())
 }
 
-fn main() -> ExitCode {
-    // Initialize logger
-    init_log();
-
-    // Parse arguments
-    let args = Cli::parse();
-
-    // Determine the usage of styles
-    let enable_styles = match args.color.unwrap_or_default() {
+/// Whether to use styles with the `color` option. Only `auto` depends on the output, so
+/// `always` also works when piping into a pager like `less -R`.
+fn styles_enabled(color: ColorOptions, output_to_file: bool) -> bool {
+    match color {
         // Attempt to use styling if instructed
         ColorOptions::Always => {
-            yansi::enable(); // disable yansi's automatic detection for ANSI support on Windows
+            // Try to enable VT processing on Windows, but use styles even if that fails
+            yansi::Condition::os_support();
+            yansi::enable();
             true
         }
         // Enable styling if:
         // * NO_COLOR env var isn't set: https://no-color.org/
+        // * The output is not written to a file
         // * The output stream is stdout (not being piped)
-        ColorOptions::Auto => env::var_os("NO_COLOR").is_none() && io::stdout().is_terminal(),
+        // * The console supports escape codes, which are enabled on Windows if possible
+        ColorOptions::Auto => {
+            env::var_os("NO_COLOR").is_none()
+                && !output_to_file
+                && io::stdout().is_terminal()
+                && yansi::Condition::os_support()
+        }
         // Disable styling
         ColorOptions::Never => false,
-    };
+    }
+}
 
-    try_main(args, enable_styles).unwrap_or_else(|error| {
-        print_error(enable_styles, &error);
-        ExitCode::FAILURE
-    })
+fn main() -> ExitCode {
+    // Initialize logger
+    init_log();
+
+    // Parse arguments
+    let args = Cli::parse();
+
+    // Determine the usage of styles, the config can only change it once it's loaded
+    let enable_styles = styles_enabled(args.color.unwrap_or_default(), args.output_file.is_some());
+
+    let mut timings = Timings::new(args.timings);
+    let quiet = args.quiet;
+    let reason = try_main(args, enable_styles, &mut timings).unwrap_or_else(|error| {
+        // With `-qq`, errors are only reported through the exit code
+        if quiet < 2 {
+            print_error(enable_styles, &error);
+        }
+        ExitReason::from_error(&error)
+    });
+    timings.print();
+    reason.into()
 }
 
-fn try_main(args: Cli, enable_styles: bool) -> Result<ExitCode> {
+fn try_main(mut args: Cli, enable_styles: bool, timings: &mut Timings) -> Result<ExitReason> {
+    // `-q` suppresses informational messages and warnings. `--exists` is meant for scripts, so it
+    // only reports the result through its exit code.
+    let quietly = args.quiet > 0 || args.exists;
+    set_warnings_enabled(!quietly);
+
+    if let Some(profile) = &args.profile {
+        let path = get_profile_config_path(profile)?;
+        ensure!(
+            path.is_file() || args.seed_config || args.setup,
+            "The config file of the profile `{profile}` does not exist at {}.\n\
+             To create it, run `tldr --profile {profile} --seed-config`.",
+            path.display(),
+        );
+        args.config_path = Some(path);
+    }
+
+    // Migrate before loading the config, so that deprecated options don't cause warnings
+    if args.migrate_config {
+        let path = match &args.config_path {
+            Some(path) => path.clone(),
+            None => get_default_config_path()?.path().to_path_buf(),
+        };
+        migrate_config_file(&path, quietly)?;
+        return Ok(ExitReason::Success);
+    }
+
+    // Reports and pages have different output formats, which clap can't tell apart
+    match args.output {
+        Some(ReportFormat::Tokens) => ensure!(
+            !args.update && !args.show_paths,
+            "`--output tokens` is only available for pages, not with `--update` or `--show-paths`."
+        ),
+        Some(ReportFormat::Json | ReportFormat::Text) => ensure!(
+            args.update || args.show_paths,
+            "`--output json` and `--output text` are only available with `--update` or \
+             `--show-paths`."
+        ),
+        None => {}
+    }
+
+    // The hooks and completions don't depend on the config
+    if let Some(shell) = args.print_hook {
+        print!("{}", command_not_found_hook(shell));
+        return Ok(ExitReason::Success);
+    }
+    if let Some(shell) = args.completions {
+        print!("{}", completion_script(shell));
+        return Ok(ExitReason::Success);
+    }
+
+    // After creating the config, continue with the initial update
+    if args.setup {
+        run_setup(args.config_path.as_deref())?;
+        args.update = true;
+    }
+
     // Look up config file, if none is found fall back to default config.
     debug!("Loading config");
+    let config_start = Instant::now();
     let config_loader = match &args.config_path {
-        Some(path) if !args.seed_config => {
-            ConfigLoader::read(path.clone()).context("Could not read config from given path")?
-        }
-        _ => {
-            ConfigLoader::read_default_path().context("Could not read config from default path")?
-        }
+        Some(path) if !args.seed_config => ConfigLoader::read(path.clone(), &args.set)
+            .context("Could not read config from given path")?,
+        _ => ConfigLoader::read_default_path(&args.set)
+            .context("Could not read config from default path")?,
     };
+    for warning in config_loader.warnings() {
+        print_warning(enable_styles, warning);
+    }
     let mut config = config_loader.load()?;
+    timings.record("config load", config_start.elapsed());
+
+    // `--color` takes precedence over `display.color`
+    let enable_styles = match args.color {
+        Some(_) => enable_styles,
+        None => styles_enabled(config.display.color, args.output_file.is_some()),
+    };
 
     // Override styles if needed
     if !enable_styles {
         config.style = StyleConfig::default();
     }
 
+    let deprecations = DeprecationWarnings::new(
+        config.directories.state_dir.path(),
+        (!args.no_deprecation_warnings).then(|| config.hidden_deprecations.clone()),
+        enable_styles,
+    );
+    if config.directories.cache_dir.source == PathSource::EnvVar {
+        deprecations.warn(
+            Deprecation::CacheDirEnvVar,
+            "The $TEALDEER_CACHE_DIR env variable is deprecated, use the `cache_dir` option in \
+             the config file instead (`tldr --migrate-config` can do this for you).",
+        );
+    }
+
+    if let Some(example) = args.example {
+        config.display.example = Some(example.get());
+    }
+    config.display.grep.clone_from(&args.grep);
+    config.display.strict = args.strict;
+    if config.display.wrap_text {
+        config.display.width = terminal_width();
+    }
+
+    // Rendered pages are written to `output`, other messages always go to stdout or stderr
+    // With `--new-page`, the output file is the path of the new page instead
+    let mut output: Box<dyn Write> = match args.output_file.as_ref().filter(|_| !args.new_page) {
+        Some(path) => {
+            // A pager would show the output instead of writing it to the file
+            args.pager = false;
+            config.display.use_pager = false;
+            Box::new(OutputFile::new(path.clone()))
+        }
+        // Stdout is line buffered, which is slow for long pages
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    let format = output_format(&args, &config);
+
+    if let Some(platforms) = &args.platforms {
+        config.search.platforms = config
+            .search
+            .compute_platforms(&PlatformArg::flatten(platforms));
+        if !config.search.platforms.contains(&PlatformType::Common) {
+            config.search.platforms.push(PlatformType::Common);
+        }
+    }
+
     let custom_pages_dir = config
         .directories
         .custom_pages_dir
         .as_ref()
         .map(PathWithSource::path);
 
+    let (search_languages, download_languages): (&[_], &[_]) = match args.language.as_deref() {
+        Some(lang) => (&[Language(lang)], &[Language(lang)]),
+        None => (&config.search.languages, &config.updates.download_languages),
+    };
+    let download_languages_override: Option<Vec<_>> = args
+        .download_languages
+        .as_ref()
+        .map(|languages| languages.iter().map(|lang| Language(lang)).collect());
+    let download_languages = download_languages_override
+        .as_deref()
+        .unwrap_or(download_languages);
+
+    let cache_config = CacheConfig {
+        pages_directory: &config.directories.cache_dir.path().join(TLDR_PAGES_DIR),
+        custom_pages_directory: custom_pages_dir,
+        system_pages_directory: config
+            .directories
+            .system_pages_dir
+            .as_ref()
+            .map(PathWithSource::path),
+        platforms: &config.search.platforms,
+        search_languages,
+        download_languages,
+        download_platforms: &config.updates.download_platforms,
+        state_directory: config.directories.state_dir.path(),
+        sources: &config.sources,
+    };
+
+    let history = History::new(config.directories.state_dir.path());
+    if args.history.is_some() || args.stats_usage {
+        ensure!(
+            config.history.enabled,
+            "The history is disabled. To record the pages you look up, set `history.enabled = true` \
+             in your config file."
+        );
+    }
+    if args.history == Some(None) {
+        list_history(&history)?;
+        return Ok(ExitReason::Success);
+    }
+
+    if args.stats_usage {
+        print_usage_stats(&history)?;
+        return Ok(ExitReason::Success);
+    }
+
     // Note: According to the TLDR client spec, page names must be transparently
     // lowercased before lookup:
     // https://github.com/tldr-pages/tldr/blob/main/CLIENT-SPECIFICATION.md#page-names
-    let command = args.command.join("-").to_lowercase();
+    let command = if let Some(Some(number)) = args.history {
+        history_page(&history, number)?
+    } else {
+        config.resolve_alias(&args.command).join("-").to_lowercase()
+    };
+
+    if args.new_page {
+        let page_path = if let Some(path) = &args.output_file {
+            path.clone()
+        } else {
+            validate_page_name(&command)?;
+            custom_pages_dir
+                .context("To create custom pages, please specify a custom pages directory.")?
+                .join(format!("{command}.page.md"))
+        };
+        create_page_skeleton(&page_path, &args.command.join(" "))?;
+        spawn_editor(&page_path)?;
+
+        return Ok(ExitReason::Success);
+    }
 
     if args.edit_patch || args.edit_page {
+        validate_page_name(&command)?;
         let file_name = if args.edit_patch {
             format!("{command}.patch.md")
         } else {
             format!("{command}.page.md")
         };
 
-        custom_pages_dir
-            .context("To edit custom pages/patches, please specify a custom pages directory.")
-            .and_then(|custom_pages_dir| spawn_editor(custom_pages_dir, &file_name))?;
+        let custom_pages_dir = custom_pages_dir
+            .context("To edit custom pages/patches, please specify a custom pages directory.")?;
+        if args.from_cache {
+            seed_custom_page(cache_config, custom_pages_dir, &command)?;
+        }
+        create_dir_all(custom_pages_dir).context("Failed to create custom pages directory")?;
+        spawn_editor(&custom_pages_dir.join(file_name))?;
+
+        return Ok(ExitReason::Success);
+    }
+
+    if args.delete_page || args.delete_patch {
+        validate_page_name(&command)?;
+        let file_name = if args.delete_patch {
+            format!("{command}.patch.md")
+        } else {
+            format!("{command}.page.md")
+        };
 
-        return Ok(ExitCode::SUCCESS);
+        let custom_pages_dir = custom_pages_dir
+            .context("To delete custom pages/patches, please specify a custom pages directory.")?;
+        delete_custom_file(&custom_pages_dir.join(file_name), quietly)?;
+
+        return Ok(ExitReason::Success);
+    }
+
+    if args.lint {
+        let custom_pages_dir = custom_pages_dir
+            .context("To lint custom pages/patches, please specify a custom pages directory.")?;
+        let found_problems = lint_custom_pages(custom_pages_dir, quietly)?;
+        return Ok(if found_problems {
+            ExitReason::Failure
+        } else {
+            ExitReason::Success
+        });
     }
 
     // Show various paths
     if args.show_paths {
-        show_paths(&config);
+        if args.output == Some(ReportFormat::Json) {
+            print_paths_report(&config)?;
+        } else {
+            show_paths(&config);
+        }
+    }
+
+    if args.print_config {
+        let overrides = cli_overrides(&config, &args)?;
+        print!("{}", config_loader.effective_config(&config, overrides)?);
+        return Ok(ExitReason::Success);
+    }
+
+    if let Some(key) = &args.explain_config {
+        let overrides = cli_overrides(&config, &args)?;
+        print!("{}", config_loader.explain_config(&config, overrides, key)?);
+        return Ok(ExitReason::Success);
+    }
+
+    if args.self_update {
+        update_binary(config.updates.tls_backend, quietly)?;
+        return Ok(ExitReason::Success);
     }
 
     // Create a basic config and exit
     if args.seed_config {
         create_config(args.config_path.as_deref())?;
-        return Ok(ExitCode::SUCCESS);
+        return Ok(ExitReason::Success);
+    }
+
+    if args.watch {
+        let path = args
+            .render
+            .as_deref()
+            .context("--watch requires --render")?;
+        ensure!(
+            path.as_os_str() != "-",
+            "The page can't be read from stdin with --watch."
+        );
+        // The pager would only show the first rendering
+        config.display.use_pager = false;
+        watch_pages(path, format, enable_styles, &config, timings)?;
+        return Ok(ExitReason::Success);
+    }
+
+    // If a directory was passed in, check or render every page in it and exit
+    if let Some(directory) = args.render.as_deref().filter(|path| path.is_dir()) {
+        let files = markdown_files(directory)?;
+        if args.check {
+            let results = lint_pages(&files)?;
+            return Ok(if report_lint_results(&results, "pages", quietly) {
+                ExitReason::Failure
+            } else {
+                ExitReason::Success
+            });
+        }
+        render_pages(
+            &mut output,
+            &files,
+            format,
+            enable_styles,
+            args.pager,
+            &config,
+            timings,
+        )?;
+        return Ok(ExitReason::Success);
     }
 
-    // If a local file was passed in, render it and exit
+    // If a local file (or stdin) was passed in, render or check it and exit
     if let Some(file) = args.render {
-        let reader = PageLookupResult::with_page(file).reader()?;
-        print_page(reader, args.raw, enable_styles, args.pager, &config)?;
-        return Ok(ExitCode::SUCCESS);
+        if args.check {
+            let results = if file.as_os_str() == "-" {
+                let mut content = String::new();
+                io::stdin()
+                    .read_to_string(&mut content)
+                    .context("Could not read the page from stdin")?;
+                vec![(file, lint_page(&content, CustomFileKind::Page))]
+            } else {
+                lint_pages(&[file])?
+            };
+            return Ok(if report_lint_results(&results, "pages", quietly) {
+                ExitReason::Failure
+            } else {
+                ExitReason::Success
+            });
+        }
+        if file.as_os_str() == "-" {
+            print_page(
+                &mut output,
+                io::stdin().lock(),
+                format,
+                enable_styles,
+                args.pager,
+                &config,
+                timings,
+            )?;
+        } else {
+            let reader = PageLookupResult::with_page(file).reader()?;
+            print_page(
+                &mut output,
+                reader,
+                format,
+                enable_styles,
+                args.pager,
+                &config,
+                timings,
+            )?;
+        }
+        return Ok(ExitReason::Success);
+    }
+
+    if let Some(url) = &args.render_url {
+        let page = Cache::download_page(url, config.updates.tls_backend)?;
+        print_page(
+            &mut output,
+            &page[..],
+            format,
+            enable_styles,
+            args.pager,
+            &config,
+            timings,
+        )?;
+        return Ok(ExitReason::Success);
     }
 
     // The tealdeer page is embedded in the binary, no cache needed
     if command == "tealdeer" {
         print_page(
+            &mut output,
             TEALDEER_PAGE.as_bytes(),
-            args.raw,
+            format,
             enable_styles,
             args.pager,
             &config,
+            timings,
         )?;
-        return Ok(ExitCode::SUCCESS);
+        return Ok(ExitReason::Success);
     }
 
-    if let Some(platforms) = args.platforms {
-        config.search.platforms = platforms;
-        if !config.search.platforms.contains(&PlatformType::Common) {
-            config.search.platforms.push(PlatformType::Common);
-        }
+    if args.migrate_custom_pages {
+        let custom_pages_dir = custom_pages_dir
+            .context("To migrate custom pages, please specify a custom pages directory.")?;
+        migrate_custom_pages(custom_pages_dir, args.dry_run, quietly, enable_styles)?;
+        return Ok(ExitReason::Success);
     }
 
-    let (search_languages, download_languages): (&[_], &[_]) = match args.language.as_deref() {
-        Some(lang) => (&[Language(lang)], &[Language(lang)]),
-        None => (&config.search.languages, &config.updates.download_languages),
-    };
-
-    let cache_config = CacheConfig {
-        pages_directory: &config.directories.cache_dir.path().join(TLDR_PAGES_DIR),
-        custom_pages_directory: config
-            .directories
-            .custom_pages_dir
-            .as_ref()
-            .map(PathWithSource::path),
-        platforms: &config.search.platforms,
-        search_languages,
-        download_languages,
-    };
+    if args.list_custom {
+        let custom_pages_dir = custom_pages_dir
+            .context("To list custom pages/patches, please specify a custom pages directory.")?;
+        let cache = Cache::open(cache_config)?;
+        list_custom_pages(custom_pages_dir, cache.as_ref())?;
+        return Ok(ExitReason::Success);
+    }
 
     // TODO: remove in tealdeer 1.9
     let old_config = CacheConfig {
         pages_directory: &config.directories.cache_dir.path().join(TLDR_OLD_PAGES_DIR),
+        system_pages_directory: None,
         ..cache_config
     };
     if let Ok(Some(old_cache)) = Cache::open(old_config) {
         old_cache.clear()?;
-        eprintln!("Cleared pages from old cache location.");
+        deprecations.warn(
+            Deprecation::OldCacheLocation,
+            "Cleared pages from old cache location.",
+        );
     }
 
     if args.clear_cache {
         if let Some(cache) = Cache::open(cache_config)? {
-            clear_cache(cache, args.quiet)?;
+            let fetched_pages_dir = config.directories.cache_dir.path().join(FETCHED_PAGES_DIR);
+            clear_cache(cache, &fetched_pages_dir, quietly)?;
+        }
+        return Ok(ExitReason::Success);
+    }
+
+    if args.prune_languages {
+        if let Some(cache) = Cache::open(cache_config)? {
+            prune_languages(&cache, quietly)?;
         }
-        return Ok(ExitCode::SUCCESS);
+        return Ok(ExitReason::Success);
+    }
+
+    if let Some(language) = &args.add_language {
+        add_language(cache_config, &config, language, quietly)?;
+        return Ok(ExitReason::Success);
+    }
+
+    if let Some(address) = &args.serve_cache {
+        serve_cache(cache_config, address, quietly)?;
+        return Ok(ExitReason::Success);
+    }
+
+    if let Some(language) = &args.remove_language {
+        remove_language(cache_config, &config, language, quietly)?;
+        return Ok(ExitReason::Success);
+    }
+
+    if args.check_cache {
+        let is_healthy = check_cache(cache_config, &config, quietly)?;
+        return Ok(if is_healthy {
+            ExitReason::Success
+        } else {
+            ExitReason::Failure
+        });
     }
 
-    let cache = if args.update || config.updates.auto_update && !args.no_auto_update {
+    if args.dry_run_update {
+        let needs_update = check_for_updates(cache_config, &config)?;
+        return Ok(if needs_update {
+            ExitReason::Failure
+        } else {
+            ExitReason::Success
+        });
+    }
+
+    let mut was_updated = false;
+    let mut cache = if args.update || config.updates.auto_update && !args.no_auto_update {
         let (mut cache, was_created) = Cache::open_or_create(cache_config)?;
-        if was_created || args.update || cache.age()? >= config.updates.auto_update_interval {
+        if was_created || args.update || auto_update_due(&cache, &config.updates)? {
+            was_updated = true;
+            // The attempt is only recorded to limit the number of attempts, updating the cache
+            // is more important
+            if let Err(e) = cache.record_update_attempt() {
+                warn!("Could not record the update attempt: {e:?}");
+            }
+            let json_report = args.output == Some(ReportFormat::Json);
+            let update_start = Instant::now();
             let result = update_cache(
                 &mut cache,
-                config.updates.archive_source,
-                config.updates.tls_backend,
-                args.quiet,
+                &config,
+                json_report,
+                quietly || json_report,
+                enable_styles,
             );
+            if json_report {
+                print_update_report(&result, update_start.elapsed())?;
+            }
 
-            if let Err(e) = result {
-                print_error(enable_styles, &e);
+            match result {
+                // When offline, automatic updates fall back to the existing pages
+                Err(e) if !args.update && !was_created && is_network_unreachable(&e) => {
+                    print_warning(
+                        enable_styles,
+                        "Could not update the cache because the network is unreachable, \
+                         using the existing pages.",
+                    );
+                }
+                Err(e) => {
+                    print_error(enable_styles, &e);
 
-                eprintln!();
-                eprintln!("Note: Update errors are often caused by unexpected or missing TLS certificates.");
-                eprintln!(
-                    "You are currently using the following TLS backend: {}",
-                    config.updates.tls_backend,
-                );
-                eprintln!(
+                    eprintln!();
+                    eprintln!("Note: Update errors are often caused by unexpected or missing TLS certificates.");
+                    eprintln!(
+                        "You are currently using the following TLS backend: {}",
+                        config.updates.tls_backend,
+                    );
+                    eprintln!(
                     "Try changing the updates.tls_backend setting in the config file, for example:"
                 );
-                eprintln!();
-                eprintln!("  [updates]");
-                eprintln!("  tls_backend = \"rustls-with-native-roots\"");
-                eprintln!();
-                eprintln!(
-                    "This build of tealdeer has support for the following options: {}",
-                    supported_tls_backends_string(),
-                );
+                    eprintln!();
+                    eprintln!("  [updates]");
+                    eprintln!("  tls_backend = \"rustls-with-native-roots\"");
+                    eprintln!();
+                    eprintln!(
+                        "This build of tealdeer has support for the following options: {}",
+                        supported_tls_backends_string(),
+                    );
 
-                return Ok(ExitCode::FAILURE);
+                    return Ok(ExitReason::from_error(&e));
+                }
+                Ok(_) => {
+                    if config.updates.check_new_version && !quietly {
+                        notify_new_version(&cache, config.updates.tls_backend, enable_styles);
+                    }
+                }
             }
         }
 
         cache
-    } else if args.list || !command.is_empty() {
+    } else if args.list
+        || args.random
+        || args.random_platform
+        || args.tip
+        || args.which_flag.is_some()
+        || args.apropos.is_some()
+        || !command.is_empty()
+    {
         // Cache is needed for these commands to work
         let Some(cache) = Cache::open(cache_config)? else {
+            if args.exists {
+                return Ok(ExitReason::Failure);
+            }
             print_error(
                 enable_styles,
-                &anyhow::anyhow!(
-                    "Page cache not found. Please run `tldr --update` to download the cache."
-                ),
+                &anyhow::anyhow!(Message::CacheNotFound.to_string()),
+            );
+            println!(
+                "\n{}https://tealdeer-rs.github.io/tealdeer/config_updates.html",
+                Message::CacheNotFoundHint
             );
-            println!("\nNote: You can optionally enable automatic cache updates by adding the");
-            println!("following config to your config file:\n");
-            println!("  [updates]");
-            println!("  auto_update = true\n");
-            println!("The path to your config file can be looked up with `tldr --show-paths`.");
-            println!("To create an initial config file, use `tldr --seed-config`.\n");
-            println!("You can find more tips and tricks in our docs:\n");
-            println!("  https://tealdeer-rs.github.io/tealdeer/config_updates.html");
-
-            return Ok(ExitCode::FAILURE);
+
+            return Ok(ExitReason::Failure);
         };
 
         if let Some(max_cache_age) = config.updates.warn_cache_age {
-            let age = cache.age()?;
-            if age > max_cache_age && !args.quiet {
+            // Shared system pages are updated by the system, so only a user cache can be stale
+            let age = if cache.cached_languages()?.is_empty() {
+                Duration::ZERO
+            } else {
+                cache.age()?
+            };
+            if age > max_cache_age {
                 print_warning(
                     enable_styles,
-                    &format!(
-                        "The cache hasn't been updated for {} days.\n\
-                         You should probably run `tldr --update` soon.",
-                        age.as_secs() / 24 / 3600
-                    ),
+                    &Message::CacheOutdated {
+                        days: age.as_secs() / 24 / 3600,
+                    }
+                    .to_string(),
                 );
             }
         }
@@ -393,26 +2586,154 @@ fn try_main(args: Cli, enable_styles: bool) -> Result<ExitCode> {
         cache
     } else {
         // There is nothing left to do
-        return Ok(ExitCode::SUCCESS);
+        return Ok(ExitReason::Success);
+    };
+
+    // Download the pages again instead of failing to find any pages, unless that just happened
+    let recover_empty_cache = |cache: &mut Cache| {
+        recover_empty_cache(
+            cache,
+            &config,
+            config.updates.auto_update && !args.no_auto_update && !was_updated,
+            quietly,
+            enable_styles,
+        )
     };
 
     if args.list {
-        for page in cache.list_pages()? {
-            println!("{page}");
+        recover_empty_cache(&mut cache)?;
+        let mut pages: Vec<String> = cache.list_pages()?.into_iter().collect();
+        pages.extend(cache.list_indexed_pages()?);
+        pages.sort_unstable();
+        pages.dedup();
+        if args.show_platforms {
+            print_page_platforms(&cache, &pages)?;
+        } else {
+            for page in pages {
+                println!("{page}");
+            }
+        }
+
+        return Ok(ExitReason::Success);
+    }
+
+    if args.random || args.random_platform {
+        recover_empty_cache(&mut cache)?;
+        let random_page = if args.random_platform {
+            find_random_platform_page(&cache)?.map(|(platform, page, result)| {
+                if !quietly {
+                    eprintln!("Showing the page `{page}` for {platform}.");
+                }
+                (page, result)
+            })
+        } else {
+            find_random_page(&cache)?
+        };
+        let (page, result) = random_page.context("The cache does not contain any pages.")?;
+        print_page(
+            &mut output,
+            result.reader()?,
+            format,
+            enable_styles,
+            args.pager,
+            &config,
+            timings,
+        )?;
+        record_in_history(&history, &config, &page, enable_styles);
+        return Ok(ExitReason::Success);
+    }
+
+    if args.tip {
+        recover_empty_cache(&mut cache)?;
+        print_random_tip(&cache, &config)?;
+        return Ok(ExitReason::Success);
+    }
+
+    if let Some(keyword) = &args.apropos {
+        recover_empty_cache(&mut cache)?;
+        if print_apropos(&cache, &config, keyword)? {
+            return Ok(ExitReason::Success);
+        }
+        print_warning(
+            enable_styles,
+            &format!("No pages in the cache match `{keyword}`."),
+        );
+        return Ok(ExitReason::PageNotFound);
+    }
+
+    if let Some(flag) = &args.which_flag {
+        recover_empty_cache(&mut cache)?;
+        if print_pages_with_flag(&cache, &config, flag)? {
+            return Ok(ExitReason::Success);
+        }
+        print_warning(
+            enable_styles,
+            &format!("No examples in the cache contain `{flag}`."),
+        );
+        return Ok(ExitReason::PageNotFound);
+    }
+
+    if args.diff_patch {
+        diff_patch(&cache, &command, enable_styles)?;
+        return Ok(ExitReason::Success);
+    }
+
+    if args.exists {
+        let exists = cache
+            .find_page_in_sources(&command, config.updates.tls_backend, enable_styles)
+            .is_some()
+            || cache.list_indexed_pages()?.contains(&command);
+        return Ok(if exists {
+            ExitReason::Success
+        } else {
+            ExitReason::Failure
+        });
+    }
+
+    if args.availability {
+        if show_availability(&cache, &command)? {
+            return Ok(ExitReason::Success);
         }
+        print_warning(
+            enable_styles,
+            &format!("Page `{command}` is not listed in the index of the tldr pages."),
+        );
+        return Ok(ExitReason::PageNotFound);
+    }
 
-        return Ok(ExitCode::SUCCESS);
+    if args.all_matches || args.compare {
+        recover_empty_cache(&mut cache)?;
+        if print_platform_pages(
+            &mut output,
+            &cache,
+            &command,
+            &args,
+            enable_styles,
+            &config,
+            timings,
+        )? {
+            record_in_history(&history, &config, &command, enable_styles);
+            return Ok(ExitReason::Success);
+        }
+        if args.compare {
+            print_warning(
+                enable_styles,
+                &format!("Page `{command}` not found in cache for any of the given platforms."),
+            );
+            return Ok(ExitReason::PageNotFound);
+        }
     }
 
     // Show command from cache
     if !command.is_empty() {
         // TODO: Remove this check 1 year after version 1.7.0 was released
         if cache.old_custom_pages_exist()? {
-            print_warning(
-                enable_styles,
+            deprecations.warn(
+                Deprecation::OldCustomPageNames,
                 &format!(
                     "Custom pages using the old naming convention were found in {}.\n\
-                     Please rename them to follow the new convention:\n\
+                     Please rename them to follow the new convention, or run \
+                     `tldr --migrate-custom-pages` to do it for you:\n\
                      - `<name>.page` → `<name>.page.md`\n\
                      - `<name>.patch` → `<name>.patch.md`",
                     cache
@@ -424,29 +2745,87 @@ fn try_main(args: Cli, enable_styles: bool) -> Result<ExitCode> {
             );
         }
 
-        let Some(result) = cache.find_page(&command) else {
-            if !args.quiet {
-                print_warning(
-                    enable_styles,
-                    &format!(
-                        "Page `{}` not found in cache.\n\
-                         Try updating with `tldr --update`, or submit a pull request to:\n\
-                         https://github.com/tldr-pages/tldr",
-                        &command
-                    ),
-                );
+        if let Some(unapplied_patch) = cache.find_unapplied_patch(&command) {
+            print_warning(enable_styles, &unapplied_patch.to_string());
+        }
+
+        let missing_languages = missing_search_languages(&cache, &config, &args)?;
+        let all_languages_missing =
+            missing_languages.len() == cache.config().search_languages.len();
+        if !missing_languages.is_empty() && !all_languages_missing {
+            print_warning(enable_styles, &no_pages_for_languages(&missing_languages));
+        }
+
+        let tls_backend = config.updates.tls_backend;
+        let mut result = timings.measure("cache lookup", || {
+            cache.find_page_in_sources(&command, tls_backend, enable_styles)
+        });
+        if result.is_none() && recover_empty_cache(&mut cache)? {
+            result = cache.find_page_in_sources(&command, tls_backend, enable_styles);
+        }
+        if result.is_none() && config.updates.use_index {
+            result = fetch_indexed_page(&cache, &config, &command, enable_styles)?;
+        }
+        if result.is_none() && config.updates.fetch_missing {
+            result = find_or_fetch_page(&cache, &config, &command, quietly, enable_styles);
+        }
+        let Some(result) = result else {
+            // The page can't be found without pages in any of the languages
+            if !missing_languages.is_empty() && all_languages_missing {
+                bail!(no_pages_for_languages(&missing_languages));
             }
-            return Ok(ExitCode::FAILURE);
+            let usage_counts = if config.history.enabled && config.history.boost_frequent_pages {
+                history.usage_counts()?
+            } else {
+                Vec::new()
+            };
+            let suggestions = similar_pages(&cache, &command, &usage_counts)?;
+            print_warning(
+                enable_styles,
+                &Message::PageNotFound {
+                    command: &command,
+                    suggestions: &suggestions,
+                }
+                .to_string(),
+            );
+            return Ok(ExitReason::PageNotFound);
         };
-
-        print_page(
-            result.reader()?,
-            args.raw,
-            enable_styles,
-            args.pager,
-            &config,
+        let result = if config.display.follow_aliases {
+            follow_alias(
+                &cache,
+                tls_backend,
+                &command,
+                result,
+                quietly,
+                enable_styles,
+            )?
+        } else {
+            result
+        };
+        let result = cache.resolve_includes(
+            &command,
+            result.with_expanded_templates(config.display.expand_templates),
         )?;
+
+        if args.fill {
+            let code = fill_example(result.reader()?, &config)?;
+            writeln!(output, "{code}").context("Could not write the example")?;
+            output.flush().context("Could not flush the output")?;
+        } else {
+            print_page(
+                &mut output,
+                result.reader()?,
+                format,
+                enable_styles,
+                args.pager,
+                &config,
+                timings,
+            )?;
+        }
+
+        record_in_history(&history, &config, &command, enable_styles);
+        record_access(&cache, &config, &result, enable_styles);
     }
 
-    Ok(ExitCode::SUCCESS)
+    Ok(ExitReason::Success)
 }
@@ -0,0 +1,139 @@
+//! Verification of [minisign](https://jedisct1.github.io/minisign/) signatures, used to check the
+//! archives of the tldr pages if `updates.archive_public_key` is configured.
+//!
+//! The signatures are verified with the `minisign-verify` crate, which is only included with the
+//! `archive-signatures` feature.
+
+use std::io::Read;
+
+#[cfg(not(feature = "archive-signatures"))]
+use anyhow::bail;
+use anyhow::Result;
+#[cfg(feature = "archive-signatures")]
+use anyhow::{anyhow, Context};
+
+#[cfg(feature = "archive-signatures")]
+const UNTRUSTED_COMMENT: &str = "untrusted comment: ";
+
+/// A minisign public key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PublicKey {
+    #[cfg(feature = "archive-signatures")]
+    key: minisign_verify::PublicKey,
+}
+
+#[cfg(feature = "archive-signatures")]
+impl PublicKey {
+    /// Parse a public key, either the base64 encoded key on its own or the content of a `.pub`
+    /// file created by minisign.
+    pub fn parse(s: &str) -> Result<Self> {
+        let encoded = s
+            .lines()
+            .map(str::trim)
+            .rfind(|line| !line.is_empty() && !line.starts_with(UNTRUSTED_COMMENT))
+            .context("The public key is empty")?;
+        let key = minisign_verify::PublicKey::from_base64(encoded)
+            .map_err(|_| anyhow!("The public key is not a minisign public key"))?;
+        Ok(Self { key })
+    }
+
+    /// Verify that `signature`, the content of a `.minisig` file, is a valid signature of the
+    /// content of `reader` made with this key.
+    pub fn verify(&self, mut reader: impl Read, signature: &str) -> Result<()> {
+        use minisign_verify::{Error, Signature};
+
+        let signature = Signature::decode(signature)
+            .map_err(|_| anyhow!("The signature is not a minisign signature"))?;
+        let result = match self.key.verify_stream(&signature) {
+            // Signatures of the BLAKE2b hash of the file, the default of recent versions of
+            // minisign, are verified without reading the whole archive into memory
+            Ok(mut verifier) => {
+                let mut buffer = [0; 8192];
+                loop {
+                    let n = reader.read(&mut buffer)?;
+                    if n == 0 {
+                        break;
+                    }
+                    verifier.update(&buffer[..n]);
+                }
+                verifier.finalize()
+            }
+            // Signatures of the whole file, made by older versions of minisign
+            Err(Error::UnsupportedLegacyMode) => {
+                let mut content = Vec::new();
+                reader.read_to_end(&mut content)?;
+                self.key.verify(&content, &signature, true)
+            }
+            Err(e) => Err(e),
+        };
+        result.map_err(|e| match e {
+            Error::UnexpectedKeyId => anyhow!("The signature was made with a different key"),
+            Error::InvalidSignature => anyhow!("The signature does not match"),
+            e => anyhow!("The signature could not be verified: {e}"),
+        })
+    }
+}
+
+#[cfg(not(feature = "archive-signatures"))]
+impl PublicKey {
+    pub fn parse(_s: &str) -> Result<Self> {
+        bail!(
+            "Signatures can't be verified, because tealdeer was built without the \
+             `archive-signatures` feature"
+        )
+    }
+
+    #[allow(clippy::unused_self)]
+    pub fn verify(&self, _reader: impl Read, _signature: &str) -> Result<()> {
+        unreachable!("Public keys can't be parsed without the `archive-signatures` feature")
+    }
+}
+
+#[cfg(all(test, feature = "archive-signatures"))]
+mod tests {
+    use super::*;
+
+    /// A key created for these tests, with the key ID `0807060504030201`.
+    const PUBLIC_KEY: &str = "untrusted comment: minisign public key 0807060504030201\n\
+                              RWQBAgMEBQYHCBl/ayPhbIUyxqvIOPrNXqeJvgx2spIDNAOb+os9No1h\n";
+
+    /// A signature of `pages` made with [`PUBLIC_KEY`] by older versions of minisign.
+    const LEGACY_SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RWQBAgMEBQYHCOYnhRq5N6/kzk5vrWd2FF4veziF33c/t3fR+91VkdfHdQa2bwN0dMkW24s7r1YpVNtH8OJ6wLnQX3MYfm0RLgM=
+trusted comment: timestamp:1700000000\tfile:tldr.zip
+fVA7ngQK/1W6O0Y9U+tMjr3T55d4mz8OCYX1Hl99DmGcmQnYUy7O0sgEkOrpjKCV961qvWJcOl5agdgQJo8uAg==
+";
+
+    /// A signature of the hash of `pages` made with [`PUBLIC_KEY`].
+    const PREHASHED_SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQBAgMEBQYHCLAR0Ve3Q2Wqw7qQlREOMj8oE7TbRUT+jdLNF+R5xZ5wOgmnBCl7lyHM5AX1m233mfTPUedJ5OviA8ajuLUVMQI=
+trusted comment: timestamp:1700000000\tfile:tldr.zip
+VVCErrbnYmzZhKiqvbRaL+SMgk/AgUyQjReR2eZGeyR0Wtfftz2VLTRlMJ9AAyPKZDEQShn6TaeZrc3kUF3ZAw==
+";
+
+    #[test]
+    fn test_verify() {
+        let key = PublicKey::parse(PUBLIC_KEY).unwrap();
+        assert_eq!(
+            PublicKey::parse(PUBLIC_KEY.lines().nth(1).unwrap()).unwrap(),
+            key
+        );
+
+        for signature in [LEGACY_SIGNATURE, PREHASHED_SIGNATURE] {
+            key.verify(&b"pages"[..], signature).unwrap();
+            let error = key.verify(&b"tampered"[..], signature).unwrap_err();
+            assert_eq!(error.to_string(), "The signature does not match");
+            // The trusted comment is signed as well
+            let altered_comment = signature.replace("tldr.zip", "other.zip");
+            let error = key.verify(&b"pages"[..], &altered_comment).unwrap_err();
+            assert_eq!(error.to_string(), "The signature does not match");
+        }
+    }
+
+    #[test]
+    fn test_invalid_keys() {
+        assert!(PublicKey::parse("").is_err());
+        assert!(PublicKey::parse("not base64!").is_err());
+        assert!(PublicKey::parse("AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA").is_err());
+    }
+}
@@ -1,97 +1,728 @@
 //! Functions for printing pages to the terminal
 
-use std::io::{self, BufRead, BufReader, Read, Write};
+use std::{
+    env,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 
-use anyhow::{Context, Result};
-use yansi::Paint;
+use anyhow::{ensure, Context, Result};
+use serde_derive::Serialize;
+use yansi::{Paint, Style};
 
 use crate::{
-    config::{Config, StyleConfig},
-    formatter::{highlight_lines, PageSnippet},
+    config::{
+        Config, DisplayConfig, ExampleShell, Indent, OptionStyle, StyleConfig, TitleDecoration,
+    },
+    extensions::FindIgnoreAsciiCase,
+    formatter::{highlight_lines, HighlightSummary, PageSnippet},
     line_iterator::LineIterator,
+    timings::{TimedIter, Timings},
+    types::{ColorOptions, LineType},
+    utils::{print_warning, prompt},
 };
 
 /// Set up display pager
 ///
 /// SAFETY: this function may be called multiple times
 #[cfg(not(target_os = "windows"))]
-fn configure_pager(_: bool) {
+fn configure_pager(enable_styles: bool) {
     use std::sync::Once;
     static INIT: Once = Once::new();
-    INIT.call_once(|| pager::Pager::with_default_pager("less -R").setup());
+    INIT.call_once(|| match default_pager() {
+        Some(pager) => pager::Pager::with_default_pager(pager).setup(),
+        None => print_warning(
+            enable_styles,
+            "Neither `less` nor `more` was found, set the PAGER env variable to use a pager.",
+        ),
+    });
+}
+
+/// The pager used if the `PAGER` env variable is not set. `less` isn't installed everywhere, e.g.
+/// not on Android outside of Termux, so `more` is used as a fallback.
+#[cfg(not(target_os = "windows"))]
+fn default_pager() -> Option<&'static str> {
+    use crate::utils::executable_exists;
+    if std::env::var_os("PAGER").is_some() || executable_exists("less") {
+        Some("less -R")
+    } else if executable_exists("more") {
+        Some("more")
+    } else {
+        None
+    }
 }
 
 #[cfg(target_os = "windows")]
 fn configure_pager(enable_styles: bool) {
-    use crate::utils::print_warning;
     print_warning(enable_styles, "--pager flag not available on Windows!");
 }
 
-/// Print page by path
+/// The file given with `--output-file`. It is only created once something is written to it, so
+/// that no empty file is left behind if no page is rendered.
+pub struct OutputFile {
+    path: PathBuf,
+    file: Option<BufWriter<File>>,
+}
+
+impl OutputFile {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path, file: None }
+    }
+}
+
+impl Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(file) = &mut self.file {
+            return file.write(buf);
+        }
+        let file = File::create(&self.path).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("Could not create output file {}: {e}", self.path.display()),
+            )
+        })?;
+        self.file.insert(BufWriter::new(file)).write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.as_mut().map_or(Ok(()), Write::flush)
+    }
+}
+
+/// How a page is printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// The markdown of the page as it is, see `--raw`.
+    Raw,
+    /// The page rendered with the styles and layout from the config.
+    Rendered,
+    /// The page rendered without styles and with a fixed layout, see `--plain`.
+    Plain,
+    /// The snippets of the page as JSON objects, one per line, see `--output tokens`.
+    Tokens,
+}
+
+/// Print page by path to `writer`
+///
+/// The time spent parsing and rendering the page is recorded in `timings`.
 pub fn print_page(
+    writer: &mut impl Write,
     reader: impl Read,
-    enable_markdown: bool,
+    format: OutputFormat,
     enable_styles: bool,
     use_pager: bool,
     config: &Config,
+    timings: &mut Timings,
 ) -> Result<()> {
     let reader = BufReader::new(reader);
+    let start = Instant::now();
+    let mut parsing = Duration::ZERO;
 
     // Configure pager if applicable
     if use_pager || config.display.use_pager {
         configure_pager(enable_styles);
     }
 
-    // Lock stdout only once, this improves performance considerably
+    let summary = match format {
+        OutputFormat::Raw => {
+            for line in reader.lines() {
+                let line = line.context("Error while reading from a page")?;
+                writeln!(writer, "{line}").context("Could not write the page")?;
+            }
+            None
+        }
+        OutputFormat::Rendered => {
+            // Matches of `--grep` are only highlighted with styles, as they are shown inverted
+            let highlight = config.display.grep.as_deref().filter(|_| enable_styles);
+
+            // Closure that processes a page snippet and writes it to `writer`
+            let mut process_snippet = |snip: PageSnippet<&str>| {
+                if snip.is_empty() {
+                    Ok(())
+                } else {
+                    print_snippet(&mut *writer, snip, &config.style, highlight)
+                        .context("Failed to print snippet")
+                }
+            };
+
+            // Print highlighted lines
+            let summary = highlight_lines(
+                TimedIter::new(LineIterator::new(reader), &mut parsing),
+                &mut process_snippet,
+                &config.display,
+            )
+            .context("Could not write the page")?;
+            Some(summary)
+        }
+        OutputFormat::Plain => {
+            let lines = TimedIter::new(LineIterator::new(reader), &mut parsing);
+            let summary = print_plain(&mut *writer, lines, &config.display)
+                .context("Could not write the page")?;
+            Some(summary)
+        }
+        OutputFormat::Tokens => {
+            let lines = TimedIter::new(LineIterator::new(reader), &mut parsing);
+            let summary = print_tokens(&mut *writer, lines, &config.display)
+                .context("Could not write the page")?;
+            Some(summary)
+        }
+    };
+
+    // We're done outputting data, flush the writer now! This has to happen before printing
+    // warnings, which would appear above the page otherwise.
+    writer.flush().context("Could not flush the output")?;
+
+    if let Some(summary) = summary {
+        check_example_selection(&config.display, &summary)?;
+        if summary.unrecognized_lines > 0 {
+            let message = format!(
+                "The page contains {} unrecognized line(s), which are not shown.",
+                summary.unrecognized_lines
+            );
+            ensure!(!config.display.strict, message);
+            print_warning(enable_styles, &message);
+        }
+    }
+
+    // Parsing is interleaved with rendering, so it is subtracted from the total time
+    timings.record("parsing", parsing);
+    timings.record("rendering", start.elapsed().saturating_sub(parsing));
+
+    Ok(())
+}
+
+/// Print the page in `lines` without any styles, for tests and scripts.
+///
+/// The layout is fixed, so that the output is the same for everyone: only the examples selected
+/// with `--example` or `--grep` in `display` are taken into account, the other display options
+/// are ignored.
+fn print_plain(
+    writer: &mut impl Write,
+    lines: impl Iterator<Item = LineType>,
+    display: &DisplayConfig,
+) -> io::Result<HighlightSummary> {
+    highlight_lines(
+        lines,
+        &mut |snip: PageSnippet<&str>| {
+            use PageSnippet::*;
+
+            match snip {
+                CommandName(s) | Variable(s) | NormalCode(s) | CommandOption(s)
+                | Description(s) | Text(s) | Title(s) | InlineCode(s) | Link(s) => {
+                    writer.write_all(s.as_bytes())
+                }
+                Linebreak => writeln!(writer),
+            }
+        },
+        &plain_display(display),
+    )
+}
+
+/// A snippet of a page as printed by `--output tokens`.
+///
+/// This is a stable format for other programs that render pages themselves: the kinds and
+/// fields are only ever added to, never changed or removed.
+#[derive(Debug, Serialize)]
+struct Token<'a> {
+    kind: &'static str,
+    /// The text of the snippet, missing for line breaks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<&'a str>,
+}
+
+impl<'a> From<PageSnippet<&'a str>> for Token<'a> {
+    fn from(snip: PageSnippet<&'a str>) -> Self {
+        let (kind, text) = match snip {
+            PageSnippet::CommandName(s) => ("command_name", Some(s)),
+            PageSnippet::Variable(s) => ("variable", Some(s)),
+            PageSnippet::NormalCode(s) => ("normal_code", Some(s)),
+            PageSnippet::CommandOption(s) => ("command_option", Some(s)),
+            PageSnippet::Description(s) => ("description", Some(s)),
+            PageSnippet::Text(s) => ("text", Some(s)),
+            PageSnippet::Title(s) => ("title", Some(s)),
+            PageSnippet::InlineCode(s) => ("inline_code", Some(s)),
+            PageSnippet::Link(s) => ("link", Some(s)),
+            PageSnippet::Linebreak => ("linebreak", None),
+        };
+        Self { kind, text }
+    }
+}
+
+/// Fail if the examples selected with `display.example` or `display.grep` are not on the page.
+fn check_example_selection(display: &DisplayConfig, summary: &HighlightSummary) -> Result<()> {
+    if let Some(example) = display.example {
+        ensure!(
+            example <= summary.examples,
+            "There is no example {example}, the page only has {} examples.",
+            summary.examples,
+        );
+    }
+    if let Some(pattern) = &display.grep {
+        ensure!(summary.shown_examples > 0, "No examples match `{pattern}`.");
+    }
+    Ok(())
+}
+
+/// Print the snippets of the page in `lines` as JSON objects, one per line.
+///
+/// Like with `--plain`, the layout is fixed, except that the title is included and nothing is
+/// indented: indentation, wrapping and colors are left to the program reading the tokens.
+fn print_tokens(
+    writer: &mut impl Write,
+    lines: impl Iterator<Item = LineType>,
+    display: &DisplayConfig,
+) -> io::Result<HighlightSummary> {
+    let token_display = DisplayConfig {
+        show_title: true,
+        highlight_options: true,
+        indent: Indent {
+            base: 0,
+            command: 0,
+        },
+        ..plain_display(display)
+    };
+    highlight_lines(
+        lines,
+        &mut |snip: PageSnippet<&str>| {
+            if snip.is_empty() {
+                return Ok(());
+            }
+            serde_json::to_writer(&mut *writer, &Token::from(snip))?;
+            writeln!(writer)
+        },
+        &token_display,
+    )
+}
+
+/// The display config of `--plain`, keeping only the examples selected in `display`.
+fn plain_display(display: &DisplayConfig) -> DisplayConfig {
+    DisplayConfig {
+        compact: false,
+        use_pager: false,
+        show_title: false,
+        title_decoration: TitleDecoration::None,
+        option_style: OptionStyle::Both,
+        shell: ExampleShell::Sh,
+        show_example_numbers: false,
+        raw_when_piped: false,
+        wrap_text: false,
+        expand_templates: false,
+        follow_aliases: false,
+        color: ColorOptions::Never,
+        width: None,
+        example: display.example,
+        grep: display.grep.clone(),
+        strict: display.strict,
+        example_values: None,
+        highlight_options: false,
+        indent: Indent {
+            base: 2,
+            command: 6,
+        },
+    }
+}
+
+/// Placeholders that are filled in with the value of an env variable by [`fill_example`].
+const ENV_PLACEHOLDERS: &[(&str, &str)] = &[("user", "USER"), ("username", "USER")];
+
+/// Return the code of the example selected with `display.example`, with its placeholders filled
+/// in.
+///
+/// The values are taken from the `[placeholders]` config section, or from env variables for the
+/// placeholders in [`ENV_PLACEHOLDERS`]. The user is asked for the values of all other
+/// placeholders, once for each distinct placeholder.
+pub fn fill_example(reader: impl Read, config: &Config) -> Result<String> {
+    let display = DisplayConfig {
+        compact: true,
+        show_title: false,
+        // Only one of the alternative options can be used in a command
+        option_style: match config.display.option_style {
+            OptionStyle::Both => OptionStyle::Long,
+            option_style => option_style,
+        },
+        example_values: None,
+        indent: Indent {
+            base: 0,
+            command: 0,
+        },
+        ..config.display.clone()
+    };
+
+    // The code of the example, as literal code and placeholders
+    let mut parts = Vec::new();
+    let mut in_code = false;
+    let summary = highlight_lines(
+        LineIterator::new(BufReader::new(reader)),
+        &mut |snip: PageSnippet<&str>| {
+            match snip {
+                PageSnippet::CommandName(s)
+                | PageSnippet::NormalCode(s)
+                | PageSnippet::CommandOption(s) => {
+                    parts.push((false, s.to_string()));
+                    in_code = true;
+                }
+                PageSnippet::Variable(s) => {
+                    parts.push((true, s.to_string()));
+                    in_code = true;
+                }
+                PageSnippet::Linebreak if in_code => {
+                    parts.push((false, "\n".to_string()));
+                    in_code = false;
+                }
+                _ => {}
+            }
+            Ok::<(), anyhow::Error>(())
+        },
+        &display,
+    )?;
+    check_example_selection(&display, &summary)?;
+
+    let mut values = config.placeholders.clone();
+    for (placeholder, var) in ENV_PLACEHOLDERS {
+        if let Ok(value) = env::var(var) {
+            values.entry((*placeholder).to_string()).or_insert(value);
+        }
+    }
+
+    let mut code = String::new();
+    for (is_placeholder, text) in parts {
+        if !is_placeholder {
+            code.push_str(&text);
+            continue;
+        }
+        if !values.contains_key(&text) {
+            let value = prompt(&format!("{text}:"), &text).context("Could not read the value")?;
+            values.insert(text.clone(), value);
+        }
+        code.push_str(&values[&text]);
+    }
+    Ok(code.trim_end().to_string())
+}
+
+/// Print the header above the page of `platform` when comparing the pages of several platforms.
+///
+/// `common_page` indicates that there is no page specific to `platform`, so the common page is
+/// shown instead.
+pub fn print_header(
+    writer: &mut impl Write,
+    header: &str,
+    enable_styles: bool,
+    use_pager: bool,
+    config: &Config,
+) -> Result<()> {
+    // The pager has to be set up before anything is written
+    if use_pager || config.display.use_pager {
+        configure_pager(enable_styles);
+    }
+
+    writeln!(writer, "{}", header.paint(config.style.title)).context("Could not write the page")
+}
+
+/// Print the raw markdown of `page` followed by the lines contributed by `patch`.
+///
+/// Lines from the patch are prefixed with `+` (and colored green if `enable_styles` is true),
+/// lines from the page are indented to line up with them.
+pub fn print_patch_diff(page: impl Read, patch: impl Read, enable_styles: bool) -> Result<()> {
+    let added_style = if enable_styles {
+        Style::new().green()
+    } else {
+        Style::new()
+    };
+
     let stdout = io::stdout();
     let mut handle = stdout.lock();
 
-    if enable_markdown {
-        // Print the raw markdown of the file.
-        for line in reader.lines() {
-            let line = line.context("Error while reading from a page")?;
-            writeln!(handle, "{line}").context("Could not write to stdout")?;
+    for line in BufReader::new(page).lines() {
+        let line = line.context("Error while reading from a page")?;
+        if line.is_empty() {
+            writeln!(handle).context("Could not write to stdout")?;
+        } else {
+            writeln!(handle, "  {line}").context("Could not write to stdout")?;
         }
-    } else {
-        // Closure that processes a page snippet and writes it to stdout
-        let mut process_snippet = |snip: PageSnippet<&str>| {
+    }
+
+    // The patch is separated from the page by an empty line, see `PageLookupResult::reader`
+    writeln!(handle, "{}", "+".paint(added_style)).context("Could not write to stdout")?;
+    for line in BufReader::new(patch).lines() {
+        let line = line.context("Error while reading from a patch")?;
+        let line = if line.is_empty() {
+            "+".to_string()
+        } else {
+            format!("+ {line}")
+        };
+        writeln!(handle, "{}", line.paint(added_style)).context("Could not write to stdout")?;
+    }
+
+    handle.flush().context("Could not flush stdout")?;
+
+    Ok(())
+}
+
+/// Print a random example of the page as a tip: its description on the first line, followed by
+/// the indented command on the second line.
+///
+/// Returns `false` if the page does not contain any examples.
+pub fn print_tip(reader: impl Read, config: &Config) -> Result<bool> {
+    let mut title = String::new();
+    let mut text = None;
+    let mut examples = Vec::new();
+    for line in LineIterator::new(BufReader::new(reader)) {
+        match line {
+            LineType::Title(t) => title = t,
+            LineType::ExampleText(t) => text = Some(t),
+            LineType::ExampleCode(code) => {
+                if let Some(text) = text.take() {
+                    examples.push((text, code));
+                }
+            }
+            _ => {}
+        }
+    }
+    let Some((text, code)) = fastrand::choice(examples) else {
+        return Ok(false);
+    };
+
+    let mut buffer = Vec::new();
+    highlight_lines(
+        [
+            LineType::Title(title),
+            LineType::ExampleText(text),
+            LineType::ExampleCode(code),
+        ]
+        .into_iter(),
+        &mut |snip: PageSnippet<&str>| {
             if snip.is_empty() {
                 Ok(())
             } else {
-                print_snippet(&mut handle, snip, &config.style).context("Failed to print snippet")
+                print_snippet(&mut buffer, snip, &config.style, None)
             }
-        };
+        },
+        &DisplayConfig {
+            compact: true,
+            show_title: false,
+            show_example_numbers: false,
+            raw_when_piped: false,
+            example: None,
+            grep: None,
+            indent: Indent {
+                base: 0,
+                command: 2,
+            },
+            ..config.display.clone()
+        },
+    )
+    .context("Could not render tip")?;
 
-        // Print highlighted lines
-        highlight_lines(
-            LineIterator::new(reader),
-            &mut process_snippet,
-            !config.display.compact,
-            config.display.show_title,
-            config.display.indent,
-        )
+    // The tip should only take up two lines, so trailing line breaks are removed
+    while buffer.last() == Some(&b'\n') {
+        buffer.pop();
+    }
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    handle
+        .write_all(&buffer)
         .context("Could not write to stdout")?;
+    writeln!(handle).context("Could not write to stdout")?;
+    Ok(true)
+}
+
+/// Print the examples of the page `page_name` whose command contains `flag` (case-sensitively),
+/// below the name of the page.
+///
+/// Returns `false` if no example matches, in which case nothing is printed.
+pub fn print_flag_matches(
+    page_name: &str,
+    reader: impl Read,
+    flag: &str,
+    config: &Config,
+) -> Result<bool> {
+    let mut title = String::new();
+    let mut text = None;
+    let mut matches = Vec::new();
+    for line in LineIterator::new(BufReader::new(reader)) {
+        match line {
+            LineType::Title(t) => title = t,
+            LineType::ExampleText(t) => text = Some(t),
+            LineType::ExampleCode(code) => {
+                let Some(text) = text.take() else {
+                    continue;
+                };
+                if code.replace("{{", "").replace("}}", "").contains(flag) {
+                    matches.push(LineType::ExampleText(text));
+                    matches.push(LineType::ExampleCode(code));
+                }
+            }
+            _ => {}
+        }
+    }
+    if matches.is_empty() {
+        return Ok(false);
     }
 
-    // We're done outputting data, flush stdout now!
-    handle.flush().context("Could not flush stdout")?;
+    let mut buffer = Vec::new();
+    print_snippet(
+        &mut buffer,
+        PageSnippet::CommandName(page_name),
+        &config.style,
+        None,
+    )?;
+    writeln!(buffer)?;
+    highlight_lines(
+        std::iter::once(LineType::Title(title)).chain(matches),
+        &mut |snip: PageSnippet<&str>| {
+            if snip.is_empty() {
+                Ok(())
+            } else {
+                print_snippet(&mut buffer, snip, &config.style, None)
+            }
+        },
+        &DisplayConfig {
+            compact: true,
+            show_title: false,
+            // The numbers of the matching examples on the page are not known here
+            show_example_numbers: false,
+            raw_when_piped: false,
+            example: None,
+            grep: None,
+            ..config.display.clone()
+        },
+    )
+    .context("Could not render examples")?;
+
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    handle
+        .write_all(&buffer)
+        .context("Could not write to stdout")?;
+    Ok(true)
+}
+
+/// The first line of the description of a page, which summarizes what the command does.
+pub fn page_summary(reader: impl Read) -> Option<String> {
+    LineIterator::new(BufReader::new(reader)).find_map(|line| match line {
+        LineType::Description(text) => Some(text),
+        _ => None,
+    })
+}
+
+/// A page that only says that its command is an alias of another command.
+#[derive(Debug, PartialEq, Eq)]
+pub struct AliasPage {
+    /// The aliased command, as written in the description, e.g. `grep -E`.
+    pub command: String,
+    /// The name of the page of the aliased command, e.g. `grep`.
+    pub target: String,
+}
+
+/// Detect pages of the form used by tldr-pages for aliases:
+///
+/// ```markdown
+/// > This command is an alias of `grep -E`.
+///
+/// - View documentation for the original command:
+///
+/// `tldr grep`
+/// ```
+///
+/// The target page is taken from the `tldr` example. Without one, it is derived from the aliased
+/// command, leaving out its options.
+pub fn alias_page(reader: impl Read) -> Option<AliasPage> {
+    let mut command = None;
+    let mut target = None;
+    for line in LineIterator::new(BufReader::new(reader)) {
+        match line {
+            LineType::Description(text) if command.is_none() => {
+                let aliased = text.strip_prefix("This command is an alias of ")?;
+                let aliased = aliased.trim_end_matches('.').trim_matches('`');
+                command = Some(aliased.to_string());
+            }
+            LineType::ExampleCode(code) => {
+                if let Some(page) = code.strip_prefix("tldr ") {
+                    target = Some(page.split_whitespace().collect::<Vec<_>>().join("-"));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let command = command?;
+    let target = target.unwrap_or_else(|| {
+        command
+            .split_whitespace()
+            .take_while(|word| !word.starts_with('-'))
+            .collect::<Vec<_>>()
+            .join("-")
+    });
+    (!target.is_empty()).then(|| AliasPage {
+        command,
+        target: target.to_lowercase(),
+    })
+}
 
+/// Print the pages found by `--apropos` as `name - summary` lines.
+pub fn print_apropos_matches(matches: &[(String, String)], config: &Config) -> Result<()> {
+    let mut buffer = Vec::new();
+    for (page, summary) in matches {
+        print_snippet(
+            &mut buffer,
+            PageSnippet::CommandName(page),
+            &config.style,
+            None,
+        )?;
+        buffer.write_all(b" - ")?;
+        print_snippet(
+            &mut buffer,
+            PageSnippet::Description(summary),
+            &config.style,
+            None,
+        )?;
+        writeln!(buffer)?;
+    }
+
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    handle
+        .write_all(&buffer)
+        .context("Could not write to stdout")?;
     Ok(())
 }
 
+/// Print `snip` in its style. Occurrences of `highlight` in the examples are inverted.
 fn print_snippet(
     writer: &mut impl Write,
     snip: PageSnippet<&str>,
     style: &StyleConfig,
+    highlight: Option<&str>,
 ) -> io::Result<()> {
     use PageSnippet::*;
 
-    match snip {
-        CommandName(s) | Title(s) => write!(writer, "{}", s.paint(style.command_name)),
-        Variable(s) => write!(writer, "{}", s.paint(style.example_variable)),
-        NormalCode(s) => write!(writer, "{}", s.paint(style.example_code)),
-        Description(s) => write!(writer, "{}", s.paint(style.description)),
-        Text(s) => write!(writer, "{}", s.paint(style.example_text)),
-        Linebreak => writeln!(writer),
+    let (mut text, style, is_example) = match snip {
+        CommandName(s) => (s, style.command_name, true),
+        Title(s) => (s, style.title, false),
+        Variable(s) => (s, style.example_variable, true),
+        NormalCode(s) | InlineCode(s) => (s, style.example_code, true),
+        CommandOption(s) => (s, style.example_option, true),
+        Description(s) => (s, style.description, false),
+        Text(s) => (s, style.example_text, true),
+        Link(s) => (s, style.description.underline(), true),
+        Linebreak => return writeln!(writer),
+    };
+
+    if let Some(pattern) = highlight.filter(|pattern| is_example && !pattern.is_empty()) {
+        while let Some(start) = text.find_ignore_ascii_case(pattern) {
+            let end = start + pattern.len();
+            if start > 0 {
+                write!(writer, "{}", text[..start].paint(style))?;
+            }
+            write!(writer, "{}", text[start..end].paint(style.invert()))?;
+            text = &text[end..];
+        }
+    }
+    // Formatting through `paint` is comparatively slow, which adds up for long pages
+    if style == Style::new() {
+        return writer.write_all(text.as_bytes());
     }
+    write!(writer, "{}", text.paint(style))
 }
@@ -0,0 +1,225 @@
+//! Checking the GitHub releases for a new version of tealdeer, and updating the binary itself
+//! with `tldr --self-update`.
+//!
+//! Replacing the binary is only compiled with the `self-update` feature, because it is usually
+//! the job of a package manager.
+
+#[cfg(feature = "self-update")]
+use std::{env, fs, io::Write};
+
+#[cfg(feature = "self-update")]
+use anyhow::{anyhow, ensure};
+use anyhow::{bail, Context, Result};
+#[cfg(feature = "self-update")]
+use ring::digest;
+use serde_derive::Deserialize;
+use ureq::{http::StatusCode, Agent};
+
+/// The GitHub API endpoint for the latest release of tealdeer.
+const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/tealdeer-rs/tealdeer/releases/latest";
+
+/// The maximum size of a downloaded binary, which is a lot larger than the release binaries.
+#[cfg(feature = "self-update")]
+const MAX_BINARY_SIZE: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Deserialize)]
+pub struct Release {
+    tag_name: String,
+    #[cfg(feature = "self-update")]
+    assets: Vec<ReleaseAsset>,
+}
+
+#[cfg(feature = "self-update")]
+#[derive(Debug, Deserialize)]
+struct ReleaseAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+impl Release {
+    /// The version of the release, without the `v` prefix of the tag.
+    pub fn version(&self) -> &str {
+        self.tag_name.trim_start_matches('v')
+    }
+
+    #[cfg(feature = "self-update")]
+    fn asset_url(&self, name: &str) -> Option<&str> {
+        self.assets
+            .iter()
+            .find(|asset| asset.name == name)
+            .map(|asset| asset.browser_download_url.as_str())
+    }
+}
+
+/// Fetch the description of the latest release from GitHub.
+pub fn latest_release(client: &Agent) -> Result<Release> {
+    let response = client
+        .get(LATEST_RELEASE_URL)
+        .header("Accept", "application/vnd.github+json")
+        .call()
+        .context("Could not fetch the latest release of tealdeer")?;
+    if response.status() != StatusCode::OK {
+        bail!(
+            "Could not fetch the latest release of tealdeer: HTTP status {}",
+            response.status()
+        );
+    }
+    let body = response
+        .into_body()
+        .read_to_vec()
+        .context("Could not fetch the latest release of tealdeer")?;
+    serde_json::from_slice(&body).context("Invalid description of the latest release")
+}
+
+/// Whether `version` is newer than `current`. Both are compared by their numeric components, so
+/// that e.g. `1.10.0` is newer than `1.9.1`.
+pub fn is_newer(version: &str, current: &str) -> bool {
+    let components = |version: &str| -> Vec<u64> {
+        version
+            .split(['.', '-', '+'])
+            .map_while(|component| component.parse().ok())
+            .collect()
+    };
+    components(version) > components(current)
+}
+
+/// The name of the release binary for the platform tealdeer was built for.
+#[cfg(feature = "self-update")]
+fn binary_asset_name() -> Option<&'static str> {
+    if cfg!(all(target_os = "linux", target_arch = "x86_64")) {
+        Some("tealdeer-linux-x86_64-musl")
+    } else if cfg!(all(target_os = "linux", target_arch = "x86")) {
+        Some("tealdeer-linux-i686-musl")
+    } else if cfg!(all(target_os = "linux", target_arch = "aarch64")) {
+        Some("tealdeer-linux-aarch64-musl")
+    } else if cfg!(all(target_os = "macos", target_arch = "x86_64")) {
+        Some("tealdeer-macos-x86_64")
+    } else if cfg!(all(target_os = "macos", target_arch = "aarch64")) {
+        Some("tealdeer-macos-aarch64")
+    } else if cfg!(all(target_os = "windows", target_arch = "x86_64")) {
+        Some("tealdeer-windows-x86_64-msvc.exe")
+    } else {
+        None
+    }
+}
+
+/// Parse the hex-encoded SHA-256 checksum at the start of a checksum file in the format of
+/// `sha256sum`.
+#[cfg(feature = "self-update")]
+fn parse_checksum(content: &str) -> Option<Vec<u8>> {
+    let hex = content.split_whitespace().next()?;
+    if hex.len() != 64 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(feature = "self-update")]
+fn download(client: &Agent, url: &str) -> Result<Vec<u8>> {
+    let response = client
+        .get(url)
+        .call()
+        .with_context(|| format!("Could not download {url}"))?;
+    ensure!(
+        response.status() == StatusCode::OK,
+        "Could not download {url}: HTTP status {}",
+        response.status()
+    );
+    response
+        .into_body()
+        .with_config()
+        .limit(MAX_BINARY_SIZE)
+        .read_to_vec()
+        .with_context(|| format!("Could not download {url}"))
+}
+
+/// Download the binary of `release` for this platform, verify its checksum, and replace the
+/// running executable with it.
+#[cfg(feature = "self-update")]
+pub fn install_release(client: &Agent, release: &Release) -> Result<()> {
+    let name = binary_asset_name()
+        .ok_or_else(|| anyhow!("There are no release binaries for this platform."))?;
+    let binary_url = release
+        .asset_url(name)
+        .ok_or_else(|| anyhow!("The release {} has no binary {name}.", release.version()))?;
+    let checksum_name = format!("{name}.sha256");
+    let checksum_url = release.asset_url(&checksum_name).ok_or_else(|| {
+        anyhow!(
+            "The release {} has no checksum {checksum_name}.",
+            release.version()
+        )
+    })?;
+
+    let checksum = String::from_utf8(download(client, checksum_url)?)
+        .ok()
+        .as_deref()
+        .and_then(parse_checksum)
+        .ok_or_else(|| anyhow!("Invalid checksum file at {checksum_url}"))?;
+    let binary = download(client, binary_url)?;
+    ensure!(
+        digest::digest(&digest::SHA256, &binary).as_ref() == checksum.as_slice(),
+        "The checksum of the downloaded binary does not match, it was not installed."
+    );
+
+    // The new binary is written next to the executable, so that it can be moved into place
+    let executable = env::current_exe()
+        .and_then(fs::canonicalize)
+        .context("Could not determine the path of the tldr executable")?;
+    let directory = executable
+        .parent()
+        .context("Could not determine the directory of the tldr executable")?;
+    let mut file = tempfile::NamedTempFile::new_in(directory).with_context(|| {
+        format!(
+            "Could not create a temporary file in {}",
+            directory.display()
+        )
+    })?;
+    file.write_all(&binary)
+        .context("Could not write the new binary")?;
+    fs::set_permissions(file.path(), fs::metadata(&executable)?.permissions())
+        .context("Could not make the new binary executable")?;
+
+    // A running executable cannot be replaced on Windows, but it can be renamed
+    #[cfg(windows)]
+    {
+        let old = executable.with_extension("exe.old");
+        let _ = fs::remove_file(&old);
+        fs::rename(&executable, &old)
+            .with_context(|| format!("Could not move {} aside", executable.display()))?;
+    }
+    file.persist(&executable)
+        .with_context(|| format!("Could not replace {}", executable.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("1.8.0", "1.7.2"));
+        assert!(is_newer("1.10.0", "1.9.1"));
+        assert!(is_newer("2.0.0", "1.99.99"));
+        assert!(!is_newer("1.7.2", "1.7.2"));
+        assert!(!is_newer("1.7.1", "1.7.2"));
+        assert!(!is_newer("1.7.2-rc.1", "1.7.2"));
+    }
+
+    #[cfg(feature = "self-update")]
+    #[test]
+    fn test_parse_checksum() {
+        let hex = "00ff".repeat(16);
+        assert_eq!(
+            parse_checksum(&format!("{hex}  tealdeer-linux-x86_64-musl\n")),
+            Some([0x00, 0xff].repeat(16))
+        );
+        assert_eq!(parse_checksum("00ff  too-short"), None);
+        assert_eq!(parse_checksum(&"zz".repeat(32)), None);
+        assert_eq!(parse_checksum(""), None);
+    }
+}
@@ -0,0 +1,311 @@
+//! Serving the pages in the cache over HTTP with `tldr --serve-cache`, so that other machines on
+//! the network can use this one as a mirror by setting `updates.archive_source` to its address.
+//!
+//! This is a minimal HTTP/1.1 server, which only answers `GET` and `HEAD` requests for the zip
+//! archives of the languages in the cache. The archives have the same names and layout as the ones
+//! of the GitHub releases, and are created from the cache for every request. Signatures are not
+//! served, so clients must not set `updates.archive_public_key`. It is only compiled with the
+//! `serve` feature.
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    io::{BufRead, BufReader, Cursor, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use log::debug;
+use zip::{write::SimpleFileOptions, DateTime, ZipWriter};
+
+use crate::config::validate_language;
+
+/// How long reading a request or writing a response may stall before the connection is closed.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The maximum size of the request line and headers of a request.
+const MAX_REQUEST_SIZE: u64 = 8 * 1024;
+
+/// The maximum number of connections that are answered at the same time. Further connections are
+/// closed right away.
+const MAX_CONNECTIONS: usize = 32;
+
+/// Serve the archives of the pages in `pages_directory` at `address` until the process is
+/// stopped.
+pub fn serve(pages_directory: &Path, address: &str, quietly: bool) -> Result<()> {
+    let listener = TcpListener::bind(address)
+        .with_context(|| format!("Could not listen on address {address}"))?;
+    let local_address = listener.local_addr()?;
+    // The address is printed even with `--quiet`, as the port might have been chosen by the OS
+    println!("Serving the cache at http://{local_address}");
+    if !quietly {
+        eprintln!(
+            "To use it as a mirror, set `updates.archive_source` to this address on other \
+             machines. Press Ctrl+C to stop."
+        );
+    }
+
+    let connections = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                debug!("Could not accept connection: {e}");
+                continue;
+            }
+        };
+        if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            connections.fetch_sub(1, Ordering::SeqCst);
+            debug!("Too many connections, closing the new one");
+            continue;
+        }
+        let connections = Arc::clone(&connections);
+        let pages_directory = pages_directory.to_path_buf();
+        thread::spawn(move || {
+            if let Err(e) = handle_connection(&stream, &pages_directory, quietly) {
+                debug!("Could not answer request: {e:#}");
+            }
+            connections.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+    Ok(())
+}
+
+/// Answer a single request. The connection is closed afterwards.
+fn handle_connection(stream: &TcpStream, pages_directory: &Path, quietly: bool) -> Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut reader = BufReader::new(stream.take(MAX_REQUEST_SIZE));
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // The headers are not needed, but have to be read before answering. If the end of the
+    // headers isn't reached within the size limit, the request is rejected.
+    let mut complete = request_line.ends_with('\n');
+    let mut header = String::new();
+    while complete {
+        header.clear();
+        if reader.read_line(&mut header)? == 0 || !header.ends_with('\n') {
+            complete = false;
+        } else if header.trim_end().is_empty() {
+            break;
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (
+        parts.next().unwrap_or_default(),
+        parts.next().unwrap_or("/"),
+    );
+    let path = target.split('?').next().unwrap_or_default();
+    let response = match method {
+        _ if !complete => Response::BadRequest,
+        "GET" | "HEAD" if path.ends_with(".minisig") => Response::SignatureNotServed,
+        "GET" | "HEAD" => match archive_language(path) {
+            Some(language) => match language_archive(pages_directory, language)? {
+                Some(archive) => Response::Archive(archive),
+                None => Response::NotFound,
+            },
+            None => Response::NotFound,
+        },
+        _ => Response::MethodNotAllowed,
+    };
+    if !quietly {
+        eprintln!("{method} {path} {}", response.status());
+    }
+    response.write(&mut &*stream, method == "HEAD")?;
+    Ok(())
+}
+
+/// The language of the archive requested at `path`, e.g. `de` for `/tldr-pages.de.zip`.
+fn archive_language(path: &str) -> Option<&str> {
+    let language = path.strip_prefix("/tldr-pages.")?.strip_suffix(".zip")?;
+    validate_language(language).ok()?;
+    Some(language)
+}
+
+/// Create a zip archive of the pages for `language` in `pages_directory`, with one directory per
+/// platform. If there are no pages for the language, `Ok(None)` is returned.
+fn language_archive(pages_directory: &Path, language: &str) -> Result<Option<Vec<u8>>> {
+    let language_directory = pages_directory.join(format!("pages.{language}"));
+    if !language_directory.is_dir() {
+        return Ok(None);
+    }
+
+    // The modification times are fixed, so that the archive only changes with the pages
+    let options = SimpleFileOptions::default().last_modified_time(DateTime::default());
+    let mut archive = ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, path) in sorted_entries(&language_directory)? {
+        if path.is_dir() {
+            archive.add_directory(format!("{name}/"), options)?;
+            for (page_name, page_path) in sorted_entries(&path)? {
+                if page_path.is_file() {
+                    archive.start_file(format!("{name}/{page_name}"), options)?;
+                    archive.write_all(&fs::read(&page_path)?)?;
+                }
+            }
+        } else {
+            archive.start_file(name, options)?;
+            archive.write_all(&fs::read(&path)?)?;
+        }
+    }
+    Ok(Some(archive.finish()?.into_inner()))
+}
+
+/// The names and paths of the entries of `directory`, sorted by name.
+fn sorted_entries(directory: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let mut entries = fs::read_dir(directory)
+        .with_context(|| format!("Could not read {}", directory.display()))?
+        .map(|entry| {
+            let entry = entry?;
+            Ok((
+                entry.file_name().to_string_lossy().into_owned(),
+                entry.path(),
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    entries.sort();
+    Ok(entries)
+}
+
+enum Response {
+    Archive(Vec<u8>),
+    BadRequest,
+    NotFound,
+    /// The archives are created from the cache, so there are no signatures of them.
+    SignatureNotServed,
+    MethodNotAllowed,
+}
+
+impl Response {
+    fn status(&self) -> &'static str {
+        match self {
+            Self::Archive(_) => "200 OK",
+            Self::BadRequest => "400 Bad Request",
+            Self::NotFound | Self::SignatureNotServed => "404 Not Found",
+            Self::MethodNotAllowed => "405 Method Not Allowed",
+        }
+    }
+
+    /// Write the response to `writer`, without the body for `HEAD` requests. The `ETag` of
+    /// archives is a hash of their content, so that `tldr --dry-run-update` works with mirrors.
+    fn write(&self, writer: &mut impl Write, head: bool) -> Result<()> {
+        let body: &[u8] = match self {
+            Self::Archive(archive) => archive,
+            Self::SignatureNotServed => {
+                b"This mirror does not serve signatures, unset `updates.archive_public_key` to use it.\n"
+            }
+            Self::BadRequest | Self::NotFound | Self::MethodNotAllowed => b"",
+        };
+        write!(
+            writer,
+            "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            self.status(),
+            body.len(),
+        )?;
+        if let Self::Archive(archive) = self {
+            let mut hasher = DefaultHasher::new();
+            archive.hash(&mut hasher);
+            write!(
+                writer,
+                "Content-Type: application/zip\r\nETag: \"{:016x}\"\r\n",
+                hasher.finish(),
+            )?;
+        }
+        writer.write_all(b"\r\n")?;
+        if !head {
+            writer.write_all(body)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use zip::ZipArchive;
+
+    use super::*;
+
+    #[test]
+    fn test_archive_language() {
+        assert_eq!(archive_language("/tldr-pages.en.zip"), Some("en"));
+        assert_eq!(archive_language("/tldr-pages.pt_BR.zip"), Some("pt_BR"));
+        assert_eq!(archive_language("/tldr-pages.en.tar.gz"), None);
+        assert_eq!(archive_language("/tldr-pages.../etc.zip"), None);
+        assert_eq!(archive_language("/index.json"), None);
+    }
+
+    /// Send `request` to [`handle_connection`] and return the response.
+    fn respond(pages_directory: &Path, request: &[u8]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        client.write_all(request).unwrap();
+        handle_connection(&server, pages_directory, true).unwrap();
+        drop(server);
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_handle_connection() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("pages.en").join("common")).unwrap();
+
+        let response = respond(dir.path(), b"HEAD /tldr-pages.en.zip HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+
+        let response = respond(
+            dir.path(),
+            b"GET /tldr-pages.en.zip.minisig HTTP/1.1\r\n\r\n",
+        );
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        assert!(response.ends_with("unset `updates.archive_public_key` to use it.\n"));
+
+        // Requests whose headers don't end within the size limit are rejected
+        let mut request = b"GET /tldr-pages.en.zip HTTP/1.1\r\nX-Long: ".to_vec();
+        request.resize(usize::try_from(MAX_REQUEST_SIZE).unwrap(), b'a');
+        let response = respond(dir.path(), &request);
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+    }
+
+    #[test]
+    fn test_language_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let common = dir.path().join("pages.en").join("common");
+        fs::create_dir_all(&common).unwrap();
+        fs::write(common.join("tar.md"), "# tar\n").unwrap();
+        fs::write(dir.path().join("pages.en").join("LICENSE.md"), "License").unwrap();
+
+        let archive = language_archive(dir.path(), "en").unwrap().unwrap();
+        // Archives of the same pages are identical
+        assert_eq!(
+            language_archive(dir.path(), "en").unwrap().unwrap(),
+            archive
+        );
+        assert!(language_archive(dir.path(), "de").unwrap().is_none());
+
+        let mut archive = ZipArchive::new(Cursor::new(archive)).unwrap();
+        let mut names: Vec<_> = archive.file_names().map(str::to_string).collect();
+        names.sort();
+        assert_eq!(names, ["LICENSE.md", "common/", "common/tar.md"]);
+        let mut page = String::new();
+        archive
+            .by_name("common/tar.md")
+            .unwrap()
+            .read_to_string(&mut page)
+            .unwrap();
+        assert_eq!(page, "# tar\n");
+    }
+}
@@ -0,0 +1,195 @@
+//! Adaptation of example code, which is written for POSIX shells, to other shells.
+
+use std::borrow::Cow;
+
+use crate::config::ExampleShell;
+
+/// Constructs that only POSIX shells understand, which are not adapted.
+const FISH_INCOMPATIBLE: &[&str] = &["<(", "<<", "$((", "${", "; do ", "; then ", "; fi"];
+const POWERSHELL_INCOMPATIBLE: &[&str] =
+    &["<(", "<<", "$((", "${", "; do ", "; then ", "; fi", "`"];
+
+/// Example code adapted to a shell.
+#[derive(Debug, PartialEq, Eq)]
+pub struct AdaptedCode<'a> {
+    pub code: Cow<'a, str>,
+    /// Whether the code uses syntax that could not be adapted to the shell.
+    pub incompatible: bool,
+}
+
+/// Adapt the syntax of `code` to `shell` where that is possible: variable assignments, `export`
+/// and line continuations are rewritten. Other syntax that does not work in `shell` is only
+/// detected.
+pub fn adapt_to_shell(code: &str, shell: ExampleShell) -> AdaptedCode<'_> {
+    let incompatible_syntax = match shell {
+        ExampleShell::Sh => {
+            return AdaptedCode {
+                code: Cow::Borrowed(code),
+                incompatible: false,
+            }
+        }
+        ExampleShell::Fish => FISH_INCOMPATIBLE,
+        ExampleShell::Powershell => POWERSHELL_INCOMPATIBLE,
+    };
+    let incompatible = incompatible_syntax
+        .iter()
+        .any(|syntax| code.contains(syntax));
+
+    let (exported, rest) = match code.strip_prefix("export ") {
+        Some(rest) => (true, rest),
+        None => (false, code),
+    };
+    let mut assignments = Vec::new();
+    let mut rest = rest;
+    while let Some((assignment, after)) = split_assignment(rest) {
+        assignments.push(assignment);
+        rest = after.trim_start();
+    }
+
+    let code = match shell {
+        ExampleShell::Sh => unreachable!("Handled above"),
+        // Fish understands `VAR=value command` and `export`, but not plain assignments
+        ExampleShell::Fish => match assignments[..] {
+            [(name, value)] if rest.is_empty() && !exported => {
+                Cow::Owned(format!("set {name} {value}"))
+            }
+            _ => Cow::Borrowed(code),
+        },
+        ExampleShell::Powershell => {
+            let mut adapted = String::new();
+            if assignments.is_empty() || (exported && !rest.is_empty()) {
+                adapted.push_str(code);
+            } else {
+                // Variables set for a single command become env variables of the session
+                let prefix = if exported || !rest.is_empty() {
+                    "$env:"
+                } else {
+                    "$"
+                };
+                let statements: Vec<String> = assignments
+                    .iter()
+                    .map(|(name, value)| format!("{prefix}{name} = {}", quote(value)))
+                    .collect();
+                adapted.push_str(&statements.join("; "));
+                if !rest.is_empty() {
+                    adapted.push_str("; ");
+                    adapted.push_str(rest);
+                }
+            }
+            match adapted.strip_suffix(" \\") {
+                Some(continued) => Cow::Owned(format!("{continued} `")),
+                None if adapted == code => Cow::Borrowed(code),
+                None => Cow::Owned(adapted),
+            }
+        }
+    };
+
+    AdaptedCode { code, incompatible }
+}
+
+/// Split a variable assignment (`NAME=value`) from the start of `code`, returning the name, the
+/// value and the code after it. Placeholders and quoted strings in the value may contain spaces.
+fn split_assignment(code: &str) -> Option<((&str, &str), &str)> {
+    let (name, rest) = code.split_once('=')?;
+    let is_name = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !is_name {
+        return None;
+    }
+
+    let mut end = 0;
+    while let Some(c) = rest[end..].chars().next() {
+        let skip_to = |marker: &str, start: usize| {
+            rest[start..]
+                .find(marker)
+                .map_or(rest.len(), |i| start + i + marker.len())
+        };
+        end = if rest[end..].starts_with("{{") {
+            skip_to("}}", end + 2)
+        } else if c == '"' || c == '\'' {
+            skip_to(&c.to_string(), end + 1)
+        } else if c.is_whitespace() {
+            break;
+        } else {
+            end + c.len_utf8()
+        };
+    }
+    Some(((name, &rest[..end]), &rest[end..]))
+}
+
+/// Quote `value` for PowerShell, unless it is quoted already.
+fn quote(value: &str) -> Cow<'_, str> {
+    if value.starts_with(['"', '\'']) {
+        Cow::Borrowed(value)
+    } else {
+        Cow::Owned(format!("\"{value}\""))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn adapt(code: &str, shell: ExampleShell) -> (String, bool) {
+        let adapted = adapt_to_shell(code, shell);
+        (adapted.code.into_owned(), adapted.incompatible)
+    }
+
+    #[test]
+    fn sh_is_unchanged() {
+        let code = "FOO=bar cmd <(ls)";
+        assert_eq!(adapt(code, ExampleShell::Sh), (code.to_string(), false));
+    }
+
+    #[test]
+    fn fish() {
+        assert_eq!(
+            adapt("EDITOR={{vim}}", ExampleShell::Fish),
+            ("set EDITOR {{vim}}".to_string(), false)
+        );
+        assert_eq!(
+            adapt("LANG=C sort {{file}}", ExampleShell::Fish),
+            ("LANG=C sort {{file}}".to_string(), false)
+        );
+        assert_eq!(
+            adapt("export PATH={{path}}", ExampleShell::Fish),
+            ("export PATH={{path}}".to_string(), false)
+        );
+        assert!(adapt("diff <(ls {{dir1}}) <(ls {{dir2}})", ExampleShell::Fish).1);
+    }
+
+    #[test]
+    fn powershell() {
+        assert_eq!(
+            adapt(
+                "LANG=C DEBUG=\"a b\" sort {{file}}",
+                ExampleShell::Powershell
+            ),
+            (
+                "$env:LANG = \"C\"; $env:DEBUG = \"a b\"; sort {{file}}".to_string(),
+                false
+            )
+        );
+        assert_eq!(
+            adapt("export PATH={{path/to/dir}}", ExampleShell::Powershell),
+            ("$env:PATH = \"{{path/to/dir}}\"".to_string(), false)
+        );
+        assert_eq!(
+            adapt("name={{some value}}", ExampleShell::Powershell),
+            ("$name = \"{{some value}}\"".to_string(), false)
+        );
+        assert_eq!(
+            adapt("make all \\", ExampleShell::Powershell),
+            ("make all `".to_string(), false)
+        );
+        assert_eq!(
+            adapt("cat << EOF", ExampleShell::Powershell),
+            ("cat << EOF".to_string(), true)
+        );
+        // Options with values are not assignments
+        assert_eq!(
+            adapt("dd if={{file}} of={{device}}", ExampleShell::Powershell),
+            ("dd if={{file}} of={{device}}".to_string(), false)
+        );
+    }
+}
@@ -0,0 +1,104 @@
+//! Expansion of template variables in custom pages and patches, see `display.expand_templates`.
+//!
+//! Variables are written in single braces, like `{hostname}` or `{env:HOME}`, so that they can't
+//! be confused with the `{{placeholders}}` of tldr pages. Unknown variables and env variables
+//! that aren't set are left as they are.
+
+use std::{borrow::Cow, env, fs};
+
+/// Expand the template variables in `text`.
+pub fn expand_templates(text: &str) -> Cow<'_, str> {
+    if !text.contains('{') {
+        return Cow::Borrowed(text);
+    }
+
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        let (before, from_brace) = rest.split_at(start);
+        expanded.push_str(before);
+
+        // Placeholders (`{{...}}`) and escaped braces (`\{`) are skipped as a whole
+        let escaped = before.ends_with('\\');
+        if from_brace.starts_with("{{") || escaped {
+            let skip = from_brace
+                .find("}}")
+                .filter(|_| !escaped)
+                .map_or(1, |end| end + 2);
+            expanded.push_str(&from_brace[..skip]);
+            rest = &from_brace[skip..];
+            continue;
+        }
+
+        let value = from_brace[1..]
+            .find('}')
+            .and_then(|end| Some((end, variable_value(&from_brace[1..=end])?)));
+        if let Some((end, value)) = value {
+            expanded.push_str(&value);
+            rest = &from_brace[end + 2..];
+        } else {
+            expanded.push('{');
+            rest = &from_brace[1..];
+        }
+    }
+    expanded.push_str(rest);
+    Cow::Owned(expanded)
+}
+
+/// The value of the template variable `name`, if it is known.
+fn variable_value(name: &str) -> Option<String> {
+    if let Some(variable) = name.strip_prefix("env:") {
+        return env::var(variable).ok();
+    }
+    match name {
+        "hostname" => hostname(),
+        _ => None,
+    }
+}
+
+/// The name of this machine. Windows sets `COMPUTERNAME`, Linux has the hostname in `/proc`, other
+/// systems usually have it in `/etc/hostname`.
+fn hostname() -> Option<String> {
+    env::var("COMPUTERNAME")
+        .ok()
+        .or_else(|| fs::read_to_string("/proc/sys/kernel/hostname").ok())
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|hostname| hostname.trim().to_string())
+        .filter(|hostname| !hostname.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_variables() {
+        let path = env::var("PATH").unwrap();
+        assert_eq!(expand_templates("`ls {env:PATH}`"), format!("`ls {path}`"));
+        assert_eq!(
+            expand_templates("`ls {env:TEALDEER_TEST_UNSET_VARIABLE}`"),
+            "`ls {env:TEALDEER_TEST_UNSET_VARIABLE}`"
+        );
+    }
+
+    #[test]
+    fn placeholders_are_kept() {
+        for text in [
+            "`cp {{path/to/file}} {{env:HOME}}`",
+            "`echo \\{\\{hostname\\}\\}`",
+            "`awk '{print $1}'`",
+            "`echo {unknown} {`",
+            "no braces",
+        ] {
+            assert_eq!(expand_templates(text), text);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn hostname_is_expanded() {
+        let expanded = expand_templates("`ssh {hostname}`");
+        assert!(!expanded.contains("{hostname}"));
+        assert!(!expanded.ends_with(" `"));
+    }
+}
@@ -0,0 +1,72 @@
+//! Timing diagnostics, printed with the hidden `--timings` flag.
+
+use std::time::{Duration, Instant};
+
+/// Collects the time spent in the different phases of a run.
+#[derive(Debug)]
+pub struct Timings {
+    /// Whether the timings are printed at the end of the run.
+    enabled: bool,
+    /// When the run was started.
+    start: Instant,
+    /// The recorded phases, in the order they were recorded.
+    phases: Vec<(&'static str, Duration)>,
+}
+
+impl Timings {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            start: Instant::now(),
+            phases: Vec::new(),
+        }
+    }
+
+    /// Record that `duration` was spent in `phase`.
+    pub fn record(&mut self, phase: &'static str, duration: Duration) {
+        self.phases.push((phase, duration));
+    }
+
+    /// Run `f` and record the time it took as `phase`.
+    pub fn measure<T>(&mut self, phase: &'static str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.record(phase, start.elapsed());
+        result
+    }
+
+    /// Print the recorded phases and the total time to stderr, if enabled.
+    pub fn print(&self) {
+        if !self.enabled {
+            return;
+        }
+        eprintln!("Timings:");
+        for (phase, duration) in &self.phases {
+            eprintln!("  {:<14}{duration:>12.3?}", format!("{phase}:"));
+        }
+        eprintln!("  {:<14}{:>12.3?}", "total:", self.start.elapsed());
+    }
+}
+
+/// An iterator adapter that adds the time spent in `next` of the inner iterator to `elapsed`.
+pub struct TimedIter<'a, I> {
+    inner: I,
+    elapsed: &'a mut Duration,
+}
+
+impl<'a, I> TimedIter<'a, I> {
+    pub fn new(inner: I, elapsed: &'a mut Duration) -> Self {
+        Self { inner, elapsed }
+    }
+}
+
+impl<I: Iterator> Iterator for TimedIter<'_, I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = Instant::now();
+        let item = self.inner.next();
+        *self.elapsed += start.elapsed();
+        item
+    }
+}
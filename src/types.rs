@@ -1,10 +1,12 @@
 //! Shared types used in tealdeer.
 
-use std::{fmt, str};
+use std::{error::Error, fmt, io, process::ExitCode, str};
 
 use serde_derive::{Deserialize, Serialize};
 
-#[derive(Debug, Eq, PartialEq, Copy, Clone, Serialize, Deserialize)]
+use crate::extensions::Dedup;
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 #[allow(dead_code)]
 pub enum PlatformType {
@@ -116,7 +118,62 @@ impl PlatformType {
     }
 }
 
-#[derive(Debug, Eq, PartialEq, Copy, Clone, Deserialize, clap::ValueEnum)]
+/// A platform passed to `--platform`, or the pseudo-platform `all`.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum PlatformArg {
+    All,
+    Platform(PlatformType),
+}
+
+impl PlatformArg {
+    /// Resolve the platforms in order of preference. `all` expands to the current platform,
+    /// followed by the other platforms and `common`.
+    pub fn flatten(args: &[Self]) -> Vec<PlatformType> {
+        let mut platforms = Vec::new();
+        for arg in args {
+            match arg {
+                Self::All => {
+                    platforms.push(PlatformType::current());
+                    platforms.extend(
+                        <PlatformType as clap::ValueEnum>::value_variants()
+                            .iter()
+                            .filter(|platform| **platform != PlatformType::Common),
+                    );
+                    platforms.push(PlatformType::Common);
+                }
+                Self::Platform(platform) => platforms.push(*platform),
+            }
+        }
+        platforms.clear_duplicates();
+        platforms
+    }
+}
+
+impl clap::ValueEnum for PlatformArg {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[
+            Self::Platform(PlatformType::Linux),
+            Self::Platform(PlatformType::OsX),
+            Self::Platform(PlatformType::SunOs),
+            Self::Platform(PlatformType::Windows),
+            Self::Platform(PlatformType::Android),
+            Self::Platform(PlatformType::FreeBsd),
+            Self::Platform(PlatformType::NetBsd),
+            Self::Platform(PlatformType::OpenBsd),
+            Self::Platform(PlatformType::Common),
+            Self::All,
+        ]
+    }
+
+    fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+        match self {
+            Self::All => Some(clap::builder::PossibleValue::new("all")),
+            Self::Platform(platform) => clap::ValueEnum::to_possible_value(platform),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Serialize, Deserialize, clap::ValueEnum)]
 #[serde(rename_all = "lowercase")]
 #[derive(Default)]
 pub enum ColorOptions {
@@ -126,6 +183,33 @@ pub enum ColorOptions {
     Never,
 }
 
+/// The format of reports, selected with `--output`. `Tokens` is the format of pages instead.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, clap::ValueEnum)]
+pub enum ReportFormat {
+    Text,
+    Json,
+    Tokens,
+}
+
+/// A shell for which `tldr --print-hook` can print a hook.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, clap::ValueEnum)]
+pub enum HookShell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// A shell for which `tldr --completions` can print a completion script.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, clap::ValueEnum)]
+pub enum CompletionShell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+    Elvish,
+    Nushell,
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub enum LineType {
     Empty,
@@ -193,7 +277,8 @@ impl LineType {
 }
 
 /// The reason why a certain path (e.g. config path or cache dir) was chosen.
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum PathSource {
     /// OS convention (e.g. XDG on Linux)
     OsConvention,
@@ -246,3 +331,48 @@ mod test {
         );
     }
 }
+
+/// The reason why tealdeer exits, which determines its exit code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitReason {
+    Success,
+    /// Any failure not covered by the other variants.
+    Failure,
+    /// The requested page was not found.
+    PageNotFound,
+    /// Reading or writing a file failed.
+    IoError,
+    /// Downloading pages failed.
+    NetworkError,
+}
+
+impl ExitReason {
+    /// Determine the exit reason for an error returned while running tealdeer.
+    pub fn from_error(error: &anyhow::Error) -> Self {
+        if error.chain().any(<dyn Error>::is::<ureq::Error>) {
+            Self::NetworkError
+        } else if error.chain().any(<dyn Error>::is::<io::Error>) {
+            Self::IoError
+        } else {
+            Self::Failure
+        }
+    }
+
+    /// The exit code for this reason. Note that the exit code 2 is used for invalid command
+    /// line arguments.
+    pub const fn code(self) -> u8 {
+        match self {
+            Self::Success => 0,
+            Self::Failure => 1,
+            Self::PageNotFound => 3,
+            Self::IoError => 4,
+            Self::NetworkError => 5,
+        }
+    }
+}
+
+impl From<ExitReason> for ExitCode {
+    fn from(reason: ExitReason) -> Self {
+        Self::from(reason.code())
+    }
+}
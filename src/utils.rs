@@ -1,21 +1,205 @@
+use std::{
+    env, fs,
+    io::{self, Write},
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use terminal_size::Width;
 use yansi::{Color, Paint};
 
-/// Print a warning to stderr. If `enable_styles` is true, then a yellow
-/// message will be printed.
+use crate::i18n::Message;
+
+/// Whether warnings are printed, see [`set_warnings_enabled`].
+static WARNINGS_ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Enable or disable printing warnings with [`print_warning`] (used for `--quiet`).
+pub fn set_warnings_enabled(enabled: bool) {
+    WARNINGS_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Print a warning to stderr, unless warnings were disabled. If `enable_styles`
+/// is true, then a yellow message will be printed.
 pub fn print_warning(enable_styles: bool, message: &str) {
-    print_msg(enable_styles, message, "Warning: ", Color::Yellow);
+    if !WARNINGS_ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    print_msg(
+        enable_styles,
+        message,
+        Message::WarningPrefix,
+        Color::Yellow,
+    );
 }
 
 /// Print an anyhow error to stderr. If `enable_styles` is true, then a red
 /// message will be printed.
 pub fn print_error(enable_styles: bool, error: &anyhow::Error) {
-    print_msg(enable_styles, &format!("{error:?}"), "Error: ", Color::Red);
+    print_msg(
+        enable_styles,
+        &format!("{error:?}"),
+        Message::ErrorPrefix,
+        Color::Red,
+    );
 }
 
-fn print_msg(enable_styles: bool, message: &str, prefix: &'static str, color: Color) {
+fn print_msg(enable_styles: bool, message: &str, prefix: Message, color: Color) {
     if enable_styles {
-        eprintln!("{}{}", prefix.paint(color), message.paint(color));
+        eprintln!(
+            "{}{}",
+            prefix.to_string().paint(color),
+            message.paint(color)
+        );
     } else {
         eprintln!("{message}");
     }
 }
+
+/// The width of the terminal (or of the console on Windows) in columns. The `COLUMNS` environment
+/// variable takes precedence, like for other command line tools.
+pub fn terminal_width() -> Option<usize> {
+    env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .or_else(|| terminal_size::terminal_size().map(|(Width(width), _)| usize::from(width)))
+        .filter(|&width| width > 0)
+}
+
+/// Ask the user a yes/no question on stderr and read the answer from stdin. Anything but an
+/// explicit "yes" is treated as "no".
+pub fn confirm(question: &str) -> io::Result<bool> {
+    eprint!("{question} [y/N] ");
+    io::stderr().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Ask the user for a value on stderr and read the answer from stdin. If the answer is empty,
+/// `default` is returned.
+pub fn prompt(question: &str, default: &str) -> io::Result<String> {
+    eprint!("{question} [{default}] ");
+    io::stderr().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    let answer = answer.trim();
+    Ok(if answer.is_empty() { default } else { answer }.to_string())
+}
+
+/// Whether tealdeer runs in Termux, the terminal emulator for Android. Termux sets
+/// `TERMUX_VERSION`, and its `PREFIX` lies in the data directory of the app.
+pub fn is_termux() -> bool {
+    env::var_os("TERMUX_VERSION").is_some()
+        || env::var("PREFIX").is_ok_and(|prefix| prefix.starts_with("/data/data/com.termux/"))
+}
+
+/// Write `contents` to the file at `path` atomically: they are written to a temporary file in the
+/// same directory first, which then replaces the file. Concurrent invocations or a crash therefore
+/// never leave a truncated file behind. The permissions of an existing file are kept, new files
+/// get the same permissions as with [`fs::write`].
+///
+/// If `path` is a symlink (e.g. a config file in a dotfiles repository), the file it points to is
+/// replaced instead of the symlink.
+pub fn write_atomically(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let path = match fs::canonicalize(path) {
+        Ok(path) => path,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => path.to_path_buf(),
+        Err(e) => return Err(e),
+    };
+    let path = path.as_path();
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let mut builder = tempfile::Builder::new();
+    // Temporary files are only accessible by the owner by default. Like with `fs::write`, the
+    // umask is applied to these permissions.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        builder.permissions(fs::Permissions::from_mode(0o666));
+    }
+    let mut file = builder.tempfile_in(directory)?;
+    file.write_all(contents.as_ref())?;
+    if let Ok(metadata) = fs::metadata(path) {
+        file.as_file().set_permissions(metadata.permissions())?;
+    }
+    file.as_file().sync_all()?;
+    file.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+/// Whether an executable called `name` is in one of the directories of `PATH`.
+pub fn executable_exists(name: &str) -> bool {
+    env::var_os("PATH")
+        .is_some_and(|path| env::split_paths(&path).any(|dir| dir.join(name).is_file()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_atomically() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state");
+        write_atomically(&path, "first").unwrap();
+        write_atomically(&path, "second").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        // No temporary files are left behind
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomically_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        write_atomically(&path, "new").unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomically_follows_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let dotfiles = dir.path().join("dotfiles");
+        fs::create_dir(&dotfiles).unwrap();
+        let target = dotfiles.join("config.toml");
+        fs::write(&target, "old").unwrap();
+        let link = dir.path().join("config.toml");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        write_atomically(&link, "new").unwrap();
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "new");
+        // The temporary file was created next to the target
+        assert_eq!(fs::read_dir(&dotfiles).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomically_default_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let expected_path = dir.path().join("expected");
+        fs::write(&expected_path, "").unwrap();
+        let path = dir.path().join("state");
+        write_atomically(&path, "new").unwrap();
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode(),
+            fs::metadata(&expected_path).unwrap().permissions().mode()
+        );
+    }
+}
@@ -2,9 +2,10 @@
 
 use std::{
     fs::{self, create_dir_all, File},
-    io::{self, Write},
+    io::{self, BufRead, Write},
     path::{Path, PathBuf},
-    process::Command,
+    process::{Command, Stdio},
+    thread,
     time::{Duration, SystemTime},
 };
 
@@ -55,10 +56,45 @@ impl TestEnv {
         self._test_dir.path().join(".config")
     }
 
+    fn system_config_dir(&self) -> PathBuf {
+        self._test_dir.path().join(".system_config")
+    }
+
     fn custom_pages_dir(&self) -> PathBuf {
         self._test_dir.path().join(".custom_pages")
     }
 
+    fn state_dir(&self) -> PathBuf {
+        self._test_dir.path().join(".state")
+    }
+
+    /// The state directory of the tldr pages in the cache, named after a FNV-1a hash of the path
+    /// of the pages directory.
+    fn cache_state_dir(&self) -> PathBuf {
+        let pages_dir = self.cache_dir().join(TLDR_PAGES_DIR);
+        let hash = pages_dir
+            .as_os_str()
+            .as_encoded_bytes()
+            .iter()
+            .fold(0xcbf2_9ce4_8422_2325_u64, |hash, &byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+            });
+        self.state_dir().join("caches").join(format!("{hash:016x}"))
+    }
+
+    fn set_last_update(&self, time: SystemTime) {
+        create_dir_all(self.cache_state_dir()).unwrap();
+        let seconds = time
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        fs::write(
+            self.cache_state_dir().join("last_update"),
+            seconds.to_string(),
+        )
+        .unwrap();
+    }
+
     fn append_to_config(&self, content: impl AsRef<str>) {
         File::options()
             .create(true)
@@ -68,13 +104,24 @@ impl TestEnv {
             .write_all(content.as_ref().as_bytes())
             .expect("Failed to append to config file.");
     }
+    fn append_to_system_config(&self, content: impl AsRef<str>) {
+        create_dir_all(self.system_config_dir()).unwrap();
+        File::options()
+            .create(true)
+            .append(true)
+            .open(self.system_config_dir().join("config.toml"))
+            .expect("Failed to open system config file")
+            .write_all(content.as_ref().as_bytes())
+            .expect("Failed to append to system config file.");
+    }
     fn delete_config(&self) {
         fs::remove_file(self.config_dir().join("config.toml")).unwrap();
     }
     fn init_config(&self) {
         self.append_to_config(format!(
-            "directories.cache_dir = '{}'\n",
+            "directories.cache_dir = '{}'\ndirectories.state_dir = '{}'\n",
             self.cache_dir().to_str().unwrap(),
+            self.state_dir().to_str().unwrap(),
         ));
     }
 
@@ -175,14 +222,25 @@ impl TestEnv {
         let relevant_env_variables = [
             "LANG",
             "LANGUAGE",
+            "LC_ALL",
+            "LC_MESSAGES",
             "TEALDEER_CACHE_DIR",
             "EDITOR",
+            "VISUAL",
             "NO_COLOR",
+            "COLORFGBG",
+            "TERM",
+            "COLORTERM",
         ];
         for variable_name in relevant_env_variables {
             cmd.env_remove(variable_name);
         }
         cmd.env("TEALDEER_CONFIG_DIR", self.config_dir().to_str().unwrap());
+        cmd.env("XDG_STATE_HOME", self.state_dir().to_str().unwrap());
+        cmd.env(
+            "TEALDEER_SYSTEM_CONFIG_DIR",
+            self.system_config_dir().to_str().unwrap(),
+        );
         cmd
     }
 
@@ -236,6 +294,112 @@ fn test_cannot_build_without_tls_feature() {
     let _ = TestEnv::new().no_default_features().command();
 }
 
+#[test]
+fn test_style_background() {
+    let testenv = TestEnv::new().install_default_cache();
+
+    // Blue (34) instead of cyan (36) on light backgrounds
+    testenv
+        .command()
+        .env("COLORFGBG", "0;15")
+        .args(["--color", "always", "which"])
+        .assert()
+        .success()
+        .stdout(contains("\x1b[34m").and(contains("\x1b[36m").not()));
+
+    testenv
+        .command()
+        .env("COLORFGBG", "0;15")
+        .args([
+            "--color",
+            "always",
+            "--set",
+            "style.background='dark'",
+            "which",
+        ])
+        .assert()
+        .success()
+        .stdout(contains("\x1b[36m").and(contains("\x1b[34m").not()));
+}
+
+#[test]
+fn test_style_color_depth() {
+    let testenv = TestEnv::new().install_default_cache();
+    testenv.append_to_config(
+        "[style.command_name]\nforeground = { rgb = { r = 250, g = 10, b = 10 } }\n",
+    );
+
+    testenv
+        .command()
+        .env("TERM", "xterm-256color")
+        .env("COLORTERM", "truecolor")
+        .args(["--color", "always", "which"])
+        .assert()
+        .success()
+        .stdout(contains("\x1b[38;2;250;10;10m"));
+
+    testenv
+        .command()
+        .env("TERM", "xterm-256color")
+        .args(["--color", "always", "which"])
+        .assert()
+        .success()
+        .stdout(contains("\x1b[38;5;196m"));
+
+    // Bright red on terminals with basic colors
+    testenv
+        .command()
+        .env("TERM", "linux")
+        .args(["--color", "always", "which"])
+        .assert()
+        .success()
+        .stdout(contains("\x1b[91m").and(contains("38;2").not()));
+
+    testenv
+        .command()
+        .env("TERM", "linux")
+        .args([
+            "--color",
+            "always",
+            "--set",
+            "style.color_depth='256'",
+            "which",
+        ])
+        .assert()
+        .success()
+        .stdout(contains("\x1b[38;5;196m"));
+}
+
+#[test]
+fn test_style_inheritance() {
+    let testenv = TestEnv::new().install_default_cache();
+    testenv.append_to_config(
+        "[style.example_code]\nforeground = 'red'\nbold = true\n\
+         [style.example_variable]\nbase = 'example_code'\nbold = false\n\
+         [style.example_text]\nforeground = 'green'\nenabled = false\n",
+    );
+
+    testenv
+        .command()
+        .args(["--color", "always", "which"])
+        .assert()
+        .success()
+        .stdout(contains("\x1b[1;31m -a "))
+        .stdout(contains("\x1b[31mexecutable"))
+        .stdout(contains("\x1b[32m").not());
+
+    testenv
+        .command()
+        .args([
+            "--set",
+            "style.example_code.base='example_variable'",
+            "which",
+        ])
+        .assert()
+        .failure()
+        .stderr(contains("is based on itself"));
+}
+
 #[test]
 fn test_load_the_correct_config() {
     let testenv = TestEnv::new()
@@ -333,6 +497,270 @@ fn test_update_cache_default_features() {
     testenv.command().args(["sl"]).assert().success();
 }
 
+#[test]
+fn test_dry_run_update_without_cache() {
+    let testenv = TestEnv::new();
+
+    testenv
+        .command()
+        .arg("--dry-run-update")
+        .assert()
+        .failure()
+        .stdout(contains("The cache does not exist yet."));
+}
+
+#[test]
+fn test_cache_without_pages() {
+    let testenv = TestEnv::new();
+    fs::create_dir_all(testenv.cache_dir().join(TLDR_PAGES_DIR)).unwrap();
+
+    for args in [&["which"][..], &["--list"]] {
+        testenv
+            .command()
+            .args(args)
+            .assert()
+            .failure()
+            .stderr(contains(
+                "does not contain any pages, it might be corrupted.",
+            ))
+            .stderr(contains("Page `which` not found in cache.").not());
+    }
+}
+
+#[test]
+fn test_check_cache() {
+    let testenv = TestEnv::new();
+    testenv.append_to_config("updates.download_languages = ['en']\n");
+
+    testenv
+        .command()
+        .arg("--check-cache")
+        .assert()
+        .failure()
+        .stdout(contains("The cache does not exist yet."));
+
+    let testenv = testenv.install_default_cache();
+    testenv
+        .command()
+        .arg("--check-cache")
+        .assert()
+        .success()
+        .stdout(contains("No problems found in the cache."));
+
+    // An empty page is left behind by an interrupted update
+    fs::write(
+        testenv
+            .cache_dir()
+            .join(TLDR_PAGES_DIR)
+            .join("pages.en")
+            .join("common")
+            .join("which.md"),
+        "",
+    )
+    .unwrap();
+    assert_cmd::Command::from_std(testenv.command())
+        .arg("--check-cache")
+        .write_stdin("n\n")
+        .assert()
+        .failure()
+        .stdout(contains("en: 1 page(s) are empty"));
+
+    fs::remove_dir_all(testenv.cache_dir().join(TLDR_PAGES_DIR).join("pages.en")).unwrap();
+    assert_cmd::Command::from_std(testenv.command())
+        .arg("--check-cache")
+        .write_stdin("n\n")
+        .assert()
+        .failure()
+        .stdout(contains("en: no pages in the cache"));
+}
+
+/// Write an archive of the pages for `language` in the format of the upstream archives to
+/// `directory`. The pages are given by their path in the archive, e.g. `common/tar.md`.
+fn write_archive(directory: &Path, language: &str, pages: &[(&str, &str)]) {
+    create_dir_all(directory).unwrap();
+    let file = File::create(directory.join(format!("tldr-pages.{language}.zip"))).unwrap();
+    let mut archive = zip::ZipWriter::new(file);
+    for (path, contents) in pages {
+        archive
+            .start_file(*path, zip::write::SimpleFileOptions::default())
+            .unwrap();
+        archive.write_all(contents.as_bytes()).unwrap();
+    }
+    archive.finish().unwrap();
+}
+
+#[test]
+fn test_serve_cache() {
+    let server_env = TestEnv::new().install_default_cache().with_feature("serve");
+    let mut server = server_env
+        .command()
+        .args(["--quiet", "--serve-cache", "127.0.0.1:0"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // The port is chosen by the OS and printed on the first line
+    let mut line = String::new();
+    io::BufReader::new(server.stdout.take().unwrap())
+        .read_line(&mut line)
+        .unwrap();
+    let address = line
+        .trim()
+        .strip_prefix("Serving the cache at ")
+        .expect("The address should be printed")
+        .to_string();
+
+    let client_env = TestEnv::new();
+    client_env.append_to_config(format!("updates.archive_source = '{address}'\n"));
+    let update = client_env.command().arg("--update").assert();
+    let list = client_env.command().arg("--list").assert();
+    server.kill().unwrap();
+    server.wait().unwrap();
+
+    update
+        .success()
+        .stderr(contains("Successfully updated cache."));
+    list.success().stdout(contains("which"));
+}
+
+#[test]
+fn test_update_from_local_archives() {
+    let testenv = TestEnv::new();
+    let mirror = testenv.config_dir().join("mirror");
+    write_archive(
+        &mirror,
+        "en",
+        &[("common/local.md", "# local\n\n> A local page.\n")],
+    );
+    // Relative paths are relative to the config file
+    testenv.append_to_config("updates.archive_source = 'mirror'\n");
+
+    testenv
+        .command()
+        .arg("--update")
+        .assert()
+        .success()
+        .stderr(contains("Successfully updated cache."));
+    testenv
+        .command()
+        .arg("local")
+        .assert()
+        .success()
+        .stdout(contains("A local page."));
+    testenv
+        .command()
+        .arg("--dry-run-update")
+        .assert()
+        .success()
+        .stdout(eq("en: up to date\n"));
+
+    // Changes of the archives are detected
+    write_archive(
+        &mirror,
+        "en",
+        &[("common/local.md", "# local\n\n> An updated local page.\n")],
+    );
+    testenv
+        .command()
+        .arg("--dry-run-update")
+        .assert()
+        .failure()
+        .stdout(eq("en: outdated\n"));
+
+    let file_url = format!("'file://{}'", mirror.display());
+    testenv
+        .command()
+        .args([
+            "--set",
+            &format!("updates.archive_source={file_url}"),
+            "--update",
+        ])
+        .assert()
+        .success();
+    testenv
+        .command()
+        .arg("local")
+        .assert()
+        .success()
+        .stdout(contains("An updated local page."));
+
+    testenv
+        .command()
+        .args(["--set", "updates.archive_source='missing'", "--update"])
+        .assert()
+        .failure()
+        .stderr(contains("missing is not a directory"));
+}
+
+#[test]
+fn test_update_json_report_failed() {
+    let testenv = TestEnv::new().install_default_cache();
+    testenv.append_to_config("updates.archive_source = 'http://127.0.0.1:1'\n");
+
+    testenv
+        .command()
+        .args(["--update", "--output", "json"])
+        .assert()
+        .failure()
+        .code(5)
+        .stdout(is_match(r#"^\{"success":false,"error":"Could not update cache: [^"]*","languages":\[\],"bytes_downloaded":0,"pages_added":0,"pages_removed":0,"pages_modified":0,"duration_ms":\d+\}\n$"#).unwrap());
+
+    // The report is only available for updates
+    testenv
+        .command()
+        .args(["--output", "json", "which"])
+        .assert()
+        .failure()
+        .stderr(contains("--update"));
+}
+
+#[cfg_attr(feature = "ignore-online-tests", ignore = "online test")]
+#[test]
+fn test_update_json_report() {
+    let testenv = TestEnv::new();
+
+    let assert = testenv
+        .command()
+        .args(["--update", "--output", "json"])
+        .assert()
+        .success()
+        .stderr(contains("Successfully updated cache").not());
+    let report: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+    assert_eq!(report["success"], true);
+    assert_eq!(report["languages"], serde_json::json!(["en"]));
+    assert!(report["bytes_downloaded"].as_u64().unwrap() > 0);
+    assert!(report["pages_added"].as_u64().unwrap() > 1000);
+    assert_eq!(report["pages_removed"], 0);
+}
+
+#[cfg_attr(feature = "ignore-online-tests", ignore = "online test")]
+#[test]
+fn test_dry_run_update() {
+    let testenv = TestEnv::new();
+    testenv.append_to_config("updates.download_languages = ['en', 'de']\n");
+
+    testenv.command().arg("--update").assert().success();
+    testenv
+        .command()
+        .arg("--dry-run-update")
+        .assert()
+        .success()
+        .stdout(eq("en: up to date\nde: up to date\n"));
+
+    // The check doesn't modify the cache
+    fs::write(
+        testenv.cache_state_dir().join("etags").join("pages.de"),
+        "\"outdated\"",
+    )
+    .unwrap();
+    testenv
+        .command()
+        .args(["--download-language", "de", "--download-language", "it"])
+        .arg("--dry-run-update")
+        .assert()
+        .failure()
+        .stdout(eq("de: outdated\nit: not downloaded yet\n"));
+}
+
 #[cfg_attr(feature = "ignore-online-tests", ignore = "online test")]
 #[test]
 fn test_update_cache_rustls_webpki() {
@@ -381,6 +809,103 @@ fn test_update_cache_native_tls() {
     testenv.command().args(["sl"]).assert().success();
 }
 
+#[cfg(unix)]
+#[test]
+fn test_update_hooks_failed_update() {
+    let testenv = TestEnv::new().install_default_cache();
+    let hook_output = testenv.config_dir().join("hooks.txt");
+    testenv.append_to_config(format!(
+        "updates.archive_source = 'http://127.0.0.1:1'\n\
+         hooks.pre_update = '''sh -c 'echo \"pre $TEALDEER_PAGES_DIR\" >> {0}' '''\n\
+         hooks.post_update = '''sh -c 'echo post >> {0}' '''\n",
+        hook_output.display(),
+    ));
+
+    testenv.command().arg("--update").assert().failure();
+    // The `post_update` hook only runs after successful updates
+    assert_eq!(
+        fs::read_to_string(&hook_output).unwrap(),
+        format!(
+            "pre {}\n",
+            testenv.cache_dir().join(TLDR_PAGES_DIR).display()
+        ),
+    );
+
+    // Failing hooks don't prevent updates
+    testenv
+        .command()
+        .args(["--set", "hooks.pre_update='false'", "--update"])
+        .assert()
+        .failure()
+        .stderr(contains("The `hooks.pre_update` command `false` failed"))
+        .stderr(contains("Could not update cache"));
+}
+
+#[cfg(unix)]
+#[cfg_attr(feature = "ignore-online-tests", ignore = "online test")]
+#[test]
+fn test_update_hooks() {
+    let testenv = TestEnv::new();
+    let hook_output = testenv.config_dir().join("hooks.txt");
+    testenv.append_to_config(format!(
+        "hooks.post_update = '''sh -c 'echo \"$TEALDEER_UPDATED_LANGUAGES\" > {0}; \
+         grep -c . \"$TEALDEER_ADDED_PAGES_FILE\" >> {0}' '''\n",
+        hook_output.display(),
+    ));
+
+    testenv.command().arg("--update").assert().success();
+    let output = fs::read_to_string(&hook_output).unwrap();
+    let (languages, added_pages) = output.split_once('\n').unwrap();
+    assert_eq!(languages, "en");
+    assert!(added_pages.trim().parse::<usize>().unwrap() > 1000);
+
+    // Nothing changes with a second update
+    testenv.command().arg("--update").assert().success();
+    assert_eq!(fs::read_to_string(&hook_output).unwrap(), "en\n0\n");
+}
+
+#[test]
+fn test_update_hooks_page_lists() {
+    let testenv = TestEnv::new();
+    let mirror = testenv.config_dir().join("mirror");
+    let hook_output = testenv.config_dir().join("hooks.txt");
+    testenv.append_to_config(format!(
+        "updates.archive_source = 'mirror'\n\
+         hooks.post_update = '''sh -c 'cat \"$TEALDEER_ADDED_PAGES_FILE\" \
+         \"$TEALDEER_MODIFIED_PAGES_FILE\" > {}' '''\n",
+        hook_output.display(),
+    ));
+
+    // The lists of pages are passed as files, as they can be too long for env variables
+    write_archive(
+        &mirror,
+        "en",
+        &[
+            ("common/a.md", "# a\n\n> A page.\n"),
+            ("linux/b.md", "# b\n\n> A page.\n"),
+        ],
+    );
+    testenv.command().arg("--update").assert().success();
+    assert_eq!(
+        fs::read_to_string(&hook_output).unwrap(),
+        "pages.en/common/a.md\npages.en/linux/b.md\n"
+    );
+
+    write_archive(
+        &mirror,
+        "en",
+        &[
+            ("common/a.md", "# a\n\n> A changed page.\n"),
+            ("linux/b.md", "# b\n\n> A page.\n"),
+        ],
+    );
+    testenv.command().arg("--update").assert().success();
+    assert_eq!(
+        fs::read_to_string(&hook_output).unwrap(),
+        "pages.en/common/a.md\n"
+    );
+}
+
 #[cfg_attr(feature = "ignore-online-tests", ignore = "online test")]
 #[test]
 fn test_quiet_cache() {
@@ -457,18 +982,43 @@ fn test_warn_invalid_tls_backend() {
 }
 
 #[test]
-fn test_quiet_failures() {
+fn test_timings() {
     let testenv = TestEnv::new().install_default_cache();
 
     testenv
         .command()
-        .args(["fakeprogram", "-q"])
+        .args(["which", "--timings"])
         .assert()
-        .failure()
-        .stdout(is_empty());
-}
+        .success()
+        .stdout(contains("Locate a program in the user's path."))
+        .stderr(contains("Timings:"))
+        .stderr(contains("config load:"))
+        .stderr(contains("cache lookup:"))
+        .stderr(contains("parsing:"))
+        .stderr(contains("rendering:"))
+        .stderr(contains("total:"));
 
-#[test]
+    testenv
+        .command()
+        .args(["which"])
+        .assert()
+        .success()
+        .stderr(contains("Timings:").not());
+}
+
+#[test]
+fn test_quiet_failures() {
+    let testenv = TestEnv::new().install_default_cache();
+
+    testenv
+        .command()
+        .args(["fakeprogram", "-q"])
+        .assert()
+        .failure()
+        .stdout(is_empty());
+}
+
+#[test]
 fn test_quiet_old_cache() {
     let testenv = TestEnv::new().install_default_cache();
 
@@ -493,6 +1043,25 @@ fn test_quiet_old_cache() {
         .stderr(contains("The cache hasn't been updated for ").not());
 }
 
+#[test]
+fn test_quiet_errors() {
+    let testenv = TestEnv::new();
+
+    testenv
+        .command()
+        .args(["--quiet", "--render", "does-not-exist.md"])
+        .assert()
+        .failure()
+        .stderr(contains("does-not-exist.md"));
+
+    testenv
+        .command()
+        .args(["-qq", "--render", "does-not-exist.md"])
+        .assert()
+        .failure()
+        .stderr(is_empty());
+}
+
 #[test]
 fn test_warn_cache_age_never() {
     let testenv = TestEnv::new().install_default_cache();
@@ -513,6 +1082,77 @@ fn test_warn_cache_age_never() {
         .stderr(contains("The cache hasn't been updated for ").not());
 }
 
+#[test]
+fn test_cache_age_from_last_update() {
+    let testenv = TestEnv::new().install_default_cache();
+
+    // The recorded time of the last update takes precedence over the modification time
+    filetime::set_file_mtime(
+        testenv.cache_dir().join(TLDR_PAGES_DIR),
+        filetime::FileTime::from_unix_time(1, 0),
+    )
+    .unwrap();
+    testenv.set_last_update(SystemTime::now());
+    testenv
+        .command()
+        .args(["which"])
+        .assert()
+        .success()
+        .stderr(contains("The cache hasn't been updated for ").not());
+
+    filetime::set_file_mtime(
+        testenv.cache_dir().join(TLDR_PAGES_DIR),
+        filetime::FileTime::now(),
+    )
+    .unwrap();
+    testenv.set_last_update(SystemTime::UNIX_EPOCH + Duration::from_secs(1));
+    testenv
+        .command()
+        .args(["which"])
+        .assert()
+        .success()
+        .stderr(contains("The cache hasn't been updated for "));
+}
+
+#[test]
+fn test_state_per_cache_dir() {
+    let testenv = TestEnv::new().install_default_cache();
+    testenv.set_last_update(SystemTime::now());
+
+    // Another cache directory sharing the state directory, e.g. of a profile
+    let other_cache_dir = testenv.cache_dir().join("other");
+    let other_pages_dir = other_cache_dir
+        .join(TLDR_PAGES_DIR)
+        .join("pages.en")
+        .join("common");
+    create_dir_all(&other_pages_dir).unwrap();
+    fs::write(other_pages_dir.join("which.md"), "# which\n").unwrap();
+    filetime::set_file_mtime(
+        other_cache_dir.join(TLDR_PAGES_DIR),
+        filetime::FileTime::from_unix_time(1, 0),
+    )
+    .unwrap();
+
+    // The last update of the first cache doesn't apply to the other one
+    testenv
+        .command()
+        .args(["--set"])
+        .arg(format!(
+            "directories.cache_dir='{}'",
+            other_cache_dir.display()
+        ))
+        .arg("which")
+        .assert()
+        .success()
+        .stderr(contains("The cache hasn't been updated for "));
+    testenv
+        .command()
+        .arg("which")
+        .assert()
+        .success()
+        .stderr(contains("The cache hasn't been updated for ").not());
+}
+
 #[cfg_attr(feature = "ignore-online-tests", ignore = "online test")]
 #[test]
 fn test_create_cache_directory_path() {
@@ -625,6 +1265,44 @@ fn test_cache_location_source() {
         .stdout(is_match("\nCache dir:        [^(]* \\(env variable\\)\n").unwrap());
 }
 
+#[test]
+fn test_deprecation_warnings() {
+    let testenv = TestEnv::new().install_default_cache();
+    let warning = "The $TEALDEER_CACHE_DIR env variable is deprecated";
+    let run = |args: &[&str]| {
+        testenv
+            .command()
+            .env("TEALDEER_CACHE_DIR", testenv.cache_dir())
+            .args(args)
+            .arg("which")
+            .assert()
+            .success()
+    };
+
+    run(&["--no-deprecation-warnings"]).stderr(contains(warning).not());
+    testenv
+        .command()
+        .env("TEALDEER_CACHE_DIR", testenv.cache_dir())
+        .args([
+            "--set",
+            "deprecations.hidden=['cache_dir_env_var']",
+            "which",
+        ])
+        .assert()
+        .success()
+        .stderr(contains(warning).not());
+
+    // The warning is only shown once per day
+    run(&[]).stderr(contains(warning));
+    run(&[]).stderr(contains(warning).not());
+    fs::write(
+        testenv.state_dir().join("deprecation_warnings"),
+        "cache_dir_env_var\t1000\n",
+    )
+    .unwrap();
+    run(&[]).stderr(contains(warning));
+}
+
 #[test]
 fn test_setup_seed_config() {
     let testenv = TestEnv::new();
@@ -692,677 +1370,2998 @@ fn test_setup_seed_config() {
 }
 
 #[test]
-fn test_show_paths() {
-    let testenv = TestEnv::new();
+fn test_history() {
+    let testenv = TestEnv::new().install_default_cache();
 
-    // Show general commands
+    // Lookups are not recorded unless the history is enabled
+    testenv.command().args(["which"]).assert().success();
     testenv
         .command()
-        .args(["--show-paths"])
+        .args(["--history"])
+        .assert()
+        .failure()
+        .stderr(contains("The history is disabled"));
+
+    testenv.append_to_config("history.enabled = true\n");
+    testenv
+        .command()
+        .args(["--history"])
         .assert()
         .success()
-        .stdout(contains(format!(
-            "Config dir:       {}",
-            testenv.config_dir().to_str().unwrap(),
-        )))
-        .stdout(contains(format!(
-            "Config path:      {}",
-            testenv.config_dir().join("config.toml").to_str().unwrap(),
-        )))
-        .stdout(contains(format!(
-            "Cache dir:        {}",
-            testenv.cache_dir().to_str().unwrap(),
-        )))
-        .stdout(contains(format!(
-            "Pages dir:        {}",
-            testenv.cache_dir().join(TLDR_PAGES_DIR).to_str().unwrap(),
-        )));
+        .stderr(contains("The history is empty."));
 
-    let testenv = testenv.write_custom_pages_config();
+    testenv.command().args(["which"]).assert().success();
+    testenv
+        .command()
+        .args(["git", "checkout"])
+        .assert()
+        .success();
+    testenv.command().args(["fakeprogram"]).assert().failure();
+    testenv.command().args(["which"]).assert().success();
+    testenv
+        .command()
+        .args(["--history"])
+        .assert()
+        .success()
+        .stdout(eq(
+            "  1  which         just now\n  2  git-checkout  just now\n",
+        ));
 
-    // Now ensure that this path is contained in the output
     testenv
         .command()
-        .args(["--show-paths"])
+        .args(["--history", "2"])
         .assert()
         .success()
-        .stdout(contains(format!(
-            "Custom pages dir: {}",
-            testenv.custom_pages_dir().to_str().unwrap(),
-        )));
+        .stdout(contains("Checkout a branch or paths to the working tree."));
+    testenv
+        .command()
+        .args(["--history", "3"])
+        .assert()
+        .failure()
+        .stderr(contains("There is no page with number 3 in the history"));
 }
 
 #[test]
-fn test_os_specific_page() {
-    let testenv = TestEnv::new();
+fn test_stats_usage() {
+    let testenv = TestEnv::new().install_default_cache();
+    testenv.append_to_config("history.enabled = true\n");
 
-    testenv.add_os_entry("sunos", "truss", "contents");
+    testenv
+        .command()
+        .args(["inkscape"])
+        .assert()
+        .failure()
+        .stderr(contains("Did you mean `inkscape-v1`, `inkscape-v2`?"));
 
+    testenv.command().args(["inkscape-v2"]).assert().success();
+    testenv.command().args(["which"]).assert().success();
+    testenv.command().args(["inkscape-v2"]).assert().success();
     testenv
         .command()
-        .args(["--platform", "sunos", "truss"])
+        .args(["--stats-usage"])
         .assert()
-        .success();
+        .success()
+        .stdout(eq("2  inkscape-v2\n1  which\n"));
+
+    // Frequently used pages are only preferred if enabled
+    testenv
+        .command()
+        .args(["inkscape"])
+        .assert()
+        .failure()
+        .stderr(contains("Did you mean `inkscape-v1`, `inkscape-v2`?"));
+    testenv.append_to_config("history.boost_frequent_pages = true\n");
+    testenv
+        .command()
+        .args(["inkscape"])
+        .assert()
+        .failure()
+        .stderr(contains("Did you mean `inkscape-v2`, `inkscape-v1`?"));
 }
 
 #[test]
-fn test_config_platforms() {
+fn test_exists() {
     let testenv = TestEnv::new();
-    testenv.add_os_entry("sunos", "sunos-command", "");
-
-    let set_config_platforms = |platforms| {
-        testenv.delete_config();
-        testenv.init_config();
-        testenv.append_to_config(format!("search.platforms = {platforms}"));
-    };
-
-    // By default all platforms are searched
-    testenv.command().arg("sunos-command").assert().success();
-
-    set_config_platforms("[]");
-    testenv.command().arg("sunos-command").assert().failure();
-
-    set_config_platforms("['linux']");
-    testenv.command().arg("sunos-command").assert().failure();
-
-    set_config_platforms("['sunos']");
-    testenv.command().arg("sunos-command").assert().success();
-
-    set_config_platforms("['linux', 'all']");
-    testenv.command().arg("sunos-command").assert().success();
 
-    set_config_platforms("['current', 'all']");
-    testenv.command().arg("sunos-command").assert().success();
+    // No cache yet
+    testenv
+        .command()
+        .args(["--exists", "which"])
+        .assert()
+        .code(1)
+        .stdout("")
+        .stderr("");
+
+    let testenv = testenv.install_default_cache();
+    testenv.add_entry("my-page", "# my-page\n\n> My page.\n");
+    for (page, code) in [("which", 0), ("my-page", 0), ("missing", 1)] {
+        testenv
+            .command()
+            .args(["--exists", page])
+            .assert()
+            .code(code)
+            .stdout("")
+            .stderr("");
+    }
 }
 
 #[test]
-fn test_markdown_rendering() {
-    let testenv = TestEnv::new().install_default_cache();
+#[cfg(not(feature = "self-update"))]
+fn test_self_update_unsupported() {
+    TestEnv::new()
+        .command()
+        .args(["--self-update"])
+        .assert()
+        .failure()
+        .stderr(contains("does not support --self-update"));
+}
 
-    let expected = include_str!("cache/pages.en/common/which.md");
+#[test]
+fn test_completions() {
+    let testenv = TestEnv::new();
     testenv
         .command()
-        .args(["--raw", "which"])
+        .args(["--completions", "bash"])
         .assert()
         .success()
-        .stdout(diff(expected));
-}
+        .stdout(contains("complete -F _tldr"));
+    testenv
+        .command()
+        .args(["--completions", "nushell"])
+        .assert()
+        .success()
+        .stdout(contains("export extern tldr"));
 
-fn _test_correct_rendering(page: &str, expected: &'static str, additional_args: &[&str]) {
-    let testenv = TestEnv::new().install_default_cache();
+    // The scripts are generated, so they know every flag
+    let output = testenv
+        .command()
+        .args(["--completions", "fish"])
+        .output()
+        .unwrap();
+    let script = String::from_utf8(output.stdout).unwrap();
+    for flag in [
+        "all-matches",
+        "show-platforms",
+        "explain-config",
+        "migrate-custom-pages",
+    ] {
+        assert!(
+            script.contains(&format!("-l {flag}")),
+            "`--{flag}` is missing"
+        );
+    }
+    testenv
+        .command()
+        .args(["--completions", "tcsh"])
+        .assert()
+        .failure()
+        .stderr(contains("invalid value 'tcsh'"));
+}
 
+#[test]
+fn test_print_hook() {
+    let testenv = TestEnv::new();
     testenv
         .command()
-        .args(additional_args)
-        .arg(page)
+        .args(["--print-hook", "bash"])
         .assert()
         .success()
-        .stdout(diff(expected));
+        .stdout(contains("command_not_found_handle()"))
+        .stdout(contains("tldr --no-auto-update --exists -- \"$1\""))
+        .stdout(contains("tldr --no-auto-update -- \"$1\""));
+    testenv
+        .command()
+        .args(["--print-hook", "zsh"])
+        .assert()
+        .success()
+        .stdout(contains("tldr --no-auto-update --exists -- \"$1\""))
+        .stdout(contains("tldr --no-auto-update -- \"$1\""));
+    testenv
+        .command()
+        .args(["--print-hook", "fish"])
+        .assert()
+        .success()
+        .stdout(contains("function fish_command_not_found"))
+        .stdout(contains("tldr --no-auto-update --exists -- $argv[1]"))
+        .stdout(contains("tldr --no-auto-update -- $argv[1]"));
+
+    // Commands starting with `-` are not parsed as flags
+    testenv
+        .command()
+        .args(["--no-auto-update", "--exists", "--", "-foo"])
+        .assert()
+        .failure()
+        .stderr(is_empty());
+    testenv
+        .command()
+        .args(["--print-hook", "tcsh"])
+        .assert()
+        .failure()
+        .stderr(contains("invalid value 'tcsh'"));
 }
 
-/// An end-to-end integration test for direct file rendering (v1 syntax).
 #[test]
-fn test_correct_rendering_v1() {
-    _test_correct_rendering(
-        "inkscape-v1",
-        include_str!("rendered/inkscape-default.expected"),
-        &["--color", "always"],
-    );
+fn test_random_page() {
+    let testenv = TestEnv::new().install_default_cache();
+    testenv.add_os_entry("sunos", "sunos-only", "# sunos-only\n\n> Only on SunOS.\n");
+    testenv.append_to_config("search.platforms = ['linux', 'common']\n");
+
+    let descriptions = [
+        "Locate a program in the user's path.",
+        "Checkout a branch or paths to the working tree.",
+        "An SVG (Scalable Vector Graphics) editing program.",
+    ];
+    for _ in 0..5 {
+        let output = testenv.command().args(["--random"]).output().unwrap();
+        assert!(output.status.success());
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(
+            descriptions
+                .iter()
+                .any(|description| stdout.contains(description)),
+            "Unexpected page: {stdout}",
+        );
+    }
+
+    // Only SunOS and common pages exist, so a random platform always shows one of them
+    for _ in 0..5 {
+        testenv
+            .command()
+            .args(["--random-platform"])
+            .assert()
+            .success()
+            .stderr(contains("Showing the page `"))
+            .stderr(contains(" for SunOS.").or(contains(" for Common.")));
+    }
 }
 
-/// An end-to-end integration test for direct file rendering (v2 syntax).
 #[test]
-fn test_correct_rendering_v2() {
-    _test_correct_rendering(
-        "inkscape-v2",
-        include_str!("rendered/inkscape-default.expected"),
-        &["--color", "always"],
+fn test_tip() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "tip",
+        "# tip\n\n> A page for tips.\n\n- Print the tip:\n\n`tip --print {{file}}`\n",
     );
-}
-
-#[test]
-/// An end-to-end integration test for direct file rendering with the `--color auto` option. This
-/// will not use styling since output is not stdout.
-fn test_rendering_color_auto() {
-    _test_correct_rendering(
-        "inkscape-v2",
-        include_str!("rendered/inkscape-default-no-color.expected"),
-        &["--color", "auto"],
+    testenv.add_entry(
+        "no-examples",
+        "# no-examples\n\n> A page without examples.\n",
     );
-}
 
-#[test]
-/// An end-to-end integration test for direct file rendering with the `--color never` option.
-fn test_rendering_color_never() {
-    _test_correct_rendering(
-        "inkscape-v2",
-        include_str!("rendered/inkscape-default-no-color.expected"),
-        &["--color", "never"],
-    );
+    for _ in 0..5 {
+        testenv
+            .command()
+            .args(["--tip"])
+            .assert()
+            .success()
+            .stdout(eq("Print the tip:\n  tip --print file\n"));
+    }
 }
 
-/// An end-to-end integration test for the indent config option
 #[test]
-fn test_rendering_with_indentation() {
+fn test_cheat_sources() {
     let testenv = TestEnv::new().install_default_cache();
-    let expected_custom_indentation = include_str!("rendered/inkscape-compact-no-color.expected");
+    let cheat_dir = testenv.custom_pages_dir().join("cheat");
+    create_dir_all(cheat_dir.join("personal")).unwrap();
+    fs::write(
+        cheat_dir.join("personal").join("mycommand"),
+        "# To run it quickly:\nmycommand --fast\n",
+    )
+    .unwrap();
+    fs::write(cheat_dir.join("which"), "# To find it:\nwhich it\n").unwrap();
+    testenv.append_to_config(format!(
+        "sources.local.kind = 'cheat'\nsources.local.path = '{}'\n",
+        cheat_dir.to_str().unwrap(),
+    ));
 
-    // Configure to set base and command indents
-    testenv.append_to_config("display.indent.base = 3\n");
-    testenv.append_to_config("display.indent.command = 1\n");
+    testenv
+        .command()
+        .args(["mycommand"])
+        .assert()
+        .success()
+        .stdout(contains("To run it quickly:"))
+        .stdout(contains("mycommand --fast"));
 
+    // The tldr pages take precedence over sources with the same priority
     testenv
         .command()
-        .args(["--color", "never", "inkscape-v2"])
+        .args(["which"])
         .assert()
         .success()
-        .stdout(diff(expected_custom_indentation));
-}
+        .stdout(contains("Locate a program in the user's path."))
+        .stdout(contains("To find it:").not());
 
-#[test]
-fn test_rendering_i18n() {
-    _test_correct_rendering(
-        "apt",
-        include_str!("rendered/apt.ja.expected"),
-        &["--color", "always", "--language", "ja"],
-    );
+    testenv.append_to_config("sources.local.priority = 1\n");
+    testenv
+        .command()
+        .args(["which"])
+        .assert()
+        .success()
+        .stdout(contains("To find it:"));
 }
 
-/// An end-to-end integration test for rendering with custom syntax config.
 #[test]
-fn test_correct_rendering_with_config() {
+fn test_unreachable_source() {
     let testenv = TestEnv::new().install_default_cache();
+    testenv.append_to_config(
+        "sources.offline.kind = 'cheat.sh'
+sources.offline.url = 'http://127.0.0.1:1'
+         sources.offline.priority = 1
+",
+    );
 
-    testenv.append_to_config(include_str!("style-config.toml"));
-
-    let expected = include_str!("rendered/inkscape-with-config.expected");
-
+    // A failing source is skipped, the page is still found in the cache
     testenv
         .command()
-        .args(["--color", "always", "inkscape-v2"])
+        .args(["which"])
         .assert()
         .success()
-        .stdout(diff(expected));
+        .stdout(contains("Locate a program in the user's path."))
+        .stderr(contains(
+            "Could not look up `which` in the `offline` source",
+        ));
 }
 
-/// An end-to-end integration test for rendering with show_title config option enabled.
 #[test]
-fn test_show_title_config() {
-    // Test that default behavior without show_title shows no title
+fn test_tldr_archive_sources() {
     let testenv = TestEnv::new().install_default_cache();
-    let expected_no_title = include_str!("rendered/inkscape-default.expected");
+    let source_dir = testenv
+        .cache_dir()
+        .join("sources")
+        .join("internal")
+        .join("pages.de")
+        .join("common");
+    create_dir_all(&source_dir).unwrap();
+    fs::write(
+        source_dir.join("deploy.md"),
+        "# deploy\n\n> Deploy our service.\n",
+    )
+    .unwrap();
+    testenv.append_to_config(
+        "sources.internal.kind = 'tldr'\n\
+         sources.internal.url = 'https://pages.example.com'\n\
+         sources.internal.languages = ['de']\n",
+    );
 
     testenv
         .command()
-        .args(["--color", "always", "inkscape-v2"])
+        .args(["deploy"])
         .assert()
         .success()
-        .stdout(diff(expected_no_title));
-
-    // Configure to enable show_title
-    testenv.append_to_config("display.show_title = true\n");
-
-    let expected_no_color = include_str!("rendered/inkscape-with-title-no-color.expected");
-
+        .stdout(contains("Deploy our service."));
     testenv
         .command()
-        .args(["inkscape-v2"])
+        .args(["which"])
         .assert()
         .success()
-        .stdout(diff(expected_no_color));
-
-    let expected = include_str!("rendered/inkscape-with-title.expected");
+        .stdout(contains("Locate a program in the user's path."));
 
+    testenv.append_to_config("sources.invalid.kind = 'tldr'\n");
     testenv
         .command()
-        .args(["--color", "always", "inkscape-v2"])
+        .args(["deploy"])
         .assert()
-        .success()
-        .stdout(diff(expected));
+        .failure()
+        .stderr(contains("The `tldr` source `invalid` requires a `url`"));
 }
 
 #[test]
-fn test_spaces_find_command() {
-    let testenv = TestEnv::new().install_default_cache();
+fn test_pages_index() {
+    let testenv = TestEnv::new();
+    let pages_dir = testenv.cache_dir().join("tldr-pages");
+    create_dir_all(pages_dir.join("pages.en").join("common")).unwrap();
+    fs::write(
+        pages_dir.join("index.json"),
+        r#"{"commands": [
+            {"name": "fetched", "targets": [{"os": "common", "language": "en"}]},
+            {"name": "unfetched", "targets": [
+                {"os": "linux", "language": "en"},
+                {"os": "linux", "language": "de"},
+                {"os": "osx", "language": "en"}
+            ]},
+            {"name": "windows-only", "targets": [{"os": "windows", "language": "en"}]}
+        ]}"#,
+    )
+    .unwrap();
+    fs::write(
+        pages_dir.join("pages.en").join("common").join("fetched.md"),
+        "# fetched\n\n> Fetched on demand.\n",
+    )
+    .unwrap();
+    testenv.append_to_config(
+        "updates.use_index = true\n\
+         search.platforms = ['linux', 'common']\n",
+    );
 
     testenv
         .command()
-        .args(["git", "checkout"])
+        .args(["fetched"])
         .assert()
-        .success();
-}
-
-#[test]
-fn test_pager_flag_enable() {
-    let testenv = TestEnv::new().install_default_cache();
-
+        .success()
+        .stdout(contains("Fetched on demand."));
     testenv
         .command()
-        .args(["--pager", "which"])
+        .args(["--list"])
         .assert()
-        .success();
-}
-
-#[test]
-fn test_multiple_platform_command_search() {
-    let testenv = TestEnv::new();
-    testenv.add_os_entry("linux", "linux-only", "this command only exists for linux");
-    testenv.add_os_entry(
-        "linux",
-        "windows-and-linux",
-        "# windows-and-linux \n\n > linux version",
-    );
-    testenv.add_os_entry(
-        "windows",
-        "windows-and-linux",
-        "# windows-and-linux \n\n > windows version",
-    );
-
+        .success()
+        .stdout("fetched\nunfetched\n");
     testenv
         .command()
-        .args(["--platform", "windows", "--platform", "linux", "linux-only"])
+        .args(["--list", "--show-platforms"])
         .assert()
-        .success();
-
-    // test order of platforms supplied if preserved
+        .success()
+        .stdout("fetched    [common]\nunfetched  [linux, osx]\n");
     testenv
         .command()
-        .args([
-            "--platform",
-            "windows",
-            "--platform",
-            "linux",
-            "windows-and-linux",
-        ])
+        .args(["--availability", "unfetched"])
         .assert()
         .success()
-        .stdout(contains("windows version"));
-
+        .stdout("linux: de, en\nosx: en\n");
     testenv
         .command()
-        .args([
-            "--platform",
-            "linux",
-            "--platform",
-            "windows",
-            "windows-and-linux",
-        ])
+        .args(["--availability", "missing"])
         .assert()
-        .success()
-        .stdout(contains("linux version"));
+        .code(3)
+        .stderr(contains("not listed in the index"));
 }
 
 #[test]
-fn test_multiple_platform_command_search_not_found() {
+fn test_profile() {
     let testenv = TestEnv::new();
-    testenv.add_os_entry(
-        "windows",
-        "windows-only",
-        "this command only exists for Windows",
-    );
 
     testenv
         .command()
-        .args(["--platform", "macos", "--platform", "linux", "windows-only"])
+        .args(["--profile", "work", "--show-paths"])
         .assert()
-        .stderr(contains("Page `windows-only` not found in cache."));
+        .failure()
+        .stderr(contains(
+            "The config file of the profile `work` does not exist",
+        ));
+
+    let profile_path = testenv.config_dir().join("config.work.toml");
+    fs::copy(testenv.config_dir().join("config.toml"), &profile_path).unwrap();
+    testenv
+        .command()
+        .args(["--profile", "work", "--show-paths"])
+        .assert()
+        .success()
+        .stdout(contains(format!(
+            "Config path:      {}",
+            profile_path.to_str().unwrap(),
+        )));
+
+    testenv
+        .command()
+        .args(["--profile", "../work", "--show-paths"])
+        .assert()
+        .failure()
+        .stderr(contains("Invalid profile `../work`"));
 }
 
 #[test]
-fn test_macos_is_alias_for_osx() {
+fn test_system_pages_dir() {
     let testenv = TestEnv::new();
-    testenv.add_os_entry("osx", "maconly", "this command only exists on mac");
+    let system_pages_dir = testenv.config_dir().join("system-pages");
+    let page_dir = system_pages_dir.join("pages.en").join("common");
+    create_dir_all(&page_dir).unwrap();
+    fs::write(
+        page_dir.join("shared.md"),
+        "# shared\n\n> System page.\n\n- Run it:\n\n`shared`\n",
+    )
+    .unwrap();
+    testenv.append_to_config(format!(
+        "search.platforms = ['common']\ndirectories.system_pages_dir = '{}'\n",
+        system_pages_dir.display(),
+    ));
 
+    // Without a user cache, the system pages are used
     testenv
         .command()
-        .args(["--platform", "macos", "maconly"])
+        .args(["--raw", "shared"])
         .assert()
-        .success();
+        .success()
+        .stdout(contains("System page."));
     testenv
         .command()
-        .args(["--platform", "osx", "maconly"])
+        .args(["--list"])
         .assert()
-        .success();
+        .success()
+        .stdout("shared\n");
+    // Using the system pages doesn't create a user cache
+    assert!(!testenv.cache_dir().join(TLDR_PAGES_DIR).exists());
 
+    // Pages in the user cache take precedence
+    let user_page_dir = testenv
+        .cache_dir()
+        .join(TLDR_PAGES_DIR)
+        .join("pages.en")
+        .join("common");
+    create_dir_all(&user_page_dir).unwrap();
+    fs::write(
+        user_page_dir.join("shared.md"),
+        "# shared\n\n> User page.\n\n- Run it:\n\n`shared`\n",
+    )
+    .unwrap();
     testenv
         .command()
-        .args(["--platform", "macos", "--list"])
+        .args(["--raw", "shared"])
         .assert()
-        .stdout("maconly\n");
+        .success()
+        .stdout(contains("User page."));
+}
+
+#[test]
+fn test_show_paths() {
+    let testenv = TestEnv::new();
+
+    // Show general commands
     testenv
         .command()
-        .args(["--platform", "osx", "--list"])
+        .args(["--show-paths"])
         .assert()
-        .stdout("maconly\n");
+        .success()
+        .stdout(contains(format!(
+            "Config dir:       {}",
+            testenv.config_dir().to_str().unwrap(),
+        )))
+        .stdout(contains(format!(
+            "Config path:      {}",
+            testenv.config_dir().join("config.toml").to_str().unwrap(),
+        )))
+        .stdout(contains(format!(
+            "Cache dir:        {}",
+            testenv.cache_dir().to_str().unwrap(),
+        )))
+        .stdout(contains(format!(
+            "Pages dir:        {}",
+            testenv.cache_dir().join(TLDR_PAGES_DIR).to_str().unwrap(),
+        )))
+        .stdout(contains(format!(
+            "State dir:        {}",
+            testenv.state_dir().to_str().unwrap(),
+        )));
 
-    testenv.append_to_config("search.platforms = ['osx']\n");
-    testenv.command().arg("--list").assert().stdout("maconly\n");
+    let testenv = testenv.write_custom_pages_config();
 
-    testenv.delete_config();
-    testenv.init_config();
-    testenv.append_to_config("search.platforms = ['macos']\n");
-    testenv.command().arg("--list").assert().stdout("maconly\n");
+    // Now ensure that this path is contained in the output
+    testenv
+        .command()
+        .args(["--show-paths"])
+        .assert()
+        .success()
+        .stdout(contains(format!(
+            "Custom pages dir: {}",
+            testenv.custom_pages_dir().to_str().unwrap(),
+        )));
 }
 
 #[test]
-fn test_common_platform_is_used_as_fallback() {
+fn test_show_paths_json() {
     let testenv = TestEnv::new();
-    testenv.add_entry("in-common", "this command comes from common");
 
-    // No platform specified
-    testenv.command().args(["in-common"]).assert().success();
+    let output = testenv
+        .command()
+        .args(["--show-paths", "--output", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
 
-    // Platform specified
+    assert_eq!(
+        report["config_dir"]["path"],
+        testenv.config_dir().to_str().unwrap()
+    );
+    assert_eq!(report["config_dir"]["source"], "env_var");
+    assert_eq!(
+        report["pages_dir"]["path"],
+        testenv.cache_dir().join(TLDR_PAGES_DIR).to_str().unwrap()
+    );
+    assert_eq!(report["pages_dir"]["exists"], false);
+    assert_eq!(report["cache_dir"]["exists"], true);
+    assert_eq!(
+        report["state_dir"]["path"],
+        testenv.state_dir().to_str().unwrap()
+    );
+    assert!(report["system_pages_dir"].is_null());
+
+    // The output format is only used for reports
     testenv
         .command()
-        .args(["--platform", "linux", "in-common"])
+        .args(["--output", "json", "--list"])
         .assert()
-        .success();
+        .failure();
+}
+
+#[test]
+fn test_os_specific_page() {
+    let testenv = TestEnv::new();
+
+    testenv.add_os_entry("sunos", "truss", "contents");
+
+    testenv
+        .command()
+        .args(["--platform", "sunos", "truss"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_platform_all() {
+    let testenv = TestEnv::new();
+    testenv.append_to_config("search.platforms = ['common']\n");
+    testenv.add_os_entry("sunos", "truss", "contents");
+
+    testenv.command().arg("truss").assert().failure();
+    testenv
+        .command()
+        .args(["--platform", "all", "truss"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_config_platforms() {
+    let testenv = TestEnv::new();
+    testenv.add_os_entry("sunos", "sunos-command", "");
+
+    let set_config_platforms = |platforms| {
+        testenv.delete_config();
+        testenv.init_config();
+        testenv.append_to_config(format!("search.platforms = {platforms}"));
+    };
+
+    // By default all platforms are searched
+    testenv.command().arg("sunos-command").assert().success();
+
+    set_config_platforms("[]");
+    testenv.command().arg("sunos-command").assert().failure();
+
+    set_config_platforms("['linux']");
+    testenv.command().arg("sunos-command").assert().failure();
+
+    set_config_platforms("['sunos']");
+    testenv.command().arg("sunos-command").assert().success();
+
+    set_config_platforms("['linux', 'all']");
+    testenv.command().arg("sunos-command").assert().success();
+
+    set_config_platforms("['current', 'all']");
+    testenv.command().arg("sunos-command").assert().success();
+}
+
+#[test]
+fn test_markdown_rendering() {
+    let testenv = TestEnv::new().install_default_cache();
+
+    let expected = include_str!("cache/pages.en/common/which.md");
+    testenv
+        .command()
+        .args(["--raw", "which"])
+        .assert()
+        .success()
+        .stdout(diff(expected));
+}
+
+#[test]
+fn test_raw_when_piped() {
+    let testenv = TestEnv::new().install_default_cache();
+    let expected = include_str!("cache/pages.en/common/which.md");
+
+    // The output of the tests is piped
+    testenv
+        .command()
+        .args(["--set", "display.raw_when_piped=true", "which"])
+        .assert()
+        .success()
+        .stdout(diff(expected));
+
+    // Options that only apply to rendered pages, and `--plain`, still render the page
+    for args in [["--example", "1"], ["--color", "always"]] {
+        testenv
+            .command()
+            .args(["--set", "display.raw_when_piped=true", "which"])
+            .args(args)
+            .assert()
+            .success()
+            .stdout(contains("- Search").not());
+    }
+    testenv
+        .command()
+        .args(["--set", "display.raw_when_piped=true", "--plain", "which"])
+        .assert()
+        .success()
+        .stdout(contains("# which").not());
+}
+
+fn _test_correct_rendering(page: &str, expected: &'static str, additional_args: &[&str]) {
+    let testenv = TestEnv::new().install_default_cache();
+
+    testenv
+        .command()
+        .args(additional_args)
+        .arg(page)
+        .assert()
+        .success()
+        .stdout(diff(expected));
+}
+
+#[test]
+fn test_rendering_from_stdin() {
+    let testenv = TestEnv::new();
+
+    assert_cmd::Command::from_std(testenv.command())
+        .args(["--color", "always", "--render", "-"])
+        .write_stdin(include_str!("cache/pages.en/common/inkscape-v2.md"))
+        .assert()
+        .success()
+        .stdout(diff(include_str!("rendered/inkscape-default.expected")));
+}
+
+#[test]
+fn test_plain_output() {
+    let testenv = TestEnv::new().install_default_cache();
+    testenv.append_to_config(
+        "[display]\ncompact = true\nshow_title = true\nshow_example_numbers = true\n\
+         [display.indent]\nbase = 4\n",
+    );
+
+    // Neither styles nor the display config affect the plain output
+    testenv
+        .command()
+        .args(["--color", "always", "--plain", "inkscape-v2"])
+        .assert()
+        .success()
+        .stdout(diff(include_str!(
+            "rendered/inkscape-default-no-color.expected"
+        )));
+}
+
+#[test]
+fn test_output_tokens() {
+    let testenv = TestEnv::new().install_default_cache();
+    testenv.append_to_config("[display]\ncompact = true\n");
+
+    let output = testenv
+        .command()
+        .args(["--color", "always", "--output", "tokens", "which"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let tokens: Vec<serde_json::Value> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+    // The tokens don't depend on the config, and the title is always included
+    assert_eq!(tokens[0], serde_json::json!({"kind": "linebreak"}));
+    assert_eq!(
+        tokens[1],
+        serde_json::json!({"kind": "title", "text": "which"})
+    );
+    assert!(tokens.contains(&serde_json::json!({"kind": "command_option", "text": "-a"})));
+    assert!(tokens.contains(&serde_json::json!({"kind": "variable", "text": "executable"})));
+    assert!(tokens
+        .iter()
+        .all(|token| token["kind"] == "linebreak" || token["text"].as_str().is_some()));
+
+    // Reports have their own formats
+    testenv
+        .command()
+        .args(["--update", "--output", "tokens"])
+        .assert()
+        .failure()
+        .stderr(contains("only available for pages"));
+}
+
+#[test]
+fn test_color_config() {
+    let testenv = TestEnv::new().install_default_cache();
+    testenv.append_to_config("[display]\ncolor = 'always'\n");
+
+    // The config styles the output even though it is piped
+    testenv
+        .command()
+        .args(["inkscape-v2"])
+        .assert()
+        .success()
+        .stdout(diff(include_str!("rendered/inkscape-default.expected")));
+
+    // The command line argument takes precedence
+    testenv
+        .command()
+        .args(["--color", "never", "inkscape-v2"])
+        .assert()
+        .success()
+        .stdout(diff(include_str!(
+            "rendered/inkscape-default-no-color.expected"
+        )));
+}
+
+#[test]
+fn test_output_file() {
+    let testenv = TestEnv::new().install_default_cache();
+    let output_file = testenv.config_dir().join("inkscape.txt");
+
+    testenv
+        .command()
+        .args([
+            "--output-file",
+            output_file.to_str().unwrap(),
+            "inkscape-v2",
+        ])
+        .assert()
+        .success()
+        .stdout("");
+    assert_eq!(
+        fs::read_to_string(&output_file).unwrap(),
+        include_str!("rendered/inkscape-default-no-color.expected")
+    );
+
+    // Styles are only written to the file if requested
+    testenv
+        .command()
+        .args(["--color", "always", "-o", output_file.to_str().unwrap()])
+        .arg("inkscape-v2")
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(&output_file).unwrap(),
+        include_str!("rendered/inkscape-default.expected")
+    );
+
+    // The file is only created if a page is rendered
+    let unused_file = testenv.config_dir().join("unused.txt");
+    testenv
+        .command()
+        .args(["-o", unused_file.to_str().unwrap(), "does-not-exist"])
+        .assert()
+        .failure();
+    testenv
+        .command()
+        .args(["-o", unused_file.to_str().unwrap(), "--list"])
+        .assert()
+        .success()
+        .stdout(contains("inkscape-v2"));
+    assert!(!unused_file.exists());
+}
+
+#[test]
+fn test_auto_update_offline() {
+    let testenv = TestEnv::new().install_default_cache();
+    testenv.append_to_config(
+        "updates.auto_update = true\nupdates.auto_update_interval_hours = 0\n\
+         updates.archive_source = 'http://127.0.0.1:1'\n",
+    );
+
+    // The existing pages are used if an automatic update fails
+    testenv
+        .command()
+        .arg("which")
+        .assert()
+        .success()
+        .stdout(contains("Locate a program"))
+        .stderr(contains(
+            "the network is unreachable, using the existing pages",
+        ))
+        .stderr(contains("TLS").not());
+
+    // Explicit updates still fail
+    testenv
+        .command()
+        .arg("--update")
+        .assert()
+        .failure()
+        .code(5)
+        .stderr(contains("Could not update cache"));
+}
+
+#[test]
+fn test_auto_update_attempt_interval() {
+    let testenv = TestEnv::new().install_default_cache();
+    testenv.append_to_config(
+        "updates.auto_update = true\nupdates.auto_update_interval_hours = 0\n\
+         updates.archive_source = 'http://127.0.0.1:1'\n",
+    );
+    let attempt_update = || testenv.command().arg("which").assert().success();
+
+    attempt_update().stderr(contains("Could not update the cache"));
+    // Updates are not attempted again right after the last attempt
+    attempt_update().stderr(is_empty());
+
+    fs::remove_file(testenv.cache_state_dir().join("last_update_attempt")).unwrap();
+    attempt_update().stderr(contains("Could not update the cache"));
+
+    testenv.append_to_config("updates.min_update_attempt_interval_minutes = 0\n");
+    attempt_update().stderr(contains("Could not update the cache"));
+}
+
+#[test]
+fn test_auto_update_jitter() {
+    let testenv = TestEnv::new().install_default_cache();
+    testenv.append_to_config(
+        "updates.auto_update = true\nupdates.auto_update_interval_hours = 0\n\
+         updates.auto_update_jitter_hours = 100000\n\
+         updates.archive_source = 'http://127.0.0.1:1'\n",
+    );
+    let due_file = testenv.cache_state_dir().join("auto_update_due");
+
+    // The jitter is chosen once, not again on every run
+    testenv.command().arg("which").assert().success();
+    let due = fs::read_to_string(&due_file).unwrap();
+    testenv
+        .command()
+        .arg("which")
+        .assert()
+        .success()
+        .stderr(is_empty());
+    assert_eq!(fs::read_to_string(&due_file).unwrap(), due);
+
+    // The due time is chosen again if it doesn't fit the config anymore
+    testenv
+        .command()
+        .args(["--set", "updates.auto_update_jitter_hours=0", "which"])
+        .assert()
+        .success()
+        .stderr(contains("Could not update the cache"));
+    assert_ne!(fs::read_to_string(&due_file).unwrap(), due);
+}
+
+#[test]
+fn test_fetch_missing_uses_fetched_pages() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("which", "# which\n");
+    testenv.append_to_config(
+        "updates.fetch_missing = true\nupdates.pages_source = 'http://127.0.0.1:1'\n",
+    );
+    let fetched_dir = testenv
+        .cache_dir()
+        .join("fetched-pages")
+        .join("pages.en")
+        .join("common");
+    create_dir_all(&fetched_dir).unwrap();
+    let fetched_page = fetched_dir.join("fetched.md");
+    fs::write(&fetched_page, "# fetched\n\n> A fetched page.\n").unwrap();
+
+    testenv
+        .command()
+        .arg("fetched")
+        .assert()
+        .success()
+        .stdout(contains("A fetched page."));
+
+    // Expired pages are fetched again
+    filetime::set_file_mtime(&fetched_page, filetime::FileTime::from_unix_time(1, 0)).unwrap();
+    testenv
+        .command()
+        .arg("fetched")
+        .assert()
+        .failure()
+        .stderr(contains("fetching it from http://127.0.0.1:1"))
+        .stderr(contains("Could not fetch page `fetched`"));
+
+    // Page names that would leave the fetched pages directory are not fetched
+    testenv
+        .command()
+        .arg("../../x")
+        .assert()
+        .failure()
+        .stderr(contains("Invalid page name `../../x`"));
+
+    testenv.command().arg("--clear-cache").assert().success();
+    assert!(!fetched_page.exists());
+}
+
+#[cfg_attr(feature = "ignore-online-tests", ignore = "online test")]
+#[test]
+fn test_fetch_missing() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("which", "# which\n");
+    testenv.append_to_config("updates.fetch_missing = true\n");
+
+    testenv
+        .command()
+        .args(["--platform", "linux", "tar"])
+        .assert()
+        .success()
+        .stdout(contains("Archiving utility"));
+    assert!(testenv
+        .cache_dir()
+        .join("fetched-pages/pages.en/common/tar.md")
+        .is_file());
+}
+
+#[cfg_attr(feature = "ignore-online-tests", ignore = "online test")]
+#[test]
+fn test_render_url() {
+    let testenv = TestEnv::new();
+
+    testenv
+        .command()
+        .args([
+            "--raw",
+            "--render-url",
+            "https://raw.githubusercontent.com/tldr-pages/tldr/main/pages/common/tar.md",
+        ])
+        .assert()
+        .success()
+        .stdout(contains("# tar"));
+    assert!(!testenv.cache_dir().join(TLDR_PAGES_DIR).exists());
+
+    testenv
+        .command()
+        .args([
+            "--render-url",
+            "https://raw.githubusercontent.com/tldr-pages/tldr/main/pages/common/does-not-exist.md",
+        ])
+        .assert()
+        .failure()
+        .stderr(contains("No page found at"));
+}
+
+/// An end-to-end integration test for direct file rendering (v1 syntax).
+#[test]
+fn test_correct_rendering_v1() {
+    _test_correct_rendering(
+        "inkscape-v1",
+        include_str!("rendered/inkscape-default.expected"),
+        &["--color", "always"],
+    );
+}
+
+/// An end-to-end integration test for direct file rendering (v2 syntax).
+#[test]
+fn test_correct_rendering_v2() {
+    _test_correct_rendering(
+        "inkscape-v2",
+        include_str!("rendered/inkscape-default.expected"),
+        &["--color", "always"],
+    );
+}
+
+#[test]
+/// An end-to-end integration test for direct file rendering with the `--color auto` option. This
+/// will not use styling since output is not stdout.
+fn test_rendering_color_auto() {
+    _test_correct_rendering(
+        "inkscape-v2",
+        include_str!("rendered/inkscape-default-no-color.expected"),
+        &["--color", "auto"],
+    );
+}
+
+#[test]
+/// An end-to-end integration test for direct file rendering with the `--color never` option.
+fn test_rendering_color_never() {
+    _test_correct_rendering(
+        "inkscape-v2",
+        include_str!("rendered/inkscape-default-no-color.expected"),
+        &["--color", "never"],
+    );
+}
+
+/// An end-to-end integration test for the indent config option
+#[test]
+fn test_rendering_with_indentation() {
+    let testenv = TestEnv::new().install_default_cache();
+    let expected_custom_indentation = include_str!("rendered/inkscape-compact-no-color.expected");
+
+    // Configure to set base and command indents
+    testenv.append_to_config("display.indent.base = 3\n");
+    testenv.append_to_config("display.indent.command = 1\n");
+
+    testenv
+        .command()
+        .args(["--color", "never", "inkscape-v2"])
+        .assert()
+        .success()
+        .stdout(diff(expected_custom_indentation));
+}
+
+/// The user config is merged on top of the system-wide config
+#[test]
+fn test_system_config() {
+    let testenv = TestEnv::new().install_default_cache();
+    let expected_custom_indentation = include_str!("rendered/inkscape-compact-no-color.expected");
+
+    testenv
+        .command()
+        .arg("--show-paths")
+        .assert()
+        .success()
+        .stdout(contains(format!(
+            "System config:    {} (env variable) [not present]",
+            testenv
+                .system_config_dir()
+                .join("config.toml")
+                .to_str()
+                .unwrap(),
+        )));
+
+    testenv.append_to_system_config("[display.indent]\nbase = 3\ncommand = 5\n");
+    testenv.append_to_config("display.indent.command = 1\n");
+    testenv
+        .command()
+        .args(["--color", "never", "inkscape-v2"])
+        .assert()
+        .success()
+        .stdout(diff(expected_custom_indentation));
+
+    testenv
+        .command()
+        .arg("--show-paths")
+        .assert()
+        .success()
+        .stdout(contains(format!(
+            "System config:    {} (env variable)\n",
+            testenv
+                .system_config_dir()
+                .join("config.toml")
+                .to_str()
+                .unwrap(),
+        )));
+}
+
+#[test]
+fn test_print_config() {
+    let testenv = TestEnv::new();
+    testenv.append_to_system_config("[updates]\narchive_source = 'https://example.com'\n");
+    testenv.append_to_config("[display]\ncompact = true\n");
+
+    testenv
+        .command()
+        .env("TEALDEER_DISPLAY__INDENT__BASE", "4")
+        .args(["--print-config", "--language", "de"])
+        .assert()
+        .success()
+        .stdout(contains("\ncompact = true # config file\n"))
+        .stdout(contains("\nuse_pager = false # default\n"))
+        .stdout(contains(
+            "\n[display.indent]\nbase = 4 # env variable TEALDEER_DISPLAY__INDENT__BASE\n",
+        ))
+        .stdout(contains(format!(
+            "\n[directories]\ncache_dir = {:?} # config file\n",
+            testenv.cache_dir().to_str().unwrap(),
+        )))
+        .stdout(contains(
+            "\narchive_source = \"https://example.com\" # system config file\n",
+        ))
+        .stdout(contains("\nlanguages = [\"de\"] # command line argument\n"));
+}
+
+#[test]
+fn test_explain_config() {
+    let testenv = TestEnv::new();
+    testenv.append_to_config("[display]\ncompact = true\n");
+
+    testenv
+        .command()
+        .args(["--explain-config", "display.compact"])
+        .assert()
+        .success()
+        .stdout(contains("display.compact\n\n"))
+        .stdout(contains("\nDefault: false\nCurrent: true (config file)\n"))
+        .stdout(contains(
+            "Docs:    https://tealdeer-rs.github.io/tealdeer/config_display.html#compact",
+        ));
+
+    testenv
+        .command()
+        .args(["--explain-config", "display.indent"])
+        .assert()
+        .success()
+        .stdout(contains("\n[display.indent]\nbase = 2 # default\n"));
+
+    testenv
+        .command()
+        .args(["--explain-config", "display.compcat"])
+        .assert()
+        .failure()
+        .stderr(contains(
+            "Unknown config key `display.compcat`, did you mean `display.compact`?",
+        ));
+}
+
+#[test]
+fn test_explain_documented_config_keys() {
+    let testenv = TestEnv::new();
+    let docs_dir = PathBuf::from_iter([env!("CARGO_MANIFEST_DIR"), "docs", "src"]);
+    let mut keys = Vec::new();
+    for entry in fs::read_dir(docs_dir).unwrap() {
+        let path = entry.unwrap().path();
+        let file_name = path.file_name().unwrap().to_str().unwrap();
+        let Some(section) = file_name
+            .strip_prefix("config_")
+            .and_then(|name| name.strip_suffix(".md"))
+        else {
+            continue;
+        };
+        // The options of sources are documented without the user-chosen source name
+        if section == "sources" {
+            continue;
+        }
+        for line in fs::read_to_string(&path).unwrap().lines() {
+            if let Some(name) = line
+                .trim_start_matches('#')
+                .strip_prefix(" `")
+                .and_then(|name| name.strip_suffix('`'))
+                .filter(|_| line.starts_with('#'))
+            {
+                keys.push(format!("{section}.{name}"));
+            }
+        }
+    }
+    assert!(keys.contains(&"updates.archive_public_key".to_string()));
+
+    for key in keys {
+        testenv
+            .command()
+            .args(["--explain-config", &key])
+            .assert()
+            .success()
+            .stdout(contains(format!("{key}\n")));
+    }
+}
+
+#[test]
+fn test_set_config_option() {
+    let testenv = TestEnv::new();
+    testenv.append_to_config("[display]\ncompact = false\n");
+
+    testenv
+        .command()
+        .env("TEALDEER_DISPLAY__COMPACT", "false")
+        .args(["--set", "display.compact=true", "--print-config"])
+        .assert()
+        .success()
+        .stdout(contains("\ncompact = true # command line argument\n"));
+
+    testenv
+        .command()
+        .args(["--set", "display.compat=true", "--print-config"])
+        .assert()
+        .success()
+        .stderr(contains(
+            "Unknown key `display.compat` in `--set`, did you mean `display.compact`?",
+        ));
+
+    testenv
+        .command()
+        .args(["--set", "display.compact", "--print-config"])
+        .assert()
+        .failure()
+        .stderr(contains("expected `KEY=VALUE`"));
+}
+
+#[test]
+fn test_setup_invalid_answers() {
+    let testenv = TestEnv::new();
+
+    // An existing config is only replaced after confirmation
+    let config_path = testenv.config_dir().join("config.toml");
+    let original = fs::read_to_string(&config_path).unwrap();
+    assert_cmd::Command::from_std(testenv.command())
+        .arg("--setup")
+        .write_stdin("n\n")
+        .assert()
+        .failure()
+        .stderr(contains("Setup cancelled"));
+    assert_eq!(fs::read_to_string(&config_path).unwrap(), original);
+
+    let testenv = testenv.remove_initial_config();
+    assert_cmd::Command::from_std(testenv.command())
+        .arg("--setup")
+        .write_stdin("n\n../en\n")
+        .assert()
+        .failure()
+        .stderr(contains("Invalid language `../en`"));
+    assert_cmd::Command::from_std(testenv.command())
+        .arg("--setup")
+        .write_stdin("n\nen\nn\nrainbow\n")
+        .assert()
+        .failure()
+        .stderr(contains("Unknown color theme `rainbow`"));
+    assert!(!config_path.exists());
+}
+
+#[cfg_attr(feature = "ignore-online-tests", ignore = "online test")]
+#[test]
+fn test_setup() {
+    let testenv = TestEnv::new().remove_initial_config();
+    // The config written by the wizard doesn't set the cache directory
+    testenv.append_to_system_config(format!(
+        "directories.cache_dir = '{}'\n",
+        testenv.cache_dir().to_str().unwrap(),
+    ));
+
+    assert_cmd::Command::from_std(testenv.command())
+        .arg("--setup")
+        .write_stdin("y\nde, en\nn\nmonochrome\n")
+        .assert()
+        .success()
+        .stderr(contains("Successfully created config file here"))
+        .stderr(contains("Successfully updated cache."));
+
+    let config = fs::read_to_string(testenv.config_dir().join("config.toml")).unwrap();
+    assert!(config.contains("auto_update = true"));
+    assert!(config.contains(r#"languages = ["de", "en"]"#));
+    assert!(testenv
+        .cache_dir()
+        .join(TLDR_PAGES_DIR)
+        .join("pages.de")
+        .is_dir());
+}
+
+#[test]
+fn test_migrate_config() {
+    let testenv = TestEnv::new();
+    testenv.append_to_config("[style.command_name]\nforeground = \"purple\"\n");
+    let config_path = testenv.config_dir().join("config.toml");
+    let original = fs::read_to_string(&config_path).unwrap();
+
+    testenv
+        .command()
+        .arg("--migrate-config")
+        .assert()
+        .success()
+        .stderr(contains(
+            "- Replaced the color `purple` with `magenta` in `style.command_name.foreground`",
+        ));
+    assert!(fs::read_to_string(&config_path)
+        .unwrap()
+        .contains("foreground = \"magenta\""));
+    assert_eq!(
+        fs::read_to_string(testenv.config_dir().join("config.toml.bak")).unwrap(),
+        original
+    );
+
+    testenv
+        .command()
+        .arg("--migrate-config")
+        .assert()
+        .success()
+        .stderr(contains("is up to date"));
+}
+
+#[test]
+fn test_unknown_config_keys() {
+    let testenv = TestEnv::new();
+    testenv.append_to_config("[updates]\nauto_update_interval = 24\n");
+
+    testenv
+        .command()
+        .arg("--show-paths")
+        .assert()
+        .success()
+        .stderr(contains(format!(
+            "Unknown key `updates.auto_update_interval` in config file {}, \
+             did you mean `updates.auto_update_interval_hours`?",
+            testenv.config_dir().join("config.toml").to_str().unwrap(),
+        )));
+
+    testenv
+        .command()
+        .env("TEALDEER_DISPLAY__FOO", "true")
+        .arg("--list-custom")
+        .assert()
+        .success()
+        .stderr(contains(
+            "Unknown key `display.foo` in env variable TEALDEER_DISPLAY__FOO",
+        ));
+
+    testenv
+        .command()
+        .args(["--quiet", "--list-custom"])
+        .assert()
+        .success()
+        .stderr(is_empty());
+}
+
+/// Config options can be overridden with `TEALDEER_*` environment variables
+#[test]
+fn test_config_env_overrides() {
+    let testenv = TestEnv::new().install_default_cache();
+    let expected_custom_indentation = include_str!("rendered/inkscape-compact-no-color.expected");
+
+    testenv.append_to_config("display.indent.base = 2\n");
+    testenv
+        .command()
+        .env("TEALDEER_DISPLAY__INDENT__BASE", "3")
+        .env("TEALDEER_DISPLAY__INDENT__COMMAND", "1")
+        .args(["--color", "never", "inkscape-v2"])
+        .assert()
+        .success()
+        .stdout(diff(expected_custom_indentation));
+
+    testenv
+        .command()
+        .env("TEALDEER_DISPLAY__INDENT__BASE", "many")
+        .arg("inkscape-v2")
+        .assert()
+        .failure()
+        .stderr(contains("`TEALDEER_*` environment variables"));
+}
+
+#[test]
+fn test_rendering_i18n() {
+    _test_correct_rendering(
+        "apt",
+        include_str!("rendered/apt.ja.expected"),
+        &["--color", "always", "--language", "ja"],
+    );
+}
+
+/// An end-to-end integration test for rendering with custom syntax config.
+#[test]
+fn test_correct_rendering_with_config() {
+    let testenv = TestEnv::new().install_default_cache();
+
+    testenv.append_to_config(include_str!("style-config.toml"));
+
+    let expected = include_str!("rendered/inkscape-with-config.expected");
+
+    testenv
+        .command()
+        .args(["--color", "always", "inkscape-v2"])
+        .assert()
+        .success()
+        .stdout(diff(expected));
+}
+
+/// An end-to-end integration test for rendering with show_title config option enabled.
+#[test]
+fn test_show_title_config() {
+    // Test that default behavior without show_title shows no title
+    let testenv = TestEnv::new().install_default_cache();
+    let expected_no_title = include_str!("rendered/inkscape-default.expected");
+
+    testenv
+        .command()
+        .args(["--color", "always", "inkscape-v2"])
+        .assert()
+        .success()
+        .stdout(diff(expected_no_title));
+
+    // Configure to enable show_title
+    testenv.append_to_config("display.show_title = true\n");
+
+    let expected_no_color = include_str!("rendered/inkscape-with-title-no-color.expected");
+
+    testenv
+        .command()
+        .args(["inkscape-v2"])
+        .assert()
+        .success()
+        .stdout(diff(expected_no_color));
+
+    let expected = include_str!("rendered/inkscape-with-title.expected");
+
+    testenv
+        .command()
+        .args(["--color", "always", "inkscape-v2"])
+        .assert()
+        .success()
+        .stdout(diff(expected));
+}
+
+#[test]
+fn test_title_decoration_and_style() {
+    let testenv = TestEnv::new().install_default_cache();
+    testenv.append_to_config(
+        "display.show_title = true\n\
+         display.title_decoration = 'hash'\n\
+         style.title.foreground = 'red'\n",
+    );
+
+    testenv
+        .command()
+        .args(["--color", "always", "inkscape-v2"])
+        .assert()
+        .success()
+        .stdout(predicates::str::starts_with(
+            "\n\u{1b}[31m  \u{1b}[0m\u{1b}[31m# inkscape\u{1b}[0m\n",
+        ));
+}
+
+#[test]
+fn test_example_option_style() {
+    let testenv = TestEnv::new().install_default_cache();
+
+    // Options use the `example_code` style by default, so they aren't styled on their own
+    testenv
+        .command()
+        .args(["--color", "always", "which"])
+        .assert()
+        .success()
+        .stdout(contains("\u{1b}[36m -a \u{1b}[0m"));
+
+    testenv.append_to_config("style.example_option.foreground = 'red'\n");
+    testenv
+        .command()
+        .args(["--color", "always", "which"])
+        .assert()
+        .success()
+        .stdout(contains("\u{1b}[31m-a\u{1b}[0m"));
+}
+
+#[test]
+fn test_option_style() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "foo",
+        "# foo\n\n> Description.\n\n- Example:\n\n`foo {{[-f|--format]}} {{json}}`\n",
+    );
+
+    for (option_style, expected) in [
+        ("both", "foo [-f|--format] json"),
+        ("short", "foo -f json"),
+        ("long", "foo --format json"),
+    ] {
+        testenv
+            .command()
+            .args([
+                "--set",
+                &format!("display.option_style={option_style}"),
+                "foo",
+            ])
+            .assert()
+            .success()
+            .stdout(contains(expected));
+    }
+}
+
+#[test]
+fn test_example_values() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "foo",
+        "# foo\n\n> Description.\n\n- Example:\n\n\
+         `foo {{path/to/file}} --user {{user}} {{branch}} {{value}}`\n",
+    );
+
+    testenv
+        .command()
+        .args(["foo"])
+        .assert()
+        .success()
+        .stdout(contains("foo path/to/file --user user branch value"));
+
+    testenv.append_to_config(
+        "display.example_values = true\n\
+         [placeholders]\n\
+         branch = 'main'\n\
+         user = 'sam'\n",
+    );
+    testenv
+        .command()
+        .args(["foo"])
+        .assert()
+        .success()
+        .stdout(contains("foo notes.txt --user sam main value"));
+
+    // The values are still styled like placeholders
+    testenv
+        .command()
+        .args(["--color", "always", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("\u{1b}[4;36mnotes.txt\u{1b}[0m"));
+}
+
+#[test]
+fn test_fill_example() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "foo",
+        "# foo\n\n> Description.\n\n- First example:\n\n`foo`\n\n- Second example:\n\n\
+         `foo {{[-u|--user]}} {{user}} {{branch}} {{path/to/file}} {{path/to/file}}.bak`\n",
+    );
+    testenv.append_to_config("[placeholders]\nbranch = 'main'\n");
+
+    // Only the value of `path/to/file` is asked for, once
+    let mut command = testenv.command();
+    command
+        .args(["--example", "2", "--fill", "foo"])
+        .env("USER", "sam");
+    assert_cmd::Command::from_std(command)
+        .write_stdin("notes.txt\n")
+        .assert()
+        .success()
+        .stdout("foo --user sam main notes.txt notes.txt.bak\n")
+        .stderr("path/to/file: [path/to/file] ");
+
+    testenv
+        .command()
+        .args(["--example", "3", "--fill", "foo"])
+        .assert()
+        .failure()
+        .stderr(contains("There is no example 3"));
+}
+
+#[test]
+fn test_shell_adaptation() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "foo",
+        "# foo\n\n> Description.\n\n- Set a variable:\n\n`FOO={{value}} foo`\n\n\
+         - Compare:\n\n`diff <(foo) <(foo)`\n",
+    );
+
+    testenv
+        .command()
+        .args(["foo"])
+        .assert()
+        .success()
+        .stdout(contains("FOO=value foo").and(contains("POSIX").not()));
+    testenv
+        .command()
+        .args(["--set", "display.shell=powershell", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("$env:FOO = \"value\"; foo"))
+        .stdout(contains("diff <(foo) <(foo)  # needs a POSIX shell"));
+}
+
+#[test]
+fn test_example_selection() {
+    let testenv = TestEnv::new().install_default_cache();
+    testenv.append_to_config("display.show_example_numbers = true\n");
+
+    testenv
+        .command()
+        .args(["which"])
+        .assert()
+        .success()
+        .stdout(contains("  1. Search the PATH environment variable"))
+        .stdout(contains(
+            "  2. If there are multiple executables which match",
+        ));
+    testenv
+        .command()
+        .args(["--example", "2", "which"])
+        .assert()
+        .success()
+        .stdout(
+            "\n  2. If there are multiple executables which match, display all:\n\n      \
+             which -a executable\n\n",
+        );
+    testenv
+        .command()
+        .args(["--example", "3", "which"])
+        .assert()
+        .failure()
+        .stderr(contains(
+            "There is no example 3, the page only has 2 examples.",
+        ));
+}
+
+#[test]
+fn test_grep_examples() {
+    let testenv = TestEnv::new().install_default_cache();
+
+    testenv
+        .command()
+        .args(["--grep", "MULTIPLE", "which"])
+        .assert()
+        .success()
+        .stdout(
+            "\n  If there are multiple executables which match, display all:\n\n      \
+             which -a executable\n\n",
+        );
+    // Placeholders are matched without their braces
+    testenv
+        .command()
+        .args(["--grep", "-a executable", "which"])
+        .assert()
+        .success()
+        .stdout(contains("which -a executable"))
+        .stdout(contains("Search the PATH").not());
+    testenv
+        .command()
+        .args(["--color", "always", "--grep", "multiple", "which"])
+        .assert()
+        .success()
+        .stdout(contains("\u{1b}[7;32mmultiple\u{1b}[0m"));
+    testenv
+        .command()
+        .args(["--grep", "missing", "which"])
+        .assert()
+        .failure()
+        .stderr(contains("No examples match `missing`."));
+}
+
+#[test]
+fn test_unrecognized_lines() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "foo",
+        "# foo\n\n> Description.\n\nsome text\n- Example:\n\n`foo`\n<br>\n",
+    );
+
+    testenv
+        .command()
+        .args(["foo"])
+        .assert()
+        .success()
+        .stdout(contains("foo").and(contains("some text").not()))
+        .stderr(contains("The page contains 2 unrecognized line(s)"));
+    testenv
+        .command()
+        .args(["--strict", "foo"])
+        .assert()
+        .failure()
+        .stderr(contains("The page contains 2 unrecognized line(s)"));
+
+    testenv.add_entry("bar", "# bar\n\n> Description.\n");
+    testenv
+        .command()
+        .args(["--strict", "bar"])
+        .assert()
+        .success()
+        .stderr(is_empty());
+}
+
+#[test]
+fn test_apropos() {
+    let testenv = TestEnv::new().install_default_cache();
+    testenv.append_to_config("search.platforms = ['common']\n");
+
+    testenv
+        .command()
+        .args(["--apropos", "PROGRAM"])
+        .assert()
+        .success()
+        .stdout(
+            "inkscape-v1 - An SVG (Scalable Vector Graphics) editing program.\n\
+             inkscape-v2 - An SVG (Scalable Vector Graphics) editing program.\n\
+             which - Locate a program in the user's path.\n",
+        );
+    // Matching names are ranked before matching summaries
+    testenv
+        .command()
+        .args(["--apropos", "which"])
+        .assert()
+        .success()
+        .stdout("which - Locate a program in the user's path.\n");
+    testenv
+        .command()
+        .args(["--apropos", "git checkout"])
+        .assert()
+        .success()
+        .stdout(contains("git-checkout - Checkout a branch"));
+    testenv
+        .command()
+        .args(["--apropos", "missing"])
+        .assert()
+        .failure()
+        .stderr(contains("No pages in the cache match `missing`."));
+}
+
+#[test]
+fn test_which_flag() {
+    let testenv = TestEnv::new().install_default_cache();
+    testenv.append_to_config("search.platforms = ['common']\n");
+
+    testenv
+        .command()
+        .args(["--which-flag", "-a"])
+        .assert()
+        .success()
+        .stdout(contains(
+            "which\n  If there are multiple executables which match, display all:\n      \
+             which -a executable\n",
+        ))
+        .stdout(contains("Search the PATH").not());
+    // Placeholders are matched without their braces
+    testenv
+        .command()
+        .args(["--which-flag", "-b branch_name"])
+        .assert()
+        .success()
+        .stdout(contains("git-checkout\n"))
+        .stdout(contains("which").not());
+    testenv
+        .command()
+        .args(["--which-flag", "--missing"])
+        .assert()
+        .failure()
+        .stderr(contains("No examples in the cache contain `--missing`."));
+}
+
+#[test]
+fn test_wrap_text() {
+    let testenv = TestEnv::new().install_default_cache();
+    testenv.append_to_config("display.wrap_text = true\ndisplay.show_example_numbers = true\n");
+
+    testenv
+        .command()
+        .args(["which"])
+        .env("COLUMNS", "40")
+        .assert()
+        .success()
+        .stdout(contains(
+            "  1. Search the PATH environment\n     variable and display the location\n     \
+             of any matching executables:\n",
+        ))
+        .stdout(contains("      which -a executable\n"));
+}
+
+#[test]
+fn test_spaces_find_command() {
+    let testenv = TestEnv::new().install_default_cache();
+
+    testenv
+        .command()
+        .args(["git", "checkout"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_pager_flag_enable() {
+    let testenv = TestEnv::new().install_default_cache();
+
+    testenv
+        .command()
+        .args(["--pager", "which"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_multiple_platform_command_search() {
+    let testenv = TestEnv::new();
+    testenv.add_os_entry("linux", "linux-only", "this command only exists for linux");
+    testenv.add_os_entry(
+        "linux",
+        "windows-and-linux",
+        "# windows-and-linux \n\n > linux version",
+    );
+    testenv.add_os_entry(
+        "windows",
+        "windows-and-linux",
+        "# windows-and-linux \n\n > windows version",
+    );
+
+    testenv
+        .command()
+        .args(["--platform", "windows", "--platform", "linux", "linux-only"])
+        .assert()
+        .success();
+
+    // test order of platforms supplied if preserved
+    testenv
+        .command()
+        .args([
+            "--platform",
+            "windows",
+            "--platform",
+            "linux",
+            "windows-and-linux",
+        ])
+        .assert()
+        .success()
+        .stdout(contains("windows version"));
+
+    testenv
+        .command()
+        .args([
+            "--platform",
+            "linux",
+            "--platform",
+            "windows",
+            "windows-and-linux",
+        ])
+        .assert()
+        .success()
+        .stdout(contains("linux version"));
+}
+
+#[test]
+fn test_compare_platforms() {
+    let testenv = TestEnv::new();
+    testenv.add_os_entry("linux", "ls", "# ls\n\n> GNU version.\n");
+    testenv.add_os_entry("osx", "ls", "# ls\n\n> BSD version.\n");
+    testenv.add_entry("ls", "# ls\n\n> Common version.\n");
+
+    testenv
+        .command()
+        .args([
+            "--raw",
+            "--platform",
+            "linux",
+            "--platform",
+            "macos",
+            "--platform",
+            "windows",
+            "--compare",
+            "ls",
+        ])
+        .assert()
+        .success()
+        .stdout(
+            "Linux\n# ls\n\n> GNU version.\n\n\
+             macOS / BSD\n# ls\n\n> BSD version.\n\n\
+             Windows (common page)\n# ls\n\n> Common version.\n",
+        );
+
+    testenv
+        .command()
+        .args(["--platform", "linux", "--compare", "missing"])
+        .assert()
+        .code(3)
+        .stderr(contains(
+            "not found in cache for any of the given platforms",
+        ));
+}
+
+#[test]
+fn test_list_show_platforms() {
+    let testenv = TestEnv::new().write_custom_pages_config();
+    testenv.add_os_entry("linux", "ls", "# ls\n");
+    testenv.add_os_entry("osx", "ls", "# ls\n");
+    testenv.add_entry("cp", "# cp\n");
+    testenv.add_page_entry("mine", "# mine\n");
+
+    // Platforms that are not searched are shown as well
+    testenv
+        .command()
+        .args(["--platform", "linux", "--list", "--show-platforms"])
+        .assert()
+        .success()
+        .stdout("cp    [common]\nls    [linux, osx]\nmine\n");
+
+    testenv
+        .command()
+        .args(["--show-platforms"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_all_matches() {
+    let testenv = TestEnv::new();
+    testenv.add_os_entry("linux", "ls", "# ls\n\n> GNU version.\n");
+    testenv.add_os_entry("osx", "ls", "# ls\n\n> BSD version.\n");
+    testenv.add_os_entry("windows", "ls", "# ls\n\n> GNU version.\n");
+    testenv.add_entry("cp", "# cp\n\n> Common version.\n");
+
+    // Pages with the same content are only shown once
+    testenv
+        .command()
+        .args([
+            "--raw",
+            "--platform",
+            "linux",
+            "--platform",
+            "macos",
+            "--platform",
+            "windows",
+            "--all-matches",
+            "ls",
+        ])
+        .assert()
+        .success()
+        .stdout("Linux\n# ls\n\n> GNU version.\n\nmacOS / BSD\n# ls\n\n> BSD version.\n");
+
+    // A single match is shown as usual
+    testenv
+        .command()
+        .args(["--raw", "--platform", "linux", "--all-matches", "cp"])
+        .assert()
+        .success()
+        .stdout("# cp\n\n> Common version.\n");
+
+    // Aliases are followed for every platform, like for a single page
+    testenv.add_os_entry(
+        "osx",
+        "dir",
+        "# dir\n\n> This command is an alias of `ls`.\n",
+    );
+    testenv.add_os_entry("linux", "dir", "# dir\n\n> GNU dir.\n");
+    testenv
+        .command()
+        .args([
+            "--raw",
+            "--platform",
+            "linux",
+            "--platform",
+            "macos",
+            "--all-matches",
+            "--set",
+            "display.follow_aliases=true",
+            "dir",
+        ])
+        .assert()
+        .success()
+        .stdout("Linux\n# dir\n\n> GNU dir.\n\nmacOS / BSD\n# ls\n\n> BSD version.\n");
+}
+
+#[test]
+fn test_multiple_platform_command_search_not_found() {
+    let testenv = TestEnv::new();
+    testenv.add_os_entry(
+        "windows",
+        "windows-only",
+        "this command only exists for Windows",
+    );
+
+    testenv
+        .command()
+        .args(["--platform", "macos", "--platform", "linux", "windows-only"])
+        .assert()
+        .stderr(contains("Page `windows-only` not found in cache."));
+}
+
+#[test]
+fn test_macos_is_alias_for_osx() {
+    let testenv = TestEnv::new();
+    testenv.add_os_entry("osx", "maconly", "this command only exists on mac");
+
+    testenv
+        .command()
+        .args(["--platform", "macos", "maconly"])
+        .assert()
+        .success();
+    testenv
+        .command()
+        .args(["--platform", "osx", "maconly"])
+        .assert()
+        .success();
+
+    testenv
+        .command()
+        .args(["--platform", "macos", "--list"])
+        .assert()
+        .stdout("maconly\n");
+    testenv
+        .command()
+        .args(["--platform", "osx", "--list"])
+        .assert()
+        .stdout("maconly\n");
+
+    testenv.append_to_config("search.platforms = ['osx']\n");
+    testenv.command().arg("--list").assert().stdout("maconly\n");
+
+    testenv.delete_config();
+    testenv.init_config();
+    testenv.append_to_config("search.platforms = ['macos']\n");
+    testenv.command().arg("--list").assert().stdout("maconly\n");
+}
+
+#[test]
+fn test_common_platform_is_used_as_fallback() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("in-common", "this command comes from common");
+
+    // No platform specified
+    testenv.command().args(["in-common"]).assert().success();
+
+    // Platform specified
+    testenv
+        .command()
+        .args(["--platform", "linux", "in-common"])
+        .assert()
+        .success();
 }
 
 #[test]
 fn test_search_language_precedence() {
     let testenv = TestEnv::new();
-    for lang in ["en", "de", "it", "fr", "pl", "nl"] {
-        testenv.add_lang_entry(lang, lang, "");
-    }
+    for lang in ["en", "de", "it", "fr", "pl", "nl"] {
+        testenv.add_lang_entry(lang, lang, "");
+    }
+
+    #[expect(clippy::type_complexity)]
+    let run = |cases: &[(Vec<(&str, &str)>, Vec<&str>, &str)]| {
+        for (extra_env, extra_args, expected) in cases {
+            let mut cmd = testenv.command();
+            for (key, value) in extra_env {
+                cmd.env(key, value);
+            }
+            cmd.args(extra_args);
+            cmd.arg("--list");
+            cmd.assert().success().stdout(eq(*expected));
+        }
+    };
+
+    let env_cases = &[
+        (vec![], vec![], "en\n"),
+        (vec![("LANGUAGE", "de:it")], vec![], "en\n"),
+        (
+            vec![("LANG", "fr"), ("LANGUAGE", "de:it")],
+            vec![],
+            "de\nen\nfr\nit\n",
+        ),
+        (
+            vec![("LANG", "fr"), ("LANGUAGE", "de:it")],
+            vec!["--language", "pl"],
+            "pl\n",
+        ),
+    ];
+    run(env_cases);
+
+    // Environment is only used when config setting is not set
+    testenv.append_to_config("search.languages = ['nl']\n");
+    let config_cases = &[
+        (vec![], vec![], "nl\n"),
+        (vec![("LANGUAGE", "de:it")], vec![], "nl\n"),
+        (vec![("LANG", "fr"), ("LANGUAGE", "de:it")], vec![], "nl\n"),
+        (
+            vec![("LANG", "fr"), ("LANGUAGE", "de:it")],
+            vec!["--language", "pl"],
+            "pl\n",
+        ),
+    ];
+    run(config_cases);
+
+    // The above update setting does not change anything
+    testenv.append_to_config("updates.download_languages = ['cz']");
+    run(config_cases);
+    testenv.delete_config();
+    testenv.init_config();
+    testenv.append_to_config("updates.download_languages = ['cz']");
+    run(env_cases);
+}
+
+#[test]
+fn test_remove_language() {
+    let testenv = TestEnv::new();
+    for lang in ["en", "de"] {
+        testenv.add_lang_entry(lang, lang, "");
+    }
+    testenv.append_to_config("[updates]\ndownload_languages = ['en', 'de']\n");
+    let pages_dir = testenv.cache_dir().join(TLDR_PAGES_DIR);
+
+    // Only the languages in the config file are written back to it
+    testenv
+        .command()
+        .args(["--remove-language", "de"])
+        .args(["--set", "updates.download_languages=['en', 'de', 'fr']"])
+        .assert()
+        .success()
+        .stderr(contains("Removed the pages for language `de`."));
+    assert!(!pages_dir.join("pages.de").exists());
+    assert!(pages_dir.join("pages.en").exists());
+    let config = fs::read_to_string(testenv.config_dir().join("config.toml")).unwrap();
+    assert!(config.contains("download_languages = [\"en\"]"));
+
+    testenv
+        .command()
+        .args(["--remove-language", "en"])
+        .assert()
+        .failure()
+        .stderr(contains(
+            "`en` is the only language configured for download",
+        ));
+    assert!(pages_dir.join("pages.en").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_edit_symlinked_config() {
+    let testenv = TestEnv::new();
+    for lang in ["en", "de"] {
+        testenv.add_lang_entry(lang, lang, "");
+    }
+    testenv.append_to_config("[updates]\ndownload_languages = ['en', 'de']\n");
+    // The config file is a symlink into a dotfiles repository
+    let config_path = testenv.config_dir().join("config.toml");
+    let dotfiles_config = testenv.config_dir().join("dotfiles").join("tldr.toml");
+    create_dir_all(dotfiles_config.parent().unwrap()).unwrap();
+    fs::rename(&config_path, &dotfiles_config).unwrap();
+    std::os::unix::fs::symlink(&dotfiles_config, &config_path).unwrap();
+
+    testenv
+        .command()
+        .args(["--remove-language", "de"])
+        .assert()
+        .success();
+    assert!(fs::symlink_metadata(&config_path)
+        .unwrap()
+        .file_type()
+        .is_symlink());
+    let config = fs::read_to_string(&dotfiles_config).unwrap();
+    assert!(config.contains("download_languages = [\"en\"]"));
+}
+
+#[test]
+fn test_edit_locale_download_languages() {
+    let testenv = TestEnv::new();
+    // Without a config file, the download languages are the ones of the locale
+    testenv.delete_config();
+    testenv.append_to_system_config(format!(
+        "directories.cache_dir = '{}'\n",
+        testenv.cache_dir().to_str().unwrap(),
+    ));
+    for lang in ["en", "de"] {
+        testenv.add_lang_entry(lang, lang, "");
+    }
+    let config_path = testenv.config_dir().join("config.toml");
+
+    testenv
+        .command()
+        .env("LANG", "de_DE.UTF-8")
+        .args(["--remove-language", "de"])
+        .assert()
+        .success()
+        .stderr(contains("Removed the pages for language `de`."));
+    let config = fs::read_to_string(&config_path).unwrap();
+    assert!(config.contains("download_languages = [\"de_DE\", \"en\"]"));
+
+    testenv.delete_config();
+    let mirror = testenv.cache_dir().join("mirror");
+    write_archive(&mirror, "fr", &[("common/fr.md", "# fr\n")]);
+    testenv
+        .command()
+        .env("LANG", "de_DE.UTF-8")
+        .args([
+            "--set",
+            &format!("updates.archive_source='{}'", mirror.display()),
+        ])
+        .args(["--add-language", "fr"])
+        .assert()
+        .success()
+        .stderr(contains("Downloaded the pages for language `fr`."));
+    let config = fs::read_to_string(&config_path).unwrap();
+    assert!(config.contains("download_languages = [\"de_DE\", \"de\", \"en\", \"fr\"]"));
+}
+
+#[test]
+fn test_prune_languages() {
+    let testenv = TestEnv::new();
+    for lang in ["en", "de", "it"] {
+        testenv.add_lang_entry(lang, lang, "");
+    }
+    let pages_dir = testenv.cache_dir().join(TLDR_PAGES_DIR);
+
+    testenv
+        .command()
+        .env("LANG", "it")
+        .arg("--prune-languages")
+        .assert()
+        .success()
+        .stderr(contains("Removed pages for the following languages: de"));
+    assert!(!pages_dir.join("pages.de").exists());
+    assert!(pages_dir.join("pages.en").exists());
+    assert!(pages_dir.join("pages.it").exists());
+
+    testenv
+        .command()
+        .env("LANG", "it")
+        .arg("--prune-languages")
+        .assert()
+        .success()
+        .stderr(contains("There are no pages for languages"));
+
+    testenv
+        .command()
+        .args(["--download-language", "en", "--download-language", "it"])
+        .arg("--prune-languages")
+        .assert()
+        .success()
+        .stderr(contains("There are no pages for languages"));
+
+    testenv
+        .command()
+        .args(["--language", "en", "--prune-languages"])
+        .assert()
+        .success();
+    assert!(!pages_dir.join("pages.it").exists());
+    assert!(pages_dir.join("pages.en").exists());
+}
+
+#[test]
+fn test_language_validation() {
+    let testenv = TestEnv::new();
+    testenv.add_lang_entry("en", "foo", "");
+
+    testenv
+        .command()
+        .args(["--language", "it", "foo"])
+        .assert()
+        .failure()
+        .stderr(contains("No pages for language `it` found in the cache."));
+
+    testenv
+        .command()
+        .args(["--language", "../en", "foo"])
+        .assert()
+        .failure()
+        .stderr(contains("Invalid language `../en`"));
+
+    testenv.append_to_config("search.languages = ['en', '']\n");
+    testenv
+        .command()
+        .arg("foo")
+        .assert()
+        .failure()
+        .stderr(contains("Invalid value in `search.languages`"));
+}
+
+#[test]
+fn test_missing_search_languages() {
+    let testenv = TestEnv::new().write_custom_pages_config();
+    testenv.add_lang_entry("en", "foo", "# foo\n\n> English page.\n");
+    testenv.add_page_entry("mine", "# mine\n\n> Custom page.\n");
+
+    // Custom pages are found without pages in the language
+    testenv
+        .command()
+        .args(["--language", "de", "mine"])
+        .assert()
+        .success()
+        .stdout(contains("Custom page."));
+
+    // Configured search languages are checked like `--language`
+    testenv.append_to_config("search.languages = ['de']\n");
+    testenv
+        .command()
+        .arg("foo")
+        .assert()
+        .failure()
+        .stderr(contains("No pages for language `de` found in the cache."));
+
+    // If only some of the languages are missing, the others are still searched
+    testenv
+        .command()
+        .args(["--set", "search.languages=['de', 'fr', 'en']", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("English page."))
+        .stderr(contains(
+            "No pages for languages `de`, `fr` found in the cache.",
+        ));
+
+    // The languages of the locale are not checked
+    let env_testenv = TestEnv::new();
+    env_testenv.add_lang_entry("en", "foo", "# foo\n\n> English page.\n");
+    env_testenv
+        .command()
+        .env("LANG", "de_DE.UTF-8")
+        .arg("foo")
+        .assert()
+        .success()
+        .stderr(is_empty());
+}
+
+#[cfg_attr(feature = "ignore-online-tests", ignore = "online test")]
+#[test]
+fn test_update_language_arg() {
+    let testenv = TestEnv::new();
+    testenv
+        .command()
+        .env("LANG", "it")
+        .arg("--update")
+        .assert()
+        .success()
+        .stderr(contains("it"))
+        .stderr(contains("en"))
+        .stderr(contains("English pages were not downloaded").not());
+
+    // English pages are skipped, which is worth a warning
+    testenv
+        .command()
+        .env("LANG", "en")
+        .args(["--language", "it"])
+        .arg("--update")
+        .assert()
+        .success()
+        .stderr(contains("were downloaded: it\n"))
+        .stderr(contains(
+            "English pages were not downloaded, so pages which are not translated to it",
+        ));
+}
+
+#[test]
+fn test_list_flag_rendering() {
+    let testenv = TestEnv::new().write_custom_pages_config();
+
+    testenv
+        .command()
+        .args(["--list"])
+        .assert()
+        .failure()
+        .stderr(contains("Page cache not found. Please run `tldr --update`"));
+
+    testenv.add_entry("foo", "");
+
+    testenv
+        .command()
+        .args(["--list"])
+        .assert()
+        .success()
+        .stdout("foo\n");
+
+    testenv.add_entry("bar", "");
+    testenv.add_entry("baz", "");
+    testenv.add_entry("qux", "");
+    testenv.add_page_entry("faz", "");
+    testenv.add_page_entry("bar", "");
+    testenv.add_page_entry("fiz", "");
+    testenv.add_patch_entry("buz", "");
+
+    testenv
+        .command()
+        .args(["--list"])
+        .assert()
+        .success()
+        .stdout("bar\nbaz\nfaz\nfiz\nfoo\nqux\n");
+}
+
+#[test]
+fn test_list_custom_flag_rendering() {
+    let testenv = TestEnv::new().write_custom_pages_config();
+
+    testenv
+        .command()
+        .args(["--list-custom"])
+        .assert()
+        .success()
+        .stdout(is_empty());
+
+    testenv.add_entry("foo", "");
+    testenv.add_page_entry("foo", "");
+    testenv.add_page_entry("bar", "");
+    testenv.add_patch_entry("baz", "");
+
+    testenv
+        .command()
+        .args(["--list-custom"])
+        .assert()
+        .success()
+        .stdout("bar.page.md\nbaz.patch.md\nfoo.page.md (shadows official page)\n");
+}
+
+#[test]
+fn test_multi_platform_list_flag_rendering() {
+    let testenv = TestEnv::new().write_custom_pages_config();
+
+    testenv.add_entry("common", "");
+
+    testenv
+        .command()
+        .args(["--list"])
+        .assert()
+        .success()
+        .stdout("common\n");
+
+    testenv
+        .command()
+        .args(["--platform", "linux", "--list"])
+        .assert()
+        .success()
+        .stdout("common\n");
+
+    testenv
+        .command()
+        .args(["--platform", "windows", "--list"])
+        .assert()
+        .success()
+        .stdout("common\n");
+
+    testenv.add_os_entry("linux", "rm", "");
+    testenv.add_os_entry("linux", "ls", "");
+    testenv.add_os_entry("windows", "del", "");
+    testenv.add_os_entry("windows", "dir", "");
+    testenv.add_os_entry("linux", "winux", "");
+    testenv.add_os_entry("windows", "winux", "");
+
+    // test `--list` for `--platform linux` by itself
+    testenv
+        .command()
+        .args(["--platform", "linux", "--list"])
+        .assert()
+        .success()
+        .stdout("common\nls\nrm\nwinux\n");
+
+    // test `--list` for `--platform windows` by itself
+    testenv
+        .command()
+        .args(["--platform", "windows", "--list"])
+        .assert()
+        .success()
+        .stdout("common\ndel\ndir\nwinux\n");
+
+    // test `--list` for `--platform linux --platform windows`
+    testenv
+        .command()
+        .args(["--platform", "linux", "--platform", "windows", "--list"])
+        .assert()
+        .success()
+        .stdout("common\ndel\ndir\nls\nrm\nwinux\n");
+
+    // test `--list` for `--platform windows --platform linux`
+    testenv
+        .command()
+        .args(["--platform", "linux", "--platform", "windows", "--list"])
+        .assert()
+        .success()
+        .stdout("common\ndel\ndir\nls\nrm\nwinux\n");
+}
+
+#[cfg_attr(feature = "ignore-online-tests", ignore = "online test")]
+#[test]
+fn test_autoupdate_cache() {
+    let testenv = TestEnv::new();
 
-    #[expect(clippy::type_complexity)]
-    let run = |cases: &[(Vec<(&str, &str)>, Vec<&str>, &str)]| {
-        for (extra_env, extra_args, expected) in cases {
-            let mut cmd = testenv.command();
-            for (key, value) in extra_env {
-                cmd.env(key, value);
-            }
-            cmd.args(extra_args);
-            cmd.arg("--list");
-            cmd.assert().success().stdout(eq(*expected));
-        }
+    // The first time, if automatic updates are disabled, the cache should not be found
+    testenv
+        .command()
+        .args(["--list"])
+        .assert()
+        .failure()
+        .stderr(contains("Page cache not found. Please run `tldr --update`"));
+
+    testenv
+        .append_to_config("updates.auto_update = true\nupdates.auto_update_interval_hours = 24\n");
+
+    // Helper function that runs `tldr --list` and asserts that the cache is automatically updated
+    // or not, depending on the value of `expected`.
+    let check_cache_updated = |expected| {
+        let assert = testenv.command().args(["--list"]).assert().success();
+        let pred = contains("Successfully updated cache");
+        if expected {
+            assert.stderr(pred)
+        } else {
+            assert.stderr(pred.not())
+        };
     };
 
-    let env_cases = &[
-        (vec![], vec![], "en\n"),
-        (vec![("LANGUAGE", "de:it")], vec![], "en\n"),
-        (
-            vec![("LANG", "fr"), ("LANGUAGE", "de:it")],
-            vec![],
-            "de\nen\nfr\nit\n",
-        ),
-        (
-            vec![("LANG", "fr"), ("LANGUAGE", "de:it")],
-            vec!["--language", "pl"],
-            "pl\n",
-        ),
-    ];
-    run(env_cases);
+    // The cache is updated the first time we run `tldr --list`
+    check_cache_updated(true);
 
-    // Environment is only used when config setting is not set
-    testenv.append_to_config("search.languages = ['nl']\n");
-    let config_cases = &[
-        (vec![], vec![], "nl\n"),
-        (vec![("LANGUAGE", "de:it")], vec![], "nl\n"),
-        (vec![("LANG", "fr"), ("LANGUAGE", "de:it")], vec![], "nl\n"),
-        (
-            vec![("LANG", "fr"), ("LANGUAGE", "de:it")],
-            vec!["--language", "pl"],
-            "pl\n",
-        ),
-    ];
-    run(config_cases);
+    // The cache is not updated with a subsequent call
+    check_cache_updated(false);
 
-    // The above update setting does not change anything
-    testenv.append_to_config("updates.download_languages = ['cz']");
-    run(config_cases);
-    testenv.delete_config();
-    testenv.init_config();
-    testenv.append_to_config("updates.download_languages = ['cz']");
-    run(env_cases);
+    // We set the time of the last update to about 23 hours ago.
+    // auto-update interval is 24 hours, the cache should not be updated
+    testenv.set_last_update(SystemTime::now() - Duration::from_secs(82_800));
+    check_cache_updated(false);
+
+    // We set the time of the last update to about 25 hours ago.
+    // auto-update interval is 24 hours, the cache should be updated
+    testenv.set_last_update(SystemTime::now() - Duration::from_secs(90_000));
+    check_cache_updated(true);
+
+    // The cache is not updated with a subsequent call
+    check_cache_updated(false);
 }
 
-#[cfg_attr(feature = "ignore-online-tests", ignore = "online test")]
+/// End-end test to ensure .page.md files overwrite pages in cache_dir
 #[test]
-fn test_update_language_arg() {
-    let testenv = TestEnv::new();
+fn test_custom_page_overwrites() {
+    let testenv = TestEnv::new().write_custom_pages_config();
+
+    // Add file that should be ignored to the cache dir
+    testenv.add_entry("inkscape-v2", "");
+    // Add .page.md file to custom_pages_dir
+    testenv.add_page_entry(
+        "inkscape-v2",
+        include_str!("cache/pages.en/common/inkscape-v2.md"),
+    );
+
+    // Load expected output
+    let expected = include_str!("rendered/inkscape-default-no-color.expected");
+
     testenv
         .command()
-        .env("LANG", "it")
-        .arg("--update")
+        .args(["inkscape-v2", "--color", "never"])
         .assert()
         .success()
-        .stderr(contains("it"))
-        .stderr(contains("en"));
+        .stdout(diff(expected));
+}
+
+/// End-End test to ensure that .patch.md files are appended to pages in the cache_dir
+#[test]
+fn test_custom_patch_appends_to_common() {
+    let testenv = TestEnv::new()
+        .install_default_cache()
+        .install_default_custom_pages();
+
+    // Load expected output
+    let expected = include_str!("rendered/inkscape-patched-no-color.expected");
 
     testenv
         .command()
-        .env("LANG", "en")
-        .args(["--language", "it"])
-        .arg("--update")
+        .args(["inkscape-v2", "--color", "never"])
         .assert()
         .success()
-        .stderr(contains("it"))
-        .stderr(contains("en").not());
+        .stdout(diff(expected));
 }
 
+/// End-End test to ensure that .patch.md files are not appended to .page.md files in the custom_pages_dir
+/// Maybe this interaction should change but I put this test here for the coverage
 #[test]
-fn test_list_flag_rendering() {
+fn test_custom_patch_does_not_append_to_custom() {
+    let testenv = TestEnv::new()
+        .install_default_cache()
+        .install_default_custom_pages();
+
+    // In addition to the page in the cache, add the same page as a custom page.
+    testenv.add_page_entry(
+        "inkscape-v2",
+        include_str!("cache/pages.en/common/inkscape-v2.md"),
+    );
+
+    // Load expected output
+    let expected = include_str!("rendered/inkscape-default-no-color.expected");
+
+    testenv
+        .command()
+        .args(["inkscape-v2", "--color", "never"])
+        .assert()
+        .success()
+        .stdout(diff(expected))
+        .stderr(contains("is not applied, because the custom page at"));
+}
+
+/// End-End test to ensure that a warning is shown for patches without a page to append to
+#[test]
+fn test_custom_patch_without_page() {
     let testenv = TestEnv::new().write_custom_pages_config();
+    testenv.add_entry("foo", "");
+    testenv.add_patch_entry("bar", "- Example:\n\n`bar`\n");
 
     testenv
         .command()
-        .args(["--list"])
+        .args(["bar"])
         .assert()
         .failure()
-        .stderr(contains("Page cache not found. Please run `tldr --update`"));
+        .stderr(contains("Page `bar` not found in cache."))
+        .stderr(contains(
+            "is not applied, because there is no page in the cache to append it to.",
+        ));
 
-    testenv.add_entry("foo", "");
+    testenv
+        .command()
+        .args(["bar", "--quiet"])
+        .assert()
+        .failure()
+        .stderr(is_empty());
+}
+
+#[test]
+fn test_diff_patch() {
+    let testenv = TestEnv::new()
+        .install_default_cache()
+        .install_default_custom_pages();
 
     testenv
         .command()
-        .args(["--list"])
+        .args(["--diff-patch", "inkscape-v2"])
         .assert()
         .success()
-        .stdout("foo\n");
+        .stdout(contains(
+            "  > An SVG (Scalable Vector Graphics) editing program.",
+        ))
+        .stdout(contains(
+            "+ Custom inkscape entry\n+\n+     My Inkscape example\n",
+        ));
 
-    testenv.add_entry("bar", "");
-    testenv.add_entry("baz", "");
-    testenv.add_entry("qux", "");
-    testenv.add_page_entry("faz", "");
-    testenv.add_page_entry("bar", "");
-    testenv.add_page_entry("fiz", "");
-    testenv.add_patch_entry("buz", "");
+    testenv
+        .command()
+        .args(["--diff-patch", "which"])
+        .assert()
+        .failure()
+        .stderr(contains("No custom patch found for `which`."));
 
+    // A custom page shadows the patch
+    testenv.add_page_entry("inkscape-v2", "");
     testenv
         .command()
-        .args(["--list"])
+        .args(["--diff-patch", "inkscape-v2"])
         .assert()
         .success()
-        .stdout("bar\nbaz\nfaz\nfiz\nfoo\nqux\n");
+        .stderr(contains("this patch is currently not applied"));
 }
 
 #[test]
-fn test_multi_platform_list_flag_rendering() {
+fn test_lint_custom_pages() {
+    let testenv = TestEnv::new().install_default_custom_pages();
+
+    testenv
+        .command()
+        .args(["--lint"])
+        .assert()
+        .success()
+        .stdout(is_empty())
+        .stderr(contains(
+            "Checked 1 custom pages and patches, found 0 problem(s).",
+        ));
+
+    testenv.add_page_entry("foo", "# foo\n\n- Example without code\n");
+    fs::write(testenv.custom_pages_dir().join("bar.patch"), "").unwrap();
+
+    testenv
+        .command()
+        .args(["--lint"])
+        .assert()
+        .failure()
+        .stdout(contains("bar.patch: Old naming convention"))
+        .stdout(contains(
+            "foo.page.md:3: Example description is not followed by a code example",
+        ));
+}
+
+#[test]
+fn test_migrate_custom_pages() {
     let testenv = TestEnv::new().write_custom_pages_config();
+    let dir = testenv.custom_pages_dir();
+    create_dir_all(&dir).unwrap();
+    fs::write(dir.join("foo.page"), "# foo\n").unwrap();
+    fs::write(dir.join("bar.patch"), "- bar\n").unwrap();
+    fs::write(dir.join("baz.page"), "# old baz\n").unwrap();
+    fs::write(dir.join("baz.page.md"), "# baz\n").unwrap();
 
-    testenv.add_entry("common", "");
+    testenv
+        .command()
+        .args(["--migrate-custom-pages", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(eq("bar.patch -> bar.patch.md\nfoo.page -> foo.page.md\n"))
+        .stderr(contains(
+            "Not renaming baz.page, because baz.page.md already exists.",
+        ));
+    assert!(dir.join("foo.page").is_file());
 
     testenv
         .command()
-        .args(["--list"])
+        .arg("--migrate-custom-pages")
         .assert()
         .success()
-        .stdout("common\n");
+        .stderr(contains("Renamed foo.page to foo.page.md."))
+        .stderr(contains("Renamed 2 custom page(s)"));
+    assert_eq!(
+        fs::read_to_string(dir.join("foo.page.md")).unwrap(),
+        "# foo\n"
+    );
+    assert!(dir.join("bar.patch.md").is_file());
+    assert_eq!(
+        fs::read_to_string(dir.join("baz.page.md")).unwrap(),
+        "# baz\n"
+    );
+    assert!(dir.join("baz.page").is_file());
+
+    // `--dry-run` is only used for migrations
+    testenv
+        .command()
+        .args(["--dry-run", "foo"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_expand_templates() {
+    let testenv = TestEnv::new()
+        .install_default_cache()
+        .write_custom_pages_config();
+    testenv.add_page_entry(
+        "deploy",
+        "# deploy\n\n> Deploy things.\n\n- Deploy to {env:DEPLOY_TARGET}:\n\n\
+         `deploy {{path/to/app}}`\n",
+    );
+
+    testenv
+        .command()
+        .env("DEPLOY_TARGET", "staging")
+        .args(["--raw", "deploy"])
+        .assert()
+        .success()
+        .stdout(contains("- Deploy to {env:DEPLOY_TARGET}:"));
+
+    testenv
+        .command()
+        .env("DEPLOY_TARGET", "staging")
+        .args(["--raw", "--set", "display.expand_templates=true", "deploy"])
+        .assert()
+        .success()
+        .stdout(contains("- Deploy to staging:"))
+        .stdout(contains("{{path/to/app}}"));
+}
+
+#[test]
+fn test_aliases() {
+    let testenv = TestEnv::new().install_default_cache();
+    testenv.append_to_config("[aliases]\nw = 'which'\ninks = 'inkscape'\n");
+
+    testenv
+        .command()
+        .args(["--raw", "W"])
+        .assert()
+        .success()
+        .stdout(contains("# which"));
 
+    // `inks v2` resolves to `inkscape-v2`
     testenv
         .command()
-        .args(["--platform", "linux", "--list"])
+        .args(["--raw", "inks", "v2"])
         .assert()
         .success()
-        .stdout("common\n");
+        .stdout(contains(
+            "An SVG (Scalable Vector Graphics) editing program.",
+        ));
 
     testenv
         .command()
-        .args(["--platform", "windows", "--list"])
+        .args(["--explain-config", "aliases.w"])
         .assert()
         .success()
-        .stdout("common\n");
+        .stdout(contains("which"));
+}
 
-    testenv.add_os_entry("linux", "rm", "");
-    testenv.add_os_entry("linux", "ls", "");
-    testenv.add_os_entry("windows", "del", "");
-    testenv.add_os_entry("windows", "dir", "");
-    testenv.add_os_entry("linux", "winux", "");
-    testenv.add_os_entry("windows", "winux", "");
+#[test]
+fn test_follow_aliases() {
+    let testenv = TestEnv::new()
+        .install_default_cache()
+        .write_custom_pages_config();
+    testenv.add_page_entry(
+        "wh",
+        "# wh\n\n> This command is an alias of `which -a`.\n\n\
+         - View documentation for the original command:\n\n`tldr which`\n",
+    );
+    testenv.add_page_entry(
+        "ink",
+        "# ink\n\n> This command is an alias of `inkscape v2 --verbose`.\n",
+    );
+    testenv.add_page_entry(
+        "gone",
+        "# gone\n\n> This command is an alias of `missing`.\n",
+    );
 
-    // test `--list` for `--platform linux` by itself
     testenv
         .command()
-        .args(["--platform", "linux", "--list"])
+        .args(["--raw", "wh"])
         .assert()
         .success()
-        .stdout("common\nls\nrm\nwinux\n");
+        .stdout(contains("# wh"));
 
-    // test `--list` for `--platform windows` by itself
     testenv
         .command()
-        .args(["--platform", "windows", "--list"])
+        .args(["--raw", "--set", "display.follow_aliases=true", "wh"])
         .assert()
         .success()
-        .stdout("common\ndel\ndir\nwinux\n");
+        .stdout(contains("# which"))
+        .stderr(contains(
+            "`wh` is an alias of `which -a`, showing the page for `which`.",
+        ));
 
-    // test `--list` for `--platform linux --platform windows`
+    // Without a `tldr` example, the options of the aliased command are left out
     testenv
         .command()
-        .args(["--platform", "linux", "--platform", "windows", "--list"])
+        .args(["--raw", "--set", "display.follow_aliases=true", "ink"])
         .assert()
         .success()
-        .stdout("common\ndel\ndir\nls\nrm\nwinux\n");
+        .stdout(contains(
+            "An SVG (Scalable Vector Graphics) editing program.",
+        ));
 
-    // test `--list` for `--platform windows --platform linux`
+    // The alias page is shown if the page of the aliased command does not exist
     testenv
         .command()
-        .args(["--platform", "linux", "--platform", "windows", "--list"])
+        .args(["--raw", "--set", "display.follow_aliases=true", "gone"])
         .assert()
         .success()
-        .stdout("common\ndel\ndir\nls\nrm\nwinux\n");
+        .stdout(contains("# gone"));
 }
 
-#[cfg_attr(feature = "ignore-online-tests", ignore = "online test")]
 #[test]
-fn test_autoupdate_cache() {
-    let testenv = TestEnv::new();
+fn test_include_directives() {
+    let testenv = TestEnv::new()
+        .install_default_cache()
+        .write_custom_pages_config();
+    testenv.add_page_entry(
+        "tools",
+        "# tools\n\n> My tools.\n\n<!-- include: which -->\n\n- My own example:\n\n`mine`\n",
+    );
 
-    // The first time, if automatic updates are disabled, the cache should not be found
     testenv
         .command()
-        .args(["--list"])
+        .args(["--raw", "tools"])
         .assert()
-        .failure()
-        .stderr(contains("Page cache not found. Please run `tldr --update`"));
+        .success()
+        .stdout(eq("# tools\n\n> My tools.\n\n\
+             - Search the PATH environment variable and display the location of any matching \
+             executables:\n\n`which {{executable}}`\n\n\
+             - If there are multiple executables which match, display all:\n\n\
+             `which -a {{executable}}`\n\n\
+             - My own example:\n\n`mine`\n"));
 
-    let cache_file_path = testenv.cache_dir().join(TLDR_PAGES_DIR);
+    testenv.command().arg("--lint").assert().success();
 
+    testenv.add_page_entry("a", "# a\n\n<!-- include: b -->\n");
+    testenv.add_page_entry("b", "# b\n\n<!-- include: a -->\n");
     testenv
-        .append_to_config("updates.auto_update = true\nupdates.auto_update_interval_hours = 24\n");
-
-    // Helper function that runs `tldr --list` and asserts that the cache is automatically updated
-    // or not, depending on the value of `expected`.
-    let check_cache_updated = |expected| {
-        let assert = testenv.command().args(["--list"]).assert().success();
-        let pred = contains("Successfully updated cache");
-        if expected {
-            assert.stderr(pred)
-        } else {
-            assert.stderr(pred.not())
-        };
-    };
-
-    // The cache is updated the first time we run `tldr --list`
-    check_cache_updated(true);
-
-    // The cache is not updated with a subsequent call
-    check_cache_updated(false);
-
-    // We update the modification and access times such that they are about 23 hours from now.
-    // auto-update interval is 24 hours, the cache should not be updated
-    let new_mtime = SystemTime::now() - Duration::from_secs(82_800);
-    filetime::set_file_mtime(&cache_file_path, new_mtime.into()).unwrap();
-    check_cache_updated(false);
-
-    // We update the modification and access times such that they are about 25 hours from now.
-    // auto-update interval is 24 hours, the cache should be updated
-    let new_mtime = SystemTime::now() - Duration::from_secs(90_000);
-    filetime::set_file_mtime(&cache_file_path, new_mtime.into()).unwrap();
-    check_cache_updated(true);
+        .command()
+        .arg("a")
+        .assert()
+        .failure()
+        .stderr(contains("Custom pages include each other: a -> b -> a"));
 
-    // The cache is not updated with a subsequent call
-    check_cache_updated(false);
+    testenv.add_page_entry("c", "# c\n\n<!-- include: missing -->\n");
+    testenv
+        .command()
+        .arg("c")
+        .assert()
+        .failure()
+        .stderr(contains(
+            "Page `missing` included by the custom page for `c` not found",
+        ));
 }
 
-/// End-end test to ensure .page.md files overwrite pages in cache_dir
 #[test]
-fn test_custom_page_overwrites() {
-    let testenv = TestEnv::new().write_custom_pages_config();
-
-    // Add file that should be ignored to the cache dir
-    testenv.add_entry("inkscape-v2", "");
-    // Add .page.md file to custom_pages_dir
-    testenv.add_page_entry(
-        "inkscape-v2",
-        include_str!("cache/pages.en/common/inkscape-v2.md"),
-    );
-
-    // Load expected output
-    let expected = include_str!("rendered/inkscape-default-no-color.expected");
+fn test_render_directory() {
+    let testenv = TestEnv::new();
+    let pages_dir = testenv.config_dir().join("pages");
+    fs::create_dir_all(pages_dir.join("linux")).unwrap();
+    fs::write(
+        pages_dir.join("linux").join("foo.md"),
+        "# foo\n\n> Foo things.\n\n- Foo:\n\n`foo`\n",
+    )
+    .unwrap();
+    fs::write(
+        pages_dir.join("bar.md"),
+        "# bar\n\n> Bar things.\n\n- Example without code\n",
+    )
+    .unwrap();
+    fs::write(pages_dir.join("notes.txt"), "not a page").unwrap();
 
+    let bar_path = pages_dir.join("bar.md");
+    let foo_path = pages_dir.join("linux").join("foo.md");
+    let pages_dir = pages_dir.to_str().unwrap();
     testenv
         .command()
-        .args(["inkscape-v2", "--color", "never"])
+        .args(["--render", pages_dir])
         .assert()
         .success()
-        .stdout(diff(expected));
-}
-
-/// End-End test to ensure that .patch.md files are appended to pages in the cache_dir
-#[test]
-fn test_custom_patch_appends_to_common() {
-    let testenv = TestEnv::new()
-        .install_default_cache()
-        .install_default_custom_pages();
+        .stdout(format!(
+            "{}\n\n  Bar things.\n\n  Example without code\n\n\
+             {}\n\n  Foo things.\n\n  Foo:\n\n      foo\n\n",
+            bar_path.display(),
+            foo_path.display(),
+        ));
 
-    // Load expected output
-    let expected = include_str!("rendered/inkscape-patched-no-color.expected");
+    testenv
+        .command()
+        .args(["--render", pages_dir, "--check"])
+        .assert()
+        .failure()
+        .stdout(format!(
+            "{}:5: Example description is not followed by a code example\n",
+            bar_path.display(),
+        ))
+        .stderr(contains("Checked 2 pages, found 1 problem(s)."));
 
     testenv
         .command()
-        .args(["inkscape-v2", "--color", "never"])
+        .args(["--render", foo_path.to_str().unwrap(), "--check"])
         .assert()
         .success()
-        .stdout(diff(expected));
+        .stdout(is_empty());
 }
 
-/// End-End test to ensure that .patch.md files are not appended to .page.md files in the custom_pages_dir
-/// Maybe this interaction should change but I put this test here for the coverage
 #[test]
-fn test_custom_patch_does_not_append_to_custom() {
-    let testenv = TestEnv::new()
-        .install_default_cache()
-        .install_default_custom_pages();
-
-    // In addition to the page in the cache, add the same page as a custom page.
-    testenv.add_page_entry(
-        "inkscape-v2",
-        include_str!("cache/pages.en/common/inkscape-v2.md"),
-    );
-
-    // Load expected output
-    let expected = include_str!("rendered/inkscape-default-no-color.expected");
+fn test_watch_page() {
+    let testenv = TestEnv::new();
+    let page_path = testenv.config_dir().join("foo.md");
+    fs::write(&page_path, "# foo\n\n> Before the change.\n").unwrap();
 
-    testenv
+    let mut child = testenv
         .command()
-        .args(["inkscape-v2", "--color", "never"])
-        .assert()
-        .success()
-        .stdout(diff(expected));
+        .args(["--watch", "--render", page_path.to_str().unwrap()])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    thread::sleep(Duration::from_secs(1));
+    fs::write(&page_path, "# foo\n\n> After the change.\n").unwrap();
+    thread::sleep(Duration::from_secs(1));
+    child.kill().unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    // Each rendering clears the screen
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "\x1b[2J\x1b[H\n  Before the change.\n\n\x1b[2J\x1b[H\n  After the change.\n\n"
+    );
 }
 
 #[test]
@@ -1480,6 +4479,232 @@ fn test_recreate_dir() {
     touch_custom_page(&testenv);
 }
 
+#[cfg(unix)]
+#[test]
+fn test_editor_selection() {
+    let testenv = TestEnv::new().write_custom_pages_config();
+    let page_path = testenv.custom_pages_dir().join("foo.page.md");
+
+    // `VISUAL` takes precedence over `EDITOR`
+    testenv
+        .command()
+        .args(["--edit-page", "foo"])
+        .env("VISUAL", "touch")
+        .env("EDITOR", "false")
+        .assert()
+        .success();
+    assert!(page_path.exists());
+
+    // Editor commands can contain arguments
+    testenv
+        .command()
+        .args(["--edit-page", "foo"])
+        .env("EDITOR", r#"sh -c 'echo "edited" > "$0"'"#)
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(&page_path).unwrap(), "edited\n");
+}
+
+#[test]
+fn test_new_page() {
+    let testenv = TestEnv::new().write_custom_pages_config();
+    let page_path = testenv.custom_pages_dir().join("git-foo.page.md");
+
+    testenv
+        .command()
+        .args(["--new-page", "git", "foo"])
+        .env("EDITOR", "true")
+        .assert()
+        .success();
+    let page = fs::read_to_string(&page_path).unwrap();
+    assert!(page.starts_with("# git foo\n\n> Short description"));
+    assert!(page.contains("`git foo {{path/to/file}}`"));
+
+    // The skeleton passes the linter
+    testenv
+        .command()
+        .args(["--render", page_path.to_str().unwrap(), "--check"])
+        .assert()
+        .success();
+
+    // Existing pages are not overwritten
+    testenv
+        .command()
+        .args(["--new-page", "git", "foo"])
+        .env("EDITOR", "true")
+        .assert()
+        .failure()
+        .stderr(contains("already exists"));
+    assert_eq!(fs::read_to_string(&page_path).unwrap(), page);
+
+    // The page can be written to a different path, e.g. in a tldr-pages checkout
+    let output_path = testenv.custom_pages_dir().join("common").join("foo.md");
+    testenv
+        .command()
+        .args(["--new-page", "foo", "--output-file"])
+        .arg(&output_path)
+        .env("EDITOR", "true")
+        .assert()
+        .success();
+    assert!(fs::read_to_string(&output_path)
+        .unwrap()
+        .starts_with("# foo\n"));
+}
+
+#[test]
+fn test_page_name_validation() {
+    let testenv = TestEnv::new().write_custom_pages_config();
+    let escaped_path = testenv
+        .custom_pages_dir()
+        .parent()
+        .unwrap()
+        .join("x.page.md");
+
+    for flag in ["--new-page", "--edit-page"] {
+        testenv
+            .command()
+            .args([flag, "../x"])
+            .env("EDITOR", "touch")
+            .assert()
+            .failure()
+            .stderr(contains("Invalid page name `../x`"));
+    }
+    assert!(!escaped_path.exists());
+}
+
+#[test]
+fn test_edit_page_from_cache() {
+    let testenv = TestEnv::new()
+        .install_default_cache()
+        .write_custom_pages_config();
+
+    testenv
+        .command()
+        .args(["--edit-page", "--from-cache", "which"])
+        .env("EDITOR", "true")
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(testenv.custom_pages_dir().join("which.page.md")).unwrap(),
+        include_str!("cache/pages.en/common/which.md"),
+    );
+
+    // An existing custom page is not overwritten
+    testenv.add_page_entry("which", "custom");
+    testenv
+        .command()
+        .args(["--edit-page", "--from-cache", "which"])
+        .env("EDITOR", "true")
+        .assert()
+        .success()
+        .stderr(contains("A custom page already exists"));
+    assert_eq!(
+        fs::read_to_string(testenv.custom_pages_dir().join("which.page.md")).unwrap(),
+        "custom",
+    );
+
+    testenv
+        .command()
+        .args(["--edit-page", "--from-cache", "does-not-exist"])
+        .env("EDITOR", "true")
+        .assert()
+        .failure()
+        .stderr(contains("Page `does-not-exist` not found in cache"));
+}
+
+#[test]
+fn test_localized_messages() {
+    let testenv = TestEnv::new().install_default_cache();
+
+    testenv
+        .command()
+        .args(["does-not-exist"])
+        .env("LANG", "de_DE.UTF-8")
+        .assert()
+        .code(3)
+        .stderr(contains("Seite `does-not-exist` nicht im Cache gefunden."));
+
+    // LC_ALL takes precedence over LANG
+    testenv
+        .command()
+        .args(["does-not-exist"])
+        .env("LANG", "de_DE.UTF-8")
+        .env("LC_ALL", "C")
+        .assert()
+        .code(3)
+        .stderr(contains("Page `does-not-exist` not found in cache."));
+}
+
+#[test]
+fn test_exit_codes() {
+    let testenv = TestEnv::new().install_default_cache();
+
+    // Missing page
+    testenv
+        .command()
+        .args(["does-not-exist"])
+        .assert()
+        .code(3)
+        .stderr(contains("Page `does-not-exist` not found in cache."));
+
+    // Missing file to render
+    testenv
+        .command()
+        .args(["--render", "/does/not/exist.md"])
+        .assert()
+        .code(4);
+
+    // Unreachable server
+    testenv
+        .command()
+        .args(["--render-url", "http://127.0.0.1:1/page.md"])
+        .assert()
+        .code(5);
+
+    // Invalid arguments are reported by clap
+    testenv.command().args(["--no-such-flag"]).assert().code(2);
+}
+
+#[test]
+fn test_delete_custom_page_and_patch() {
+    let testenv = TestEnv::new().write_custom_pages_config();
+    testenv.add_page_entry("foo", "");
+    testenv.add_patch_entry("foo", "");
+    let page_path = testenv.custom_pages_dir().join("foo.page.md");
+    let patch_path = testenv.custom_pages_dir().join("foo.patch.md");
+
+    assert_cmd::Command::from_std(testenv.command())
+        .args(["--delete-page", "foo"])
+        .write_stdin("n\n")
+        .assert()
+        .success()
+        .stderr(contains("Nothing was deleted."));
+    assert!(page_path.exists());
+
+    assert_cmd::Command::from_std(testenv.command())
+        .args(["--delete-page", "foo"])
+        .write_stdin("y\n")
+        .assert()
+        .success()
+        .stderr(contains("Successfully deleted"));
+    assert!(!page_path.exists());
+    assert!(patch_path.exists());
+
+    testenv
+        .command()
+        .args(["--delete-page", "foo"])
+        .assert()
+        .failure()
+        .stderr(contains("No file found at"));
+
+    assert_cmd::Command::from_std(testenv.command())
+        .args(["--delete-patch", "foo"])
+        .write_stdin("yes\n")
+        .assert()
+        .success();
+    assert!(!patch_path.exists());
+}
+
 #[test]
 fn test_custom_pages_dir_is_not_dir() {
     let testenv = TestEnv::new().write_custom_pages_config();